@@ -52,7 +52,11 @@ fn benchmark_files_without_partitions(c: &mut Criterion) {
                     sinks::file::FileSinkConfig {
                         path: output.try_into().unwrap(),
                         idle_timeout_secs: None,
-                        encoding: sinks::file::Encoding::Text.into(),
+                        encoding: sinks::util::encoding::StandardEncodings::text().into(),
+                        compression: sinks::file::Compression::None,
+                        rotation: Default::default(),
+                        max_open_files: None,
+                        fsync: sinks::file::FsyncPolicy::Never,
                     },
                 );
 
@@ -5,5 +5,24 @@ fn main() {
     prost_build
         .compile_protos(&["proto/event.proto"], &["proto/"])
         .unwrap();
+
+    #[cfg(feature = "sinks-prometheus")]
+    {
+        println!("cargo:rerun-if-changed=proto/prometheus.proto");
+        prost_build::compile_protos(&["proto/prometheus.proto"], &["proto/"]).unwrap();
+    }
+
+    #[cfg(feature = "tonic-build")]
+    {
+        println!("cargo:rerun-if-changed=proto/vector.proto");
+        let mut prost_build = prost_build::Config::new();
+        prost_build
+            .btree_map(&["."])
+            .extern_path(".event.proto", "crate::event::proto");
+        tonic_build::configure()
+            .compile_with_config(prost_build, &["proto/vector.proto"], &["proto/"])
+            .unwrap();
+    }
+
     built::write_built_file().expect("Failed to acquire build-time information");
 }
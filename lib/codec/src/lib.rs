@@ -1,6 +1,10 @@
 #[macro_use]
 extern crate tracing;
 
+mod octet_counting;
+
+pub use octet_counting::OctetCountingCodec;
+
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::{cmp, io, usize};
 use tokio_util::codec::{Decoder, Encoder};
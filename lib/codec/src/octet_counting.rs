@@ -0,0 +1,155 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{io, usize};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frames according to `Octet Counting` in https://tools.ietf.org/html/rfc6587,
+/// where each frame is prefixed by its length in bytes as an ASCII decimal
+/// number followed by a single space.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct OctetCountingCodec {
+    max_length: usize,
+    /// Number of bytes still to be dropped from a frame that exceeded
+    /// `max_length`.
+    discarding: usize,
+}
+
+impl OctetCountingCodec {
+    /// Returns an `OctetCountingCodec` without a frame length limit.
+    pub fn new() -> Self {
+        Self::new_with_max_length(usize::MAX)
+    }
+
+    /// Returns an `OctetCountingCodec` with a maximum frame length limit.
+    pub fn new_with_max_length(max_length: usize) -> Self {
+        OctetCountingCodec {
+            max_length,
+            discarding: 0,
+        }
+    }
+
+    /// Returns the maximum frame length when decoding.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Whether the rest of a frame that exceeded `max_length` is still
+    /// being dropped.
+    pub fn is_discarding(&self) -> bool {
+        self.discarding > 0
+    }
+
+    fn discard(&mut self, buf: &mut BytesMut) -> bool {
+        let skip = std::cmp::min(self.discarding, buf.len());
+        buf.advance(skip);
+        self.discarding -= skip;
+        self.discarding == 0
+    }
+}
+
+impl Default for OctetCountingCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for OctetCountingCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        loop {
+            if !self.discard(buf) {
+                return Ok(None);
+            }
+
+            // Some senders terminate each frame with a newline anyway, so
+            // skip any line breaks between frames.
+            let skip = buf
+                .iter()
+                .take_while(|&&b| b == b'\n' || b == b'\r')
+                .count();
+            buf.advance(skip);
+
+            // Encoding scheme:
+            //
+            // len ' ' data
+            // |    |  | len number of bytes of data
+            // |    |
+            // |    | Separating whitespace
+            // |
+            // | ASCII decimal number of unknown length
+            let space = match buf.iter().position(|&b| b == b' ') {
+                Some(space) => space,
+                None if buf.iter().all(u8::is_ascii_digit) && buf.len() <= 20 => return Ok(None),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Expected octet counted frame",
+                    ))
+                }
+            };
+
+            let len = std::str::from_utf8(&buf[..space])
+                .ok()
+                .filter(|num| num.starts_with(|c: char| ('1'..='9').contains(&c)))
+                .and_then(|num| num.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Unable to decode frame length as number",
+                    )
+                })?;
+
+            if len > self.max_length {
+                warn!(
+                    message = "discarding frame larger than max_length",
+                    frame_length = len,
+                    max_length = self.max_length,
+                    rate_limit_secs = 30
+                );
+                buf.advance(space + 1);
+                self.discarding = len;
+                continue;
+            }
+
+            if buf.len() - (space + 1) < len {
+                // Wait for the rest of the frame.
+                return Ok(None);
+            }
+
+            buf.advance(space + 1);
+            trace!(message = "decoding the frame.", bytes_proccesed = len);
+            return Ok(Some(buf.split_to(len).freeze()));
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.iter().all(|&b| b == b'\n' || b == b'\r') => {
+                buf.clear();
+                Ok(None)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated octet counted frame",
+            )),
+        }
+    }
+}
+
+impl<T> Encoder<T> for OctetCountingCodec
+where
+    T: AsRef<[u8]>,
+{
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, buf: &mut BytesMut) -> Result<(), io::Error> {
+        let item = item.as_ref();
+        let header = format!("{} ", item.len());
+        buf.reserve(header.len() + item.len());
+        buf.put(header.as_bytes());
+        buf.put(item);
+        Ok(())
+    }
+}
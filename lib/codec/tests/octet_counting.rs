@@ -0,0 +1,66 @@
+use bytes::{BufMut, BytesMut};
+use codec::OctetCountingCodec;
+use tokio_util::codec::{Decoder, Encoder};
+
+#[test]
+fn octet_counting_decode() {
+    let mut codec = OctetCountingCodec::new();
+    let buf = &mut BytesMut::new();
+    buf.put_slice(b"3 abc11 hello world");
+    assert_eq!(Some("abc".into()), codec.decode(buf).unwrap());
+    assert_eq!(Some("hello world".into()), codec.decode(buf).unwrap());
+    assert_eq!(None, codec.decode_eof(buf).unwrap());
+}
+
+#[test]
+fn octet_counting_decode_partial() {
+    let mut codec = OctetCountingCodec::new();
+    let buf = &mut BytesMut::new();
+
+    buf.put_slice(b"1");
+    assert!(codec.decode(buf).unwrap().is_none());
+    buf.put_slice(b"1 hello");
+    assert!(codec.decode(buf).unwrap().is_none());
+    buf.put_slice(b" world\n");
+    assert_eq!(Some("hello world".into()), codec.decode(buf).unwrap());
+    assert!(codec.decode_eof(buf).unwrap().is_none());
+}
+
+#[test]
+fn octet_counting_encode() {
+    let mut codec = OctetCountingCodec::new();
+
+    let mut buf = BytesMut::new();
+    codec.encode(b"abc", &mut buf).unwrap();
+
+    assert_eq!(b"3 abc", &buf[..]);
+}
+
+#[test]
+fn octet_counting_decode_max_length() {
+    let mut codec = OctetCountingCodec::new_with_max_length(6);
+    let buf = &mut BytesMut::new();
+
+    buf.put_slice(b"7 1234567");
+    assert!(codec.decode(buf).unwrap().is_none());
+    buf.put_slice(b"6 123456");
+    assert_eq!(Some("123456".into()), codec.decode(buf).unwrap());
+}
+
+#[test]
+fn octet_counting_rejects_garbage() {
+    let mut codec = OctetCountingCodec::new();
+    let buf = &mut BytesMut::new();
+
+    buf.put_slice(b"hello world");
+    assert!(codec.decode(buf).is_err());
+}
+
+#[test]
+fn octet_counting_rejects_truncated_frame() {
+    let mut codec = OctetCountingCodec::new();
+    let buf = &mut BytesMut::new();
+
+    buf.put_slice(b"5 abc");
+    assert!(codec.decode_eof(buf).is_err());
+}
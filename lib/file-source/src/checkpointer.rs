@@ -0,0 +1,301 @@
+use crate::{FileFingerprint, FilePosition};
+use chrono::{DateTime, Utc};
+use glob::glob;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time;
+
+const STATE_FILE_NAME: &str = "checkpoints.json";
+const TMP_FILE_NAME: &str = "checkpoints.new.json";
+const LEGACY_DIR_NAME: &str = "checkpoints";
+
+/// The position of a single file, along with the information needed to
+/// make sense of it when reading the state file.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub fingerprint: FileFingerprint,
+    pub position: FilePosition,
+    /// Unknown for checkpoints migrated from the legacy format until the
+    /// file is read again.
+    pub path: Option<PathBuf>,
+    pub modified: DateTime<Utc>,
+}
+
+/// The on-disk representation of the checkpoints.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "version")]
+enum State {
+    #[serde(rename = "1")]
+    V1 { checkpoints: Vec<Checkpoint> },
+}
+
+/// Keeps track of the file positions and persists them to a single JSON
+/// state file in the data directory.
+///
+/// The state file is replaced atomically by writing a temporary file and
+/// renaming it over the old one, so a crash never leaves a partially written
+/// set of checkpoints behind.
+pub struct Checkpointer {
+    stable_file_path: PathBuf,
+    tmp_file_path: PathBuf,
+    legacy_directory: PathBuf,
+    legacy_glob_string: String,
+    checkpoints: HashMap<FileFingerprint, Checkpoint>,
+}
+
+impl Checkpointer {
+    pub fn new(data_dir: &Path) -> Checkpointer {
+        let legacy_directory = data_dir.join(LEGACY_DIR_NAME);
+        let legacy_glob_string = legacy_directory.join("*").to_string_lossy().into_owned();
+        Checkpointer {
+            stable_file_path: data_dir.join(STATE_FILE_NAME),
+            tmp_file_path: data_dir.join(TMP_FILE_NAME),
+            legacy_directory,
+            legacy_glob_string,
+            checkpoints: HashMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    fn encode_legacy(&self, fng: FileFingerprint, pos: FilePosition) -> PathBuf {
+        self.legacy_directory.join(format!("{:x}.{}", fng, pos))
+    }
+
+    fn decode_legacy(&self, path: &Path) -> Option<(FileFingerprint, FilePosition)> {
+        let file_name = &path.file_name()?.to_string_lossy();
+        scan_fmt!(file_name, "{x}.{}", [hex FileFingerprint], FilePosition).ok()
+    }
+
+    /// Records the position in the file at `path`. The checkpoint is as
+    /// recent as the file itself, so that `ignore_older` applies to it the
+    /// same way as to the file.
+    pub fn set_checkpoint(&mut self, fng: FileFingerprint, pos: FilePosition, path: &Path) {
+        let modified = modified_time(path);
+        self.checkpoints.insert(
+            fng,
+            Checkpoint {
+                fingerprint: fng,
+                position: pos,
+                path: Some(path.to_path_buf()),
+                modified,
+            },
+        );
+    }
+
+    pub fn get_checkpoint(&self, fng: FileFingerprint) -> Option<FilePosition> {
+        self.checkpoints
+            .get(&fng)
+            .map(|checkpoint| checkpoint.position)
+    }
+
+    /// Write the checkpoints to the state file, replacing the previous one.
+    pub fn write_checkpoints(&mut self) -> Result<(), io::Error> {
+        let mut checkpoints: Vec<Checkpoint> = self.checkpoints.values().cloned().collect();
+        checkpoints.sort_by_key(|checkpoint| checkpoint.fingerprint);
+        let state = State::V1 { checkpoints };
+
+        // Write to a temporary file, then atomically rename it over the
+        // stable one.
+        let mut f = io::BufWriter::new(fs::File::create(&self.tmp_file_path)?);
+        serde_json::to_writer(&mut f, &state)?;
+        f.flush()?;
+        f.get_ref().sync_all()?;
+        drop(f);
+        fs::rename(&self.tmp_file_path, &self.stable_file_path)?;
+        sync_parent(&self.stable_file_path)?;
+
+        // The legacy checkpoints are now part of the state file.
+        if self.legacy_directory.exists() {
+            fs::remove_dir_all(&self.legacy_directory).ok();
+        }
+
+        Ok(())
+    }
+
+    /// Read the checkpoints from the state file, falling back to the legacy
+    /// format of one file per checkpoint if there is no state file yet.
+    pub fn read_checkpoints(&mut self, ignore_before: Option<time::SystemTime>) {
+        match self.read_state_file() {
+            Ok(Some(checkpoints)) => {
+                for checkpoint in checkpoints {
+                    self.checkpoints.insert(checkpoint.fingerprint, checkpoint);
+                }
+            }
+            Ok(None) => self.read_legacy_checkpoints(),
+            Err(error) => {
+                error!(message = "Unable to read checkpoints.", path = ?self.stable_file_path, %error);
+                self.read_legacy_checkpoints();
+            }
+        }
+
+        if let Some(ignore_before) = ignore_before {
+            let ignore_before = DateTime::<Utc>::from(ignore_before);
+            self.checkpoints
+                .retain(|_fng, checkpoint| checkpoint.modified >= ignore_before);
+        }
+    }
+
+    fn read_state_file(&self) -> Result<Option<Vec<Checkpoint>>, io::Error> {
+        let f = match fs::File::open(&self.stable_file_path) {
+            Ok(f) => f,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let State::V1 { checkpoints } = serde_json::from_reader(io::BufReader::new(f))?;
+        Ok(Some(checkpoints))
+    }
+
+    fn read_legacy_checkpoints(&mut self) {
+        for path in glob(&self.legacy_glob_string).unwrap().flatten() {
+            let (fng, pos) = match self.decode_legacy(&path) {
+                Some(decoded) => decoded,
+                None => {
+                    warn!(message = "Ignoring unrecognized legacy checkpoint.", ?path);
+                    continue;
+                }
+            };
+            let modified = modified_time(&path);
+            info!(message = "Migrating legacy checkpoint.", fingerprint = %fng, position = %pos);
+            self.checkpoints.insert(
+                fng,
+                Checkpoint {
+                    fingerprint: fng,
+                    position: pos,
+                    path: None,
+                    modified,
+                },
+            );
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> DateTime<Utc> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now())
+}
+
+/// Makes the rename of the state file durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<(), io::Error> {
+    match path.parent() {
+        Some(parent) => fs::File::open(parent)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+/// Directories can't be opened, let alone synced, on Windows.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<(), io::Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Checkpointer, STATE_FILE_NAME};
+    use crate::{FileFingerprint, FilePosition};
+    use chrono::{DateTime, Utc};
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpointer_basics() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        let mut chkptr = Checkpointer::new(&data_dir.path());
+        assert_eq!(
+            chkptr.decode_legacy(&chkptr.encode_legacy(fingerprint, position)),
+            Some((fingerprint, position))
+        );
+        chkptr.set_checkpoint(fingerprint, position, Path::new("/var/log/file.log"));
+        assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+    }
+
+    #[test]
+    fn test_checkpointer_restart() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            chkptr.set_checkpoint(fingerprint, position, Path::new("/var/log/file.log"));
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            chkptr.write_checkpoints().unwrap();
+        }
+        assert!(data_dir.path().join(STATE_FILE_NAME).exists());
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            assert_eq!(chkptr.get_checkpoint(fingerprint), None);
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            assert_eq!(
+                chkptr.checkpoints[&fingerprint].path.as_deref(),
+                Some(Path::new("/var/log/file.log"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_checkpointer_uses_file_modified_time() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let data_dir = tempdir().unwrap();
+        let path = data_dir.path().join("file.log");
+        fs::write(&path, "line\n").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut chkptr = Checkpointer::new(&data_dir.path());
+        chkptr.set_checkpoint(fingerprint, 5, &path);
+        assert_eq!(
+            chkptr.checkpoints[&fingerprint].modified,
+            DateTime::<Utc>::from(modified)
+        );
+    }
+
+    #[test]
+    fn test_checkpointer_ignore_before() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let data_dir = tempdir().unwrap();
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            chkptr.set_checkpoint(fingerprint, 1234, Path::new("/var/log/file.log"));
+            chkptr.write_checkpoints().unwrap();
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            chkptr.read_checkpoints(Some(SystemTime::now() + Duration::from_secs(60)));
+            assert_eq!(chkptr.get_checkpoint(fingerprint), None);
+        }
+    }
+
+    #[test]
+    fn test_checkpointer_legacy_migration() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        {
+            let chkptr = Checkpointer::new(&data_dir.path());
+            fs::create_dir_all(&chkptr.legacy_directory).unwrap();
+            fs::File::create(chkptr.encode_legacy(fingerprint, position)).unwrap();
+            fs::File::create(chkptr.legacy_directory.join("garbage")).unwrap();
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            chkptr.write_checkpoints().unwrap();
+            assert!(!chkptr.legacy_directory.exists());
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path());
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+        }
+    }
+}
@@ -1,16 +1,15 @@
-use crate::{file_watcher::FileWatcher, FileFingerprint, FilePosition};
+use crate::{checkpointer::Checkpointer, file_watcher::FileWatcher, FileFingerprint, FilePosition};
 use bytes::Bytes;
 use futures::{
     executor::block_on,
     future::{select, Either},
     stream, Future, Sink, SinkExt,
 };
-use glob::glob;
 use indexmap::IndexMap;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fs::{self, remove_file, File};
 use std::io::{self, Read, Seek, Write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::time::{self, Duration};
 use tokio::time::delay_for;
 use tracing::field;
@@ -64,10 +63,10 @@ where
         self,
         mut chans: C,
         mut shutdown: impl Future + Unpin,
-    ) -> Result<Shutdown, <C as Sink<(Bytes, String)>>::Error>
+    ) -> Result<Shutdown, <C as Sink<Line>>::Error>
     where
-        C: Sink<(Bytes, String)> + Unpin,
-        <C as Sink<(Bytes, String)>>::Error: std::error::Error,
+        C: Sink<Line> + Unpin,
+        <C as Sink<Line>>::Error: std::error::Error,
     {
         let mut line_buffer = Vec::new();
         let mut fingerprint_buffer = Vec::new();
@@ -126,7 +125,7 @@ where
                 // Schedule the next glob time.
                 next_glob_time = now_time.checked_add(self.glob_minimum_cooldown).unwrap();
 
-                // Write any stored checkpoints (uses glob to find old checkpoints).
+                // Write any stored checkpoints.
                 checkpointer
                     .write_checkpoints()
                     .map_err(|e| warn!("Problem writing checkpoints: {:?}", e))
@@ -199,7 +198,12 @@ where
                 }
 
                 let mut bytes_read: usize = 0;
-                while let Ok(sz) = watcher.read_line(&mut line_buffer, self.max_line_bytes) {
+                loop {
+                    let sz = match watcher.read_line(&mut line_buffer, self.max_line_bytes) {
+                        Ok(sz) => sz,
+                        Err(_) => break,
+                    };
+                    let offset = watcher.get_line_position();
                     if sz > 0 {
                         trace!(
                             message = "Read bytes.",
@@ -210,10 +214,18 @@ where
                         bytes_read += sz;
 
                         if !line_buffer.is_empty() {
-                            lines.push((
-                                line_buffer.clone().into(),
-                                watcher.path.to_str().expect("not a valid path").to_owned(),
-                            ));
+                            lines.push(Line {
+                                text: line_buffer.clone().into(),
+                                filename: watcher
+                                    .path
+                                    .to_str()
+                                    .expect("not a valid path")
+                                    .to_owned(),
+                                file_id,
+                                offset,
+                                device: watcher.device(),
+                                inode: watcher.inode(),
+                            });
                             line_buffer.clear();
                         }
                     } else {
@@ -243,7 +255,11 @@ where
                 }
                 if bytes_read > 0 {
                     global_bytes_read = global_bytes_read.saturating_add(bytes_read);
-                    checkpointer.set_checkpoint(file_id, watcher.get_file_position());
+                    checkpointer.set_checkpoint(
+                        file_id,
+                        watcher.get_file_position(),
+                        &watcher.path,
+                    );
                 }
                 // Do not move on to newer files if we are behind on an older file
                 if self.oldest_first && maxed_out_reading_single_file {
@@ -324,6 +340,25 @@ where
     }
 }
 
+/// A line read from a file, along with the information about where it was
+/// read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// The line contents, without the trailing newline.
+    pub text: Bytes,
+    /// The path of the file the line was read from.
+    pub filename: String,
+    /// The fingerprint of the file the line was read from.
+    pub file_id: FileFingerprint,
+    /// The byte offset in the file the line was read at. For compressed
+    /// files this is the offset into the uncompressed data.
+    pub offset: FilePosition,
+    /// The device number of the file the line was read from.
+    pub device: u64,
+    /// The inode number of the file the line was read from.
+    pub inode: u64,
+}
+
 /// A sentinel type to signal that file server was gracefully shut down.
 ///
 /// The purpose of this type is to clarify the semantics of the result values
@@ -332,64 +367,6 @@ where
 #[derive(Debug)]
 pub struct Shutdown;
 
-pub struct Checkpointer {
-    directory: PathBuf,
-    glob_string: String,
-    checkpoints: HashMap<FileFingerprint, FilePosition>,
-}
-
-impl Checkpointer {
-    pub fn new(data_dir: &Path) -> Checkpointer {
-        let directory = data_dir.join("checkpoints");
-        let glob_string = directory.join("*").to_string_lossy().into_owned();
-        Checkpointer {
-            directory,
-            glob_string,
-            checkpoints: HashMap::new(),
-        }
-    }
-
-    fn encode(&self, fng: FileFingerprint, pos: FilePosition) -> PathBuf {
-        self.directory.join(format!("{:x}.{}", fng, pos))
-    }
-    fn decode(&self, path: &Path) -> (FileFingerprint, FilePosition) {
-        let file_name = &path.file_name().unwrap().to_string_lossy();
-        scan_fmt!(file_name, "{x}.{}", [hex FileFingerprint], FilePosition).unwrap()
-    }
-
-    pub fn set_checkpoint(&mut self, fng: FileFingerprint, pos: FilePosition) {
-        self.checkpoints.insert(fng, pos);
-    }
-
-    pub fn get_checkpoint(&self, fng: FileFingerprint) -> Option<FilePosition> {
-        self.checkpoints.get(&fng).cloned()
-    }
-
-    pub fn write_checkpoints(&mut self) -> Result<(), io::Error> {
-        fs::remove_dir_all(&self.directory).ok();
-        fs::create_dir_all(&self.directory)?;
-        for (&fng, &pos) in self.checkpoints.iter() {
-            fs::File::create(self.encode(fng, pos))?;
-        }
-        Ok(())
-    }
-
-    pub fn read_checkpoints(&mut self, ignore_before: Option<time::SystemTime>) {
-        for path in glob(&self.glob_string).unwrap().flatten() {
-            if let Some(ignore_before) = ignore_before {
-                if let Ok(Ok(modified)) = fs::metadata(&path).map(|metadata| metadata.modified()) {
-                    if modified < ignore_before {
-                        fs::remove_file(path).ok();
-                        continue;
-                    }
-                }
-            }
-            let (fng, pos) = self.decode(&path);
-            self.checkpoints.insert(fng, pos);
-        }
-    }
-}
-
 #[derive(Clone)]
 pub enum Fingerprinter {
     Checksum {
@@ -482,7 +459,7 @@ fn fingerprinter_read_until(mut r: impl Read, delim: u8, mut buf: &mut [u8]) ->
 
 #[cfg(test)]
 mod test {
-    use super::{Checkpointer, FileFingerprint, FilePosition, Fingerprinter};
+    use super::Fingerprinter;
     use std::fs;
     use tempfile::tempdir;
 
@@ -624,37 +601,4 @@ mod test {
                 .unwrap()
         );
     }
-
-    #[test]
-    fn test_checkpointer_basics() {
-        let fingerprint: FileFingerprint = 0x1234567890abcdef;
-        let position: FilePosition = 1234;
-        let data_dir = tempdir().unwrap();
-        let mut chkptr = Checkpointer::new(&data_dir.path());
-        assert_eq!(
-            chkptr.decode(&chkptr.encode(fingerprint, position)),
-            (fingerprint, position)
-        );
-        chkptr.set_checkpoint(fingerprint, position);
-        assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
-    }
-
-    #[test]
-    fn test_checkpointer_restart() {
-        let fingerprint: FileFingerprint = 0x1234567890abcdef;
-        let position: FilePosition = 1234;
-        let data_dir = tempdir().unwrap();
-        {
-            let mut chkptr = Checkpointer::new(&data_dir.path());
-            chkptr.set_checkpoint(fingerprint, position);
-            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
-            chkptr.write_checkpoints().ok();
-        }
-        {
-            let mut chkptr = Checkpointer::new(&data_dir.path());
-            assert_eq!(chkptr.get_checkpoint(fingerprint), None);
-            chkptr.read_checkpoints(None);
-            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
-        }
-    }
 }
@@ -1,9 +1,10 @@
 use crate::FilePosition;
+use bzip2::bufread::MultiBzDecoder;
 use flate2::bufread::MultiGzDecoder;
 use std::{
     fs::{self, File},
-    io::{self, BufRead, Seek},
-    path::PathBuf,
+    io::{self, BufRead, Read, Seek},
+    path::{Path, PathBuf},
     thread,
     time::{Duration, Instant, SystemTime},
 };
@@ -22,6 +23,7 @@ pub struct FileWatcher {
     findable: bool,
     reader: Box<dyn BufRead>,
     file_position: FilePosition,
+    line_position: FilePosition,
     devno: u64,
     inode: u64,
     is_dead: bool,
@@ -43,7 +45,7 @@ impl FileWatcher {
         let f = fs::File::open(&path)?;
         let (devno, ino) = (f.portable_dev()?, f.portable_ino()?);
         let metadata = f.metadata()?;
-        let mut reader = io::BufReader::new(f);
+        let reader = io::BufReader::new(f);
 
         let too_old = if let (Some(ignore_before), Ok(modified_time)) =
             (ignore_before, metadata.modified())
@@ -53,28 +55,7 @@ impl FileWatcher {
             false
         };
 
-        let (reader, file_position): (Box<dyn BufRead>, FilePosition) = if is_gzipped(&mut reader)?
-        {
-            if file_position != 0 || too_old {
-                // We can't accurately seek into gzipped files without manually scanning through
-                // the entire thing, so for now we simply refuse to read gzipped files for which we
-                // already have a stored file position from a previous run.
-                debug!(
-                    message = "Not re-reading gzipped file with existing stored offset",
-                    ?path,
-                    %file_position
-                );
-                (Box::new(null_reader()), file_position)
-            } else {
-                (Box::new(io::BufReader::new(MultiGzDecoder::new(reader))), 0)
-            }
-        } else if too_old {
-            let pos = reader.seek(io::SeekFrom::End(0)).unwrap();
-            (Box::new(reader), pos)
-        } else {
-            let pos = reader.seek(io::SeekFrom::Start(file_position)).unwrap();
-            (Box::new(reader), pos)
-        };
+        let (reader, file_position) = open_reader(&path, reader, file_position, too_old)?;
 
         let ts = metadata
             .modified()
@@ -88,6 +69,7 @@ impl FileWatcher {
             findable: true,
             reader,
             file_position,
+            line_position: file_position,
             devno,
             inode: ino,
             is_dead: false,
@@ -99,18 +81,10 @@ impl FileWatcher {
     pub fn update_path(&mut self, path: PathBuf) -> io::Result<()> {
         let file_handle = File::open(&path)?;
         if (file_handle.portable_dev()?, file_handle.portable_ino()?) != (self.devno, self.inode) {
-            let mut reader = io::BufReader::new(fs::File::open(&path)?);
-            let gzipped = is_gzipped(&mut reader)?;
-            let new_reader: Box<dyn BufRead> = if gzipped {
-                if self.file_position != 0 {
-                    Box::new(null_reader())
-                } else {
-                    Box::new(io::BufReader::new(MultiGzDecoder::new(reader)))
-                }
-            } else {
-                reader.seek(io::SeekFrom::Start(self.file_position))?;
-                Box::new(reader)
-            };
+            let reader = io::BufReader::new(fs::File::open(&path)?);
+            let (new_reader, file_position) =
+                open_reader(&path, reader, self.file_position, false)?;
+            self.file_position = file_position;
             self.reader = new_reader;
             self.devno = file_handle.portable_dev()?;
             self.inode = file_handle.portable_ino()?;
@@ -139,6 +113,20 @@ impl FileWatcher {
         self.file_position
     }
 
+    /// The position the line last read started at. Lines that were too long
+    /// and discarded along the way are skipped.
+    pub fn get_line_position(&self) -> FilePosition {
+        self.line_position
+    }
+
+    pub fn device(&self) -> u64 {
+        self.devno
+    }
+
+    pub fn inode(&self) -> u64 {
+        self.inode
+    }
+
     /// Read a single line from the underlying file
     ///
     /// This function will attempt to read a new line from its file, blocking,
@@ -151,7 +139,15 @@ impl FileWatcher {
         buffer.clear();
         let reader = &mut self.reader;
         let file_position = &mut self.file_position;
-        match read_until_with_max_size(reader, file_position, b'\n', &mut buffer, max_size) {
+        let line_position = &mut self.line_position;
+        match read_until_with_max_size(
+            reader,
+            file_position,
+            line_position,
+            b'\n',
+            &mut buffer,
+            max_size,
+        ) {
             Ok(sz) => {
                 if sz > 0 {
                     self.track_read_success()
@@ -190,9 +186,82 @@ impl FileWatcher {
     }
 }
 
-fn is_gzipped(r: &mut io::BufReader<fs::File>) -> io::Result<bool> {
-    let header_bytes = r.fill_buf()?;
-    Ok(header_bytes.starts_with(&[0x1f, 0x8b]))
+/// The compression formats detected by their magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    fn detect(r: &mut io::BufReader<fs::File>) -> io::Result<Option<Compression>> {
+        let header_bytes = r.fill_buf()?;
+        Ok(if header_bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if header_bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if header_bytes.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        })
+    }
+
+    fn decoder(self, reader: io::BufReader<fs::File>) -> io::Result<Box<dyn BufRead>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(io::BufReader::new(MultiGzDecoder::new(reader))),
+            Compression::Zstd => Box::new(io::BufReader::new(
+                zstd::stream::read::Decoder::with_buffer(reader)?,
+            )),
+            Compression::Bzip2 => Box::new(io::BufReader::new(MultiBzDecoder::new(reader))),
+        })
+    }
+}
+
+/// Opens a reader positioned at `file_position`, transparently decompressing
+/// the file if it's compressed.
+///
+/// For compressed files the position is an offset into the uncompressed data,
+/// since we can't seek into the compressed stream. Resuming is done by
+/// decompressing and discarding everything up to the stored position.
+fn open_reader(
+    path: &Path,
+    mut reader: io::BufReader<fs::File>,
+    file_position: FilePosition,
+    too_old: bool,
+) -> io::Result<(Box<dyn BufRead>, FilePosition)> {
+    match Compression::detect(&mut reader)? {
+        Some(compression) if too_old => {
+            debug!(
+                message = "Not reading old compressed file",
+                ?path,
+                ?compression
+            );
+            Ok((Box::new(null_reader()), file_position))
+        }
+        Some(compression) => {
+            let mut decoder = compression.decoder(reader)?;
+            let skipped = io::copy(&mut (&mut decoder).take(file_position), &mut io::sink())?;
+            if skipped < file_position {
+                debug!(
+                    message = "Compressed file is shorter than stored offset; reading from its end",
+                    ?path,
+                    ?compression,
+                    %file_position
+                );
+            }
+            Ok((decoder, skipped))
+        }
+        None if too_old => {
+            let pos = reader.seek(io::SeekFrom::End(0))?;
+            Ok((Box::new(reader), pos))
+        }
+        None => {
+            let pos = reader.seek(io::SeekFrom::Start(file_position))?;
+            Ok((Box::new(reader), pos))
+        }
+    }
 }
 
 fn null_reader() -> impl BufRead {
@@ -205,12 +274,14 @@ fn null_reader() -> impl BufRead {
 fn read_until_with_max_size<R: BufRead + ?Sized>(
     r: &mut R,
     p: &mut FilePosition,
+    line_start: &mut FilePosition,
     delim: u8,
     buf: &mut Vec<u8>,
     max_size: usize,
 ) -> io::Result<usize> {
     let mut total_read = 0;
     let mut discarding = false;
+    *line_start = *p;
     let mut already_slept = false;
     loop {
         let available = match r.fill_buf() {
@@ -251,6 +322,7 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
         if done && discarding {
             discarding = false;
             buf.clear();
+            *line_start = *p;
         } else if done || (used == 0 && already_slept) {
             return Ok(total_read);
         } else if used == 0 {
@@ -265,53 +337,129 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
 
 #[cfg(test)]
 mod test {
-    use super::read_until_with_max_size;
-    use std::io::Cursor;
+    use super::{read_until_with_max_size, FileWatcher};
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+    use std::{
+        fs::File,
+        io::{Cursor, Write},
+        path::Path,
+    };
 
     #[test]
     fn test_read_until_with_max_size() {
         let mut buf = Cursor::new(&b"12"[..]);
         let mut pos = 0;
+        let mut start = 0;
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'3', &mut v, 1000).unwrap();
         assert_eq!(pos, 2);
         assert_eq!(p, 2);
         assert_eq!(v, b"12");
 
         let mut buf = Cursor::new(&b"1233"[..]);
         let mut pos = 0;
+        let mut start = 0;
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'3', &mut v, 1000).unwrap();
         assert_eq!(pos, 3);
         assert_eq!(p, 3);
         assert_eq!(v, b"12");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'3', &mut v, 1000).unwrap();
         assert_eq!(pos, 4);
         assert_eq!(p, 1);
         assert_eq!(v, b"");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'3', &mut v, 1000).unwrap();
         assert_eq!(pos, 4);
         assert_eq!(p, 0);
-        assert_eq!(v, []);
+        assert_eq!(v, Vec::<u8>::new());
 
         let mut buf = Cursor::new(&b"short\nthis is too long\nexact size\n11 eleven11\n"[..]);
         let mut pos = 0;
+        let mut start = 0;
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'\n', &mut v, 10).unwrap();
         assert_eq!(pos, 6);
         assert_eq!(p, 6);
         assert_eq!(v, b"short");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'\n', &mut v, 10).unwrap();
         assert_eq!(pos, 34);
+        assert_eq!(start, 23);
         assert_eq!(p, 28);
         assert_eq!(v, b"exact size");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, &mut start, b'\n', &mut v, 10).unwrap();
         assert_eq!(pos, 46);
         assert_eq!(p, 12);
-        assert_eq!(v, []);
+        assert_eq!(v, Vec::<u8>::new());
+    }
+
+    fn read_all_lines(watcher: &mut FileWatcher) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        let mut buffer = Vec::new();
+        while watcher.read_line(&mut buffer, 1000).unwrap() > 0 {
+            lines.push(buffer.clone());
+        }
+        lines
+    }
+
+    fn check_resume_compressed(path: &Path) {
+        let mut watcher = FileWatcher::new(path.to_path_buf(), 0, None).unwrap();
+        let lines = read_all_lines(&mut watcher);
+        assert_eq!(lines, vec![b"first".to_vec(), b"second".to_vec()]);
+        assert_eq!(watcher.get_file_position(), 13);
+
+        // Resuming from the uncompressed offset of the second line.
+        let mut watcher = FileWatcher::new(path.to_path_buf(), 6, None).unwrap();
+        assert_eq!(read_all_lines(&mut watcher), vec![b"second".to_vec()]);
+        assert_eq!(watcher.get_file_position(), 13);
+
+        // Resuming from past the end of the file.
+        let watcher = FileWatcher::new(path.to_path_buf(), 100, None).unwrap();
+        assert_eq!(watcher.get_file_position(), 13);
+    }
+
+    #[test]
+    fn test_resume_gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.log.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b"first\nsecond\n").unwrap();
+        encoder.finish().unwrap();
+
+        check_resume_compressed(&path);
+    }
+
+    #[test]
+    fn test_resume_zstd() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.log.zst");
+        let mut encoder =
+            zstd::stream::write::Encoder::new(File::create(&path).unwrap(), 0).unwrap();
+        encoder.write_all(b"first\nsecond\n").unwrap();
+        encoder.finish().unwrap();
+
+        check_resume_compressed(&path);
+    }
+
+    #[test]
+    fn test_resume_bzip2() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.log.bz2");
+        let mut encoder = BzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(b"first\nsecond\n").unwrap();
+        encoder.finish().unwrap();
+
+        check_resume_compressed(&path);
     }
 }
@@ -3,15 +3,16 @@ extern crate scan_fmt;
 #[macro_use]
 extern crate tracing;
 
+mod checkpointer;
 mod file_server;
 mod file_watcher;
 mod metadata_ext;
 pub mod paths_provider;
 
-pub use self::file_server::{FileServer, Fingerprinter, Shutdown as FileServerShutdown};
+pub use self::file_server::{FileServer, Fingerprinter, Line, Shutdown as FileServerShutdown};
 
-type FileFingerprint = u64;
-type FilePosition = u64;
+pub type FileFingerprint = u64;
+pub type FilePosition = u64;
 
 #[cfg(test)]
 mod test {
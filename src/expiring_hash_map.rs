@@ -80,6 +80,23 @@ where
         Some((value, expired))
     }
 
+    /// Return the number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Iterate over the keys and values of the map, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter().map(|(k, (v, _))| (k, v))
+    }
+
+    /// Remove all the keys, returning their values, without waiting for
+    /// their deadlines.
+    pub fn drain(&mut self) -> impl Iterator<Item = (K, V)> + '_ {
+        self.expiration_queue.clear();
+        self.map.drain().map(|(k, (v, _))| (k, v))
+    }
+
     /// Check whether the [`ExpiringHashMap`] is empty.
     /// If it's empty, the `next_expired` function immediately resolves to
     /// [`None`]. Be aware that this may cause a spinlock behaviour if the
@@ -290,4 +307,26 @@ mod tests {
         let mut fut = task::spawn(map.next_expired());
         assert_pending!(fut.poll());
     }
+
+    #[tokio::test]
+    async fn drain_removes_all_items() {
+        let mut map = ExpiringHashMap::<String, String>::default();
+
+        let ttl = Duration::from_secs(1000);
+        map.insert("key1".to_owned(), "val1".to_owned(), ttl);
+        map.insert("key2".to_owned(), "val2".to_owned(), ttl);
+        assert_eq!(map.len(), 2);
+
+        let mut drained = map.drain().collect::<Vec<_>>();
+        drained.sort();
+        assert_eq!(
+            drained,
+            vec![
+                ("key1".to_owned(), "val1".to_owned()),
+                ("key2".to_owned(), "val2".to_owned())
+            ]
+        );
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+    }
 }
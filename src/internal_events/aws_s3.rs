@@ -0,0 +1,57 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct S3MultipartUploadCompleted<'a> {
+    pub key: &'a str,
+    pub parts: usize,
+    pub byte_size: u64,
+}
+
+impl<'a> InternalEvent for S3MultipartUploadCompleted<'a> {
+    fn emit_logs(&self) {
+        debug!(
+            message = "Completed multipart upload.",
+            key = %self.key,
+            parts = %self.parts,
+            byte_size = %self.byte_size,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "objects_completed", 1,
+            "component_kind" => "sink",
+            "component_type" => "aws_s3",
+        );
+        counter!(
+            "bytes_processed", self.byte_size,
+            "component_kind" => "sink",
+            "component_type" => "aws_s3",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct S3MultipartUploadAborted<'a> {
+    pub key: &'a str,
+    pub reason: &'a crate::Error,
+}
+
+impl<'a> InternalEvent for S3MultipartUploadAborted<'a> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Aborted multipart upload; buffered data for the object is dropped.",
+            key = %self.key,
+            reason = %self.reason,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "uploads_aborted", 1,
+            "component_kind" => "sink",
+            "component_type" => "aws_s3",
+        );
+    }
+}
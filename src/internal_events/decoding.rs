@@ -0,0 +1,27 @@
+use super::InternalEvent;
+use crate::sources::util::decoding::DecodeError;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct DecoderParseFailed<'a> {
+    pub component_type: &'static str,
+    pub error: &'a DecodeError,
+}
+
+impl InternalEvent for DecoderParseFailed<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Failed to decode frame, dropping it.",
+            error = %self.error,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "decoding_errors", 1,
+            "component_kind" => "source",
+            "component_type" => self.component_type,
+        );
+    }
+}
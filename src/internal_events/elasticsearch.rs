@@ -49,3 +49,53 @@ impl InternalEvent for ElasticSearchMissingKeys {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct ElasticSearchInvalidBulkAction {
+    pub action: String,
+}
+
+impl InternalEvent for ElasticSearchInvalidBulkAction {
+    fn emit_logs(&self) {
+        warn!(
+            message = "invalid bulk action; dropping event.",
+            action = %self.action,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "invalid_bulk_action", 1,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ElasticSearchDocumentRejected<'a> {
+    pub status: u16,
+    pub error_type: &'a str,
+    pub reason: &'a str,
+}
+
+impl<'a> InternalEvent for ElasticSearchDocumentRejected<'a> {
+    fn emit_logs(&self) {
+        error!(
+            message = "document rejected by elasticsearch; dropping it.",
+            status = %self.status,
+            error_type = %self.error_type,
+            reason = %self.reason,
+            rate_limit_secs = 30,
+        )
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "documents_rejected", 1,
+            "component_kind" => "sink",
+            "component_type" => "elasticsearch",
+        );
+    }
+}
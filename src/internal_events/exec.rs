@@ -0,0 +1,87 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct ExecEventReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for ExecEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received one event.");
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "exec",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "exec",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecFailedToSpawn<'a> {
+    pub command: &'a [String],
+    pub error: std::io::Error,
+}
+
+impl<'a> InternalEvent for ExecFailedToSpawn<'a> {
+    fn emit_logs(&self) {
+        error!(message = "unable to spawn command.", command = ?self.command, error = %self.error, rate_limit_secs = 30);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processes_failed", 1,
+            "component_kind" => "source",
+            "component_type" => "exec",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecCommandExited<'a> {
+    pub command: &'a [String],
+    pub pid: u32,
+    pub status: Option<i32>,
+}
+
+impl<'a> InternalEvent for ExecCommandExited<'a> {
+    fn emit_logs(&self) {
+        debug!(message = "command exited.", command = ?self.command, pid = %self.pid, status = ?self.status);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processes_exited", 1,
+            "component_kind" => "source",
+            "component_type" => "exec",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecWaitFailed<'a> {
+    pub command: &'a [String],
+    pub error: std::io::Error,
+}
+
+impl<'a> InternalEvent for ExecWaitFailed<'a> {
+    fn emit_logs(&self) {
+        error!(message = "unable to wait for command to exit.", command = ?self.command, error = %self.error);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processes_failed", 1,
+            "component_kind" => "source",
+            "component_type" => "exec",
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct FluentMessageReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for FluentMessageReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received fluent message.", byte_size = %self.byte_size);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "fluent",
+        );
+        counter!("bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "fluent",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct FluentMessageDecodeError<'a> {
+    pub error: &'a rmpv::decode::Error,
+}
+
+impl<'a> InternalEvent for FluentMessageDecodeError<'a> {
+    fn emit_logs(&self) {
+        error!(message = "error decoding fluent message.", error = %self.error, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("decode_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "fluent",
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct HostMetricsEventsReceived {
+    pub count: usize,
+}
+
+impl InternalEvent for HostMetricsEventsReceived {
+    fn emit_logs(&self) {
+        debug!(message = "scraped host metrics.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("events_processed", self.count as u64,
+            "component_kind" => "source",
+            "component_type" => "host_metrics",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct HostMetricsCollectFailed<'a, E> {
+    pub collector: &'static str,
+    pub error: &'a E,
+}
+
+impl<'a, E: std::fmt::Debug + std::fmt::Display> InternalEvent for HostMetricsCollectFailed<'a, E> {
+    fn emit_logs(&self) {
+        error!(message = "failed to collect host metrics.", collector = %self.collector, error = %self.error, rate_limit_secs = 60);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("collect_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "host_metrics",
+            "collector" => self.collector,
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct InternalLogsEventsLagged {
+    pub count: u64,
+}
+
+impl InternalEvent for InternalLogsEventsLagged {
+    fn emit_logs(&self) {
+        warn!(
+            message = "internal log events were dropped, the source is lagging behind.",
+            count = %self.count,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_dropped", self.count,
+            "component_kind" => "source",
+            "component_type" => "internal_logs",
+        );
+    }
+}
@@ -63,6 +63,25 @@ impl InternalEvent for KafkaEventFailed {
     }
 }
 
+#[derive(Debug)]
+pub struct KafkaStartOffsetLookupFailed {
+    pub error: rdkafka::error::KafkaError,
+}
+
+impl InternalEvent for KafkaStartOffsetLookupFailed {
+    fn emit_logs(&self) {
+        error!(message = "unable to look up start offsets of partitions.", error = %self.error);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "start_offset_lookups_failed", 1,
+            "component_kind" => "source",
+            "component_type" => "kafka",
+        );
+    }
+}
+
 #[derive(Debug)]
 pub struct KafkaKeyExtractionFailed<'a> {
     pub key_field: &'a str,
@@ -0,0 +1,67 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct LokiOutOfOrderEventDropped;
+
+impl InternalEvent for LokiOutOfOrderEventDropped {
+    fn emit_logs(&self) {
+        warn!(
+            message = "received out-of-order event; dropping event.",
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "out_of_order_events_dropped", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct LokiOutOfOrderEventRewritten;
+
+impl InternalEvent for LokiOutOfOrderEventRewritten {
+    fn emit_logs(&self) {
+        debug!(
+            message = "received out-of-order event, rewriting timestamp.",
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "out_of_order_events_rewritten", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct LokiTenantRenderFailed<'a> {
+    pub missing_keys: &'a [Atom],
+}
+
+impl<'a> InternalEvent for LokiTenantRenderFailed<'a> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "failed to render tenant_id template; dropping event.",
+            missing_keys = ?self.missing_keys,
+            rate_limit_secs = 30,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "processing_errors", 1,
+            "component_kind" => "sink",
+            "component_type" => "loki",
+            "error_type" => "render_error",
+        );
+    }
+}
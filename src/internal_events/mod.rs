@@ -1,11 +1,23 @@
 mod add_fields;
 mod aws_kinesis_streams;
+#[cfg(feature = "sinks-aws_s3")]
+mod aws_s3;
 mod blackhole;
+#[cfg(feature = "sources-decoding")]
+mod decoding;
 #[cfg(feature = "sources-docker")]
 mod docker;
 mod elasticsearch;
+#[cfg(feature = "sources-exec")]
+mod exec;
 mod file;
+#[cfg(feature = "sources-fluent")]
+mod fluent;
+#[cfg(all(target_os = "linux", feature = "sources-host_metrics"))]
+mod host_metrics;
 mod http;
+#[cfg(feature = "sources-internal_logs")]
+mod internal_logs;
 #[cfg(all(unix, feature = "sources-journald"))]
 mod journald;
 mod json;
@@ -13,6 +25,8 @@ mod json;
 mod kafka;
 #[cfg(feature = "sources-kubernetes-logs")]
 mod kubernetes_logs;
+#[cfg(feature = "sinks-loki")]
+mod loki;
 #[cfg(feature = "transforms-lua")]
 mod lua;
 #[cfg(feature = "sources-prometheus")]
@@ -35,12 +49,24 @@ pub mod kubernetes;
 
 pub use self::add_fields::*;
 pub use self::aws_kinesis_streams::*;
+#[cfg(feature = "sinks-aws_s3")]
+pub use self::aws_s3::*;
 pub use self::blackhole::*;
+#[cfg(feature = "sources-decoding")]
+pub use self::decoding::*;
 #[cfg(feature = "sources-docker")]
 pub use self::docker::*;
 pub use self::elasticsearch::*;
+#[cfg(feature = "sources-exec")]
+pub use self::exec::*;
 pub use self::file::*;
+#[cfg(feature = "sources-fluent")]
+pub use self::fluent::*;
+#[cfg(all(target_os = "linux", feature = "sources-host_metrics"))]
+pub use self::host_metrics::*;
 pub use self::http::*;
+#[cfg(feature = "sources-internal_logs")]
+pub use self::internal_logs::*;
 #[cfg(all(unix, feature = "sources-journald"))]
 pub(crate) use self::journald::*;
 pub use self::json::*;
@@ -48,6 +74,8 @@ pub use self::json::*;
 pub use self::kafka::*;
 #[cfg(feature = "sources-kubernetes-logs")]
 pub use self::kubernetes_logs::*;
+#[cfg(feature = "sinks-loki")]
+pub use self::loki::*;
 #[cfg(feature = "transforms-lua")]
 pub use self::lua::*;
 #[cfg(feature = "sources-prometheus")]
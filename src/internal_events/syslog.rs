@@ -60,3 +60,22 @@ impl InternalEvent for SyslogUdpUtf8Error {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct SyslogParseFailed {
+    pub byte_size: usize,
+    pub dropped: bool,
+}
+
+impl InternalEvent for SyslogParseFailed {
+    fn emit_logs(&self) {
+        warn!(message = "unable to parse line as syslog.", byte_size = %self.byte_size, dropped = %self.dropped, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("parse_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "syslog",
+        );
+    }
+}
@@ -20,7 +20,7 @@ impl InternalEvent for UnixSocketConnectionEstablished<'_> {
 
 #[derive(Debug)]
 pub struct UnixSocketConnectionFailure<'a> {
-    pub error: tokio::io::Error,
+    pub error: crate::tls::TlsError,
     pub path: &'a std::path::Path,
 }
 
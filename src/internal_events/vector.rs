@@ -64,3 +64,72 @@ impl InternalEvent for VectorProtoDecodeError {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct VectorEventBatchSent {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for VectorEventBatchSent {
+    fn emit_logs(&self) {
+        trace!(message = "batch acknowledged.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", self.count as u64,
+            "component_kind" => "sink",
+            "component_type" => "vector",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "sink",
+            "component_type" => "vector",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct VectorEventBatchReceived {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for VectorEventBatchReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received batch.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", self.count as u64,
+            "component_kind" => "source",
+            "component_type" => "vector",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "vector",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct VectorDecompressionError {
+    pub error: std::io::Error,
+}
+
+impl InternalEvent for VectorDecompressionError {
+    fn emit_logs(&self) {
+        error!(message = "failed to decompress events.", error = %self.error, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "decompression_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "vector",
+        );
+    }
+}
@@ -16,6 +16,7 @@ where
 {
     inner: WriteHandle<String, Value<T>>,
     debounced_flush: Option<Debounce>,
+    key: KeyFn,
 }
 
 impl<T> Writer<T>
@@ -23,10 +24,19 @@ where
     T: Metadata<Ty = ObjectMeta> + Send,
 {
     /// Take a [`WriteHandle`], initialize it and return it wrapped with
-    /// [`Self`].
+    /// [`Self`]. Objects are keyed by their uid.
     pub fn new(
+        inner: WriteHandle<String, Value<T>>,
+        flush_debounce_timeout: Option<Duration>,
+    ) -> Self {
+        Self::with_key(inner, flush_debounce_timeout, by_uid)
+    }
+
+    /// Like [`Self::new`], but objects are keyed by the specified function.
+    pub fn with_key(
         mut inner: WriteHandle<String, Value<T>>,
         flush_debounce_timeout: Option<Duration>,
+        key: KeyFn,
     ) -> Self {
         // Prepare inner.
         inner.purge();
@@ -38,6 +48,7 @@ where
         Self {
             inner,
             debounced_flush,
+            key,
         }
     }
 
@@ -65,22 +76,24 @@ where
     type Item = T;
 
     async fn add(&mut self, item: Self::Item) {
-        if let Some((key, value)) = kv(item) {
+        if let Some((key, value)) = kv(item, self.key) {
             self.inner.insert(key, value);
             self.debounced_flush();
         }
     }
 
     async fn update(&mut self, item: Self::Item) {
-        if let Some((key, value)) = kv(item) {
+        if let Some((key, value)) = kv(item, self.key) {
             self.inner.update(key, value);
             self.debounced_flush();
         }
     }
 
     async fn delete(&mut self, item: Self::Item) {
-        if let Some((key, _value)) = kv(item) {
-            self.inner.empty(key);
+        if let Some((key, value)) = kv(item, self.key) {
+            // Only remove this object, in case another one has since been
+            // stored under the same key.
+            self.inner.remove(key, value);
             self.debounced_flush();
         }
     }
@@ -118,10 +131,23 @@ where
 /// An alias to the value used at [`evmap`].
 pub type Value<T> = Box<HashValue<T>>;
 
+/// A function to extract the key to store an object under from its metadata.
+pub type KeyFn = fn(&ObjectMeta) -> Option<&str>;
+
+/// Keys objects by their uid.
+pub fn by_uid(metadata: &ObjectMeta) -> Option<&str> {
+    metadata.uid.as_deref()
+}
+
+/// Keys objects by their name, for cluster-scoped resources.
+pub fn by_name(metadata: &ObjectMeta) -> Option<&str> {
+    metadata.name.as_deref()
+}
+
 /// Build a key value pair for using in [`evmap`].
-fn kv<T: Metadata<Ty = ObjectMeta>>(object: T) -> Option<(String, Value<T>)> {
+fn kv<T: Metadata<Ty = ObjectMeta>>(object: T, key: KeyFn) -> Option<(String, Value<T>)> {
     let value = Box::new(HashValue::new(object));
-    let key = value.uid()?.to_owned();
+    let key = key(value.metadata())?.to_owned();
     Some((key, value))
 }
 
@@ -144,11 +170,19 @@ mod tests {
     #[test]
     fn test_kv() {
         let pod = make_pod("uid");
-        let (key, val) = kv(pod.clone()).unwrap();
+        let (key, val) = kv(pod.clone(), by_uid).unwrap();
         assert_eq!(key, "uid");
         assert_eq!(val, Box::new(HashValue::new(pod)));
     }
 
+    #[test]
+    fn test_kv_by_name() {
+        let mut pod = make_pod("uid");
+        pod.metadata.name = Some("name".to_owned());
+        let (key, _val) = kv(pod, by_name).unwrap();
+        assert_eq!(key, "name");
+    }
+
     #[tokio::test]
     async fn test_without_debounce() {
         let (state_reader, state_writer) = evmap10::new();
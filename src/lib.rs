@@ -35,9 +35,13 @@ pub mod hyper;
 #[cfg(feature = "rdkafka")]
 pub mod kafka;
 pub mod kubernetes;
+#[cfg(any(feature = "sources-file", feature = "sources-docker"))]
+pub mod line_agg;
 pub mod list;
 pub mod metrics;
 pub(crate) mod pipeline;
+#[cfg(any(feature = "sources-vector", feature = "sinks-vector"))]
+pub mod proto;
 pub mod region;
 pub mod runtime;
 pub mod serde;
@@ -83,24 +83,26 @@ impl Config {
 ///
 /// Provides a `Stream` implementation that reads lines from the `inner` stream
 /// and yields aggregated lines.
+/// Every line carries a context `C`; aggregated lines keep the context of the
+/// first line of the group.
 #[pin_project(project = LineAggProj)]
-pub struct LineAgg<T, K> {
+pub struct LineAgg<T, K, C> {
     /// The stream from which we read the lines.
     #[pin]
     inner: T,
 
     /// The core line aggregation logic.
-    logic: Logic<K>,
+    logic: Logic<K, C>,
 
     /// Stashed lines. When line aggreation results in more than one line being
     /// emitted, we have to stash lines and return them into the stream after
     /// that before doing any other work.
-    stashed: Option<(Bytes, K)>,
+    stashed: Option<(Bytes, K, C)>,
 
     /// Draining queue. We switch to draining mode when we get `None` from
     /// the inner stream. In this mode we stop polling `inner` for new lines
     /// and just flush all the buffered data.
-    draining: Option<Vec<(Bytes, K)>>,
+    draining: Option<Vec<(Bytes, K, C)>>,
 
     /// A queue of keys with expired timeouts.
     expired: VecDeque<K>,
@@ -110,19 +112,20 @@ pub struct LineAgg<T, K> {
 ///
 /// Encapsulates the essential state and the core logic for the line
 /// aggregation algorithm.
-pub struct Logic<K> {
+pub struct Logic<K, C> {
     /// Configuration parameters to use.
     config: Config,
 
     /// Line per key.
     /// Key is usually a filename or other line source identifier.
-    buffers: HashMap<K, BytesMut>,
+    /// The context of the first buffered line is kept along with the data.
+    buffers: HashMap<K, (BytesMut, C)>,
 
     /// A queue of key timeouts.
     timeouts: DelayQueue<K>,
 }
 
-impl<K> Logic<K> {
+impl<K, C> Logic<K, C> {
     /// Create a new `Logic` using the specified `Config`.
     pub fn new(config: Config) -> Self {
         Self {
@@ -133,14 +136,14 @@ impl<K> Logic<K> {
     }
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K)> + Unpin,
+    T: Stream<Item = (Bytes, K, C)> + Unpin,
     K: Hash + Eq + Clone,
 {
     /// Create a new `LineAgg` using the specified `inner` stream and
     /// preconfigured `logic`.
-    pub fn new(inner: T, logic: Logic<K>) -> Self {
+    pub fn new(inner: T, logic: Logic<K, C>) -> Self {
         Self {
             inner,
             logic,
@@ -151,24 +154,25 @@ where
     }
 }
 
-impl<T, K> Stream for LineAgg<T, K>
+impl<T, K, C> Stream for LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K)> + Unpin,
+    T: Stream<Item = (Bytes, K, C)> + Unpin,
     K: Hash + Eq + Clone,
 {
-    /// `Bytes` - the line data; `K` - file name, or other line source.
-    type Item = (Bytes, K);
+    /// `Bytes` - the line data; `K` - file name, or other line source;
+    /// `C` - the context of the (first) line.
+    type Item = (Bytes, K, C);
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
         loop {
             // If we have a stashed line, process it before doing anything else.
-            if let Some((line, src)) = this.stashed.take() {
+            if let Some((line, src, context)) = this.stashed.take() {
                 // Handle the stashed line. If the handler gave us something -
                 // return it, otherwise restart the loop iteration to start
                 // anew. Handler could've stashed another value, continuing to
                 // the new loop iteration handles that.
-                if let Some(val) = Self::handle_line_and_stashing(&mut this, line, src) {
+                if let Some(val) = Self::handle_line_and_stashing(&mut this, line, src, context) {
                     return Poll::Ready(Some(val));
                 }
                 continue;
@@ -189,11 +193,12 @@ where
             }
 
             match this.inner.poll_next_unpin(cx) {
-                Poll::Ready(Some((line, src))) => {
+                Poll::Ready(Some((line, src, context))) => {
                     // Handle the incoming line we got from `inner`. If the
                     // handler gave us something - return it, otherwise continue
                     // with the flow.
-                    if let Some(val) = Self::handle_line_and_stashing(&mut this, line, src) {
+                    if let Some(val) = Self::handle_line_and_stashing(&mut this, line, src, context)
+                    {
                         return Poll::Ready(Some(val));
                     }
                 }
@@ -204,7 +209,7 @@ where
                         this.logic
                             .buffers
                             .drain()
-                            .map(|(k, v)| (v.into(), k))
+                            .map(|(k, (v, context))| (v.into(), k, context))
                             .collect(),
                     );
                 }
@@ -212,8 +217,8 @@ where
                     // We didn't get any lines from `inner`, so we just give
                     // a line from the expired lines queue.
                     if let Some(key) = this.expired.pop_front() {
-                        if let Some(buffered) = this.logic.buffers.remove(&key) {
-                            return Poll::Ready(Some((buffered.freeze(), key)));
+                        if let Some((buffered, context)) = this.logic.buffers.remove(&key) {
+                            return Poll::Ready(Some((buffered.freeze(), key, context)));
                         }
                     }
 
@@ -224,35 +229,36 @@ where
     }
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K)> + Unpin,
+    T: Stream<Item = (Bytes, K, C)> + Unpin,
     K: Hash + Eq + Clone,
 {
     /// Handle line and do stashing of extra emitted lines.
     /// Requires that the `stashed` item is empty (i.e. entry is vacant). This
     /// invariant has to be taken care of by the caller.
     fn handle_line_and_stashing(
-        this: &mut LineAggProj<'_, T, K>,
+        this: &mut LineAggProj<'_, T, K, C>,
         line: Bytes,
         src: K,
-    ) -> Option<(Bytes, K)> {
+        context: C,
+    ) -> Option<(Bytes, K, C)> {
         // Stashed line is always consumed at the start of the `poll`
         // loop before entering this line processing logic. If it's
         // non-empty here - it's a bug.
         debug_assert!(this.stashed.is_none());
-        let val = this.logic.handle_line(line, src)?;
+        let val = this.logic.handle_line(line, src, context)?;
         let val = match val {
             // If we have to emit just one line - that's easy,
             // we just return it.
-            (Emit::One(line), src) => (line, src),
+            (Emit::One((line, context)), src) => (line, src, context),
             // If we have to emit two lines - take the second
             // one and stash it, then return the first one.
             // This way, the stashed line will be returned
             // on the next stream poll.
-            (Emit::Two(line, to_stash), src) => {
-                *this.stashed = Some((to_stash, src.clone()));
-                (line, src)
+            (Emit::Two((line, context), (to_stash, to_stash_context)), src) => {
+                *this.stashed = Some((to_stash, src.clone(), to_stash_context));
+                (line, src, context)
             }
         };
         Some(val)
@@ -260,20 +266,20 @@ where
 }
 
 /// Specifies the amount of lines to emit in response to a single input line.
-/// We have to emit either one or two lines.
-pub enum Emit {
+/// We have to emit either one or two lines, each with its context.
+pub enum Emit<C> {
     /// Emit one line.
-    One(Bytes),
+    One((Bytes, C)),
     /// Emit two lines, in the order they're specified.
-    Two(Bytes, Bytes),
+    Two((Bytes, C), (Bytes, C)),
 }
 
-impl<K> Logic<K>
+impl<K, C> Logic<K, C>
 where
     K: Hash + Eq + Clone,
 {
     /// Handle line, if we have something to output - return it.
-    pub fn handle_line(&mut self, line: Bytes, src: K) -> Option<(Emit, K)> {
+    pub fn handle_line(&mut self, line: Bytes, src: K, context: C) -> Option<(Emit<C>, K)> {
         // Check if we already have the buffered data for the source.
         match self.buffers.entry(src) {
             Entry::Occupied(mut entry) => {
@@ -283,35 +289,41 @@ where
                     // the group.
                     Mode::ContinueThrough => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             None
                         } else {
-                            let (src, buffered) = entry.remove_entry();
-                            Some((Emit::Two(buffered.freeze(), line), src))
+                            let (src, (buffered, buffered_context)) = entry.remove_entry();
+                            Some((
+                                Emit::Two((buffered.freeze(), buffered_context), (line, context)),
+                                src,
+                            ))
                         }
                     }
                     // All consecutive lines matching this pattern, plus one
                     // additional line, are included in the group.
                     Mode::ContinuePast => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             None
                         } else {
-                            let (src, mut buffered) = entry.remove_entry();
+                            let (src, (mut buffered, buffered_context)) = entry.remove_entry();
                             add_next_line(&mut buffered, line);
-                            Some((Emit::One(buffered.freeze()), src))
+                            Some((Emit::One((buffered.freeze(), buffered_context)), src))
                         }
                     }
                     // All consecutive lines not matching this pattern are included
                     // in the group.
                     Mode::HaltBefore => {
                         if condition_matched {
-                            let (src, buffered) = entry.remove_entry();
-                            Some((Emit::Two(buffered.freeze(), line), src))
+                            let (src, (buffered, buffered_context)) = entry.remove_entry();
+                            Some((
+                                Emit::Two((buffered.freeze(), buffered_context), (line, context)),
+                                src,
+                            ))
                         } else {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             None
                         }
@@ -320,11 +332,11 @@ where
                     // matching this pattern, are included in the group.
                     Mode::HaltWith => {
                         if condition_matched {
-                            let (src, mut buffered) = entry.remove_entry();
+                            let (src, (mut buffered, buffered_context)) = entry.remove_entry();
                             add_next_line(&mut buffered, line);
-                            Some((Emit::One(buffered.freeze()), src))
+                            Some((Emit::One((buffered.freeze(), buffered_context)), src))
                         } else {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             None
                         }
@@ -338,11 +350,11 @@ where
                     // Set the timeout and buffer this line.
                     self.timeouts
                         .insert(entry.key().clone(), self.config.timeout);
-                    entry.insert(line.as_ref().into());
+                    entry.insert((line.as_ref().into(), context));
                     None
                 } else {
                     // It's just a regular line we don't really care about.
-                    Some((Emit::One(line), entry.into_key()))
+                    Some((Emit::One((line, context)), entry.into_key()))
                 }
             }
         }
@@ -643,18 +655,26 @@ mod tests {
 
     fn stream_from_lines<'a>(
         lines: &'a [&'static str],
-    ) -> impl Stream<Item = (Bytes, Filename)> + 'a {
-        futures::stream::iter(
-            lines
-                .iter()
-                .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned())),
-        )
+    ) -> impl Stream<Item = (Bytes, Filename, ())> + 'a {
+        futures::stream::iter(lines.iter().map(|line| {
+            (
+                Bytes::from_static(line.as_bytes()),
+                "test.log".to_owned(),
+                (),
+            )
+        }))
     }
 
-    fn assert_results(actual: Vec<(Bytes, Filename)>, expected: &[&'static str]) {
-        let expected_mapped: Vec<(Bytes, Filename)> = expected
+    fn assert_results(actual: Vec<(Bytes, Filename, ())>, expected: &[&'static str]) {
+        let expected_mapped: Vec<(Bytes, Filename, ())> = expected
             .iter()
-            .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned()))
+            .map(|line| {
+                (
+                    Bytes::from_static(line.as_bytes()),
+                    "test.log".to_owned(),
+                    (),
+                )
+            })
             .collect();
 
         assert_eq!(
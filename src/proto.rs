@@ -0,0 +1,5 @@
+//! gRPC services generated from the protobuf definitions in `proto/`.
+
+pub mod vector {
+    tonic::include_proto!("vector");
+}
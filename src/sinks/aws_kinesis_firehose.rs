@@ -1,9 +1,9 @@
 use crate::{
     dns::Resolver,
-    event::{self, Event},
+    event::Event,
     region::RegionOrEndpoint,
     sinks::util::{
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
         retries2::RetryLogic,
         rusoto,
         service2::TowerRequestConfig,
@@ -43,7 +43,7 @@ pub struct KinesisFirehoseSinkConfig {
     pub stream_name: String,
     #[serde(flatten)]
     pub region: RegionOrEndpoint,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
     #[serde(default)]
     pub compression: Compression,
     #[serde(default)]
@@ -60,13 +60,6 @@ lazy_static! {
     };
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Json,
-}
-
 inventory::submit! {
     SinkDescription::new_without_default::<KinesisFirehoseSinkConfig>("aws_kinesis_firehose")
 }
@@ -225,17 +218,13 @@ enum HealthcheckError {
     StreamNamesMismatch { name: String, stream_name: String },
 }
 
-fn encode_event(mut event: Event, encoding: &EncodingConfig<Encoding>) -> Option<Record> {
+fn encode_event(mut event: Event, encoding: &EncodingConfig<StandardEncodings>) -> Option<Record> {
     encoding.apply_rules(&mut event);
-    let log = event.into_log();
-    let data = match encoding.codec() {
-        Encoding::Json => serde_json::to_vec(&log).expect("Error encoding event as json."),
-
-        Encoding::Text => log
-            .get(&event::log_schema().message_key())
-            .map(|v| v.as_bytes().to_vec())
-            .unwrap_or_default(),
-    };
+    let data = encoding
+        .codec()
+        .encode_event(&event)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()?;
 
     let data = Bytes::from(data);
 
@@ -251,7 +240,8 @@ mod tests {
     #[test]
     fn firehose_encode_event_text() {
         let message = "hello world".to_string();
-        let event = encode_event(message.clone().into(), &Encoding::Text.into()).unwrap();
+        let event =
+            encode_event(message.clone().into(), &StandardEncodings::text().into()).unwrap();
 
         assert_eq!(&event.data[..], message.as_bytes());
     }
@@ -261,7 +251,7 @@ mod tests {
         let message = "hello world".to_string();
         let mut event = Event::from(message.clone());
         event.as_mut_log().insert("key", "value");
-        let event = encode_event(event, &Encoding::Json.into()).unwrap();
+        let event = encode_event(event, &StandardEncodings::Json.into()).unwrap();
 
         let map: BTreeMap<String, String> = serde_json::from_slice(&event.data[..]).unwrap();
 
@@ -308,7 +298,7 @@ mod integration_tests {
         let config = KinesisFirehoseSinkConfig {
             stream_name: stream.clone(),
             region: RegionOrEndpoint::with_endpoint("http://localhost:4573".into()),
-            encoding: EncodingConfig::from(Encoding::Json), // required for ES destination w/ localstack
+            encoding: EncodingConfig::from(StandardEncodings::Json), // required for ES destination w/ localstack
             compression: Compression::None,
             batch: BatchConfig {
                 max_events: Some(2),
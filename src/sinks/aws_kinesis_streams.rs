@@ -1,10 +1,10 @@
 use crate::{
     dns::Resolver,
-    event::{self, Event},
+    event::Event,
     internal_events::AwsKinesisStreamsEventSent,
     region::RegionOrEndpoint,
     sinks::util::{
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
         retries2::RetryLogic,
         rusoto,
         service2::TowerRequestConfig,
@@ -48,7 +48,7 @@ pub struct KinesisSinkConfig {
     pub partition_key_field: Option<Atom>,
     #[serde(flatten)]
     pub region: RegionOrEndpoint,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
     #[serde(default)]
     pub compression: Compression,
     #[serde(default)]
@@ -65,13 +65,6 @@ lazy_static! {
     };
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Derivative)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Json,
-}
-
 inventory::submit! {
     SinkDescription::new_without_default::<KinesisSinkConfig>("aws_kinesis_streams")
 }
@@ -246,7 +239,7 @@ enum HealthcheckError {
 fn encode_event(
     mut event: Event,
     partition_key_field: &Option<Atom>,
-    encoding: &EncodingConfig<Encoding>,
+    encoding: &EncodingConfig<StandardEncodings>,
 ) -> Option<PutRecordsRequestEntry> {
     encoding.apply_rules(&mut event);
     let partition_key = if let Some(partition_key_field) = partition_key_field {
@@ -270,14 +263,11 @@ fn encode_event(
         partition_key
     };
 
-    let log = event.into_log();
-    let data = match encoding.codec() {
-        Encoding::Json => serde_json::to_vec(&log).expect("Error encoding event as json."),
-        Encoding::Text => log
-            .get(&event::log_schema().message_key())
-            .map(|v| v.as_bytes().to_vec())
-            .unwrap_or_default(),
-    };
+    let data = encoding
+        .codec()
+        .encode_event(&event)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()?;
 
     let data = Bytes::from(data);
 
@@ -312,7 +302,12 @@ mod tests {
     #[test]
     fn kinesis_encode_event_text() {
         let message = "hello world".to_string();
-        let event = encode_event(message.clone().into(), &None, &Encoding::Text.into()).unwrap();
+        let event = encode_event(
+            message.clone().into(),
+            &None,
+            &StandardEncodings::text().into(),
+        )
+        .unwrap();
 
         assert_eq!(&event.data[..], message.as_bytes());
     }
@@ -322,7 +317,7 @@ mod tests {
         let message = "hello world".to_string();
         let mut event = Event::from(message.clone());
         event.as_mut_log().insert("key", "value");
-        let event = encode_event(event, &None, &Encoding::Json.into()).unwrap();
+        let event = encode_event(event, &None, &StandardEncodings::Json.into()).unwrap();
 
         let map: BTreeMap<String, String> = serde_json::from_slice(&event.data[..]).unwrap();
 
@@ -334,7 +329,12 @@ mod tests {
     fn kinesis_encode_event_custom_partition_key() {
         let mut event = Event::from("hello world");
         event.as_mut_log().insert("key", "some_key");
-        let event = encode_event(event, &Some("key".into()), &Encoding::Text.into()).unwrap();
+        let event = encode_event(
+            event,
+            &Some("key".into()),
+            &StandardEncodings::text().into(),
+        )
+        .unwrap();
 
         assert_eq!(&event.data[..], b"hello world");
         assert_eq!(&event.partition_key, &"some_key".to_string());
@@ -344,7 +344,12 @@ mod tests {
     fn kinesis_encode_event_custom_partition_key_limit() {
         let mut event = Event::from("hello world");
         event.as_mut_log().insert("key", random_string(300));
-        let event = encode_event(event, &Some("key".into()), &Encoding::Text.into()).unwrap();
+        let event = encode_event(
+            event,
+            &Some("key".into()),
+            &StandardEncodings::text().into(),
+        )
+        .unwrap();
 
         assert_eq!(&event.data[..], b"hello world");
         assert_eq!(event.partition_key.len(), 256);
@@ -381,7 +386,7 @@ mod integration_tests {
             stream_name: stream.clone(),
             partition_key_field: None,
             region: RegionOrEndpoint::with_endpoint("http://localhost:4568".into()),
-            encoding: Encoding::Text.into(),
+            encoding: StandardEncodings::text().into(),
             compression: Compression::None,
             batch: BatchConfig {
                 max_events: Some(2),
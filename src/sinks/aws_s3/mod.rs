@@ -1,10 +1,11 @@
 use crate::{
     dns::Resolver,
-    event::{self, Event},
+    event::Event,
     region::RegionOrEndpoint,
     serde::to_string,
     sinks::util::{
-        encoding::{EncodingConfigWithDefault, EncodingConfiguration},
+        encoding::{EncodingConfigWithDefault, EncodingConfiguration, StandardEncodings},
+        parquet::{ParquetBuffer, ParquetConfig, ParquetEncoder, ParquetRow, ParquetService},
         retries2::RetryLogic,
         rusoto,
         service2::{ServiceBuilderExt, TowerCompat, TowerRequestConfig},
@@ -29,6 +30,7 @@ use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 use tower03::{Service, ServiceBuilder};
@@ -36,6 +38,11 @@ use tracing::field;
 use tracing_futures::Instrument;
 use uuid::Uuid;
 
+mod multipart;
+
+pub use multipart::MultipartConfig;
+use multipart::{MultipartConfigError, MultipartSink, MultipartUploader, ObjectSettings};
+
 #[derive(Clone)]
 pub struct S3Sink {
     client: S3Client,
@@ -57,7 +64,9 @@ pub struct S3SinkConfig {
         skip_serializing_if = "crate::serde::skip_serializing_if_default",
         default
     )]
-    pub encoding: EncodingConfigWithDefault<Encoding>,
+    pub encoding: EncodingConfigWithDefault<StandardEncodings>,
+    pub parquet: Option<ParquetConfig>,
+    pub multipart: Option<MultipartConfig>,
     #[serde(default = "Compression::default_gzip")]
     pub compression: Compression,
     #[serde(default)]
@@ -82,6 +91,18 @@ struct S3Options {
     content_type: Option<String>,     // default `text/x-log`
 }
 
+impl S3Options {
+    fn tagging(&self) -> String {
+        let mut tagging = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(tags) = &self.tags {
+            for (p, v) in tags {
+                tagging.append_pair(p, v);
+            }
+        }
+        tagging.finish()
+    }
+}
+
 #[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize)]
 #[derivative(Default)]
 #[serde(rename_all = "kebab-case")]
@@ -125,15 +146,6 @@ lazy_static! {
     };
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Derivative)]
-#[serde(rename_all = "snake_case")]
-#[derivative(Default)]
-pub enum Encoding {
-    #[derivative(Default)]
-    Text,
-    Ndjson,
-}
-
 inventory::submit! {
     SinkDescription::new::<S3SinkConfig>("aws_s3")
 }
@@ -171,26 +183,65 @@ impl S3SinkConfig {
         let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
         let encoding = self.encoding.clone();
 
-        let compression = self.compression;
+        // Parquet compresses its pages itself, so objects are written as is.
+        let compression = match self.parquet {
+            Some(_) => Compression::None,
+            None => self.compression,
+        };
         let filename_time_format = self
             .filename_time_format
             .clone()
             .unwrap_or_else(|| "%s".into());
         let filename_append_uuid = self.filename_append_uuid.unwrap_or(true);
-        let batch = BatchSettings::default()
-            .bytes(10_000_000)
-            .timeout(300)
-            .parse_config(self.batch)?;
-
         let key_prefix = self.key_prefix.as_deref().unwrap_or("date=%F/");
         let key_prefix = Template::try_from(key_prefix)?;
 
-        let s3 = S3Sink { client };
-
-        let filename_extension = self.filename_extension.clone();
+        let filename_extension = self
+            .filename_extension
+            .clone()
+            .or_else(|| self.parquet.as_ref().map(|_| "parquet".into()));
         let bucket = self.bucket.clone();
-        let options = self.options.clone();
+        let mut options = self.options.clone();
+        if self.parquet.is_some() && options.content_type.is_none() {
+            options.content_type = Some("application/octet-stream".into());
+        }
 
+        if let Some(multipart) = self.multipart {
+            if self.parquet.is_some() {
+                return Err(MultipartConfigError::Parquet.into());
+            }
+            multipart.validate()?;
+
+            let object = ObjectSettings {
+                bucket,
+                time_format: filename_time_format,
+                extension: filename_extension.unwrap_or_else(|| compression.extension().into()),
+                append_uuid: filename_append_uuid,
+                content_encoding: compression.content_encoding().map(Into::into),
+                options,
+            };
+            // The uploader limits the requests in flight itself, as batches
+            // stay in flight until the part holding them fills up.
+            let uploader = MultipartUploader::new(client, object, multipart, request.clone());
+            let svc = ServiceBuilder::new()
+                .rate_limit(request.rate_limit_num, request.rate_limit_duration)
+                .service(uploader.clone());
+
+            let batch = BatchSettings::default()
+                .bytes(10_000_000)
+                .timeout(300)
+                .parse_config(self.batch)?;
+            let buffer = PartitionBuffer::new(Buffer::new(batch.size, compression));
+
+            let sink =
+                PartitionBatchSink::new(TowerCompat::new(svc), buffer, batch.timeout, cx.acker())
+                    .with_flat_map(move |e| iter_ok(encode_event(e, &key_prefix, &encoding)))
+                    .sink_map_err(|error| error!("Sink failed to flush: {}", error));
+
+            return Ok(Box::new(MultipartSink::new(sink, uploader)));
+        }
+
+        let s3 = S3Sink { client };
         let svc = ServiceBuilder::new()
             .map(move |req| {
                 build_request(
@@ -206,14 +257,46 @@ impl S3SinkConfig {
             .settings(request, S3RetryLogic)
             .service(s3);
 
-        let buffer = PartitionBuffer::new(Buffer::new(batch.size, self.compression));
+        match &self.parquet {
+            Some(parquet) => {
+                let encoder = Arc::new(ParquetEncoder::new(parquet)?);
+                let batch = BatchSettings::default()
+                    .bytes(10_000_000)
+                    .timeout(300)
+                    .parse_config(self.batch)?;
+                let buffer =
+                    PartitionBuffer::new(ParquetBuffer::new(batch.size, Arc::clone(&encoder)));
+
+                let sink = PartitionBatchSink::new(
+                    TowerCompat::new(ParquetService::new(svc)),
+                    buffer,
+                    batch.timeout,
+                    cx.acker(),
+                )
+                .with_flat_map(move |e| iter_ok(encode_parquet_row(e, &key_prefix, &encoder)))
+                .sink_map_err(|error| error!("Sink failed to flush: {}", error));
 
-        let sink =
-            PartitionBatchSink::new(TowerCompat::new(svc), buffer, batch.timeout, cx.acker())
+                Ok(Box::new(sink))
+            }
+            None => {
+                let batch = BatchSettings::default()
+                    .bytes(10_000_000)
+                    .timeout(300)
+                    .parse_config(self.batch)?;
+                let buffer = PartitionBuffer::new(Buffer::new(batch.size, compression));
+
+                let sink = PartitionBatchSink::new(
+                    TowerCompat::new(svc),
+                    buffer,
+                    batch.timeout,
+                    cx.acker(),
+                )
                 .with_flat_map(move |e| iter_ok(encode_event(e, &key_prefix, &encoding)))
                 .sink_map_err(|error| error!("Sink failed to flush: {}", error));
 
-        Ok(Box::new(sink))
+                Ok(Box::new(sink))
+            }
+        }
     }
 
     pub async fn healthcheck(self, client: S3Client) -> crate::Result<()> {
@@ -267,13 +350,7 @@ impl Service<Request> for S3Sink {
             .content_type
             .or_else(|| Some("text/x-log".to_owned()));
 
-        let mut tagging = url::form_urlencoded::Serializer::new(String::new());
-        if let Some(tags) = options.tags {
-            for (p, v) in tags {
-                tagging.append_pair(&p, &v);
-            }
-        }
-        let tagging = tagging.finish();
+        let tagging = options.tagging();
 
         let client = self.client.clone();
         let request = PutObjectRequest {
@@ -309,21 +386,8 @@ fn build_request(
 ) -> Request {
     let (inner, key) = req.into_parts();
 
-    // TODO: pull the seconds from the last event
-    let filename = {
-        let seconds = Utc::now().format(&time_format);
-
-        if uuid {
-            let uuid = Uuid::new_v4();
-            format!("{}-{}", seconds, uuid.to_hyphenated())
-        } else {
-            seconds.to_string()
-        }
-    };
-
     let extension = extension.unwrap_or_else(|| compression.extension().into());
-    let key = String::from_utf8_lossy(&key[..]).into_owned();
-    let key = format!("{}{}.{}", key, filename, extension);
+    let key = object_key(&key, &time_format, &extension, uuid);
 
     debug!(
         message = "sending events.",
@@ -341,6 +405,27 @@ fn build_request(
     }
 }
 
+fn object_key(prefix: &[u8], time_format: &str, extension: &str, uuid: bool) -> String {
+    // TODO: pull the seconds from the last event
+    let filename = {
+        let seconds = Utc::now().format(time_format);
+
+        if uuid {
+            let uuid = Uuid::new_v4();
+            format!("{}-{}", seconds, uuid.to_hyphenated())
+        } else {
+            seconds.to_string()
+        }
+    };
+
+    format!(
+        "{}{}.{}",
+        String::from_utf8_lossy(prefix),
+        filename,
+        extension
+    )
+}
+
 #[derive(Debug, Clone)]
 struct Request {
     body: Vec<u8>,
@@ -360,21 +445,21 @@ impl RetryLogic for S3RetryLogic {
     type Response = PutObjectOutput;
 
     fn is_retriable_error(&self, error: &Self::Error) -> bool {
-        match error {
-            RusotoError::HttpDispatch(_) => true,
-            RusotoError::Unknown(res) if res.status.is_server_error() => true,
-            _ => false,
-        }
+        is_retriable_error(error)
     }
 }
 
-fn encode_event(
-    mut event: Event,
-    key_prefix: &Template,
-    encoding: &EncodingConfigWithDefault<Encoding>,
-) -> Option<PartitionInnerBuffer<Vec<u8>, Bytes>> {
-    let key = key_prefix
-        .render_string(&event)
+fn is_retriable_error<E>(error: &RusotoError<E>) -> bool {
+    match error {
+        RusotoError::HttpDispatch(_) => true,
+        RusotoError::Unknown(res) if res.status.is_server_error() => true,
+        _ => false,
+    }
+}
+
+fn render_key(event: &Event, key_prefix: &Template) -> Option<String> {
+    key_prefix
+        .render_string(event)
         .map_err(|missing_keys| {
             warn!(
                 message = "Keys do not exist on the event. Dropping event.",
@@ -382,31 +467,39 @@ fn encode_event(
                 rate_limit_secs = 30,
             );
         })
-        .ok()?;
+        .ok()
+}
+
+fn encode_event(
+    mut event: Event,
+    key_prefix: &Template,
+    encoding: &EncodingConfigWithDefault<StandardEncodings>,
+) -> Option<PartitionInnerBuffer<Vec<u8>, Bytes>> {
+    let key = render_key(&event, key_prefix)?;
 
     encoding.apply_rules(&mut event);
 
-    let log = event.into_log();
-    let bytes = match encoding.codec() {
-        Encoding::Ndjson => serde_json::to_vec(&log)
-            .map(|mut b| {
-                b.push(b'\n');
-                b
-            })
-            .expect("Failed to encode event as json, this is a bug!"),
-        Encoding::Text => {
-            let mut bytes = log
-                .get(&event::log_schema().message_key())
-                .map(|v| v.as_bytes().to_vec())
-                .unwrap_or_default();
-            bytes.push(b'\n');
-            bytes
-        }
-    };
+    let bytes = encoding
+        .codec()
+        .encode_delimited(&event)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()?;
 
     Some(PartitionInnerBuffer::new(bytes, key.into()))
 }
 
+fn encode_parquet_row(
+    event: Event,
+    key_prefix: &Template,
+    encoder: &ParquetEncoder,
+) -> Option<PartitionInnerBuffer<ParquetRow, Bytes>> {
+    let key = render_key(&event, key_prefix)?;
+    Some(PartitionInnerBuffer::new(
+        encoder.encode_event(&event),
+        key.into(),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -421,7 +514,7 @@ mod tests {
         let bytes = encode_event(
             message.clone().into(),
             &batch_time_format,
-            &Encoding::Text.into(),
+            &StandardEncodings::text().into(),
         )
         .unwrap();
 
@@ -437,7 +530,8 @@ mod tests {
         event.as_mut_log().insert("key", "value");
 
         let batch_time_format = Template::try_from("date=%F").unwrap();
-        let bytes = encode_event(event, &batch_time_format, &Encoding::Ndjson.into()).unwrap();
+        let bytes =
+            encode_event(event, &batch_time_format, &StandardEncodings::Ndjson.into()).unwrap();
 
         let (bytes, _) = bytes.into_parts();
         let map: BTreeMap<String, String> = serde_json::from_slice(&bytes[..]).unwrap();
@@ -455,7 +549,7 @@ mod tests {
         let key_prefix = Template::try_from("{{ key }}").unwrap();
 
         let encoding_config = EncodingConfigWithDefault {
-            codec: Encoding::Ndjson,
+            codec: StandardEncodings::Ndjson,
             except_fields: Some(vec!["key".into()]),
             ..Default::default()
         };
@@ -517,6 +611,31 @@ mod tests {
         );
         assert_ne!(req.key, "key/date.log.gz".to_string());
     }
+
+    #[test]
+    fn s3_encode_parquet_row_partitions_by_key_prefix() {
+        let config: S3SinkConfig = toml::from_str(
+            r#"
+            bucket = "bucket"
+
+            [parquet]
+            schema = [{ name = "message", type = "string" }]
+            "#,
+        )
+        .unwrap();
+        let encoder = ParquetEncoder::new(config.parquet.as_ref().unwrap()).unwrap();
+
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("key", "value");
+        let key_prefix = Template::try_from("{{ key }}/").unwrap();
+
+        let row = encode_parquet_row(event, &key_prefix, &encoder).unwrap();
+        let (_, key) = row.into_parts();
+        assert_eq!(key, Bytes::from("value/"));
+
+        let missing = Template::try_from("{{ missing }}/").unwrap();
+        assert!(encode_parquet_row(Event::from("hello"), &missing, &encoder).is_none());
+    }
 }
 
 #[cfg(feature = "aws-s3-integration-tests")]
@@ -662,6 +781,89 @@ mod integration_tests {
         });
     }
 
+    #[test]
+    fn s3_multipart_upload() {
+        let mut rt = runtime();
+        let cx = SinkContext::new_test();
+
+        rt.block_on_std(async {
+            let config = S3SinkConfig {
+                multipart: Some(MultipartConfig {
+                    part_size: 5 * 1024 * 1024,
+                    max_object_size: 1024 * 1024 * 1024,
+                    max_object_age_secs: 3600,
+                }),
+                ..config(1_000_000).await
+            };
+
+            let prefix = config.key_prefix.clone();
+            let client = config.create_client(cx.resolver()).unwrap();
+            let sink = config.new(client, cx).unwrap();
+
+            // Enough for one full part, and a last one that's completed
+            // when the sink shuts down.
+            let (lines, events) = random_lines_with_stream(100, 60_000);
+
+            let _ = sink.send_all(events).compat().await.unwrap();
+
+            let keys = get_keys(prefix.unwrap()).await;
+            assert_eq!(keys.len(), 1);
+
+            let obj = get_object(keys[0].clone()).await;
+            assert_eq!(get_lines(obj).await, lines);
+        });
+    }
+
+    #[test]
+    fn s3_multipart_upload_completes_objects_on_shutdown() {
+        let mut rt = runtime();
+        let cx = SinkContext::new_test();
+
+        rt.block_on_std(async {
+            let config = S3SinkConfig {
+                key_prefix: Some(format!("{}/{}/", random_string(10), "{{i}}")),
+                multipart: Some(MultipartConfig {
+                    part_size: 5 * 1024 * 1024,
+                    max_object_size: 1024 * 1024 * 1024,
+                    max_object_age_secs: 3600,
+                }),
+                ..config(1_000_000).await
+            };
+
+            let prefix = config.key_prefix.clone();
+            let client = config.create_client(cx.resolver()).unwrap();
+            let sink = config.new(client, cx).unwrap();
+
+            // The first prefix gets one full part and a partial one, the
+            // second only a partial one, so both objects are still open
+            // when the sink shuts down.
+            let (lines, _) = random_lines_with_stream(100, 70_000);
+            let events = lines.clone().into_iter().enumerate().map(|(n, line)| {
+                let mut event = Event::from(line);
+                let i = if n < 60_000 { 1 } else { 2 };
+                event.as_mut_log().insert("i", format!("{}", i));
+                event
+            });
+
+            let _ = sink
+                .send_all(futures01::stream::iter_ok(events))
+                .compat()
+                .await
+                .unwrap();
+
+            let mut keys = get_keys(prefix.unwrap()).await;
+            keys.sort();
+            assert_eq!(keys.len(), 2);
+            assert!(keys[0].contains("/1/"));
+            assert!(keys[1].contains("/2/"));
+
+            let first = get_lines(get_object(keys[0].clone()).await).await;
+            assert_eq!(first.as_slice(), &lines[..60_000]);
+            let second = get_lines(get_object(keys[1].clone()).await).await;
+            assert_eq!(second.as_slice(), &lines[60_000..]);
+        });
+    }
+
     #[test]
     fn s3_healthchecks() {
         let mut rt = runtime();
@@ -0,0 +1,759 @@
+//! Multipart uploads, which stream batches into large objects.
+//!
+//! Batches for a key prefix are appended to a buffer, and every `part_size`
+//! bytes of it are uploaded as the next part of the object open for that
+//! prefix. The object is completed once it reaches `max_object_size` or is
+//! `max_object_age_secs` old, and the next batch starts a new one.
+//!
+//! A batch is only done once the part holding its end has been uploaded, so
+//! that nothing is acknowledged while it's merely buffered here. On shutdown
+//! the open objects are completed rather than aborted, as their parts have
+//! been acknowledged already.
+
+use super::{is_retriable_error, object_key, S3Options};
+use crate::{
+    internal_events::{S3MultipartUploadAborted, S3MultipartUploadCompleted},
+    serde::to_string,
+    sinks::util::{service2::TowerRequestSettings, PartitionInnerBuffer},
+};
+use bytes05::Bytes;
+use futures::{
+    compat::Compat,
+    future::{BoxFuture, FutureExt},
+};
+use futures01::{try_ready, Async, Future, Poll, Sink, StartSend};
+use rusoto_core::RusotoError;
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CompletedPart, CreateMultipartUploadRequest, S3Client, UploadPartRequest, S3,
+};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::{
+    cmp,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::{Context, Poll as Poll03},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{oneshot, Mutex as AsyncMutex, Semaphore},
+    time::{delay_for, timeout},
+};
+use tower03::Service;
+use tracing_futures::Instrument;
+
+/// S3 rejects parts smaller than this, except for the last one.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MultipartConfig {
+    #[serde(default = "default_part_size")]
+    pub part_size: usize,
+    #[serde(default = "default_max_object_size")]
+    pub max_object_size: u64,
+    #[serde(default = "default_max_object_age_secs")]
+    pub max_object_age_secs: u64,
+}
+
+fn default_part_size() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_max_object_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_max_object_age_secs() -> u64 {
+    3600
+}
+
+#[derive(Debug, Snafu)]
+pub(super) enum MultipartConfigError {
+    #[snafu(display("multipart.part_size must be at least {} bytes", MIN_PART_SIZE))]
+    PartSizeTooSmall,
+    #[snafu(display("multipart.max_object_size must be at least multipart.part_size"))]
+    ObjectSizeTooSmall,
+    #[snafu(display(
+        "multipart.max_object_size allows more than {} parts of multipart.part_size",
+        MAX_PARTS
+    ))]
+    TooManyParts,
+    #[snafu(display("multipart.max_object_age_secs must be greater than zero"))]
+    ZeroObjectAge,
+    #[snafu(display("multipart can't be used together with parquet"))]
+    Parquet,
+}
+
+#[derive(Debug, Snafu)]
+enum MultipartError {
+    #[snafu(display("Buffered data was dropped after an upload failed: {}", reason))]
+    UploadFailed { reason: String },
+    #[snafu(display("Buffered data was dropped as the sink is shutting down"))]
+    ShuttingDown,
+}
+
+impl MultipartConfig {
+    pub(super) fn validate(&self) -> Result<(), MultipartConfigError> {
+        if self.part_size < MIN_PART_SIZE {
+            Err(MultipartConfigError::PartSizeTooSmall)
+        } else if self.max_object_size < self.part_size as u64 {
+            Err(MultipartConfigError::ObjectSizeTooSmall)
+        } else if self.max_object_size / self.part_size as u64 >= MAX_PARTS {
+            Err(MultipartConfigError::TooManyParts)
+        } else if self.max_object_age_secs == 0 {
+            Err(MultipartConfigError::ZeroObjectAge)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn max_object_age(&self) -> Duration {
+        Duration::from_secs(self.max_object_age_secs)
+    }
+}
+
+/// How the objects are named and described.
+#[derive(Debug, Clone)]
+pub(super) struct ObjectSettings {
+    pub bucket: String,
+    pub time_format: String,
+    pub extension: String,
+    pub append_uuid: bool,
+    pub content_encoding: Option<String>,
+    pub options: S3Options,
+}
+
+/// Buffered data and the open upload for one key prefix.
+#[derive(Default)]
+struct Partition {
+    buffer: Vec<u8>,
+    upload: Option<Upload>,
+    opened: Option<Instant>,
+    /// Offset, in all the data written for this prefix, up to which it has
+    /// been uploaded or dropped. The buffer holds what follows.
+    flushed: u64,
+    /// The batches in the buffer, by the offset of their end.
+    waiting: Vec<(u64, oneshot::Sender<Result<(), MultipartError>>)>,
+}
+
+impl Partition {
+    fn is_empty(&self) -> bool {
+        self.buffer.is_empty() && self.upload.is_none()
+    }
+
+    /// Appends a batch, returning a receiver that resolves once it has been
+    /// uploaded.
+    fn push(&mut self, body: &[u8]) -> oneshot::Receiver<Result<(), MultipartError>> {
+        self.buffer.extend_from_slice(body);
+        let (tx, rx) = oneshot::channel();
+        self.waiting
+            .push((self.flushed + self.buffer.len() as u64, tx));
+        rx
+    }
+
+    /// Marks a part taken from the front of the buffer as uploaded.
+    fn uploaded(&mut self, size: usize) {
+        self.flushed += size as u64;
+        let flushed = self.flushed;
+        let (done, waiting) = std::mem::take(&mut self.waiting)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(end, _)| *end <= flushed);
+        self.waiting = waiting;
+        for (_, tx) in done {
+            let _ = tx.send(Ok(()));
+        }
+    }
+
+    /// Drops a part taken from the front of the buffer and everything still
+    /// buffered, failing the batches waiting for them.
+    fn drop_buffered(&mut self, part_size: usize, error: impl Fn() -> MultipartError) {
+        self.flushed += (part_size + self.buffer.len()) as u64;
+        self.buffer.clear();
+        self.opened = None;
+        for (_, tx) in self.waiting.drain(..) {
+            let _ = tx.send(Err(error()));
+        }
+    }
+}
+
+struct Upload {
+    key: String,
+    upload_id: String,
+    parts: Vec<CompletedPart>,
+    size: u64,
+}
+
+/// A service that appends each batch to the object open for its key prefix.
+#[derive(Clone)]
+pub(super) struct MultipartUploader {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: S3Client,
+    object: ObjectSettings,
+    config: MultipartConfig,
+    request: TowerRequestSettings,
+    partitions: Mutex<HashMap<Bytes, Arc<AsyncMutex<Partition>>>>,
+    /// Limits the batches being buffered and uploaded at once, not counting
+    /// those just waiting for their part to fill up.
+    in_flight: Semaphore,
+    rolling: AtomicBool,
+    closing: AtomicBool,
+}
+
+impl MultipartUploader {
+    pub fn new(
+        client: S3Client,
+        object: ObjectSettings,
+        config: MultipartConfig,
+        request: TowerRequestSettings,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                object,
+                config,
+                in_flight: Semaphore::new(request.in_flight_limit),
+                request,
+                partitions: Mutex::new(HashMap::new()),
+                rolling: AtomicBool::new(false),
+                closing: AtomicBool::new(false),
+            }),
+        }
+    }
+}
+
+impl Service<PartitionInnerBuffer<Vec<u8>, Bytes>> for MultipartUploader {
+    type Response = ();
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll03<Result<(), Self::Error>> {
+        Poll03::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: PartitionInnerBuffer<Vec<u8>, Bytes>) -> Self::Future {
+        let inner = Arc::clone(&self.inner);
+        if !inner.rolling.swap(true, Ordering::SeqCst) {
+            tokio::spawn(roll_expired(Arc::downgrade(&inner)));
+        }
+
+        let (body, prefix) = request.into_parts();
+        Box::pin(async move { inner.write(prefix, body).await }.instrument(info_span!("request")))
+    }
+}
+
+/// Completes objects that have been open for too long, even if no more
+/// batches arrive for them.
+async fn roll_expired(inner: Weak<Inner>) {
+    let period = match inner.upgrade() {
+        Some(inner) => cmp::max(inner.config.max_object_age() / 10, Duration::from_secs(1)),
+        None => return,
+    };
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let inner = match inner.upgrade() {
+            Some(inner) if !inner.closing.load(Ordering::SeqCst) => inner,
+            _ => break,
+        };
+
+        for (prefix, partition) in inner.snapshot() {
+            let mut partition = partition.lock().await;
+            if inner.is_expired(&partition) {
+                if let Err(error) = inner.complete(&prefix, &mut partition).await {
+                    error!(message = "Unable to complete object.", %error);
+                }
+            }
+        }
+        inner.remove_empty();
+    }
+}
+
+impl Inner {
+    fn partition(&self, prefix: &Bytes) -> Arc<AsyncMutex<Partition>> {
+        let mut partitions = self.partitions.lock().unwrap();
+        let partition = partitions
+            .entry(prefix.clone())
+            .or_insert_with(|| Arc::new(AsyncMutex::new(Partition::default())));
+        Arc::clone(partition)
+    }
+
+    fn snapshot(&self) -> Vec<(Bytes, Arc<AsyncMutex<Partition>>)> {
+        let partitions = self.partitions.lock().unwrap();
+        partitions
+            .iter()
+            .map(|(prefix, partition)| (prefix.clone(), Arc::clone(partition)))
+            .collect()
+    }
+
+    /// Forgets the prefixes nothing is buffered for, so that templated
+    /// prefixes like dates don't accumulate.
+    fn remove_empty(&self) {
+        let mut partitions = self.partitions.lock().unwrap();
+        partitions.retain(|_, partition| {
+            Arc::strong_count(partition) > 1
+                || partition
+                    .try_lock()
+                    .map_or(true, |partition| !partition.is_empty())
+        });
+    }
+
+    fn is_expired(&self, partition: &Partition) -> bool {
+        partition.opened.map_or(false, |opened| {
+            opened.elapsed() >= self.config.max_object_age()
+        })
+    }
+
+    async fn write(&self, prefix: Bytes, body: Vec<u8>) -> crate::Result<()> {
+        let uploaded = {
+            let _permit = self.in_flight.acquire().await;
+            let partition = self.partition(&prefix);
+            let mut partition = partition.lock().await;
+
+            if partition.opened.is_none() {
+                partition.opened = Some(Instant::now());
+            }
+            let uploaded = partition.push(&body);
+
+            while partition.buffer.len() >= self.config.part_size {
+                let rest = partition.buffer.split_off(self.config.part_size);
+                let part = std::mem::replace(&mut partition.buffer, rest);
+                self.upload_part(&prefix, &mut partition, part).await?;
+            }
+
+            let size = partition.upload.as_ref().map_or(0, |upload| upload.size);
+            // Batches flushed while shutting down complete their object
+            // right away, as nothing would complete it later.
+            if size + partition.buffer.len() as u64 >= self.config.max_object_size
+                || self.is_expired(&partition)
+                || self.closing.load(Ordering::SeqCst)
+            {
+                self.complete(&prefix, &mut partition).await?;
+            }
+            uploaded
+        };
+
+        uploaded
+            .await
+            .unwrap_or(Err(MultipartError::ShuttingDown))
+            .map_err(Into::into)
+    }
+
+    /// Uploads whatever is buffered as the last part and completes the
+    /// object.
+    async fn complete(&self, prefix: &Bytes, partition: &mut Partition) -> crate::Result<()> {
+        let part = std::mem::take(&mut partition.buffer);
+        if !part.is_empty() {
+            self.upload_part(prefix, partition, part).await?;
+        }
+        partition.opened = None;
+
+        match partition.upload.take() {
+            Some(upload) => self.complete_upload(upload).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Completes the object with the parts uploaded for it. If that fails,
+    /// the upload is aborted.
+    async fn complete_upload(&self, upload: Upload) -> crate::Result<()> {
+        let result = self
+            .retry(|| {
+                self.client
+                    .complete_multipart_upload(CompleteMultipartUploadRequest {
+                        bucket: self.object.bucket.clone(),
+                        key: upload.key.clone(),
+                        upload_id: upload.upload_id.clone(),
+                        multipart_upload: Some(CompletedMultipartUpload {
+                            parts: Some(upload.parts.clone()),
+                        }),
+                        ..Default::default()
+                    })
+            })
+            .await;
+
+        match result {
+            Ok(_) => {
+                emit!(S3MultipartUploadCompleted {
+                    key: &upload.key,
+                    parts: upload.parts.len(),
+                    byte_size: upload.size,
+                });
+                Ok(())
+            }
+            Err(error) => {
+                self.abort(&upload, &error).await;
+                Err(error)
+            }
+        }
+    }
+
+    /// Uploads a part taken from the front of the buffer. If that fails, the
+    /// buffered data is dropped, and the object is completed with the parts
+    /// uploaded before, as later parts can't fill the gap.
+    async fn upload_part(
+        &self,
+        prefix: &Bytes,
+        partition: &mut Partition,
+        part: Vec<u8>,
+    ) -> crate::Result<()> {
+        let size = part.len();
+        if partition.upload.is_none() {
+            match self.create(prefix).await {
+                Ok(upload) => partition.upload = Some(upload),
+                Err(error) => {
+                    partition.drop_buffered(size, || MultipartError::UploadFailed {
+                        reason: error.to_string(),
+                    });
+                    return Err(error);
+                }
+            }
+        }
+        let upload = partition.upload.as_mut().unwrap();
+
+        let part_number = upload.parts.len() as i64 + 1;
+        let result = self
+            .retry(|| {
+                self.client.upload_part(UploadPartRequest {
+                    body: Some(part.clone().into()),
+                    bucket: self.object.bucket.clone(),
+                    key: upload.key.clone(),
+                    upload_id: upload.upload_id.clone(),
+                    part_number,
+                    content_length: Some(size as i64),
+                    ..Default::default()
+                })
+            })
+            .await;
+
+        match result {
+            Ok(output) => {
+                upload.parts.push(CompletedPart {
+                    e_tag: output.e_tag,
+                    part_number: Some(part_number),
+                });
+                upload.size += size as u64;
+                partition.uploaded(size);
+                Ok(())
+            }
+            Err(error) => {
+                partition.drop_buffered(size, || MultipartError::UploadFailed {
+                    reason: error.to_string(),
+                });
+                let upload = partition.upload.take().unwrap();
+                if upload.parts.is_empty() {
+                    self.abort(&upload, &error).await;
+                } else if let Err(error) = self.complete_upload(upload).await {
+                    error!(message = "Unable to complete object.", %error);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    async fn create(&self, prefix: &Bytes) -> crate::Result<Upload> {
+        let object = &self.object;
+        let options = &object.options;
+        let key = object_key(
+            prefix,
+            &object.time_format,
+            &object.extension,
+            object.append_uuid,
+        );
+
+        let output = self
+            .retry(|| {
+                self.client
+                    .create_multipart_upload(CreateMultipartUploadRequest {
+                        bucket: object.bucket.clone(),
+                        key: key.clone(),
+                        content_encoding: options
+                            .content_encoding
+                            .clone()
+                            .or_else(|| object.content_encoding.clone()),
+                        content_type: options
+                            .content_type
+                            .clone()
+                            .or_else(|| Some("text/x-log".to_owned())),
+                        acl: options.acl.map(to_string),
+                        grant_full_control: options.grant_full_control.clone(),
+                        grant_read: options.grant_read.clone(),
+                        grant_read_acp: options.grant_read_acp.clone(),
+                        grant_write_acp: options.grant_write_acp.clone(),
+                        server_side_encryption: options.server_side_encryption.map(to_string),
+                        ssekms_key_id: options.ssekms_key_id.clone(),
+                        storage_class: options.storage_class.map(to_string),
+                        tagging: Some(options.tagging()),
+                        ..Default::default()
+                    })
+            })
+            .await?;
+
+        debug!(message = "Started multipart upload.", %key);
+        Ok(Upload {
+            key,
+            upload_id: output.upload_id.unwrap_or_default(),
+            parts: Vec::new(),
+            size: 0,
+        })
+    }
+
+    /// Aborts the upload so that S3 frees its parts, which would otherwise
+    /// be billed for until a lifecycle rule removes them.
+    async fn abort(&self, upload: &Upload, reason: &crate::Error) {
+        let result = self
+            .retry(|| {
+                self.client
+                    .abort_multipart_upload(AbortMultipartUploadRequest {
+                        bucket: self.object.bucket.clone(),
+                        key: upload.key.clone(),
+                        upload_id: upload.upload_id.clone(),
+                        ..Default::default()
+                    })
+            })
+            .await;
+
+        emit!(S3MultipartUploadAborted {
+            key: &upload.key,
+            reason,
+        });
+        if let Err(error) = result {
+            error!(message = "Unable to abort multipart upload.", key = %upload.key, %error);
+        }
+    }
+
+    /// Completes every open object once the sink shuts down, uploading what
+    /// is still buffered as its last part. Their earlier parts have been
+    /// acknowledged already, so aborting them would lose data. Uploads are
+    /// only aborted if no part of them could be uploaded.
+    async fn complete_all(&self) {
+        self.closing.store(true, Ordering::SeqCst);
+        for (prefix, partition) in self.snapshot() {
+            let mut partition = partition.lock().await;
+            if let Err(error) = self.complete(&prefix, &mut partition).await {
+                error!(message = "Unable to complete object.", %error);
+            }
+        }
+        self.remove_empty();
+    }
+
+    /// Requests are retried here rather than by the tower retry layer, as
+    /// retrying a whole batch would append it to the object twice.
+    async fn retry<T, E, F, Fut>(&self, mut request: F) -> crate::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, RusotoError<E>>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let mut remaining_attempts = self.request.retry_attempts;
+        let mut backoff = self.request.retry_initial_backoff_secs;
+        loop {
+            let error: crate::Error = match timeout(self.request.timeout, request()).await {
+                Ok(Ok(output)) => return Ok(output),
+                Ok(Err(error)) if !is_retriable_error(&error) => return Err(error.into()),
+                Ok(Err(error)) => error.into(),
+                Err(elapsed) => elapsed.into(),
+            };
+            if remaining_attempts == 0 {
+                return Err(error);
+            }
+            remaining_attempts -= 1;
+
+            warn!(message = "Retrying after error.", %error);
+            delay_for(backoff).await;
+            backoff = cmp::min(backoff * 2, self.request.retry_max_duration_secs);
+        }
+    }
+}
+
+/// Completes the open objects when the sink is closed on shutdown.
+pub(super) struct MultipartSink<S> {
+    inner: S,
+    uploader: MultipartUploader,
+    completing: Option<Compat<BoxFuture<'static, Result<(), ()>>>>,
+    completed: bool,
+}
+
+impl<S> MultipartSink<S> {
+    pub fn new(inner: S, uploader: MultipartUploader) -> Self {
+        Self {
+            inner,
+            uploader,
+            completing: None,
+            completed: false,
+        }
+    }
+}
+
+impl<S: Sink<SinkError = ()>> Sink for MultipartSink<S> {
+    type SinkItem = S::SinkItem;
+    type SinkError = ();
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        self.inner.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        // The batches in flight when closing the inner sink only complete
+        // once the objects they were buffered for are completed, so both
+        // are driven together.
+        if !self.completed {
+            let uploader = &self.uploader;
+            let completing = self.completing.get_or_insert_with(|| {
+                let inner = Arc::clone(&uploader.inner);
+                let complete = async move {
+                    inner.complete_all().await;
+                    Ok(())
+                };
+                Compat::new(complete.boxed())
+            });
+            self.completed = completing.poll()?.is_ready();
+        }
+
+        try_ready!(self.inner.close());
+        if self.completed {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sinks::util::service2::TowerRequestConfig;
+
+    fn config(part_size: usize, max_object_size: u64) -> MultipartConfig {
+        MultipartConfig {
+            part_size,
+            max_object_size,
+            max_object_age_secs: 60,
+        }
+    }
+
+    #[test]
+    fn multipart_config_defaults() {
+        let config: MultipartConfig = toml::from_str("").unwrap();
+        assert_eq!(config.part_size, 10 * 1024 * 1024);
+        assert_eq!(config.max_object_size, 1024 * 1024 * 1024);
+        assert_eq!(config.max_object_age_secs, 3600);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn multipart_config_validation() {
+        assert!(matches!(
+            config(1024, 1024 * 1024 * 1024).validate(),
+            Err(MultipartConfigError::PartSizeTooSmall)
+        ));
+        assert!(matches!(
+            config(MIN_PART_SIZE, MIN_PART_SIZE as u64 - 1).validate(),
+            Err(MultipartConfigError::ObjectSizeTooSmall)
+        ));
+        assert!(matches!(
+            config(MIN_PART_SIZE, MIN_PART_SIZE as u64 * MAX_PARTS).validate(),
+            Err(MultipartConfigError::TooManyParts)
+        ));
+        assert!(matches!(
+            MultipartConfig {
+                max_object_age_secs: 0,
+                ..config(MIN_PART_SIZE, MIN_PART_SIZE as u64)
+            }
+            .validate(),
+            Err(MultipartConfigError::ZeroObjectAge)
+        ));
+        assert!(config(MIN_PART_SIZE, MIN_PART_SIZE as u64)
+            .validate()
+            .is_ok());
+    }
+
+    fn uploader() -> MultipartUploader {
+        MultipartUploader::new(
+            S3Client::new(rusoto_core::Region::UsEast1),
+            ObjectSettings {
+                bucket: "bucket".into(),
+                time_format: "%s".into(),
+                extension: "log".into(),
+                append_uuid: true,
+                content_encoding: None,
+                options: S3Options::default(),
+            },
+            config(MIN_PART_SIZE, MIN_PART_SIZE as u64),
+            TowerRequestConfig::default().unwrap_with(&Default::default()),
+        )
+    }
+
+    #[test]
+    fn empty_partitions_are_removed() {
+        let uploader = uploader();
+        let inner = &uploader.inner;
+
+        let empty = inner.partition(&Bytes::from("empty/"));
+        let buffered = inner.partition(&Bytes::from("buffered/"));
+        buffered.try_lock().unwrap().buffer.push(b'a');
+        let held = inner.partition(&Bytes::from("held/"));
+        drop((empty, buffered));
+
+        inner.remove_empty();
+        let mut prefixes = inner
+            .snapshot()
+            .into_iter()
+            .map(|(prefix, _)| prefix)
+            .collect::<Vec<_>>();
+        prefixes.sort();
+        assert_eq!(
+            prefixes,
+            vec![Bytes::from("buffered/"), Bytes::from("held/")]
+        );
+        drop(held);
+    }
+
+    #[test]
+    fn batches_wait_for_the_part_holding_their_end() {
+        let mut partition = Partition::default();
+        let mut first = partition.push(b"aaa");
+        let mut second = partition.push(b"bbbb");
+
+        partition.buffer.drain(..4);
+        partition.uploaded(4);
+        assert!(matches!(first.try_recv(), Ok(Ok(()))));
+        assert!(second.try_recv().is_err());
+
+        let mut third = partition.push(b"c");
+        partition.drop_buffered(0, || MultipartError::ShuttingDown);
+        assert!(matches!(
+            second.try_recv(),
+            Ok(Err(MultipartError::ShuttingDown))
+        ));
+        assert!(matches!(
+            third.try_recv(),
+            Ok(Err(MultipartError::ShuttingDown))
+        ));
+        assert!(partition.buffer.is_empty());
+        assert_eq!(partition.flushed, 8);
+    }
+
+    #[tokio::test]
+    async fn shutdown_forgets_empty_partitions() {
+        let uploader = uploader();
+        let inner = &uploader.inner;
+
+        drop(inner.partition(&Bytes::from("prefix/")));
+        inner.complete_all().await;
+        assert!(inner.snapshot().is_empty());
+        assert!(inner.closing.load(Ordering::SeqCst));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
-    event::{self, Event},
+    event::Event,
     sinks::util::{
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodeError, EncodingConfig, EncodingConfiguration, StandardEncodings},
         StreamSink,
     },
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
@@ -33,14 +33,7 @@ impl Default for Target {
 pub struct ConsoleSinkConfig {
     #[serde(default)]
     pub target: Target,
-    pub encoding: EncodingConfig<Encoding>,
-}
-
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Json,
+    pub encoding: EncodingConfig<StandardEncodings>,
 }
 
 inventory::submit! {
@@ -75,39 +68,26 @@ impl SinkConfig for ConsoleSinkConfig {
 
 fn encode_event(
     mut event: Event,
-    encoding: &EncodingConfig<Encoding>,
-) -> Result<String, serde_json::Error> {
+    encoding: &EncodingConfig<StandardEncodings>,
+) -> Result<Vec<u8>, EncodeError> {
     encoding.apply_rules(&mut event);
-    match event {
-        Event::Log(log) => match encoding.codec() {
-            Encoding::Json => serde_json::to_string(&log),
-            Encoding::Text => {
-                let s = log
-                    .get(&event::log_schema().message_key())
-                    .map(|v| v.to_string_lossy())
-                    .unwrap_or_else(|| "".into());
-                Ok(s)
-            }
-        },
-        Event::Metric(metric) => serde_json::to_string(&metric),
-    }
+    encoding.codec().encode_delimited(&event)
 }
 
 async fn write_event_to_output(
     mut output: impl io::AsyncWrite + Send + Unpin,
     event: Event,
-    encoding: &EncodingConfig<Encoding>,
+    encoding: &EncodingConfig<StandardEncodings>,
 ) -> Result<(), std::io::Error> {
-    let mut buf =
+    let buf =
         encode_event(event, encoding).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
-    buf.push('\n');
-    output.write_all(buf.as_bytes()).await?;
+    output.write_all(&buf).await?;
     Ok(())
 }
 
 struct WriterSink {
     output: Box<dyn io::AsyncWrite + Send + Sync + Unpin>,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
 }
 
 #[async_trait]
@@ -128,18 +108,21 @@ impl StreamingSink for WriterSink {
 
 #[cfg(test)]
 mod test {
-    use super::{encode_event, Encoding, EncodingConfig};
+    use super::{encode_event, EncodingConfig, StandardEncodings};
     use crate::event::metric::{Metric, MetricKind, MetricValue, StatisticKind};
     use crate::event::{Event, Value};
     use chrono::{offset::TimeZone, Utc};
 
+    fn encode(event: Event, codec: StandardEncodings) -> String {
+        let encoded = encode_event(event, &EncodingConfig::from(codec)).unwrap();
+        let encoded = String::from_utf8(encoded).unwrap();
+        encoded.strip_suffix('\n').unwrap().to_owned()
+    }
+
     #[test]
     fn encodes_raw_logs() {
         let event = Event::from("foo");
-        assert_eq!(
-            "foo",
-            encode_event(event, &EncodingConfig::from(Encoding::Text)).unwrap()
-        );
+        assert_eq!("foo", encode(event, StandardEncodings::text()));
     }
 
     #[test]
@@ -150,9 +133,9 @@ mod test {
         log.insert("z", Value::from(25));
         log.insert("a", Value::from("0"));
 
-        let encoded = encode_event(event, &EncodingConfig::from(Encoding::Json));
+        let encoded = encode(event, StandardEncodings::Json);
         let expected = r#"{"a":"0","x":"23","z":25}"#;
-        assert_eq!(encoded.unwrap(), expected);
+        assert_eq!(encoded, expected);
     }
 
     #[test]
@@ -174,7 +157,7 @@ mod test {
         });
         assert_eq!(
             r#"{"name":"foos","timestamp":"2018-11-14T08:09:10.000000011Z","tags":{"Key3":"Value3","key1":"value1","key2":"value2"},"kind":"incremental","counter":{"value":100.0}}"#,
-            encode_event(event, &EncodingConfig::from(Encoding::Text)).unwrap()
+            encode(event, StandardEncodings::text())
         );
     }
 
@@ -191,7 +174,7 @@ mod test {
         });
         assert_eq!(
             r#"{"name":"users","timestamp":null,"tags":null,"kind":"incremental","set":{"values":["bob"]}}"#,
-            encode_event(event, &EncodingConfig::from(Encoding::Text)).unwrap()
+            encode(event, StandardEncodings::text())
         );
     }
 
@@ -210,7 +193,7 @@ mod test {
         });
         assert_eq!(
             r#"{"name":"glork","timestamp":null,"tags":null,"kind":"incremental","distribution":{"values":[10.0],"sample_rates":[1],"statistic":"histogram"}}"#,
-            encode_event(event, &EncodingConfig::from(Encoding::Text)).unwrap()
+            encode(event, StandardEncodings::text())
         );
     }
 }
@@ -2,9 +2,9 @@ use crate::{
     event::{log_schema, Event},
     sinks::util::{
         self,
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
         tcp::TcpSink,
-        Encoding, UriSerde,
+        UriSerde,
     },
     tls::{MaybeTlsSettings, TlsConfig},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
@@ -18,7 +18,7 @@ use serde::{Deserialize, Serialize};
 pub struct DatadogLogsConfig {
     endpoint: Option<UriSerde>,
     api_key: String,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
     tls: Option<TlsConfig>,
 }
 
@@ -29,6 +29,14 @@ inventory::submit! {
 #[typetag::serde(name = "datadog_logs")]
 impl SinkConfig for DatadogLogsConfig {
     fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        if self.encoding.codec().is_binary() {
+            return Err(format!(
+                "The {} encoding can't be sent to Datadog",
+                self.encoding.codec().name()
+            )
+            .into());
+        }
+
         let (host, port, tls) = if let Some(uri) = &self.endpoint {
             let host = uri
                 .host()
@@ -74,7 +82,7 @@ impl SinkConfig for DatadogLogsConfig {
 fn encode_event(
     mut event: Event,
     mut api_key: Bytes,
-    encoding: &EncodingConfig<Encoding>,
+    encoding: &EncodingConfig<StandardEncodings>,
 ) -> Option<Bytes> {
     encoding.apply_rules(&mut event);
 
@@ -1,23 +1,27 @@
 use crate::{
     emit,
-    event::Event,
-    internal_events::{ElasticSearchEventReceived, ElasticSearchMissingKeys},
+    event::{self, Event},
+    internal_events::{
+        ElasticSearchDocumentRejected, ElasticSearchEventReceived, ElasticSearchInvalidBulkAction,
+        ElasticSearchMissingKeys,
+    },
     region::{region_from_endpoint, RegionOrEndpoint},
     sinks::util::{
         encoding::{EncodingConfigWithDefault, EncodingConfiguration},
-        http::{BatchedHttpSink, HttpClient, HttpSink},
+        http::HttpClient,
         retries2::{RetryAction, RetryLogic},
         rusoto,
         service2::TowerRequestConfig,
-        BatchConfig, BatchSettings, Buffer, Compression,
+        Batch, BatchConfig, BatchSettings, Compression, EncodedLength, PushResult, VecBuffer,
     },
     template::{Template, TemplateError},
     tls::{TlsOptions, TlsSettings},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
-use bytes05::Bytes;
-use futures::{FutureExt, TryFutureExt};
-use futures01::Sink;
+use bytes05::{Buf, Bytes};
+use flate2::write::GzEncoder;
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+use futures01::{stream::iter_ok, Sink};
 use http::{
     header::{HeaderName, HeaderValue},
     uri::InvalidUri,
@@ -31,8 +35,15 @@ use rusoto_signature::{SignedRequest, SignedRequestPayload};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use snafu::{ResultExt, Snafu};
-use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    io::Write,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tower03::Service;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
@@ -42,6 +53,10 @@ pub struct ElasticSearchConfig {
     pub doc_type: Option<String>,
     pub id_key: Option<String>,
     pub pipeline: Option<String>,
+    pub bulk_action: Option<String>,
+    #[serde(default)]
+    pub mode: ElasticSearchMode,
+    pub data_stream: Option<DataStreamConfig>,
 
     #[serde(default)]
     pub compression: Compression,
@@ -77,6 +92,89 @@ pub enum Encoding {
     Default,
 }
 
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy, Derivative)]
+#[serde(rename_all = "snake_case")]
+#[derivative(Default)]
+pub enum ElasticSearchMode {
+    #[derivative(Default)]
+    Normal,
+    /// Write to data streams named `{type}-{dataset}-{namespace}` instead of
+    /// to `index`.
+    DataStream,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Derivative)]
+#[serde(deny_unknown_fields)]
+#[derivative(Default)]
+pub struct DataStreamConfig {
+    #[serde(rename = "type", default = "DataStreamConfig::default_type")]
+    #[derivative(Default(value = "DataStreamConfig::default_type()"))]
+    pub dtype: String,
+    #[serde(default = "DataStreamConfig::default_dataset")]
+    #[derivative(Default(value = "DataStreamConfig::default_dataset()"))]
+    pub dataset: String,
+    #[serde(default = "DataStreamConfig::default_namespace")]
+    #[derivative(Default(value = "DataStreamConfig::default_namespace()"))]
+    pub namespace: String,
+    /// Store the rendered `data_stream.*` values on each document, as the
+    /// Elastic data stream naming scheme expects.
+    #[serde(default = "crate::serde::default_true")]
+    #[derivative(Default(value = "true"))]
+    pub sync_fields: bool,
+}
+
+impl DataStreamConfig {
+    fn default_type() -> String {
+        "logs".into()
+    }
+
+    fn default_dataset() -> String {
+        "generic".into()
+    }
+
+    fn default_namespace() -> String {
+        "default".into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BulkAction {
+    Index,
+    Create,
+    Update,
+    Delete,
+}
+
+impl BulkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+
+    /// Updates and deletes address an existing document, so they need its id.
+    fn requires_id(&self) -> bool {
+        matches!(self, Self::Update | Self::Delete)
+    }
+}
+
+impl FromStr for BulkAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "index" => Ok(Self::Index),
+            "create" => Ok(Self::Create),
+            "update" => Ok(Self::Update),
+            "delete" => Ok(Self::Delete),
+            _ => Err(format!("Invalid bulk action {:?}", s)),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", tag = "strategy")]
 pub enum ElasticSearchAuth {
@@ -106,24 +204,27 @@ impl SinkConfig for ElasticSearchConfig {
 
         let healthcheck = healthcheck(client.clone(), common).boxed().compat();
 
-        let common = ElasticSearchCommon::parse_config(&self)?;
-        let compression = common.compression;
+        let common = Arc::new(ElasticSearchCommon::parse_config(&self)?);
         let batch = BatchSettings::default()
             .bytes(bytesize::mib(10u64))
             .timeout(1)
             .parse_config(self.batch)?;
         let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
 
-        let sink = BatchedHttpSink::with_retry_logic(
-            common,
-            Buffer::new(batch.size, compression),
-            ElasticSearchRetryLogic,
-            request,
-            batch.timeout,
+        let service = ElasticSearchService {
+            common: Arc::clone(&common),
             client,
-            cx.acker(),
-        )
-        .sink_map_err(|e| error!("Fatal elasticsearch sink error: {}", e));
+        };
+        let sink = request
+            .batch_sink(
+                ElasticSearchRetryLogic,
+                service,
+                BulkBuffer::new(batch),
+                batch.timeout,
+                cx.acker(),
+            )
+            .sink_map_err(|e| error!("Fatal elasticsearch sink error: {}", e))
+            .with_flat_map(move |event| iter_ok(common.encode_event(event)));
 
         Ok((Box::new(sink), Box::new(healthcheck)))
     }
@@ -143,7 +244,8 @@ pub struct ElasticSearchCommon {
     bulk_uri: Uri,
     authorization: Option<String>,
     credentials: Option<rusoto::AwsCredentialsProvider>,
-    index: Template,
+    target: IndexTarget,
+    bulk_action: Template,
     doc_type: String,
     tls_settings: TlsSettings,
     config: ElasticSearchConfig,
@@ -152,6 +254,18 @@ pub struct ElasticSearchCommon {
     query_params: HashMap<String, String>,
 }
 
+/// Where documents are written to.
+#[derive(Debug)]
+enum IndexTarget {
+    Index(Template),
+    DataStream {
+        dtype: Template,
+        dataset: Template,
+        namespace: Template,
+        sync_fields: bool,
+    },
+}
+
 #[derive(Debug, Snafu)]
 enum ParseError {
     #[snafu(display("Invalid host {:?}: {:?}", host, source))]
@@ -164,51 +278,95 @@ enum ParseError {
     AWSCompressionNotAllowed,
     #[snafu(display("Index template parse error: {}", source))]
     IndexTemplate { source: TemplateError },
+    #[snafu(display("Bulk action template parse error: {}", source))]
+    BulkActionTemplate { source: TemplateError },
+    #[snafu(display("{}", message))]
+    InvalidBulkAction { message: String },
+    #[snafu(display("The `{}` bulk action requires `id_key` to be set", action))]
+    BulkActionRequiresId { action: &'static str },
+    #[snafu(display("Data stream {} template parse error: {}", field, source))]
+    DataStreamTemplate {
+        field: &'static str,
+        source: TemplateError,
+    },
+    #[snafu(display("Data streams only accept the `create` bulk action"))]
+    DataStreamAction,
 }
 
-#[async_trait::async_trait]
-impl HttpSink for ElasticSearchCommon {
-    type Input = Vec<u8>;
-    type Output = Vec<u8>;
-
-    fn encode_event(&self, mut event: Event) -> Option<Self::Input> {
+impl ElasticSearchCommon {
+    fn encode_event(&self, mut event: Event) -> Option<BulkDocument> {
         self.config.encoding.apply_rules(&mut event);
 
-        let index = self
-            .index
-            .render_string(&event)
-            .map_err(|missing_keys| {
-                emit!(ElasticSearchMissingKeys { keys: missing_keys });
-            })
-            .ok()?;
-
-        let mut action = json!({
-            "index": {
-                "_index": index,
-                "_type": self.doc_type,
+        let index = match &self.target {
+            IndexTarget::Index(index) => render(index, &event)?,
+            IndexTarget::DataStream {
+                dtype,
+                dataset,
+                namespace,
+                sync_fields,
+            } => {
+                let dtype = render(dtype, &event)?;
+                let dataset = render(dataset, &event)?;
+                let namespace = render(namespace, &event)?;
+                let index = format!("{}-{}-{}", dtype, dataset, namespace);
+
+                let log = event.as_mut_log();
+                if *sync_fields {
+                    log.insert("data_stream.type", dtype);
+                    log.insert("data_stream.dataset", dataset);
+                    log.insert("data_stream.namespace", namespace);
+                }
+                // Data streams require the document timestamp in `@timestamp`.
+                let timestamp_key = event::log_schema().timestamp_key();
+                if !log.contains(&"@timestamp".into()) {
+                    if let Some(timestamp) = log.remove(timestamp_key) {
+                        log.insert("@timestamp", timestamp);
+                    }
+                }
+                index
+            }
+        };
+
+        let action = render(&self.bulk_action, &event)?;
+        let action = match action.parse::<BulkAction>() {
+            Ok(action) if !action.requires_id() || self.config.id_key.is_some() => action,
+            _ => {
+                emit!(ElasticSearchInvalidBulkAction { action });
+                return None;
             }
+        };
+
+        let mut metadata = json!({
+            "_index": index,
+            "_type": self.doc_type,
         });
-        maybe_set_id(
-            self.config.id_key.as_ref(),
-            action.pointer_mut("/index").unwrap(),
-            &mut event,
-        );
+        maybe_set_id(self.config.id_key.as_ref(), &mut metadata, &mut event);
 
-        let mut body = serde_json::to_vec(&action).unwrap();
+        let mut body = serde_json::to_vec(&json!({ action.as_str(): metadata })).unwrap();
         body.push(b'\n');
 
-        serde_json::to_writer(&mut body, &event.into_log()).unwrap();
-        body.push(b'\n');
+        match action {
+            BulkAction::Delete => (),
+            BulkAction::Update => {
+                let doc = json!({ "doc": event.into_log(), "doc_as_upsert": true });
+                serde_json::to_writer(&mut body, &doc).unwrap();
+                body.push(b'\n');
+            }
+            BulkAction::Index | BulkAction::Create => {
+                serde_json::to_writer(&mut body, &event.into_log()).unwrap();
+                body.push(b'\n');
+            }
+        }
 
         emit!(ElasticSearchEventReceived {
             byte_size: body.len(),
             index
         });
 
-        Some(body)
+        Some(BulkDocument(body))
     }
 
-    async fn build_request(&self, events: Self::Output) -> crate::Result<http::Request<Vec<u8>>> {
+    async fn build_request(&self, body: Vec<u8>) -> crate::Result<http::Request<Vec<u8>>> {
         let mut builder = Request::post(&self.bulk_uri);
 
         if let Some(credentials_provider) = &self.credentials {
@@ -222,7 +380,7 @@ impl HttpSink for ElasticSearchCommon {
                 }
             }
 
-            request.set_payload(Some(events));
+            request.set_payload(Some(body));
 
             // mut builder?
             builder = finish_signer(&mut request, &credentials_provider, builder).await?;
@@ -239,6 +397,14 @@ impl HttpSink for ElasticSearchCommon {
         } else {
             builder = builder.header("Content-Type", "application/x-ndjson");
 
+            let body = match self.compression {
+                Compression::None => body,
+                Compression::Gzip => {
+                    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                    encoder.write_all(&body)?;
+                    encoder.finish()?
+                }
+            };
             if let Some(ce) = self.compression.content_encoding() {
                 builder = builder.header("Content-Encoding", ce);
             }
@@ -253,8 +419,183 @@ impl HttpSink for ElasticSearchCommon {
                 builder = builder.header("Authorization", &auth[..]);
             }
 
-            builder.body(events).map_err(Into::into)
+            builder.body(body).map_err(Into::into)
+        }
+    }
+}
+
+fn render(template: &Template, event: &Event) -> Option<String> {
+    template
+        .render_string(event)
+        .map_err(|missing_keys| {
+            emit!(ElasticSearchMissingKeys { keys: missing_keys });
+        })
+        .ok()
+}
+
+/// A single bulk action line, followed by the document source for all actions
+/// but `delete`.
+#[derive(Clone, Debug)]
+pub struct BulkDocument(Vec<u8>);
+
+impl EncodedLength for BulkDocument {
+    fn encoded_length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// The documents of a bulk request that are yet to be accepted by
+/// Elasticsearch. Clones share the documents, so once a response comes in
+/// the documents that succeeded or were rejected outright are dropped, and a
+/// retry only sends the ones that failed with a retryable status.
+#[derive(Clone, Debug)]
+pub struct BulkRequest {
+    documents: Arc<Mutex<Vec<BulkDocument>>>,
+}
+
+impl BulkRequest {
+    fn new(documents: Vec<BulkDocument>) -> Self {
+        Self {
+            documents: Arc::new(Mutex::new(documents)),
+        }
+    }
+
+    fn body(&self) -> Vec<u8> {
+        let documents = self.documents.lock().unwrap();
+        let mut body = Vec::with_capacity(documents.iter().map(|doc| doc.0.len()).sum());
+        for document in documents.iter() {
+            body.extend_from_slice(&document.0);
+        }
+        body
+    }
+
+    /// Drops the documents that don't need to be sent again given the bulk
+    /// response for the last attempt.
+    fn settle(&self, body: &[u8]) {
+        let response = match serde_json::from_slice::<ESResultResponse>(body) {
+            Ok(response) => response,
+            // Left to `ElasticSearchRetryLogic`.
+            Err(_) => return,
+        };
+
+        let mut documents = self.documents.lock().unwrap();
+        if !response.errors {
+            documents.clear();
+            return;
+        }
+        // Without a result for every document there's no telling which ones
+        // to keep, so a retry sends all of them again.
+        if response.items.len() != documents.len() {
+            warn!(
+                message =
+                    "bulk response doesn't match the request; not retrying individual documents.",
+                documents = documents.len(),
+                items = response.items.len(),
+                rate_limit_secs = 30
+            );
+            return;
         }
+
+        let mut results = response.items.into_iter().map(ESResultItem::into_result);
+        documents.retain(|_| match results.next().flatten() {
+            Some(ESItemResult {
+                error: Some(error),
+                status,
+            }) => {
+                if is_retryable_status(status) {
+                    true
+                } else {
+                    emit!(ElasticSearchDocumentRejected {
+                        status,
+                        error_type: &error.err_type,
+                        reason: &error.reason,
+                    });
+                    false
+                }
+            }
+            _ => false,
+        });
+    }
+}
+
+/// Batches documents by their encoded size, like `Buffer`, but keeps them
+/// apart so that they can be retried individually.
+struct BulkBuffer(VecBuffer<BulkDocument>);
+
+impl BulkBuffer {
+    fn new(settings: BatchSettings<Self>) -> Self {
+        Self(VecBuffer::new(
+            settings.into::<VecBuffer<BulkDocument>>().size,
+        ))
+    }
+}
+
+impl Batch for BulkBuffer {
+    type Input = BulkDocument;
+    type Output = BulkRequest;
+
+    fn get_settings_defaults(
+        config: BatchConfig,
+        defaults: BatchSettings<Self>,
+    ) -> Result<BatchSettings<Self>, crate::sinks::util::batch::BatchError> {
+        VecBuffer::<BulkDocument>::get_settings_defaults(
+            config.use_size_as_bytes()?,
+            defaults.into(),
+        )
+        .map(BatchSettings::into)
+    }
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        self.0.push(item)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self(self.0.fresh())
+    }
+
+    fn finish(self) -> Self::Output {
+        BulkRequest::new(self.0.finish())
+    }
+
+    fn num_items(&self) -> usize {
+        self.0.num_items()
+    }
+}
+
+#[derive(Clone)]
+struct ElasticSearchService {
+    common: Arc<ElasticSearchCommon>,
+    client: HttpClient,
+}
+
+impl Service<BulkRequest> for ElasticSearchService {
+    type Response = http::Response<Bytes>;
+    type Error = crate::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: BulkRequest) -> Self::Future {
+        let common = Arc::clone(&self.common);
+        let mut client = self.client.clone();
+
+        Box::pin(async move {
+            let http_request = common.build_request(request.body()).await?;
+            let response = client.call(http_request.map(Body::from)).await?;
+            let (parts, body) = response.into_parts();
+            let mut body = hyper::body::aggregate(body).await?;
+            let response = hyper::Response::from_parts(parts, body.to_bytes());
+            if response.status().is_success() {
+                request.settle(response.body());
+            }
+            Ok(response)
+        })
     }
 }
 
@@ -263,14 +604,17 @@ struct ElasticSearchRetryLogic;
 
 #[derive(Deserialize, Debug)]
 struct ESResultResponse {
+    #[serde(default)]
+    errors: bool,
     items: Vec<ESResultItem>,
 }
+/// The result of one bulk item, keyed by its action.
 #[derive(Deserialize, Debug)]
-struct ESResultItem {
-    index: ESIndexResult,
-}
+struct ESResultItem(BTreeMap<String, ESItemResult>);
 #[derive(Deserialize, Debug)]
-struct ESIndexResult {
+struct ESItemResult {
+    #[serde(default)]
+    status: u16,
     error: Option<ESErrorDetails>,
 }
 #[derive(Deserialize, Debug)]
@@ -280,6 +624,18 @@ struct ESErrorDetails {
     err_type: String,
 }
 
+impl ESResultItem {
+    fn into_result(self) -> Option<ESItemResult> {
+        self.0.into_iter().next().map(|(_, result)| result)
+    }
+}
+
+/// Bulk items failing with these statuses may succeed when sent again, the
+/// rest are rejected for good.
+fn is_retryable_status(status: u16) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS.as_u16() || status >= 500
+}
+
 impl RetryLogic for ElasticSearchRetryLogic {
     type Error = hyper::Error;
     type Response = hyper::Response<Bytes>;
@@ -326,20 +682,38 @@ impl RetryLogic for ElasticSearchRetryLogic {
                             )
                         }
                         Ok(esrr) => {
-                            match esrr.items.into_iter().find_map(|item| item.index.error) {
-                                Some(error) => warn!(
-                                    message = "ElasticSearch error response",
-                                    err_type = %error.err_type,
-                                    reason = %error.reason,
-                                    rate_limit_secs = 30
-                                ),
-                                _ => warn!(
+                            let (retryable, rejected) = esrr
+                                .items
+                                .into_iter()
+                                .filter_map(ESResultItem::into_result)
+                                .filter(|result| result.error.is_some())
+                                .fold((0, 0), |(retryable, rejected), result| {
+                                    if is_retryable_status(result.status) {
+                                        (retryable + 1, rejected)
+                                    } else {
+                                        (retryable, rejected + 1)
+                                    }
+                                });
+                            // The documents to retry have been narrowed down
+                            // by `BulkRequest::settle` already.
+                            if retryable > 0 {
+                                RetryAction::Retry(format!(
+                                    "{} documents failed with a retryable error",
+                                    retryable
+                                ))
+                            } else if rejected > 0 {
+                                RetryAction::DontRetry(format!(
+                                    "{} documents were rejected",
+                                    rejected
+                                ))
+                            } else {
+                                warn!(
                                     message = "Unusual ElasticSearch error response",
                                     %body,
                                     rate_limit_secs = 30
-                                ),
-                            };
-                            RetryAction::DontRetry("some messages failed".into())
+                                );
+                                RetryAction::DontRetry("some messages failed".into())
+                            }
                         }
                     },
                     None => RetryAction::Successful,
@@ -391,8 +765,46 @@ impl ElasticSearchCommon {
             return Err(ParseError::AWSCompressionNotAllowed.into());
         }
 
-        let index = config.index.as_deref().unwrap_or("vector-%Y.%m.%d");
-        let index = Template::try_from(index).context(IndexTemplate)?;
+        let target = match config.mode {
+            ElasticSearchMode::Normal => {
+                let index = config.index.as_deref().unwrap_or("vector-%Y.%m.%d");
+                IndexTarget::Index(Template::try_from(index).context(IndexTemplate)?)
+            }
+            ElasticSearchMode::DataStream => {
+                let data_stream = config.data_stream.clone().unwrap_or_default();
+                let template = |field, value: &str| {
+                    Template::try_from(value).context(DataStreamTemplate { field })
+                };
+                IndexTarget::DataStream {
+                    dtype: template("type", &data_stream.dtype)?,
+                    dataset: template("dataset", &data_stream.dataset)?,
+                    namespace: template("namespace", &data_stream.namespace)?,
+                    sync_fields: data_stream.sync_fields,
+                }
+            }
+        };
+
+        let bulk_action = match (config.mode, config.bulk_action.as_deref()) {
+            (ElasticSearchMode::DataStream, Some(action)) if action != "create" => {
+                return Err(ParseError::DataStreamAction.into())
+            }
+            (ElasticSearchMode::DataStream, _) => "create",
+            (ElasticSearchMode::Normal, action) => action.unwrap_or("index"),
+        };
+        let bulk_action = Template::try_from(bulk_action).context(BulkActionTemplate)?;
+        if !bulk_action.is_dynamic() {
+            let action = String::from_utf8_lossy(bulk_action.get_ref());
+            match action.parse::<BulkAction>() {
+                Err(message) => return Err(ParseError::InvalidBulkAction { message }.into()),
+                Ok(action) if action.requires_id() && config.id_key.is_none() => {
+                    return Err(ParseError::BulkActionRequiresId {
+                        action: action.as_str(),
+                    }
+                    .into())
+                }
+                Ok(_) => (),
+            }
+        }
 
         let doc_type = config.doc_type.clone().unwrap_or_else(|| "_doc".into());
 
@@ -420,7 +832,8 @@ impl ElasticSearchCommon {
             bulk_uri,
             authorization,
             credentials,
-            index,
+            target,
+            bulk_action,
             doc_type,
             tls_settings,
             config,
@@ -558,6 +971,156 @@ mod tests {
             RetryAction::DontRetry(_)
         ));
     }
+
+    const PARTIAL_FAILURE: &str = r#"{"took":3,"errors":true,"items":[
+        {"create":{"_index":"test","status":201}},
+        {"create":{"_index":"test","status":429,"error":{"type":"es_rejected_execution_exception","reason":"rejected execution"}}},
+        {"create":{"_index":"test","status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse field [count]"}}}
+    ]}"#;
+
+    #[test]
+    fn retries_retryable_item_errors() {
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from(PARTIAL_FAILURE))
+            .unwrap();
+        let logic = ElasticSearchRetryLogic;
+        assert!(matches!(
+            logic.should_retry_response(&response),
+            RetryAction::Retry(_)
+        ));
+    }
+
+    #[test]
+    fn bulk_request_keeps_only_retryable_documents() {
+        let request = BulkRequest::new(vec![
+            BulkDocument(b"one\n".to_vec()),
+            BulkDocument(b"two\n".to_vec()),
+            BulkDocument(b"three\n".to_vec()),
+        ]);
+        let retry = request.clone();
+
+        request.settle(PARTIAL_FAILURE.as_bytes());
+        assert_eq!(retry.body(), b"two\n".to_vec());
+
+        request.settle(br#"{"took":1,"errors":false,"items":[{"create":{"status":201}}]}"#);
+        assert!(retry.body().is_empty());
+    }
+
+    #[test]
+    fn bulk_request_keeps_documents_on_mismatched_response() {
+        let request = BulkRequest::new(vec![
+            BulkDocument(b"one\n".to_vec()),
+            BulkDocument(b"two\n".to_vec()),
+        ]);
+
+        request.settle(PARTIAL_FAILURE.as_bytes());
+        assert_eq!(request.body(), b"one\ntwo\n".to_vec());
+    }
+
+    fn encode(config: ElasticSearchConfig, event: Event) -> Vec<serde_json::Value> {
+        let common = ElasticSearchCommon::parse_config(&ElasticSearchConfig {
+            host: "http://localhost:9200".into(),
+            ..config
+        })
+        .unwrap();
+        let document = common.encode_event(event).unwrap();
+        String::from_utf8(document.0)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn encodes_templated_bulk_action() {
+        let config = ElasticSearchConfig {
+            bulk_action: Some("{{ action }}".into()),
+            id_key: Some("id".into()),
+            index: Some("vector".into()),
+            ..Default::default()
+        };
+
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("action", "delete");
+        event.as_mut_log().insert("id", "42");
+        let lines = encode(config.clone(), event);
+        assert_eq!(
+            lines,
+            vec![json!({"delete": {"_index": "vector", "_type": "_doc", "_id": "42"}})]
+        );
+
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("action", "update");
+        let lines = encode(config.clone(), event);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["doc"]["message"], json!("hello"));
+        assert_eq!(lines[1]["doc_as_upsert"], json!(true));
+
+        let common = ElasticSearchCommon::parse_config(&ElasticSearchConfig {
+            host: "http://localhost:9200".into(),
+            ..config
+        })
+        .unwrap();
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("action", "upsert");
+        assert!(common.encode_event(event).is_none());
+    }
+
+    #[test]
+    fn encodes_data_stream_documents() {
+        let config = ElasticSearchConfig {
+            mode: ElasticSearchMode::DataStream,
+            data_stream: Some(DataStreamConfig {
+                dataset: "{{ app }}".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("app", "nginx");
+        let lines = encode(config, event);
+        assert_eq!(lines[0]["create"]["_index"], json!("logs-nginx-default"));
+        assert_eq!(lines[1]["data_stream"]["dataset"], json!("nginx"));
+        assert!(lines[1].get("@timestamp").is_some());
+        assert!(lines[1].get("timestamp").is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_bulk_actions() {
+        let config = ElasticSearchConfig {
+            host: "http://localhost:9200".into(),
+            bulk_action: Some("upsert".into()),
+            ..Default::default()
+        };
+        assert!(ElasticSearchCommon::parse_config(&config).is_err());
+
+        let config = ElasticSearchConfig {
+            mode: ElasticSearchMode::DataStream,
+            bulk_action: Some("index".into()),
+            ..config
+        };
+        assert!(ElasticSearchCommon::parse_config(&config).is_err());
+    }
+
+    #[test]
+    fn rejects_update_and_delete_without_id_key() {
+        for action in &["update", "delete"] {
+            let config = ElasticSearchConfig {
+                host: "http://localhost:9200".into(),
+                bulk_action: Some((*action).into()),
+                ..Default::default()
+            };
+            assert!(ElasticSearchCommon::parse_config(&config).is_err());
+
+            let config = ElasticSearchConfig {
+                id_key: Some("id".into()),
+                ..config
+            };
+            assert!(ElasticSearchCommon::parse_config(&config).is_ok());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use crate::expiring_hash_map::ExpiringHashMap;
 use crate::{
-    event::{self, Event},
+    event::Event,
     sinks::util::{
-        encoding::{EncodingConfigWithDefault, EncodingConfiguration},
+        encoding::{EncodingConfigWithDefault, EncodingConfiguration, StandardEncodings},
         StreamSink,
     },
     template::Template,
@@ -10,10 +10,16 @@ use crate::{
 };
 use async_trait::async_trait;
 use bytes::Bytes;
+use flate2::write::GzEncoder;
 use futures::pin_mut;
 use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
+use snafu::Snafu;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 use tokio::{
     fs::{self, File},
     io::AsyncWriteExt,
@@ -33,29 +39,87 @@ pub struct FileSinkConfig {
         default,
         skip_serializing_if = "crate::serde::skip_serializing_if_default"
     )]
-    pub encoding: EncodingConfigWithDefault<Encoding>,
+    pub encoding: EncodingConfigWithDefault<StandardEncodings>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub compression: Compression,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub rotation: RotationConfig,
+    /// Once reached, the least recently written file is closed before
+    /// opening a new one.
+    pub max_open_files: Option<usize>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub fsync: FsyncPolicy,
 }
 
-inventory::submit! {
-    SinkDescription::new_without_default::<FileSinkConfig>("file")
+#[derive(Deserialize, Serialize, Debug, Derivative, Copy, Clone, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[derivative(Default)]
+    None,
+    Gzip,
+    Zstd,
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
+/// Files are rotated by renaming them with a numbered suffix, `.1` being
+/// the most recent, and opening a new file at the original path.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RotationConfig {
+    /// Rotate files once they reach this size on disk.
+    pub max_bytes: Option<u64>,
+    /// Rotate files created longer ago than this, using their modification
+    /// time on filesystems that don't record creation times.
+    pub interval_secs: Option<u64>,
+    /// The number of rotated files to keep, all of them if not set.
+    pub max_files: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Derivative, Copy, Clone, Eq, PartialEq)]
+#[derivative(Default)]
 #[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Ndjson,
+pub enum FsyncPolicy {
+    /// Leave it to the operating system.
+    #[derivative(Default)]
+    Never,
+    /// Sync files when they are closed or rotated.
+    OnClose,
+    /// Sync files after every event, flushing the compressor first so the
+    /// synced data can be decompressed.
+    Always,
 }
 
-impl Default for Encoding {
-    fn default() -> Self {
-        Encoding::Text
-    }
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("max_open_files must be greater than zero"))]
+    ZeroMaxOpenFiles,
+    #[snafu(display("rotation.max_files must be greater than zero"))]
+    ZeroMaxFiles,
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<FileSinkConfig>("file")
 }
 
 #[typetag::serde(name = "file")]
 impl SinkConfig for FileSinkConfig {
     fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        if self.max_open_files == Some(0) {
+            return Err(BuildError::ZeroMaxOpenFiles.into());
+        }
+        if self.rotation.max_files == Some(0) {
+            return Err(BuildError::ZeroMaxFiles.into());
+        }
+
         let sink = FileSink::new(&self);
         let sink = streaming_sink::compat::adapt_to_topology(sink);
         let sink = StreamSink::new(sink, cx.acker());
@@ -74,9 +138,13 @@ impl SinkConfig for FileSinkConfig {
 #[derive(Debug)]
 pub struct FileSink {
     path: Template,
-    encoding: EncodingConfigWithDefault<Encoding>,
+    encoding: EncodingConfigWithDefault<StandardEncodings>,
+    compression: Compression,
+    rotation: RotationConfig,
+    max_open_files: Option<usize>,
+    fsync: FsyncPolicy,
     idle_timeout: Duration,
-    files: ExpiringHashMap<Bytes, File>,
+    files: ExpiringHashMap<Bytes, OutFile>,
 }
 
 impl FileSink {
@@ -84,6 +152,10 @@ impl FileSink {
         Self {
             path: config.path.clone(),
             encoding: config.encoding.clone(),
+            compression: config.compression,
+            rotation: config.rotation.clone(),
+            max_open_files: config.max_open_files,
+            fsync: config.fsync,
             idle_timeout: Duration::from_secs(config.idle_timeout_secs.unwrap_or(30)),
             files: ExpiringHashMap::default(),
         }
@@ -121,6 +193,7 @@ impl FileSink {
                         None => {
                             // If we got `None` - terminate the processing.
                             debug!(message = "Receiver exhausted, terminating the processing loop.");
+                            self.close_all().await;
                             break;
                         }
                         Some(event) => self.process_event(event).await,
@@ -131,13 +204,12 @@ impl FileSink {
                         // We do not poll map when it's empty, so we should
                         // never reach this branch.
                         None => unreachable!(),
-                        Some(Ok((mut expired_file, path))) => {
+                        Some(Ok((expired_file, path))) => {
                             // We got an expired file. All we really want is to
                             // flush and close it.
-                            if let Err(error) = expired_file.flush().await {
+                            if let Err(error) = expired_file.close(self.fsync).await {
                                 error!(message = "Failed to flush file.", ?path, %error);
                             }
-                            drop(expired_file); // ignore close error
                         }
                         Some(Err(error)) => error!(
                             message = "An error occured while expiring a file.",
@@ -162,6 +234,24 @@ impl FileSink {
             }
         };
 
+        if let Some(interval) = self.rotation.interval_secs {
+            let created_at = match self.files.get(&path) {
+                Some(file) => Some(file.created_at),
+                // Files closed while idle are rotated based on their age
+                // on disk, not on when they get reopened.
+                None => fs::metadata(BytesPath::new(path.clone()))
+                    .await
+                    .and_then(|metadata| created_at(&metadata))
+                    .ok(),
+            };
+            let expired = created_at.map_or(false, |created_at| {
+                elapsed_since(created_at) >= Duration::from_secs(interval)
+            });
+            if expired {
+                self.close_and_rotate(&path).await;
+            }
+        }
+
         let next_deadline = self.deadline_at();
         trace!(message = "Computed next deadline.", ?next_deadline, ?path);
 
@@ -169,8 +259,14 @@ impl FileSink {
             trace!(message = "Working with an already opened file.", ?path);
             file
         } else {
+            if let Some(max_open_files) = self.max_open_files {
+                while self.files.len() >= max_open_files {
+                    self.close_least_recently_used().await;
+                }
+            }
+
             trace!(message = "Opening new file.", ?path);
-            let file = match open_file(BytesPath::new(path.clone())).await {
+            let file = match OutFile::open(BytesPath::new(path.clone()), self.compression).await {
                 Ok(file) => file,
                 Err(error) => {
                     // We coundn't open the file for this event.
@@ -185,48 +281,262 @@ impl FileSink {
         };
 
         trace!(message = "Writing an event to file.", ?path);
-        if let Err(error) = write_event_to_file(file, event, &self.encoding).await {
+        if let Err(error) = write_event_to_file(file, event, &self.encoding, self.fsync).await {
             error!(message = "Failed to write file.", ?path, %error);
         }
+
+        if let Some(max_bytes) = self.rotation.max_bytes {
+            if file.size >= max_bytes {
+                self.close_and_rotate(&path).await;
+            }
+        }
+    }
+
+    async fn close_and_rotate(&mut self, path: &Bytes) {
+        if let Some((file, _)) = self.files.remove(path) {
+            if let Err(error) = file.close(self.fsync).await {
+                error!(message = "Failed to flush file.", ?path, %error);
+            }
+        }
+
+        trace!(message = "Rotating file.", ?path);
+        if let Err(error) = rotate_file(BytesPath::new(path.clone()), self.rotation.max_files).await
+        {
+            error!(message = "Failed to rotate file.", ?path, %error);
+        }
+    }
+
+    async fn close_least_recently_used(&mut self) {
+        let path = match self
+            .files
+            .iter()
+            .min_by_key(|(_, file)| file.last_used)
+            .map(|(path, _)| path.clone())
+        {
+            Some(path) => path,
+            None => return,
+        };
+
+        trace!(message = "Closing least recently used file.", ?path);
+        if let Some((file, _)) = self.files.remove(&path) {
+            if let Err(error) = file.close(self.fsync).await {
+                error!(message = "Failed to flush file.", ?path, %error);
+            }
+        }
+    }
+
+    async fn close_all(&mut self) {
+        let files = self.files.drain().collect::<Vec<_>>();
+        for (path, file) in files {
+            if let Err(error) = file.close(self.fsync).await {
+                error!(message = "Failed to flush file.", ?path, %error);
+            }
+        }
     }
 }
 
-async fn open_file(path: impl AsRef<std::path::Path>) -> std::io::Result<File> {
-    let parent = path.as_ref().parent();
+/// An open output file, compressing what is written to it if configured.
+#[derive(Debug)]
+struct OutFile {
+    file: File,
+    encoder: Option<Encoder>,
+    /// The size of the file on disk.
+    size: u64,
+    created_at: SystemTime,
+    last_used: Instant,
+}
+
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Encoder<Vec<u8>>),
+}
+
+impl std::fmt::Debug for Encoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Gzip(_) => f.write_str("Gzip"),
+            Self::Zstd(_) => f.write_str("Zstd"),
+        }
+    }
+}
+
+impl Encoder {
+    fn new(compression: Compression) -> io::Result<Option<Self>> {
+        Ok(match compression {
+            Compression::None => None,
+            Compression::Gzip => Some(Self::Gzip(GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            ))),
+            Compression::Zstd => Some(Self::Zstd(zstd::stream::write::Encoder::new(
+                Vec::new(),
+                0,
+            )?)),
+        })
+    }
+
+    /// Compresses the data, returning the compressed output produced so far.
+    fn compress(&mut self, buf: &[u8]) -> io::Result<Vec<u8>> {
+        let output = match self {
+            Self::Gzip(encoder) => {
+                encoder.write_all(buf)?;
+                encoder.get_mut()
+            }
+            Self::Zstd(encoder) => {
+                encoder.write_all(buf)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(std::mem::replace(output, Vec::new()))
+    }
 
-    if let Some(parent) = parent {
-        fs::create_dir_all(parent).await?;
+    /// Flushes the data buffered by the compressor, returning the output.
+    fn flush(&mut self) -> io::Result<Vec<u8>> {
+        let output = match self {
+            Self::Gzip(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Self::Zstd(encoder) => {
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(std::mem::replace(output, Vec::new()))
     }
 
-    fs::OpenOptions::new()
-        .read(false)
-        .write(true)
-        .create(true)
-        .append(true)
-        .open(path)
-        .await
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
 }
 
-pub fn encode_event(encoding: &EncodingConfigWithDefault<Encoding>, mut event: Event) -> Vec<u8> {
-    encoding.apply_rules(&mut event);
-    let log = event.into_log();
-    match encoding.codec() {
-        Encoding::Ndjson => serde_json::to_vec(&log).expect("Unable to encode event as JSON."),
-        Encoding::Text => log
-            .get(&event::log_schema().message_key())
-            .map(|v| v.to_string_lossy().into_bytes())
-            .unwrap_or_default(),
+impl OutFile {
+    async fn open(path: impl AsRef<Path>, compression: Compression) -> io::Result<Self> {
+        let parent = path.as_ref().parent();
+
+        if let Some(parent) = parent {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .read(false)
+            .write(true)
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let metadata = file.metadata().await?;
+
+        Ok(Self {
+            file,
+            encoder: Encoder::new(compression)?,
+            size: metadata.len(),
+            created_at: created_at(&metadata)?,
+            last_used: Instant::now(),
+        })
+    }
+
+    async fn write(&mut self, buf: &[u8], fsync: FsyncPolicy) -> io::Result<()> {
+        self.last_used = Instant::now();
+        let compressed;
+        let buf = match &mut self.encoder {
+            Some(encoder) => {
+                let mut output = encoder.compress(buf)?;
+                if fsync == FsyncPolicy::Always {
+                    output.extend(encoder.flush()?);
+                }
+                compressed = output;
+                &compressed[..]
+            }
+            None => buf,
+        };
+
+        if !buf.is_empty() {
+            self.file.write_all(buf).await?;
+            self.size += buf.len() as u64;
+        }
+        if fsync == FsyncPolicy::Always {
+            self.file.sync_data().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(mut self, fsync: FsyncPolicy) -> io::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            let trailer = encoder.finish()?;
+            self.file.write_all(&trailer).await?;
+        }
+        self.file.flush().await?;
+        if fsync != FsyncPolicy::Never {
+            self.file.sync_all().await?;
+        }
+        Ok(())
     }
 }
 
+/// When the file was created, or last modified on filesystems that don't
+/// record creation times.
+fn created_at(metadata: &std::fs::Metadata) -> io::Result<SystemTime> {
+    metadata.created().or_else(|_| metadata.modified())
+}
+
+/// The time elapsed since `time`, zero if the clock went backwards.
+fn elapsed_since(time: SystemTime) -> Duration {
+    time.elapsed().unwrap_or_default()
+}
+
+/// Renames the file at `path` to `path.1`, after moving the previously
+/// rotated files up by one, dropping the oldest one if `max_files` of them
+/// are kept already.
+async fn rotate_file(path: impl AsRef<Path>, max_files: Option<usize>) -> io::Result<()> {
+    let path = path.as_ref();
+    if fs::metadata(path).await.is_err() {
+        return Ok(());
+    }
+
+    let mut last = 1;
+    while max_files.map_or(true, |max_files| last < max_files)
+        && fs::metadata(numbered_path(path, last)).await.is_ok()
+    {
+        last += 1;
+    }
+    for n in (1..last).rev() {
+        fs::rename(numbered_path(path, n), numbered_path(path, n + 1)).await?;
+    }
+    fs::rename(path, numbered_path(path, 1)).await
+}
+
+fn numbered_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{}", n));
+    path.into()
+}
+
+pub fn encode_event(
+    encoding: &EncodingConfigWithDefault<StandardEncodings>,
+    mut event: Event,
+) -> Option<Vec<u8>> {
+    encoding.apply_rules(&mut event);
+    encoding
+        .codec()
+        .encode_delimited(&event)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()
+}
+
 async fn write_event_to_file(
-    file: &mut File,
+    file: &mut OutFile,
     event: Event,
-    encoding: &EncodingConfigWithDefault<Encoding>,
+    encoding: &EncodingConfigWithDefault<StandardEncodings>,
+    fsync: FsyncPolicy,
 ) -> Result<(), std::io::Error> {
-    let mut buf = encode_event(encoding, event);
-    buf.push(b'\n');
-    file.write_all(&buf[..]).await
+    match encode_event(encoding, event) {
+        Some(buf) => file.write(&buf[..], fsync).await,
+        None => Ok(()),
+    }
 }
 
 #[async_trait]
@@ -261,7 +571,11 @@ mod tests {
         let config = FileSinkConfig {
             path: template.clone().try_into().unwrap(),
             idle_timeout_secs: None,
-            encoding: Encoding::Text.into(),
+            encoding: StandardEncodings::text().into(),
+            compression: Compression::None,
+            rotation: RotationConfig::default(),
+            max_open_files: None,
+            fsync: FsyncPolicy::Never,
         };
 
         let mut sink = FileSink::new(&config);
@@ -294,7 +608,11 @@ mod tests {
         let config = FileSinkConfig {
             path: template.try_into().unwrap(),
             idle_timeout_secs: None,
-            encoding: Encoding::Text.into(),
+            encoding: StandardEncodings::text().into(),
+            compression: Compression::None,
+            rotation: RotationConfig::default(),
+            max_open_files: None,
+            fsync: FsyncPolicy::Never,
         };
 
         let mut sink = FileSink::new(&config);
@@ -377,7 +695,11 @@ mod tests {
         let config = FileSinkConfig {
             path: template.clone().try_into().unwrap(),
             idle_timeout_secs: Some(1),
-            encoding: Encoding::Text.into(),
+            encoding: StandardEncodings::text().into(),
+            compression: Compression::None,
+            rotation: RotationConfig::default(),
+            max_open_files: None,
+            fsync: FsyncPolicy::Never,
         };
 
         let mut sink = FileSink::new(&config);
@@ -407,4 +729,170 @@ mod tests {
         let output = lines_from_file(template);
         assert_eq!(input, output);
     }
+
+    fn test_config(path: impl Into<String>) -> FileSinkConfig {
+        FileSinkConfig {
+            path: path.into().try_into().unwrap(),
+            idle_timeout_secs: None,
+            encoding: StandardEncodings::text().into(),
+            compression: Compression::None,
+            rotation: RotationConfig::default(),
+            max_open_files: None,
+            fsync: FsyncPolicy::Never,
+        }
+    }
+
+    fn run_sink(config: FileSinkConfig, events: Vec<Event>) {
+        let mut sink = FileSink::new(&config);
+        let events = stream::iter(events.into_iter());
+        let mut rt = crate::test_util::runtime();
+        let _ = rt
+            .block_on_std(async move { sink.run(events).await })
+            .unwrap();
+    }
+
+    #[test]
+    fn gzip_compression() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        test_util::trace_init();
+
+        let template = temp_file();
+        let config = FileSinkConfig {
+            compression: Compression::Gzip,
+            ..test_config(template.to_string_lossy())
+        };
+        let (input, _) = random_lines_with_stream(100, 64);
+        run_sink(config, input.iter().cloned().map(Event::from).collect());
+
+        let mut output = String::new();
+        GzDecoder::new(std::fs::File::open(template).unwrap())
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(input, output.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn gzip_fsync_always_flushes_compressor() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let path = temp_file();
+        let file_path = path.clone();
+        let mut rt = crate::test_util::runtime();
+        let _file = rt.block_on_std(async move {
+            let mut file = OutFile::open(file_path, Compression::Gzip).await.unwrap();
+            file.write(b"hello\n", FsyncPolicy::Always).await.unwrap();
+            file
+        });
+
+        // The file is still open, so the gzip trailer hasn't been written.
+        let mut output = [0; 6];
+        GzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_exact(&mut output)
+            .unwrap();
+        assert_eq!(&output, b"hello\n");
+    }
+
+    #[test]
+    fn interval_rotation_uses_age_on_disk() {
+        test_util::trace_init();
+
+        let template = temp_file();
+        std::fs::write(&template, "old\n").unwrap();
+        std::thread::sleep(Duration::from_millis(1100));
+
+        let config = FileSinkConfig {
+            rotation: RotationConfig {
+                interval_secs: Some(1),
+                ..Default::default()
+            },
+            ..test_config(template.to_string_lossy())
+        };
+        run_sink(config, vec![Event::from("new")]);
+
+        assert_eq!(lines_from_file(numbered_path(&template, 1)), vec!["old"]);
+        assert_eq!(lines_from_file(&template), vec!["new"]);
+    }
+
+    #[test]
+    fn size_rotation() {
+        test_util::trace_init();
+
+        let template = temp_file();
+        let config = FileSinkConfig {
+            rotation: RotationConfig {
+                max_bytes: Some(100),
+                max_files: Some(2),
+                ..Default::default()
+            },
+            ..test_config(template.to_string_lossy())
+        };
+        // Each line is 50 bytes with the newline, so every file gets two.
+        let (input, _) = random_lines_with_stream(49, 6);
+        run_sink(config, input.iter().cloned().map(Event::from).collect());
+
+        let rotated = |n| numbered_path(&template, n);
+        assert!(!template.exists());
+        assert_eq!(lines_from_file(rotated(1)), &input[4..6]);
+        assert_eq!(lines_from_file(rotated(2)), &input[2..4]);
+        assert!(!rotated(3).exists());
+    }
+
+    #[test]
+    fn max_open_files() {
+        test_util::trace_init();
+
+        let directory = temp_dir();
+        let mut template = directory.to_string_lossy().to_string();
+        template.push_str("/{{level}}.log");
+        let config = FileSinkConfig {
+            max_open_files: Some(1),
+            ..test_config(template)
+        };
+
+        let (mut input, _) = random_events_with_stream(32, 4);
+        for (event, level) in input.iter_mut().zip(&["info", "error", "info", "error"]) {
+            event.as_mut_log().insert("level", *level);
+        }
+
+        let mut sink = FileSink::new(&config);
+        let events = stream::iter(input.clone().into_iter());
+        let mut rt = crate::test_util::runtime();
+        let sink = rt.block_on_std(async move {
+            sink.run(events).await.unwrap();
+            sink
+        });
+        assert!(sink.files.is_empty());
+
+        let message =
+            |event: &Event| event.as_log()[&event::log_schema().message_key()].to_string_lossy();
+        assert_eq!(
+            lines_from_file(directory.join("info.log")),
+            vec![message(&input[0]), message(&input[2])]
+        );
+        assert_eq!(
+            lines_from_file(directory.join("error.log")),
+            vec![message(&input[1]), message(&input[3])]
+        );
+    }
+
+    #[test]
+    fn rejects_zero_limits() {
+        let config = FileSinkConfig {
+            max_open_files: Some(0),
+            ..test_config("/tmp/test.log")
+        };
+        assert!(config.build(SinkContext::new_test()).is_err());
+
+        let config = FileSinkConfig {
+            rotation: RotationConfig {
+                max_files: Some(0),
+                ..Default::default()
+            },
+            ..test_config("/tmp/test.log")
+        };
+        assert!(config.build(SinkContext::new_test()).is_err());
+    }
 }
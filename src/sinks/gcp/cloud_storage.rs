@@ -1,11 +1,12 @@
 use super::{healthcheck_response, GcpAuthConfig, GcpCredentials, Scope};
 use crate::{
-    event::{self, Event},
+    event::Event,
     serde::to_string,
     sinks::{
         util::{
-            encoding::{EncodingConfig, EncodingConfiguration},
+            encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
             http::{HttpClient, HttpClientFuture},
+            parquet::{ParquetBuffer, ParquetConfig, ParquetEncoder, ParquetRow, ParquetService},
             retries2::{RetryAction, RetryLogic},
             service2::{ServiceBuilderExt, TowerCompat, TowerRequestConfig},
             BatchConfig, BatchSettings, Buffer, Compression, PartitionBatchSink, PartitionBuffer,
@@ -31,6 +32,7 @@ use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::sync::Arc;
 use std::task::Poll;
 use tower03::{Service, ServiceBuilder};
 use tracing::field;
@@ -65,7 +67,8 @@ pub struct GcsSinkConfig {
     filename_time_format: Option<String>,
     filename_append_uuid: Option<bool>,
     filename_extension: Option<String>,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
+    parquet: Option<ParquetConfig>,
     #[serde(default)]
     compression: Compression,
     #[serde(default)]
@@ -78,7 +81,7 @@ pub struct GcsSinkConfig {
 }
 
 #[cfg(test)]
-fn default_config(e: Encoding) -> GcsSinkConfig {
+fn default_config(e: StandardEncodings) -> GcsSinkConfig {
     GcsSinkConfig {
         bucket: Default::default(),
         acl: Default::default(),
@@ -89,6 +92,7 @@ fn default_config(e: Encoding) -> GcsSinkConfig {
         filename_append_uuid: Default::default(),
         filename_extension: Default::default(),
         encoding: e.into(),
+        parquet: Default::default(),
         compression: Compression::Gzip,
         batch: Default::default(),
         request: Default::default(),
@@ -129,26 +133,20 @@ lazy_static! {
     };
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-enum Encoding {
-    Text,
-    Ndjson,
+inventory::submit! {
+    SinkDescription::new_without_default::<GcsSinkConfig>(NAME)
 }
 
-impl Encoding {
-    fn content_type(&self) -> &'static str {
-        match self {
-            Self::Text => "text/plain",
-            Self::Ndjson => "application/x-ndjson",
+impl GcsSinkConfig {
+    // Parquet compresses its pages itself, so objects are written as is.
+    fn compression(&self) -> Compression {
+        match self.parquet {
+            Some(_) => Compression::None,
+            None => self.compression,
         }
     }
 }
 
-inventory::submit! {
-    SinkDescription::new_without_default::<GcsSinkConfig>(NAME)
-}
-
 #[async_trait::async_trait]
 #[typetag::serde(name = "gcp_cloud_storage")]
 impl SinkConfig for GcsSinkConfig {
@@ -207,11 +205,6 @@ impl GcsSink {
         let request = config.request.unwrap_with(&REQUEST_DEFAULTS);
         let encoding = config.encoding.clone();
 
-        let batch = BatchSettings::default()
-            .bytes(bytesize::mib(10u64))
-            .timeout(300)
-            .parse_config(config.batch)?;
-
         let key_prefix = config.key_prefix.as_deref().unwrap_or("date=%F/");
         let key_prefix = Template::try_from(key_prefix).context(KeyPrefixTemplate)?;
 
@@ -222,14 +215,46 @@ impl GcsSink {
             .settings(request, GcsRetryLogic)
             .service(self);
 
-        let buffer = PartitionBuffer::new(Buffer::new(batch.size, config.compression));
+        match &config.parquet {
+            Some(parquet) => {
+                let encoder = Arc::new(ParquetEncoder::new(parquet)?);
+                let batch = BatchSettings::default()
+                    .bytes(bytesize::mib(10u64))
+                    .timeout(300)
+                    .parse_config(config.batch)?;
+                let buffer =
+                    PartitionBuffer::new(ParquetBuffer::new(batch.size, Arc::clone(&encoder)));
+
+                let sink = PartitionBatchSink::new(
+                    TowerCompat::new(ParquetService::new(svc)),
+                    buffer,
+                    batch.timeout,
+                    cx.acker(),
+                )
+                .sink_map_err(|e| error!("Fatal gcs sink error: {}", e))
+                .with_flat_map(move |e| iter_ok(encode_parquet_row(e, &key_prefix, &encoder)));
 
-        let sink =
-            PartitionBatchSink::new(TowerCompat::new(svc), buffer, batch.timeout, cx.acker())
+                Ok(Box::new(sink))
+            }
+            None => {
+                let batch = BatchSettings::default()
+                    .bytes(bytesize::mib(10u64))
+                    .timeout(300)
+                    .parse_config(config.batch)?;
+                let buffer = PartitionBuffer::new(Buffer::new(batch.size, config.compression));
+
+                let sink = PartitionBatchSink::new(
+                    TowerCompat::new(svc),
+                    buffer,
+                    batch.timeout,
+                    cx.acker(),
+                )
                 .sink_map_err(|e| error!("Fatal gcs sink error: {}", e))
                 .with_flat_map(move |e| iter_ok(encode_event(e, &key_prefix, &encoding)));
 
-        Ok(Box::new(sink))
+                Ok(Box::new(sink))
+            }
+        }
     }
 
     async fn healthcheck(mut self) -> crate::Result<()> {
@@ -352,9 +377,12 @@ impl RequestSettings {
         let acl = config
             .acl
             .map(|acl| HeaderValue::from_str(&to_string(acl)).unwrap());
-        let content_type = HeaderValue::from_str(config.encoding.codec().content_type()).unwrap();
+        let content_type = match config.parquet {
+            Some(_) => HeaderValue::from_static("application/octet-stream"),
+            None => HeaderValue::from_str(config.encoding.codec().content_type()).unwrap(),
+        };
         let content_encoding = config
-            .compression
+            .compression()
             .content_encoding()
             .map(|ce| HeaderValue::from_str(&to_string(ce)).unwrap());
         let storage_class = config.storage_class.unwrap_or_default();
@@ -369,10 +397,13 @@ impl RequestSettings {
                     .collect::<Result<Vec<_>, _>>()
             })
             .unwrap_or_else(|| Ok(vec![]))?;
-        let extension = config
-            .filename_extension
-            .clone()
-            .unwrap_or_else(|| config.compression.extension().into());
+        let extension = config.filename_extension.clone().unwrap_or_else(|| {
+            match config.parquet {
+                Some(_) => "parquet",
+                None => config.compression.extension(),
+            }
+            .into()
+        });
         let time_format = config
             .filename_time_format
             .clone()
@@ -399,14 +430,9 @@ fn make_header((name, value): (&String, &String)) -> crate::Result<(HeaderName,
     ))
 }
 
-fn encode_event(
-    mut event: Event,
-    key_prefix: &Template,
-    encoding: &EncodingConfig<Encoding>,
-) -> Option<PartitionInnerBuffer<Vec<u8>, Bytes>> {
-    encoding.apply_rules(&mut event);
-    let key = key_prefix
-        .render_string(&event)
+fn render_key(event: &Event, key_prefix: &Template) -> Option<String> {
+    key_prefix
+        .render_string(event)
         .map_err(|missing_keys| {
             warn!(
                 message = "Keys do not exist on the event. Dropping event.",
@@ -414,28 +440,37 @@ fn encode_event(
                 rate_limit_secs = 30,
             );
         })
+        .ok()
+}
+
+fn encode_event(
+    mut event: Event,
+    key_prefix: &Template,
+    encoding: &EncodingConfig<StandardEncodings>,
+) -> Option<PartitionInnerBuffer<Vec<u8>, Bytes>> {
+    encoding.apply_rules(&mut event);
+    let key = render_key(&event, key_prefix)?;
+    let bytes = encoding
+        .codec()
+        .encode_delimited(&event)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
         .ok()?;
-    let log = event.into_log();
-    let bytes = match encoding.codec() {
-        Encoding::Ndjson => serde_json::to_vec(&log)
-            .map(|mut b| {
-                b.push(b'\n');
-                b
-            })
-            .expect("Failed to encode event as json, this is a bug!"),
-        Encoding::Text => {
-            let mut bytes = log
-                .get(&event::log_schema().message_key())
-                .map(|v| v.as_bytes().to_vec())
-                .unwrap_or_default();
-            bytes.push(b'\n');
-            bytes
-        }
-    };
 
     Some(PartitionInnerBuffer::new(bytes, key.into()))
 }
 
+fn encode_parquet_row(
+    event: Event,
+    key_prefix: &Template,
+    encoder: &ParquetEncoder,
+) -> Option<PartitionInnerBuffer<ParquetRow, Bytes>> {
+    let key = render_key(&event, key_prefix)?;
+    Some(PartitionInnerBuffer::new(
+        encoder.encode_event(&event),
+        key.into(),
+    ))
+}
+
 #[derive(Clone)]
 struct GcsRetryLogic;
 
@@ -477,7 +512,7 @@ mod tests {
         let bytes = encode_event(
             message.clone().into(),
             &batch_time_format,
-            &Encoding::Text.into(),
+            &StandardEncodings::text().into(),
         )
         .unwrap();
 
@@ -493,7 +528,8 @@ mod tests {
         event.as_mut_log().insert("key", "value");
 
         let batch_time_format = Template::try_from("date=%F").unwrap();
-        let bytes = encode_event(event, &batch_time_format, &Encoding::Ndjson.into()).unwrap();
+        let bytes =
+            encode_event(event, &batch_time_format, &StandardEncodings::Ndjson.into()).unwrap();
 
         let (bytes, _) = bytes.into_parts();
         let map: HashMap<String, String> = serde_json::from_slice(&bytes[..]).unwrap();
@@ -516,7 +552,7 @@ mod tests {
             filename_extension: extension.map(Into::into),
             filename_append_uuid: Some(uuid),
             compression,
-            ..default_config(Encoding::Ndjson)
+            ..default_config(StandardEncodings::Ndjson)
         })
         .expect("Could not create request settings")
     }
@@ -546,4 +582,29 @@ mod tests {
         let req = RequestWrapper::new(buf, request_settings(None, true, Compression::Gzip));
         assert_ne!(req.key, "key/date.log.gz".to_string());
     }
+
+    #[test]
+    fn gcs_parquet_request_settings() {
+        let config: GcsSinkConfig = toml::from_str(
+            r#"
+            bucket = "bucket"
+            key_prefix = "key/"
+            filename_time_format = "date"
+            filename_append_uuid = false
+            encoding = "ndjson"
+            compression = "gzip"
+
+            [parquet]
+            schema = [{ name = "message", type = "string" }]
+            "#,
+        )
+        .unwrap();
+        let settings = RequestSettings::new(&config).unwrap();
+        assert_eq!(settings.content_type, "application/octet-stream");
+        assert_eq!(settings.content_encoding, None);
+
+        let buf = PartitionInnerBuffer::new(vec![0u8; 10], Bytes::from("key/"));
+        let req = RequestWrapper::new(buf, settings);
+        assert_eq!(req.key, "key/date.parquet".to_string());
+    }
 }
@@ -1,7 +1,7 @@
 use crate::{
     event::{self, Event},
     sinks::util::{
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
         http::{Auth, BatchedHttpSink, HttpClient, HttpSink},
         service2::TowerRequestConfig,
         BatchConfig, BatchSettings, Buffer, Compression, UriSerde,
@@ -20,6 +20,7 @@ use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
+use string_cache::DefaultAtom as Atom;
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -45,7 +46,7 @@ pub struct HttpSinkConfig {
     pub headers: Option<IndexMap<String, String>>,
     #[serde(default)]
     pub compression: Compression,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
     #[serde(default)]
     pub batch: BatchConfig,
     #[serde(default)]
@@ -54,7 +55,7 @@ pub struct HttpSinkConfig {
 }
 
 #[cfg(test)]
-fn default_config(e: Encoding) -> HttpSinkConfig {
+fn default_config(e: StandardEncodings) -> HttpSinkConfig {
     HttpSinkConfig {
         uri: Default::default(),
         method: Default::default(),
@@ -87,14 +88,6 @@ pub enum HttpMethod {
     Put,
 }
 
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Ndjson,
-    Json,
-}
-
 inventory::submit! {
     SinkDescription::new_without_default::<HttpSinkConfig>("http")
 }
@@ -155,39 +148,33 @@ impl HttpSink for HttpSinkConfig {
 
     fn encode_event(&self, mut event: Event) -> Option<Self::Input> {
         self.encoding.apply_rules(&mut event);
-        let event = event.into_log();
-
-        let body = match &self.encoding.codec() {
-            Encoding::Text => {
-                if let Some(v) = event.get(&event::log_schema().message_key()) {
-                    let mut b = v.to_string_lossy().into_bytes();
-                    b.push(b'\n');
-                    b
-                } else {
-                    warn!(
-                        message = "Event missing the message key; Dropping event.",
-                        rate_limit_secs = 30,
-                    );
-                    return None;
-                }
-            }
-
-            Encoding::Ndjson => {
-                let mut b = serde_json::to_vec(&event)
-                    .map_err(|e| panic!("Unable to encode into JSON: {}", e))
-                    .ok()?;
-                b.push(b'\n');
-                b
+        let codec = self.encoding.codec();
+
+        if let (StandardEncodings::Text { field }, Event::Log(log)) = (codec, &event) {
+            let value = match field {
+                Some(field) => log.get(&Atom::from(field.as_str())),
+                None => log.get(&event::log_schema().message_key()),
+            };
+            if value.is_none() {
+                warn!(
+                    message = "Event missing the message key; Dropping event.",
+                    rate_limit_secs = 30,
+                );
+                return None;
             }
+        }
 
-            Encoding::Json => {
-                let mut b = serde_json::to_vec(&event)
-                    .map_err(|e| panic!("Unable to encode into JSON: {}", e))
-                    .ok()?;
+        // JSON bodies are sent as a single array, so records are separated
+        // with commas and wrapped up in `build_request`.
+        let body = match codec {
+            StandardEncodings::Json => codec.encode_event(&event).map(|mut b| {
                 b.push(b',');
                 b
-            }
-        };
+            }),
+            _ => codec.encode_delimited(&event),
+        }
+        .map_err(|error| error!(message = "Unable to encode event.", %error))
+        .ok()?;
 
         Some(body)
     }
@@ -199,16 +186,13 @@ impl HttpSink for HttpSinkConfig {
         };
         let uri: Uri = self.uri.clone().into();
 
-        let ct = match self.encoding.codec() {
-            Encoding::Text => "text/plain",
-            Encoding::Ndjson => "application/x-ndjson",
-            Encoding::Json => {
-                body.insert(0, b'[');
-                body.pop(); // remove trailing comma from last record
-                body.push(b']');
-                "application/json"
-            }
-        };
+        let codec = self.encoding.codec();
+        if let StandardEncodings::Json = codec {
+            body.insert(0, b'[');
+            body.pop(); // remove trailing comma from last record
+            body.push(b']');
+        }
+        let ct = codec.content_type();
 
         let mut builder = Request::builder()
             .method(method)
@@ -305,22 +289,31 @@ mod tests {
 
     #[test]
     fn http_encode_event_text() {
-        let encoding = EncodingConfig::from(Encoding::Text);
+        let encoding = EncodingConfig::from(StandardEncodings::text());
         let event = Event::from("hello world");
 
-        let mut config = default_config(Encoding::Text);
+        let mut config = default_config(StandardEncodings::text());
         config.encoding = encoding;
         let bytes = config.encode_event(event).unwrap();
 
         assert_eq!(bytes, Vec::from(&"hello world\n"[..]));
     }
 
+    #[test]
+    fn http_encode_event_text_drops_missing_message() {
+        let config = default_config(StandardEncodings::text());
+        let mut event = Event::new_empty_log();
+        event.as_mut_log().insert("other", "value");
+
+        assert!(config.encode_event(event).is_none());
+    }
+
     #[test]
     fn http_encode_event_json() {
-        let encoding = EncodingConfig::from(Encoding::Ndjson);
+        let encoding = EncodingConfig::from(StandardEncodings::Ndjson);
         let event = Event::from("hello world");
 
-        let mut config = default_config(Encoding::Json);
+        let mut config = default_config(StandardEncodings::Json);
         config.encoding = encoding;
         let bytes = config.encode_event(event).unwrap();
 
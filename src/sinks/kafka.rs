@@ -3,7 +3,10 @@ use crate::{
     event::{self, Event, Value},
     kafka::{KafkaAuthConfig, KafkaCompression},
     serde::to_string,
-    sinks::util::encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+    sinks::util::encoding::{
+        EncodeError, EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration,
+        StandardEncodings,
+    },
     template::{Template, TemplateError},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
@@ -37,7 +40,7 @@ pub struct KafkaSinkConfig {
     bootstrap_servers: String,
     topic: String,
     key_field: Option<Atom>,
-    encoding: EncodingConfigWithDefault<Encoding>,
+    encoding: EncodingConfigWithDefault<StandardEncodings>,
     #[serde(default)]
     compression: KafkaCompression,
     #[serde(flatten)]
@@ -57,20 +60,11 @@ fn default_message_timeout_ms() -> u64 {
     300000 // default in librdkafka
 }
 
-#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
-#[derivative(Default)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    #[derivative(Default)]
-    Text,
-    Json,
-}
-
 pub struct KafkaSink {
     producer: FutureProducer,
     topic: Template,
     key_field: Option<Atom>,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
     in_flight: FuturesUnordered<MetadataFuture<Compat<DeliveryFuture>, usize>>,
 
     acker: Acker,
@@ -136,6 +130,19 @@ impl KafkaSink {
             pending_acks: HashSet::new(),
         })
     }
+
+    /// Marks the event as done, acknowledging it along with any following
+    /// ones once every event before it is done too.
+    fn ack(&mut self, seqno: usize) {
+        self.pending_acks.insert(seqno);
+
+        let mut num_to_ack = 0;
+        while self.pending_acks.remove(&self.seq_tail) {
+            num_to_ack += 1;
+            self.seq_tail += 1
+        }
+        self.acker.ack(num_to_ack);
+    }
 }
 
 impl Sink for KafkaSink {
@@ -147,7 +154,16 @@ impl Sink for KafkaSink {
             error!(message = "Missing keys for topic", ?missing_keys);
         })?;
 
-        let (key, body) = encode_event(item.clone(), &self.key_field, &self.encoding);
+        let (key, body) = match encode_event(item.clone(), &self.key_field, &self.encoding) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                error!(message = "Unable to encode; dropping event.", %error);
+                let seqno = self.seq_head;
+                self.seq_head += 1;
+                self.ack(seqno);
+                return Ok(AsyncSink::Ready);
+            }
+        };
 
         let mut record = FutureRecord::to(&topic).key(&key).payload(&body[..]);
 
@@ -204,14 +220,7 @@ impl Sink for KafkaSink {
                         Err((e, _msg)) => error!("kafka error: {}", e),
                     };
 
-                    self.pending_acks.insert(seqno);
-
-                    let mut num_to_ack = 0;
-                    while self.pending_acks.remove(&self.seq_tail) {
-                        num_to_ack += 1;
-                        self.seq_tail += 1
-                    }
-                    self.acker.ack(num_to_ack);
+                    self.ack(seqno);
                 }
 
                 // request got canceled (according to docs)
@@ -255,8 +264,8 @@ fn healthcheck(config: KafkaSinkConfig) -> crate::Result<super::Healthcheck> {
 fn encode_event(
     mut event: Event,
     key_field: &Option<Atom>,
-    encoding: &EncodingConfig<Encoding>,
-) -> (Vec<u8>, Vec<u8>) {
+    encoding: &EncodingConfig<StandardEncodings>,
+) -> Result<(Vec<u8>, Vec<u8>), EncodeError> {
     encoding.apply_rules(&mut event);
     let key = key_field
         .as_ref()
@@ -264,16 +273,9 @@ fn encode_event(
         .map(|v| v.as_bytes().to_vec())
         .unwrap_or_default();
 
-    let body = match encoding.codec() {
-        Encoding::Json => serde_json::to_vec(&event.as_log()).unwrap(),
-        Encoding::Text => event
-            .as_log()
-            .get(&event::log_schema().message_key())
-            .map(|v| v.as_bytes().to_vec())
-            .unwrap_or_default(),
-    };
+    let body = encoding.codec().encode_event(&event)?;
 
-    (key, body)
+    Ok((key, body))
 }
 
 #[cfg(test)]
@@ -289,8 +291,9 @@ mod tests {
         let (key_bytes, bytes) = encode_event(
             message.clone().into(),
             &None,
-            &EncodingConfig::from(Encoding::Text),
-        );
+            &EncodingConfig::from(StandardEncodings::text()),
+        )
+        .unwrap();
 
         assert_eq!(&key_bytes[..], key.as_bytes());
         assert_eq!(&bytes[..], message.as_bytes());
@@ -306,8 +309,9 @@ mod tests {
         let (key, bytes) = encode_event(
             event,
             &Some("key".into()),
-            &EncodingConfig::from(Encoding::Json),
-        );
+            &EncodingConfig::from(StandardEncodings::Json),
+        )
+        .unwrap();
 
         let map: BTreeMap<String, String> = serde_json::from_slice(&bytes[..]).unwrap();
 
@@ -348,7 +352,7 @@ mod integration_test {
             bootstrap_servers: "localhost:9091".into(),
             topic: topic.clone(),
             compression: KafkaCompression::None,
-            encoding: EncodingConfigWithDefault::from(Encoding::Text),
+            encoding: EncodingConfigWithDefault::from(StandardEncodings::text()),
             key_field: None,
             socket_timeout_ms: 60000,
             message_timeout_ms: 300000,
@@ -449,7 +453,7 @@ mod integration_test {
             bootstrap_servers: server.to_string(),
             topic: format!("{}-%Y%m%d", topic),
             compression,
-            encoding: EncodingConfigWithDefault::from(Encoding::Text),
+            encoding: EncodingConfigWithDefault::from(StandardEncodings::text()),
             key_field: None,
             auth: kafka_auth.clone(),
             socket_timeout_ms: 60000,
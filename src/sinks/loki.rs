@@ -5,20 +5,27 @@
 //!
 //! https://github.com/grafana/loki/blob/master/docs/api.md
 //!
-//! This sink does not use `PartitionBatching` but elects to do
+//! Batches are partitioned by tenant, but within a batch this sink does
 //! stream multiplexing by organizing the streams in the `build_request`
 //! phase. There must be at least one valid set of labels.
 //!
+//! Loki rejects entries older than the newest one of their stream, so
+//! out-of-order events are either dropped or have their timestamp
+//! rewritten, see `OutOfOrderAction`.
+//!
 //! If an event produces no labels, this can happen if the template
 //! does not match, we will add a default label `{agent="vector"}`.
 
 use crate::{
     event::{self, Event, Value},
+    internal_events::LokiTenantRenderFailed,
     runtime::FutureExt,
     sinks::util::{
-        buffer::loki::{LokiBuffer, LokiEvent, LokiRecord},
+        buffer::loki::{
+            GlobalTimestamps, LokiBuffer, LokiEvent, LokiRecord, OutOfOrderAction, PartitionKey,
+        },
         encoding::{EncodingConfigWithDefault, EncodingConfiguration},
-        http::{Auth, BatchedHttpSink, HttpClient, HttpSink},
+        http::{Auth, HttpClient, HttpSink, PartitionHttpSink},
         service2::TowerRequestConfig,
         BatchConfig, BatchSettings, UriSerde,
     },
@@ -28,6 +35,7 @@ use crate::{
 };
 use derivative::Derivative;
 use futures01::Sink;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -38,9 +46,12 @@ pub struct LokiConfig {
     #[serde(default)]
     encoding: EncodingConfigWithDefault<Encoding>,
 
-    tenant_id: Option<String>,
+    tenant_id: Option<Template>,
     labels: HashMap<String, Template>,
 
+    #[serde(default)]
+    out_of_order_action: OutOfOrderAction,
+
     #[serde(default = "crate::serde::default_false")]
     remove_label_fields: bool,
     #[serde(default = "crate::serde::default_true")]
@@ -66,6 +77,17 @@ enum Encoding {
     Text,
 }
 
+lazy_static! {
+    // Batches of a stream must reach Loki in order for either
+    // `out_of_order_action` to keep Loki from rejecting them, so only one
+    // request is in flight by default. Raising the limit trades that
+    // guarantee for throughput.
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: Some(1),
+        ..Default::default()
+    };
+}
+
 inventory::submit! {
     SinkDescription::new_without_default::<LokiConfig>("loki")
 }
@@ -77,7 +99,7 @@ impl SinkConfig for LokiConfig {
             return Err("`labels` must include at least one label.".into());
         }
 
-        let request_settings = self.request.unwrap_with(&TowerRequestConfig::default());
+        let request_settings = self.request.unwrap_with(&REQUEST_DEFAULTS);
         let batch_settings = BatchSettings::default()
             .bytes(102_400)
             .events(100_000)
@@ -86,9 +108,13 @@ impl SinkConfig for LokiConfig {
         let tls = TlsSettings::from_options(&self.tls)?;
         let client = HttpClient::new(cx.resolver(), tls)?;
 
-        let sink = BatchedHttpSink::new(
+        let sink = PartitionHttpSink::new(
             self.clone(),
-            LokiBuffer::new(batch_settings.size),
+            LokiBuffer::new(
+                batch_settings.size,
+                GlobalTimestamps::default(),
+                self.out_of_order_action,
+            ),
             request_settings,
             batch_settings.timeout,
             client.clone(),
@@ -113,9 +139,22 @@ impl SinkConfig for LokiConfig {
 #[async_trait::async_trait]
 impl HttpSink for LokiConfig {
     type Input = LokiRecord;
-    type Output = serde_json::Value;
+    type Output = (PartitionKey, serde_json::Value);
 
     fn encode_event(&self, mut event: Event) -> Option<Self::Input> {
+        let tenant_id = match &self.tenant_id {
+            Some(template) => match template.render_string(&event) {
+                Ok(tenant_id) => Some(tenant_id),
+                Err(missing_keys) => {
+                    emit!(LokiTenantRenderFailed {
+                        missing_keys: &missing_keys
+                    });
+                    return None;
+                }
+            },
+            None => None,
+        };
+
         self.encoding.apply_rules(&mut event);
         let mut labels = Vec::new();
 
@@ -163,17 +202,24 @@ impl HttpSink for LokiConfig {
         }
 
         let event = LokiEvent { timestamp, event };
-        Some(LokiRecord { labels, event })
+        Some(LokiRecord {
+            partition: PartitionKey { tenant_id },
+            labels,
+            event,
+        })
     }
 
-    async fn build_request(&self, json: Self::Output) -> crate::Result<http::Request<Vec<u8>>> {
+    async fn build_request(
+        &self,
+        (key, json): Self::Output,
+    ) -> crate::Result<http::Request<Vec<u8>>> {
         let body = serde_json::to_vec(&json).unwrap();
 
         let uri = format!("{}loki/api/v1/push", self.endpoint);
 
         let mut req = http::Request::post(uri).header("Content-Type", "application/json");
 
-        if let Some(tenant_id) = &self.tenant_id {
+        if let Some(tenant_id) = &key.tenant_id {
             req = req.header("X-Scope-OrgID", tenant_id);
         }
 
@@ -242,6 +288,72 @@ mod tests {
             record.labels[1],
             ("label2".to_string(), "some-static-label".to_string())
         );
+        assert_eq!(record.partition.tenant_id, None);
+    }
+
+    #[test]
+    fn interpolate_tenant_id() {
+        let (config, _cx, _rt) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {label1 = "static"}
+            encoding = "text"
+            tenant_id = "tenant-{{ tenant }}"
+        "#,
+        )
+        .unwrap();
+
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("tenant", "a");
+        let record = config.encode_event(event).unwrap();
+        assert_eq!(record.partition.tenant_id, Some("tenant-a".to_string()));
+
+        // Events without a tenant are dropped
+        assert!(config.encode_event(Event::from("hello world")).is_none());
+    }
+
+    #[test]
+    fn tenant_id_header() {
+        let (config, _cx, mut rt) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {label1 = "static"}
+        "#,
+        )
+        .unwrap();
+
+        rt.block_on_std(async move {
+            let key = PartitionKey {
+                tenant_id: Some("tenant1".into()),
+            };
+            let request = config
+                .build_request((key, serde_json::json!({})))
+                .await
+                .unwrap();
+            assert_eq!(request.headers()["X-Scope-OrgID"], "tenant1");
+
+            let request = config
+                .build_request((PartitionKey::default(), serde_json::json!({})))
+                .await
+                .unwrap();
+            assert!(request.headers().get("X-Scope-OrgID").is_none());
+        });
+    }
+
+    #[test]
+    fn parses_out_of_order_action() {
+        let (config, _cx, _rt) = load_sink::<LokiConfig>(
+            r#"
+            endpoint = "http://localhost:3100"
+            labels = {label1 = "static"}
+            out_of_order_action = "rewrite_timestamp"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.out_of_order_action,
+            OutOfOrderAction::RewriteTimestamp
+        );
     }
 }
 
@@ -1,9 +1,9 @@
 use crate::{
     event::log_schema,
     sinks::util::{
-        encoding::{EncodingConfig, EncodingConfiguration},
+        encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings},
         tcp::TcpSink,
-        Encoding, UriSerde,
+        UriSerde,
     },
     tls::{MaybeTlsSettings, TlsSettings},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
@@ -17,7 +17,7 @@ use syslog::{Facility, Formatter3164, LogFormat, Severity};
 #[serde(deny_unknown_fields)]
 pub struct PapertrailConfig {
     endpoint: UriSerde,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
 }
 
 inventory::submit! {
@@ -27,6 +27,14 @@ inventory::submit! {
 #[typetag::serde(name = "papertrail")]
 impl SinkConfig for PapertrailConfig {
     fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        if self.encoding.codec().is_binary() {
+            return Err(format!(
+                "The {} encoding can't be embedded in syslog messages",
+                self.encoding.codec().name()
+            )
+            .into());
+        }
+
         let host = self
             .endpoint
             .host()
@@ -66,7 +74,7 @@ impl SinkConfig for PapertrailConfig {
 fn encode_event(
     mut event: crate::Event,
     pid: u32,
-    encoding: &EncodingConfig<Encoding>,
+    encoding: &EncodingConfig<StandardEncodings>,
 ) -> Option<Bytes> {
     encoding.apply_rules(&mut event);
 
@@ -85,14 +93,12 @@ fn encode_event(
 
     let mut s: Vec<u8> = Vec::new();
 
-    let log = event.into_log();
-
-    let message = match encoding.codec() {
-        Encoding::Json => serde_json::to_string(&log).unwrap(),
-        Encoding::Text => log
-            .get(&log_schema().message_key())
-            .map(|v| v.to_string_lossy())
-            .unwrap_or_default(),
+    let message = match encoding.codec().encode_event(&event) {
+        Ok(message) => String::from_utf8_lossy(&message).into_owned(),
+        Err(error) => {
+            error!(message = "Unable to encode.", %error);
+            return None;
+        }
     };
 
     formatter
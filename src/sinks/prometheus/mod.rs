@@ -1,19 +1,25 @@
 use crate::{
     buffers::Acker,
-    event::metric::{Metric, MetricKind, MetricValue},
+    event::metric::{Metric, MetricKind, MetricValue, StatisticKind},
     sinks::util::MetricEntry,
+    tls::{MaybeTlsListener, MaybeTlsSettings, TlsConfig},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
     Event,
 };
 use chrono::Utc;
-use futures::{compat::Future01CompatExt, future::FutureExt, TryFutureExt};
+use futures::{
+    compat::{AsyncRead01CompatExt, Future01CompatExt, Stream01CompatExt},
+    future::FutureExt,
+    TryFutureExt, TryStreamExt,
+};
 use futures01::{future, Async, AsyncSink, Future, Sink};
 use hyper::{
     header::HeaderValue,
+    server::accept,
     service::{make_service_fn, service_fn},
     Body, Method, Request, Response, Server, StatusCode,
 };
-use indexmap::IndexSet;
+use indexmap::{map::MutableKeys, IndexMap};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 use std::{
@@ -22,14 +28,24 @@ use std::{
     sync::{Arc, RwLock},
 };
 use stream_cancel::{Trigger, Tripwire};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
 use tracing::field;
 
+mod remote_write;
+
+pub use remote_write::RemoteWriteConfig;
+
 const MIN_FLUSH_PERIOD_SECS: u64 = 1;
+const MIN_TTL_SECS: u64 = 1;
 
 #[derive(Debug, Snafu)]
 enum BuildError {
     #[snafu(display("Flush period for sets must be greater or equal to {} secs", min))]
     FlushPeriodTooShort { min: u64 },
+    #[snafu(display("TTL of series must be greater or equal to {} secs", min))]
+    TtlTooShort { min: u64 },
+    #[snafu(display("Quantiles must be between 0 and 1, got {}", quantile))]
+    InvalidQuantile { quantile: f64 },
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -40,8 +56,13 @@ pub struct PrometheusSinkConfig {
     pub address: SocketAddr,
     #[serde(default = "default_histogram_buckets")]
     pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
     #[serde(default = "default_flush_period_secs")]
     pub flush_period_secs: u64,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    pub tls: Option<TlsConfig>,
 }
 
 pub fn default_histogram_buckets() -> Vec<f64> {
@@ -50,6 +71,10 @@ pub fn default_histogram_buckets() -> Vec<f64> {
     ]
 }
 
+pub fn default_summary_quantiles() -> Vec<f64> {
+    vec![0.5, 0.75, 0.9, 0.95, 0.99]
+}
+
 pub fn default_address() -> SocketAddr {
     use std::net::{IpAddr, Ipv4Addr};
 
@@ -60,6 +85,10 @@ pub fn default_flush_period_secs() -> u64 {
     60
 }
 
+pub fn default_ttl_secs() -> u64 {
+    300
+}
+
 inventory::submit! {
     SinkDescription::new_without_default::<PrometheusSinkConfig>("prometheus")
 }
@@ -72,8 +101,20 @@ impl SinkConfig for PrometheusSinkConfig {
                 min: MIN_FLUSH_PERIOD_SECS,
             }));
         }
+        if self.ttl_secs < MIN_TTL_SECS {
+            return Err(Box::new(BuildError::TtlTooShort { min: MIN_TTL_SECS }));
+        }
+        if let Some(&quantile) = self
+            .quantiles
+            .iter()
+            .find(|&&quantile| !(0.0..=1.0).contains(&quantile))
+        {
+            return Err(Box::new(BuildError::InvalidQuantile { quantile }));
+        }
+        let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
+        let listener = tls.bind(&self.address)?;
 
-        let sink = Box::new(PrometheusSink::new(self.clone(), cx.acker()));
+        let sink = Box::new(PrometheusSink::new(self.clone(), listener, cx.acker()));
         let healthcheck = Box::new(future::ok(()));
 
         Ok((sink, healthcheck))
@@ -90,8 +131,12 @@ impl SinkConfig for PrometheusSinkConfig {
 
 struct PrometheusSink {
     server_shutdown_trigger: Option<Trigger>,
+    // Bound when the sink is built, and served once it's first used.
+    listener: Option<MaybeTlsListener>,
     config: PrometheusSinkConfig,
-    metrics: Arc<RwLock<IndexSet<MetricEntry>>>,
+    // Every series along with the last time it was updated, so that series
+    // that are no longer reported are eventually expired.
+    metrics: Arc<RwLock<IndexMap<MetricEntry, i64>>>,
     last_flush_timestamp: Arc<RwLock<i64>>,
     acker: Acker,
 }
@@ -144,7 +189,14 @@ fn encode_metric_header(namespace: &str, metric: &Metric) -> String {
     let r#type = match &metric.value {
         MetricValue::Counter { .. } => "counter",
         MetricValue::Gauge { .. } => "gauge",
-        MetricValue::Distribution { statistic: _, .. } => "histogram",
+        MetricValue::Distribution {
+            statistic: StatisticKind::Histogram,
+            ..
+        } => "histogram",
+        MetricValue::Distribution {
+            statistic: StatisticKind::Summary,
+            ..
+        } => "summary",
         MetricValue::Set { .. } => "gauge",
         MetricValue::AggregatedHistogram { .. } => "histogram",
         MetricValue::AggregatedSummary { .. } => "summary",
@@ -155,7 +207,13 @@ fn encode_metric_header(namespace: &str, metric: &Metric) -> String {
     s
 }
 
-fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric: &Metric) -> String {
+fn encode_metric_datum(
+    namespace: &str,
+    buckets: &[f64],
+    quantiles: &[f64],
+    expired: bool,
+    metric: &Metric,
+) -> String {
     let mut s = String::new();
     let fullname = encode_namespace(namespace, &metric.name);
 
@@ -177,7 +235,29 @@ fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric:
             MetricValue::Distribution {
                 values,
                 sample_rates,
-                statistic: _,
+                statistic: StatisticKind::Summary,
+            } => {
+                let mut samples = values.iter().zip(sample_rates.iter()).collect::<Vec<_>>();
+                samples.sort_by(|a, b| a.0.partial_cmp(b.0).unwrap_or(std::cmp::Ordering::Equal));
+                let count = sample_rates.iter().sum::<u32>();
+                let sum = samples.iter().map(|&(v, c)| v * (*c as f64)).sum::<f64>();
+
+                for q in quantiles {
+                    s.push_str(&format!(
+                        "{}{} {}\n",
+                        fullname,
+                        encode_tags_with_extra(tags, "quantile".to_string(), q.to_string()),
+                        quantile(&samples, count, *q)
+                    ));
+                }
+                let tags = encode_tags(tags);
+                s.push_str(&format!("{}_sum{} {}\n", fullname, tags, sum));
+                s.push_str(&format!("{}_count{} {}\n", fullname, tags, count));
+            }
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+                statistic: StatisticKind::Histogram,
             } => {
                 // convert ditributions into aggregated histograms
                 let mut counts = Vec::new();
@@ -265,12 +345,46 @@ fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric:
     s
 }
 
+/// Computes a quantile of samples sorted by value, as the smallest value
+/// with at least the quantile of the samples at or below it.
+fn quantile(samples: &[(&f64, &u32)], count: u32, quantile: f64) -> f64 {
+    let rank = (quantile * count as f64).ceil().max(1.0) as u32;
+    let mut seen = 0;
+    for &(value, rate) in samples {
+        seen += rate;
+        if seen >= rank {
+            return *value;
+        }
+    }
+    std::f64::NAN
+}
+
+/// Removes the series that haven't been updated for `ttl_secs`.
+fn expire_metrics(metrics: &mut IndexMap<MetricEntry, i64>, now: i64, ttl_secs: u64) {
+    metrics.retain(|_, updated| now - *updated <= ttl_secs as i64);
+}
+
+/// Replaces a series in place, as the key holds the value, so that the
+/// series keep their order on the scrape endpoint.
+fn replace_metric(metrics: &mut IndexMap<MetricEntry, i64>, new: MetricEntry, now: i64) {
+    match metrics.get_full_mut2(&new) {
+        Some((_, existing, updated)) => {
+            *existing = new;
+            *updated = now;
+        }
+        None => {
+            metrics.insert(new, now);
+        }
+    }
+}
+
 fn handle(
     req: Request<Body>,
     namespace: &str,
     buckets: &[f64],
+    quantiles: &[f64],
     expired: bool,
-    metrics: &IndexSet<MetricEntry>,
+    metrics: &IndexMap<MetricEntry, i64>,
 ) -> Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send> {
     let mut response = Response::new(Body::empty());
 
@@ -281,9 +395,10 @@ fn handle(
             // output headers only once
             let mut processed_headers = HashSet::new();
 
-            for metric in metrics {
+            for metric in metrics.keys() {
                 let name = &metric.0.name;
-                let frame = encode_metric_datum(&namespace, &buckets, expired, &metric.0);
+                let frame =
+                    encode_metric_datum(&namespace, &buckets, &quantiles, expired, &metric.0);
 
                 if !processed_headers.contains(&name) {
                     let header = encode_metric_header(&namespace, &metric.0);
@@ -314,46 +429,54 @@ fn handle(
 }
 
 impl PrometheusSink {
-    fn new(config: PrometheusSinkConfig, acker: Acker) -> Self {
+    fn new(config: PrometheusSinkConfig, listener: MaybeTlsListener, acker: Acker) -> Self {
         Self {
             server_shutdown_trigger: None,
+            listener: Some(listener),
             config,
-            metrics: Arc::new(RwLock::new(IndexSet::new())),
+            metrics: Arc::new(RwLock::new(IndexMap::new())),
             last_flush_timestamp: Arc::new(RwLock::new(Utc::now().timestamp())),
             acker,
         }
     }
 
     fn start_server_if_needed(&mut self) {
-        if self.server_shutdown_trigger.is_some() {
-            return;
-        }
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => return,
+        };
 
         let metrics = Arc::clone(&self.metrics);
         let namespace = self.config.namespace.clone();
         let buckets = self.config.buckets.clone();
+        let quantiles = self.config.quantiles.clone();
         let last_flush_timestamp = Arc::clone(&self.last_flush_timestamp);
         let flush_period_secs = self.config.flush_period_secs;
+        let ttl_secs = self.config.ttl_secs;
 
         let new_service = make_service_fn(move |_| {
             let metrics = Arc::clone(&metrics);
             let namespace = namespace.clone();
             let buckets = buckets.clone();
+            let quantiles = quantiles.clone();
             let last_flush_timestamp = Arc::clone(&last_flush_timestamp);
             let flush_period_secs = flush_period_secs;
+            let ttl_secs = ttl_secs;
 
             async move {
                 Ok::<_, crate::Error>(service_fn(move |req| {
-                    let metrics = metrics.read().unwrap();
+                    let now = Utc::now().timestamp();
+                    let mut metrics = metrics.write().unwrap();
+                    expire_metrics(&mut metrics, now, ttl_secs);
                     let last_flush_timestamp = last_flush_timestamp.read().unwrap();
-                    let interval = (Utc::now().timestamp() - *last_flush_timestamp) as u64;
+                    let interval = (now - *last_flush_timestamp) as u64;
                     let expired = interval > flush_period_secs;
                     info_span!(
                         "prometheus_server",
                         method = field::debug(req.method()),
                         path = field::debug(req.uri().path()),
                     )
-                    .in_scope(|| handle(req, &namespace, &buckets, expired, &metrics))
+                    .in_scope(|| handle(req, &namespace, &buckets, &quantiles, expired, &metrics))
                     .compat()
                 }))
             }
@@ -361,10 +484,14 @@ impl PrometheusSink {
 
         let (trigger, tripwire) = Tripwire::new();
 
-        let server = Server::bind(&self.config.address)
-            .serve(new_service)
-            .with_graceful_shutdown(tripwire.compat().map(|_| ()))
-            .map_err(|e| eprintln!("server error: {}", e));
+        let incoming = listener.incoming();
+
+        let server = Server::builder(accept::from_stream(
+            incoming.compat().map_ok(|s| s.compat().compat()),
+        ))
+        .serve(new_service)
+        .with_graceful_shutdown(tripwire.compat().map(|_| ()))
+        .map_err(|e| eprintln!("server error: {}", e));
 
         tokio::spawn(server);
         self.server_shutdown_trigger = Some(trigger);
@@ -382,16 +509,21 @@ impl Sink for PrometheusSink {
         self.start_server_if_needed();
 
         let item = event.into_metric();
+        let now = Utc::now().timestamp();
         let mut metrics = self.metrics.write().unwrap();
 
         match item.kind {
+            // Quantiles can't be added up, so the latest summary replaces
+            // the previous one.
+            MetricKind::Incremental if item.value.is_aggregated_summary() => {
+                replace_metric(&mut metrics, MetricEntry(item.to_absolute()), now);
+            }
             MetricKind::Incremental => {
                 let new = MetricEntry(item.to_absolute());
-                if let Some(MetricEntry(mut existing)) = metrics.take(&new) {
+                if let Some((_, MetricEntry(existing), updated)) = metrics.get_full_mut2(&new) {
                     if item.value.is_set() {
                         // sets need to be expired from time to time
                         // because otherwise they could grow infinitelly
-                        let now = Utc::now().timestamp();
                         let interval = now - *self.last_flush_timestamp.read().unwrap();
                         if interval > self.config.flush_period_secs as i64 {
                             *self.last_flush_timestamp.write().unwrap() = now;
@@ -399,15 +531,12 @@ impl Sink for PrometheusSink {
                         }
                     }
                     existing.add(&item);
-                    metrics.insert(MetricEntry(existing));
+                    *updated = now;
                 } else {
-                    metrics.insert(new);
+                    metrics.insert(new, now);
                 };
             }
-            MetricKind::Absolute => {
-                let new = MetricEntry(item);
-                metrics.replace(new);
-            }
+            MetricKind::Absolute => replace_metric(&mut metrics, MetricEntry(item), now),
         };
 
         self.acker.ack(1);
@@ -425,7 +554,10 @@ impl Sink for PrometheusSink {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::event::metric::{Metric, MetricKind, MetricValue, StatisticKind};
+    use crate::{
+        event::metric::{Metric, MetricKind, MetricValue, StatisticKind},
+        test_util::next_addr,
+    };
     use pretty_assertions::assert_eq;
 
     fn tags() -> BTreeMap<String, String> {
@@ -445,7 +577,7 @@ mod tests {
         };
 
         let header = encode_metric_header("vector", &metric);
-        let frame = encode_metric_datum("vector", &[], false, &metric);
+        let frame = encode_metric_datum("vector", &[], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -465,7 +597,7 @@ mod tests {
         };
 
         let header = encode_metric_header("vector", &metric);
-        let frame = encode_metric_datum("vector", &[], false, &metric);
+        let frame = encode_metric_datum("vector", &[], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -487,7 +619,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", &[], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -509,7 +641,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], true, &metric);
+        let frame = encode_metric_datum("", &[], &[], true, &metric);
 
         assert_eq!(
             header,
@@ -533,7 +665,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[0.0, 2.5, 5.0], false, &metric);
+        let frame = encode_metric_datum("", &[0.0, 2.5, 5.0], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -558,7 +690,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", &[], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -583,7 +715,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", &[], &[], false, &metric);
 
         assert_eq!(
             header,
@@ -591,4 +723,145 @@ mod tests {
         );
         assert_eq!(frame, "requests{code=\"200\",quantile=\"0.01\"} 1.5\nrequests{code=\"200\",quantile=\"0.5\"} 2\nrequests{code=\"200\",quantile=\"0.99\"} 3\nrequests_sum{code=\"200\"} 12\nrequests_count{code=\"200\"} 6\n".to_owned());
     }
+
+    #[test]
+    fn test_encode_distribution_summary() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: None,
+            tags: Some(tags()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::Distribution {
+                values: vec![3.0, 1.0, 2.0],
+                sample_rates: vec![2, 3, 3],
+                statistic: StatisticKind::Summary,
+            },
+        };
+
+        let header = encode_metric_header("", &metric);
+        let frame = encode_metric_datum("", &[], &[0.0, 0.5, 0.9, 1.0], false, &metric);
+
+        assert_eq!(
+            header,
+            "# HELP requests requests\n# TYPE requests summary\n".to_owned()
+        );
+        assert_eq!(frame, "requests{code=\"200\",quantile=\"0\"} 1\nrequests{code=\"200\",quantile=\"0.5\"} 2\nrequests{code=\"200\",quantile=\"0.9\"} 3\nrequests{code=\"200\",quantile=\"1\"} 3\nrequests_sum{code=\"200\"} 15\nrequests_count{code=\"200\"} 8\n".to_owned());
+    }
+
+    #[test]
+    fn test_expire_metrics() {
+        let metric = |name: &str| {
+            MetricEntry(Metric {
+                name: name.to_owned(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Absolute,
+                value: MetricValue::Gauge { value: 1.0 },
+            })
+        };
+        let mut metrics = IndexMap::new();
+        metrics.insert(metric("stale"), 100);
+        metrics.insert(metric("fresh"), 150);
+
+        expire_metrics(&mut metrics, 200, 60);
+
+        assert_eq!(metrics.len(), 1);
+        assert!(metrics.contains_key(&metric("fresh")));
+    }
+
+    #[test]
+    fn test_invalid_quantiles() {
+        let config: PrometheusSinkConfig = toml::from_str(
+            r#"
+            namespace = "vector"
+            quantiles = [0.5, 1.5]
+            "#,
+        )
+        .unwrap();
+        let cx = SinkContext::new_test();
+
+        assert!(config.build(cx).is_err());
+    }
+
+    fn sink() -> PrometheusSink {
+        let config: PrometheusSinkConfig = toml::from_str(r#"namespace = "vector""#).unwrap();
+        let listener = MaybeTlsSettings::Raw(()).bind(&next_addr()).unwrap();
+        let (acker, _) = Acker::new_for_testing();
+        let mut sink = PrometheusSink::new(config, listener, acker);
+        // Don't start the server
+        sink.listener = None;
+        sink
+    }
+
+    #[test]
+    fn test_incremental_summaries_replace() {
+        let summary = |count| {
+            Event::Metric(Metric {
+                name: "requests".to_owned(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Incremental,
+                value: MetricValue::AggregatedSummary {
+                    quantiles: vec![0.5],
+                    values: vec![count as f64],
+                    count,
+                    sum: count as f64,
+                },
+            })
+        };
+        let mut sink = sink();
+
+        sink.start_send(summary(1)).unwrap();
+        sink.start_send(summary(2)).unwrap();
+
+        let metrics = sink.metrics.read().unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(
+            metrics.keys().next().unwrap().0.value,
+            summary(2).into_metric().value
+        );
+    }
+
+    #[test]
+    fn test_absolute_updates_keep_order() {
+        let gauge = |name: &str, value| {
+            Event::Metric(Metric {
+                name: name.to_owned(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Absolute,
+                value: MetricValue::Gauge { value },
+            })
+        };
+        let mut sink = sink();
+
+        sink.start_send(gauge("first", 1.0)).unwrap();
+        sink.start_send(gauge("second", 2.0)).unwrap();
+        sink.start_send(gauge("first", 3.0)).unwrap();
+
+        let metrics = sink.metrics.read().unwrap();
+        let series = metrics
+            .keys()
+            .map(|entry| (entry.0.name.as_str(), entry.0.value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            series,
+            vec![
+                ("first", MetricValue::Gauge { value: 3.0 }),
+                ("second", MetricValue::Gauge { value: 2.0 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bind_error() {
+        let address = next_addr();
+        let _listener = std::net::TcpListener::bind(address).unwrap();
+        let config = PrometheusSinkConfig {
+            address,
+            ..toml::from_str(r#"namespace = "vector""#).unwrap()
+        };
+
+        assert!(config.build(SinkContext::new_test()).is_err());
+    }
 }
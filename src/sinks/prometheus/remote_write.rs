@@ -0,0 +1,553 @@
+//! Prometheus remote write sink
+//!
+//! This sink pushes metrics to services implementing the Prometheus remote
+//! write protocol, such as Cortex, Thanos or Mimir.
+//!
+//! https://prometheus.io/docs/prometheus/latest/configuration/configuration/#remote_write
+//!
+//! Remote write expects cumulative series, so incremental metrics are added
+//! up into the state of the sink before being sent, the same way the
+//! `prometheus` sink aggregates them for scraping.
+
+use super::{
+    default_histogram_buckets, default_ttl_secs, encode_namespace, expire_metrics, BuildError,
+    MIN_TTL_SECS,
+};
+use crate::{
+    event::metric::{Metric, MetricKind, MetricValue},
+    sinks::{
+        util::{
+            http::{Auth, BatchedHttpSink, HttpClient, HttpSink},
+            service2::TowerRequestConfig,
+            BatchConfig, BatchSettings, EncodedLength, MetricEntry, UriSerde, VecBuffer,
+        },
+        Healthcheck, RouterSink,
+    },
+    tls::{TlsOptions, TlsSettings},
+    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    Event,
+};
+use chrono::Utc;
+use futures01::{future, Sink};
+use indexmap::{map::MutableKeys, IndexMap};
+use lazy_static::lazy_static;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex};
+
+mod proto {
+    include!(concat!(env!("OUT_DIR"), "/prometheus.rs"));
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteWriteConfig {
+    pub endpoint: UriSerde,
+    #[serde(default)]
+    pub namespace: String,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    pub tenant_id: Option<String>,
+    pub auth: Option<Auth>,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub tls: Option<TlsOptions>,
+}
+
+lazy_static! {
+    // The samples of a series must reach the remote end in order, or the
+    // older ones are rejected, so only one request is in flight by default.
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: Some(1),
+        ..Default::default()
+    };
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<RemoteWriteConfig>("prometheus_remote_write")
+}
+
+#[typetag::serde(name = "prometheus_remote_write")]
+impl SinkConfig for RemoteWriteConfig {
+    fn build(&self, cx: SinkContext) -> crate::Result<(RouterSink, Healthcheck)> {
+        if self.ttl_secs < MIN_TTL_SECS {
+            return Err(Box::new(BuildError::TtlTooShort { min: MIN_TTL_SECS }));
+        }
+
+        let batch = BatchSettings::default()
+            .bytes(4_000_000)
+            .events(1_000)
+            .timeout(1)
+            .parse_config(self.batch)?;
+        let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let client = HttpClient::new(cx.resolver(), tls)?;
+
+        let sink = RemoteWriteSink {
+            config: self.clone(),
+            state: Mutex::new(IndexMap::new()),
+        };
+        let sink = BatchedHttpSink::new(
+            sink,
+            VecBuffer::new(batch.size),
+            request,
+            batch.timeout,
+            client,
+            cx.acker(),
+        )
+        .sink_map_err(|e| error!("Fatal prometheus_remote_write sink error: {}", e));
+
+        // There is no standard endpoint to check the health of a remote
+        // write service.
+        let healthcheck = Box::new(future::ok(()));
+
+        Ok((Box::new(sink), healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "prometheus_remote_write"
+    }
+}
+
+struct RemoteWriteSink {
+    config: RemoteWriteConfig,
+    // The cumulative value of every series incremental metrics were
+    // received for, along with the time it was last updated. Absolute
+    // metrics are sent as they are and don't need any state.
+    state: Mutex<IndexMap<MetricEntry, i64>>,
+}
+
+impl RemoteWriteSink {
+    /// Turns the metric into the cumulative value of its series.
+    fn normalize(&self, metric: Metric) -> Metric {
+        let metric = distribution_to_histogram(metric, &self.config.buckets);
+
+        match metric.kind {
+            // Quantiles can't be added up, so the latest summary is sent.
+            MetricKind::Incremental if metric.value.is_aggregated_summary() => metric.to_absolute(),
+            MetricKind::Incremental => {
+                let now = Utc::now().timestamp();
+                let mut state = self.state.lock().unwrap();
+                let new = MetricEntry(metric.to_absolute());
+                match state.get_full_mut2(&new) {
+                    Some((_, MetricEntry(existing), updated)) => {
+                        existing.add(&metric);
+                        existing.timestamp = metric.timestamp;
+                        *updated = now;
+                        existing.clone()
+                    }
+                    None => {
+                        let cumulative = new.0.clone();
+                        state.insert(new, now);
+                        cumulative
+                    }
+                }
+            }
+            MetricKind::Absolute => metric,
+        }
+    }
+
+    /// Forgets the cumulative value of series that haven't been updated
+    /// within `ttl_secs`, their next update starts again from zero.
+    fn expire_state(&self) {
+        let now = Utc::now().timestamp();
+        expire_metrics(&mut self.state.lock().unwrap(), now, self.config.ttl_secs);
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSink for RemoteWriteSink {
+    type Input = Vec<proto::TimeSeries>;
+    type Output = Vec<Vec<proto::TimeSeries>>;
+
+    fn encode_event(&self, event: Event) -> Option<Self::Input> {
+        let metric = self.normalize(event.into_metric());
+        Some(encode_metric(&self.config.namespace, &metric))
+    }
+
+    async fn build_request(&self, events: Self::Output) -> crate::Result<http::Request<Vec<u8>>> {
+        self.expire_state();
+
+        let request = proto::WriteRequest {
+            timeseries: events.into_iter().flatten().collect(),
+        };
+
+        let mut buf = Vec::with_capacity(request.encoded_len());
+        request.encode(&mut buf).expect("Vec has enough capacity");
+        let body = snap::raw::Encoder::new().compress_vec(&buf)?;
+
+        let mut builder = http::Request::post(self.config.endpoint.to_string())
+            .header("Content-Type", "application/x-protobuf")
+            .header("Content-Encoding", "snappy")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0");
+
+        if let Some(tenant_id) = &self.config.tenant_id {
+            builder = builder.header("X-Scope-OrgID", tenant_id);
+        }
+
+        let mut request = builder.body(body)?;
+
+        if let Some(auth) = &self.config.auth {
+            auth.apply(&mut request);
+        }
+
+        Ok(request)
+    }
+}
+
+impl EncodedLength for Vec<proto::TimeSeries> {
+    fn encoded_length(&self) -> usize {
+        self.iter().map(Message::encoded_len).sum()
+    }
+}
+
+/// Distributions are sent as histograms, using the configured buckets.
+fn distribution_to_histogram(metric: Metric, buckets: &[f64]) -> Metric {
+    match &metric.value {
+        MetricValue::Distribution {
+            values,
+            sample_rates,
+            ..
+        } => {
+            let mut counts = vec![0; buckets.len()];
+            let mut sum = 0.0;
+            let mut count = 0;
+            for (v, c) in values.iter().zip(sample_rates.iter()) {
+                buckets
+                    .iter()
+                    .enumerate()
+                    .skip_while(|&(_, b)| b < v)
+                    .for_each(|(i, _)| {
+                        counts[i] += c;
+                    });
+
+                sum += v * (*c as f64);
+                count += c;
+            }
+
+            Metric {
+                value: MetricValue::AggregatedHistogram {
+                    buckets: buckets.to_vec(),
+                    counts,
+                    count,
+                    sum,
+                },
+                ..metric
+            }
+        }
+        _ => metric,
+    }
+}
+
+fn encode_metric(namespace: &str, metric: &Metric) -> Vec<proto::TimeSeries> {
+    let name = encode_namespace(namespace, &metric.name);
+    let timestamp = metric.timestamp.unwrap_or_else(Utc::now).timestamp_millis();
+    let series = |suffix: &str, extra: Option<(&str, String)>, value: f64| {
+        time_series(
+            &format!("{}{}", name, suffix),
+            &metric.tags,
+            extra,
+            value,
+            timestamp,
+        )
+    };
+
+    match &metric.value {
+        MetricValue::Counter { value } | MetricValue::Gauge { value } => {
+            vec![series("", None, *value)]
+        }
+        MetricValue::Set { values } => vec![series("", None, values.len() as f64)],
+        MetricValue::AggregatedHistogram {
+            buckets,
+            counts,
+            count,
+            sum,
+        } => buckets
+            .iter()
+            .zip(counts.iter())
+            .map(|(b, c)| series("_bucket", Some(("le", b.to_string())), *c as f64))
+            .chain(vec![
+                series("_bucket", Some(("le", "+Inf".to_string())), *count as f64),
+                series("_sum", None, *sum),
+                series("_count", None, *count as f64),
+            ])
+            .collect(),
+        MetricValue::AggregatedSummary {
+            quantiles,
+            values,
+            count,
+            sum,
+        } => quantiles
+            .iter()
+            .zip(values.iter())
+            .map(|(q, v)| series("", Some(("quantile", q.to_string())), *v))
+            .chain(vec![
+                series("_sum", None, *sum),
+                series("_count", None, *count as f64),
+            ])
+            .collect(),
+        // Converted to histograms when normalized.
+        MetricValue::Distribution { .. } => vec![],
+    }
+}
+
+fn time_series(
+    name: &str,
+    tags: &Option<BTreeMap<String, String>>,
+    extra: Option<(&str, String)>,
+    value: f64,
+    timestamp: i64,
+) -> proto::TimeSeries {
+    let mut labels = tags
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .chain(extra.map(|(key, value)| (key.to_string(), value)))
+        .chain(Some(("__name__".to_string(), name.to_string())))
+        .map(|(name, value)| proto::Label { name, value })
+        .collect::<Vec<_>>();
+    // Remote write requires labels sorted by name.
+    labels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    proto::TimeSeries {
+        labels,
+        samples: vec![proto::Sample { value, timestamp }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::metric::StatisticKind,
+        sinks::util::test::{build_test_server, load_sink},
+        test_util,
+    };
+    use chrono::{offset::TimeZone, DateTime};
+    use futures01::Stream;
+    use pretty_assertions::assert_eq;
+
+    fn timestamp() -> DateTime<Utc> {
+        Utc.ymd(2020, 8, 1).and_hms(12, 0, 0)
+    }
+
+    fn metric(kind: MetricKind, value: MetricValue) -> Metric {
+        Metric {
+            name: "requests".into(),
+            timestamp: Some(timestamp()),
+            tags: Some(
+                vec![("code".to_owned(), "200".to_owned())]
+                    .into_iter()
+                    .collect(),
+            ),
+            kind,
+            value,
+        }
+    }
+
+    fn sink() -> RemoteWriteSink {
+        let (config, _, _) = load_sink::<RemoteWriteConfig>(
+            r#"
+            endpoint = "http://localhost:9090/api/v1/write"
+            namespace = "vector"
+            buckets = [1.0, 2.0]
+            tenant_id = "tenant1"
+        "#,
+        )
+        .unwrap();
+
+        RemoteWriteSink {
+            config,
+            state: Mutex::new(IndexMap::new()),
+        }
+    }
+
+    // Flattens series to `name{labels} value` for easier comparisons.
+    fn render(series: &[proto::TimeSeries]) -> Vec<String> {
+        series
+            .iter()
+            .map(|series| {
+                let labels = series
+                    .labels
+                    .iter()
+                    .map(|label| format!("{}={}", label.name, label.value))
+                    .collect::<Vec<_>>();
+                let samples = series
+                    .samples
+                    .iter()
+                    .map(|sample| format!("{}@{}", sample.value, sample.timestamp))
+                    .collect::<Vec<_>>();
+                format!("{{{}}} {}", labels.join(","), samples.join(","))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encodes_counters() {
+        let sink = sink();
+        let counter = || {
+            Event::Metric(metric(
+                MetricKind::Incremental,
+                MetricValue::Counter { value: 2.0 },
+            ))
+        };
+
+        sink.encode_event(counter()).unwrap();
+        let series = sink.encode_event(counter()).unwrap();
+
+        assert_eq!(
+            render(&series),
+            vec!["{__name__=vector_requests,code=200} 4@1596283200000"]
+        );
+    }
+
+    #[test]
+    fn keeps_state_of_incremental_series_only() {
+        let sink = sink();
+        let gauge = Event::Metric(metric(
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 42.0 },
+        ));
+        let counter = Event::Metric(metric(
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 2.0 },
+        ));
+
+        sink.encode_event(gauge).unwrap();
+        assert!(sink.state.lock().unwrap().is_empty());
+
+        sink.encode_event(counter).unwrap();
+        assert_eq!(sink.state.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn expires_idle_series() {
+        let sink = sink();
+        let counter = || {
+            Event::Metric(metric(
+                MetricKind::Incremental,
+                MetricValue::Counter { value: 2.0 },
+            ))
+        };
+
+        sink.encode_event(counter()).unwrap();
+        // Pretend the series was last updated before the TTL.
+        for (_, updated) in sink.state.lock().unwrap().iter_mut() {
+            *updated -= sink.config.ttl_secs as i64 + 1;
+        }
+        sink.expire_state();
+        assert!(sink.state.lock().unwrap().is_empty());
+
+        let series = sink.encode_event(counter()).unwrap();
+        assert_eq!(
+            render(&series),
+            vec!["{__name__=vector_requests,code=200} 2@1596283200000"]
+        );
+    }
+
+    #[test]
+    fn encodes_distributions_as_histograms() {
+        let sink = sink();
+        let event = Event::Metric(metric(
+            MetricKind::Incremental,
+            MetricValue::Distribution {
+                values: vec![0.5, 1.5, 3.0],
+                sample_rates: vec![1, 2, 1],
+                statistic: StatisticKind::Histogram,
+            },
+        ));
+
+        let series = sink.encode_event(event).unwrap();
+
+        assert_eq!(
+            render(&series),
+            vec![
+                "{__name__=vector_requests_bucket,code=200,le=1} 1@1596283200000",
+                "{__name__=vector_requests_bucket,code=200,le=2} 3@1596283200000",
+                "{__name__=vector_requests_bucket,code=200,le=+Inf} 4@1596283200000",
+                "{__name__=vector_requests_sum,code=200} 6.5@1596283200000",
+                "{__name__=vector_requests_count,code=200} 4@1596283200000",
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_summaries() {
+        let sink = sink();
+        let summary = |count| {
+            Event::Metric(metric(
+                MetricKind::Incremental,
+                MetricValue::AggregatedSummary {
+                    quantiles: vec![0.5],
+                    values: vec![1.5],
+                    count,
+                    sum: 12.0,
+                },
+            ))
+        };
+
+        sink.encode_event(summary(6)).unwrap();
+        let series = sink.encode_event(summary(8)).unwrap();
+
+        assert_eq!(
+            render(&series),
+            vec![
+                "{__name__=vector_requests,code=200,quantile=0.5} 1.5@1596283200000",
+                "{__name__=vector_requests_sum,code=200} 12@1596283200000",
+                "{__name__=vector_requests_count,code=200} 8@1596283200000",
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_compressed_write_requests() {
+        let (mut config, cx, mut rt) = load_sink::<RemoteWriteConfig>(
+            r#"
+            endpoint = "http://localhost:9090/api/v1/write"
+            tenant_id = "tenant1"
+            auth = { strategy = "bearer", token = "token" }
+        "#,
+        )
+        .unwrap();
+
+        let addr = test_util::next_addr();
+        config.endpoint = format!("http://{}/api/v1/write", addr)
+            .parse::<http::Uri>()
+            .unwrap()
+            .into();
+
+        let (sink, _) = config.build(cx).unwrap();
+        let (rx, _trigger, server) = build_test_server(addr, &mut rt);
+        rt.spawn(server);
+
+        let events = vec![Event::Metric(metric(
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 42.0 },
+        ))];
+        let pump = sink.send_all(futures01::stream::iter_ok(events));
+        let _ = rt.block_on(pump).unwrap();
+
+        let output = rx.take(1).wait().collect::<Result<Vec<_>, _>>().unwrap();
+        let (parts, body) = &output[0];
+
+        assert_eq!(parts.uri.path(), "/api/v1/write");
+        assert_eq!(parts.headers["Content-Encoding"], "snappy");
+        assert_eq!(parts.headers["X-Scope-OrgID"], "tenant1");
+        assert_eq!(parts.headers["Authorization"], "Bearer token");
+
+        let body = snap::raw::Decoder::new().decompress_vec(body).unwrap();
+        let request = proto::WriteRequest::decode(&body[..]).unwrap();
+        assert_eq!(
+            render(&request.timeseries),
+            vec!["{__name__=requests,code=200} 42@1596283200000"]
+        );
+    }
+}
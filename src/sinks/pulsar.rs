@@ -1,7 +1,10 @@
 use crate::{
     buffers::Acker,
-    event::{self, Event},
-    sinks::util::encoding::{EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration},
+    event::Event,
+    sinks::util::encoding::{
+        EncodeError, EncodingConfig, EncodingConfigWithDefault, EncodingConfiguration,
+        StandardEncodings,
+    },
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
 use futures::{lock::Mutex, FutureExt, TryFutureExt};
@@ -28,7 +31,7 @@ enum BuildError {
 pub struct PulsarSinkConfig {
     address: String,
     topic: String,
-    encoding: EncodingConfigWithDefault<Encoding>,
+    encoding: EncodingConfigWithDefault<StandardEncodings>,
     auth: Option<AuthConfig>,
 }
 
@@ -38,17 +41,8 @@ pub struct AuthConfig {
     token: String, // <jwt token>
 }
 
-#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
-#[derivative(Default)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    #[derivative(Default)]
-    Text,
-    Json,
-}
-
 struct PulsarSink {
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
     producer: Arc<Mutex<Producer<TokioExecutor>>>,
     in_flight: FuturesUnordered<MetadataFuture<SendFuture, usize>>,
     // ack
@@ -137,7 +131,9 @@ impl Sink for PulsarSink {
     type SinkError = ();
 
     fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
-        let message = encode_event(item, &self.encoding).map_err(|_| ())?;
+        let message = encode_event(item, &self.encoding).map_err(|error| {
+            error!(message = "Unable to encode.", %error);
+        })?;
 
         let producer = Arc::clone(&self.producer);
         let fut = async move {
@@ -185,21 +181,18 @@ impl Sink for PulsarSink {
     }
 }
 
-fn encode_event(item: Event, encoding: &EncodingConfig<Encoding>) -> crate::Result<Vec<u8>> {
-    let log = item.into_log();
-
-    Ok(match encoding.codec() {
-        Encoding::Json => serde_json::to_vec(&log)?,
-        Encoding::Text => log
-            .get(&event::log_schema().message_key())
-            .map(|v| v.as_bytes().to_vec())
-            .unwrap_or_default(),
-    })
+fn encode_event(
+    mut item: Event,
+    encoding: &EncodingConfig<StandardEncodings>,
+) -> Result<Vec<u8>, EncodeError> {
+    encoding.apply_rules(&mut item);
+    encoding.codec().encode_event(&item)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event;
     use std::collections::HashMap;
 
     #[test]
@@ -207,7 +200,7 @@ mod tests {
         let msg = "hello_world".to_owned();
         let mut evt = Event::from(msg.clone());
         evt.as_mut_log().insert("key", "value");
-        let result = encode_event(evt, &EncodingConfig::from(Encoding::Json)).unwrap();
+        let result = encode_event(evt, &EncodingConfig::from(StandardEncodings::Json)).unwrap();
         let map: HashMap<String, String> = serde_json::from_slice(&result[..]).unwrap();
         assert_eq!(msg, map[&event::log_schema().message_key().to_string()]);
     }
@@ -216,7 +209,7 @@ mod tests {
     fn pulsar_event_text() {
         let msg = "hello_world".to_owned();
         let evt = Event::from(msg.clone());
-        let event = encode_event(evt, &EncodingConfig::from(Encoding::Text)).unwrap();
+        let event = encode_event(evt, &EncodingConfig::from(StandardEncodings::text())).unwrap();
 
         assert_eq!(&event[..], msg.as_bytes());
     }
@@ -243,7 +236,7 @@ mod integration_tests {
             let cnf = PulsarSinkConfig {
                 address: "pulsar://127.0.0.1:6650".to_owned(),
                 topic: topic.clone(),
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
                 auth: None,
             };
 
@@ -1,7 +1,11 @@
 #[cfg(unix)]
 use crate::sinks::util::unix::UnixSinkConfig;
 use crate::{
-    sinks::util::{encoding::EncodingConfig, tcp::TcpSinkConfig, udp::UdpSinkConfig, Encoding},
+    sinks::util::{
+        encoding::{EncodingConfig, StandardEncodings},
+        tcp::TcpSinkConfig,
+        udp::UdpSinkConfig,
+    },
     tls::TlsConfig,
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
@@ -31,19 +35,18 @@ inventory::submit! {
 impl SocketSinkConfig {
     pub fn make_tcp_config(
         address: String,
-        encoding: EncodingConfig<Encoding>,
+        encoding: EncodingConfig<StandardEncodings>,
         tls: Option<TlsConfig>,
     ) -> Self {
         TcpSinkConfig {
-            address,
-            encoding,
             tls,
+            ..TcpSinkConfig::new(address, encoding)
         }
         .into()
     }
 
     pub fn make_basic_tcp_config(address: String) -> Self {
-        TcpSinkConfig::new(address, EncodingConfig::from(Encoding::Text)).into()
+        TcpSinkConfig::new(address, EncodingConfig::from(StandardEncodings::text())).into()
     }
 }
 
@@ -96,6 +99,19 @@ mod test {
     use serde_json::Value;
     use std::net::UdpSocket;
 
+    #[test]
+    fn udp_rejects_framing() {
+        let config = r#"
+            mode = "udp"
+            address = "127.0.0.1:9000"
+            encoding = "text"
+        "#;
+        assert!(toml::from_str::<SocketSinkConfig>(config).is_ok());
+
+        let framed = format!("{}framing = \"length_prefixed\"\n", config);
+        assert!(toml::from_str::<SocketSinkConfig>(&framed).is_err());
+    }
+
     #[test]
     fn udp_message() {
         crate::test_util::trace_init();
@@ -103,10 +119,10 @@ mod test {
         let receiver = UdpSocket::bind(addr).unwrap();
 
         let config = SocketSinkConfig {
-            mode: Mode::Udp(UdpSinkConfig {
-                address: addr.to_string(),
-                encoding: Encoding::Json.into(),
-            }),
+            mode: Mode::Udp(UdpSinkConfig::new(
+                addr.to_string(),
+                StandardEncodings::Json.into(),
+            )),
         };
         let mut rt = runtime();
         let context = SinkContext::new_test();
@@ -134,11 +150,10 @@ mod test {
         crate::test_util::trace_init();
         let addr = next_addr();
         let config = SocketSinkConfig {
-            mode: Mode::Tcp(TcpSinkConfig {
-                address: addr.to_string(),
-                encoding: Encoding::Json.into(),
-                tls: None,
-            }),
+            mode: Mode::Tcp(TcpSinkConfig::new(
+                addr.to_string(),
+                StandardEncodings::Json.into(),
+            )),
         };
         let mut rt = runtime();
         rt.block_on_std(async move {
@@ -163,6 +178,61 @@ mod test {
         });
     }
 
+    #[test]
+    fn tcp_octet_counted_syslog() {
+        use crate::sinks::util::{
+            encoding::syslog::{Facility, Severity},
+            socket::Framing,
+        };
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        crate::test_util::trace_init();
+        let addr = next_addr();
+        let config = SocketSinkConfig {
+            mode: Mode::Tcp(TcpSinkConfig {
+                framing: Framing::OctetCounting,
+                ..TcpSinkConfig::new(
+                    addr.to_string(),
+                    StandardEncodings::Syslog {
+                        facility: Facility::Local0,
+                        severity: Severity::Notice,
+                        app_name: "test".into(),
+                    }
+                    .into(),
+                )
+            }),
+        };
+        let mut rt = runtime();
+        rt.block_on_std(async move {
+            let mut listener = TcpListener::bind(addr).await.unwrap();
+            let server = tokio::spawn(async move {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut output = Vec::new();
+                socket.read_to_end(&mut output).await.unwrap();
+                output
+            });
+
+            let context = SinkContext::new_test();
+            let (sink, _healthcheck) = config.build(context).unwrap();
+            let (lines, events) = random_lines_with_stream(10, 3);
+            // Dropping the sink closes the connection.
+            let _ = sink.send_all(events).compat().await.unwrap();
+
+            let output = server.await.unwrap();
+            let mut output = &output[..];
+            for line in lines {
+                let space = output.iter().position(|b| *b == b' ').unwrap();
+                let len = std::str::from_utf8(&output[..space]).unwrap();
+                let len = len.parse::<usize>().unwrap();
+                let message = std::str::from_utf8(&output[space + 1..space + 1 + len]).unwrap();
+                assert!(message.starts_with("<133>1 "), "{}", message);
+                assert!(message.ends_with(&format!(" test - - - {}", line)));
+                output = &output[space + 1 + len..];
+            }
+            assert!(output.is_empty());
+        });
+    }
+
     // This is a test that checks that we properly receieve all events in the
     // case of a proper server side write side shutdown.
     //
@@ -189,8 +259,6 @@ mod test {
         let addr = next_addr();
         let config = SocketSinkConfig {
             mode: Mode::Tcp(TcpSinkConfig {
-                address: addr.to_string(),
-                encoding: Encoding::Text.into(),
                 tls: Some(TlsConfig {
                     enabled: Some(true),
                     options: TlsOptions {
@@ -200,6 +268,7 @@ mod test {
                         ..Default::default()
                     },
                 }),
+                ..TcpSinkConfig::new(addr.to_string(), StandardEncodings::text().into())
             }),
         };
         let mut rt = runtime();
@@ -3,18 +3,32 @@
 //! This buffer handles stream merging -- when a record is inserted into
 //! the buffer, all records having the same stream label set are grouped
 //! together for more efficient output.
+//!
+//! Loki rejects entries older than the newest one it has for a stream, so
+//! the buffer also remembers the newest timestamp sent for every stream and
+//! handles older records according to the `OutOfOrderAction`.
 
 use super::{
-    err_event_too_large, json::BoxedRawValue, Batch, BatchConfig, BatchError, BatchSettings,
-    BatchSize, PushResult,
+    err_event_too_large, json::BoxedRawValue, partition::Partition, Batch, BatchConfig, BatchError,
+    BatchSettings, BatchSize, PushResult,
 };
+use crate::internal_events::{LokiOutOfOrderEventDropped, LokiOutOfOrderEventRewritten};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, value::to_raw_value};
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 const WRAPPER_OVERHEAD: usize = r#"{"streams":[]}"#.len();
 const STREAM_OVERHEAD: usize = r#"{"stream":{},"values":[]}"#.len();
 const LABEL_OVERHEAD: usize = r#""":"""#.len();
 
+/// Streams nothing was sent for in this long are forgotten. Loki forgets
+/// idle streams too, once it has flushed their chunks.
+const STREAM_TTL: Duration = Duration::from_secs(3600);
+
 pub type Labels = Vec<(String, String)>;
 
 #[derive(Clone, Debug)]
@@ -23,12 +37,90 @@ pub struct LokiEvent {
     pub event: String,
 }
 
+/// Records of different tenants are sent in separate requests.
+#[derive(Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub struct PartitionKey {
+    pub tenant_id: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct LokiRecord {
+    pub partition: PartitionKey,
     pub labels: Labels,
     pub event: LokiEvent,
 }
 
+impl Partition<PartitionKey> for LokiRecord {
+    fn partition(&self) -> PartitionKey {
+        self.partition.clone()
+    }
+}
+
+/// What to do with records older than the newest one already sent for
+/// their stream.
+#[derive(Clone, Copy, Debug, Derivative, Deserialize, Serialize, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfOrderAction {
+    #[derivative(Default)]
+    Drop,
+    RewriteTimestamp,
+}
+
+/// The newest timestamps sent for each stream, shared by all the batches of
+/// a sink.
+#[derive(Clone, Debug, Default)]
+pub struct GlobalTimestamps {
+    map: Arc<Mutex<HashMap<PartitionKey, HashMap<Labels, SentTimestamp>>>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SentTimestamp {
+    timestamp: i64,
+    sent_at: Instant,
+}
+
+impl GlobalTimestamps {
+    fn get(&self, partition: &PartitionKey) -> HashMap<Labels, i64> {
+        self.map
+            .lock()
+            .unwrap()
+            .get(partition)
+            .map(|streams| {
+                streams
+                    .iter()
+                    .map(|(labels, sent)| (labels.clone(), sent.timestamp))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Records the newest timestamps of the streams in a batch, and forgets
+    /// the streams that haven't been sent anything in a while.
+    fn insert(&self, partition: PartitionKey, timestamps: HashMap<Labels, i64>) {
+        self.insert_at(partition, timestamps, Instant::now())
+    }
+
+    fn insert_at(&self, partition: PartitionKey, timestamps: HashMap<Labels, i64>, now: Instant) {
+        let mut map = self.map.lock().unwrap();
+        let streams = map.entry(partition).or_default();
+        for (labels, timestamp) in timestamps {
+            streams.insert(
+                labels,
+                SentTimestamp {
+                    timestamp,
+                    sent_at: now,
+                },
+            );
+        }
+
+        map.retain(|_, streams| {
+            streams.retain(|_, sent| now.saturating_duration_since(sent.sent_at) < STREAM_TTL);
+            !streams.is_empty()
+        });
+    }
+}
+
 #[derive(Debug)]
 struct LokiEncodedEvent {
     pub timestamp: i64,
@@ -53,22 +145,36 @@ pub struct LokiBuffer {
     num_items: usize,
     streams: HashMap<Labels, Vec<LokiEncodedEvent>>,
     settings: BatchSize<Self>,
+    partition: Option<PartitionKey>,
+    // Loaded from the global timestamps when the first record is pushed, as
+    // a batch only holds records of one partition.
+    latest_timestamps: Option<HashMap<Labels, i64>>,
+    global_timestamps: GlobalTimestamps,
+    out_of_order_action: OutOfOrderAction,
 }
 
 impl LokiBuffer {
-    pub fn new(settings: BatchSize<Self>) -> Self {
+    pub fn new(
+        settings: BatchSize<Self>,
+        global_timestamps: GlobalTimestamps,
+        out_of_order_action: OutOfOrderAction,
+    ) -> Self {
         Self {
             num_bytes: WRAPPER_OVERHEAD,
             num_items: 0,
             streams: HashMap::default(),
             settings,
+            partition: None,
+            latest_timestamps: None,
+            global_timestamps,
+            out_of_order_action,
         }
     }
 }
 
 impl Batch for LokiBuffer {
     type Input = LokiRecord;
-    type Output = serde_json::Value;
+    type Output = (PartitionKey, serde_json::Value);
 
     fn get_settings_defaults(
         config: BatchConfig,
@@ -80,6 +186,29 @@ impl Batch for LokiBuffer {
     }
 
     fn push(&mut self, mut item: Self::Input) -> PushResult<Self::Input> {
+        // We must sort the stream labels here to ensure they hash to
+        // the same stream if the label set matches.
+        item.labels.sort();
+
+        let global_timestamps = &self.global_timestamps;
+        let latest_timestamps = self
+            .latest_timestamps
+            .get_or_insert_with(|| global_timestamps.get(&item.partition));
+        if let Some(&latest) = latest_timestamps.get(&item.labels) {
+            if item.event.timestamp < latest {
+                match self.out_of_order_action {
+                    OutOfOrderAction::Drop => {
+                        emit!(LokiOutOfOrderEventDropped);
+                        return PushResult::Ok(false);
+                    }
+                    OutOfOrderAction::RewriteTimestamp => {
+                        emit!(LokiOutOfOrderEventRewritten);
+                        item.event.timestamp = latest;
+                    }
+                }
+            }
+        }
+
         let labels_len = item
             .labels
             .iter()
@@ -96,9 +225,9 @@ impl Batch for LokiBuffer {
         {
             PushResult::Overflow(item)
         } else {
-            // We must sort the stream labels here to ensure they hash to
-            // the same stream if the label set matches.
-            item.labels.sort();
+            if self.partition.is_none() {
+                self.partition = Some(item.partition.clone());
+            }
             let new_bytes = match self.streams.get_mut(&item.labels) {
                 // Label exists, and we checked the size, just add it
                 Some(stream) => {
@@ -130,10 +259,18 @@ impl Batch for LokiBuffer {
     }
 
     fn fresh(&self) -> Self {
-        Self::new(self.settings)
+        Self::new(
+            self.settings,
+            self.global_timestamps.clone(),
+            self.out_of_order_action,
+        )
     }
 
     fn finish(self) -> Self::Output {
+        let partition = self.partition.unwrap_or_default();
+        let latest_timestamps = self.latest_timestamps.unwrap_or_default();
+        let mut sent_timestamps = HashMap::new();
+
         let streams_json = self
             .streams
             .into_iter()
@@ -141,6 +278,15 @@ impl Batch for LokiBuffer {
                 // Sort events by timestamp
                 events.sort_by_key(|e| e.timestamp);
 
+                if let Some(last) = events.last() {
+                    let latest = latest_timestamps
+                        .get(&stream)
+                        .map_or(last.timestamp, |&latest| {
+                            std::cmp::max(latest, last.timestamp)
+                        });
+                    sent_timestamps.insert(stream.clone(), latest);
+                }
+
                 let stream = stream.into_iter().collect::<HashMap<_, _>>();
                 let events = events.into_iter().map(|e| e.encoded).collect::<Vec<_>>();
 
@@ -169,9 +315,15 @@ impl Batch for LokiBuffer {
             })
             .collect::<Vec<_>>();
 
-        json!({
-            "streams": streams_json,
-        })
+        self.global_timestamps
+            .insert(partition.clone(), sent_timestamps);
+
+        (
+            partition,
+            json!({
+                "streams": streams_json,
+            }),
+        )
     }
 
     fn num_items(&self) -> usize {
@@ -183,9 +335,36 @@ impl Batch for LokiBuffer {
 mod tests {
     use super::*;
 
+    fn new_buffer() -> LokiBuffer {
+        new_buffer_with(&Default::default(), Default::default())
+    }
+
+    fn new_buffer_with(
+        global_timestamps: &GlobalTimestamps,
+        out_of_order_action: OutOfOrderAction,
+    ) -> LokiBuffer {
+        LokiBuffer::new(
+            BatchSettings::default().size,
+            global_timestamps.clone(),
+            out_of_order_action,
+        )
+    }
+
+    fn record(stream: &str, timestamp: i64, event: &str) -> LokiRecord {
+        LokiRecord {
+            partition: PartitionKey { tenant_id: None },
+            labels: vec![("asdf".into(), stream.into())],
+            event: LokiEvent {
+                timestamp,
+                event: event.into(),
+            },
+        }
+    }
+
     fn test_finish(buffer: LokiBuffer, expected_json: &str) {
         let buffer_bytes = buffer.num_bytes;
-        let json = serde_json::to_string(&buffer.finish()).unwrap();
+        let (_, json) = buffer.finish();
+        let json = serde_json::to_string(&json).unwrap();
         // Does it track the number of bytes exactly before encoding?
         assert!(
             buffer_bytes == expected_json.len(),
@@ -199,9 +378,10 @@ mod tests {
 
     #[test]
     fn insert_single() {
-        let mut buffer = LokiBuffer::new(BatchSettings::default().size);
+        let mut buffer = new_buffer();
         assert!(matches!(
             buffer.push(LokiRecord {
+                partition: PartitionKey::default(),
                 labels: vec![("label1".into(), "value1".into())],
                 event: LokiEvent {
                     timestamp: 123456789,
@@ -221,10 +401,11 @@ mod tests {
 
     #[test]
     fn insert_multiple_streams() {
-        let mut buffer = LokiBuffer::new(BatchSettings::default().size);
+        let mut buffer = new_buffer();
         for n in 1..4 {
             assert!(matches!(
                 buffer.push(LokiRecord {
+                    partition: PartitionKey::default(),
                     labels: vec![("asdf".into(), format!("value{}", n))],
                     event: LokiEvent {
                         timestamp: 123456780 + n,
@@ -245,10 +426,11 @@ mod tests {
 
     #[test]
     fn insert_multiple_one_stream() {
-        let mut buffer = LokiBuffer::new(BatchSettings::default().size);
+        let mut buffer = new_buffer();
         for n in 1..4 {
             assert!(matches!(
                 buffer.push(LokiRecord {
+                    partition: PartitionKey::default(),
                     labels: vec![("asdf".into(), "value1".into())],
                     event: LokiEvent {
                         timestamp: 123456780 + n,
@@ -266,4 +448,105 @@ mod tests {
             r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["123456781","event #1"],["123456782","event #2"],["123456783","event #3"]]}]}"#,
         );
     }
+
+    #[test]
+    fn sorts_events_within_a_batch() {
+        let mut buffer = new_buffer();
+        for (timestamp, event) in &[(3, "c"), (1, "a"), (2, "b")] {
+            assert!(matches!(
+                buffer.push(record("value1", *timestamp, event)),
+                PushResult::Ok(false)
+            ));
+        }
+
+        test_finish(
+            buffer,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["1","a"],["2","b"],["3","c"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn drops_out_of_order_events() {
+        let global_timestamps = GlobalTimestamps::default();
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::Drop);
+        let _ = buffer.push(record("value1", 10, "first"));
+        let (partition, _) = buffer.finish();
+        assert_eq!(partition, PartitionKey::default());
+
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::Drop);
+        assert!(matches!(
+            buffer.push(record("value1", 5, "late")),
+            PushResult::Ok(false)
+        ));
+        let _ = buffer.push(record("value1", 10, "same"));
+        let _ = buffer.push(record("value2", 5, "other stream"));
+
+        assert_eq!(buffer.num_items, 2);
+        test_finish(
+            buffer,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["10","same"]]},{"stream":{"asdf":"value2"},"values":[["5","other stream"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn rewrites_out_of_order_timestamps() {
+        let global_timestamps = GlobalTimestamps::default();
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::RewriteTimestamp);
+        let _ = buffer.push(record("value1", 10, "first"));
+        buffer.finish();
+
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::RewriteTimestamp);
+        let _ = buffer.push(record("value1", 5, "late"));
+        let _ = buffer.push(record("value1", 12, "new"));
+
+        assert_eq!(buffer.num_items, 2);
+        test_finish(
+            buffer,
+            r#"{"streams":[{"stream":{"asdf":"value1"},"values":[["10","late"],["12","new"]]}]}"#,
+        );
+    }
+
+    #[test]
+    fn tracks_timestamps_per_partition() {
+        let global_timestamps = GlobalTimestamps::default();
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::Drop);
+        let _ = buffer.push(LokiRecord {
+            partition: PartitionKey {
+                tenant_id: Some("tenant1".into()),
+            },
+            ..record("value1", 10, "first")
+        });
+        let (partition, _) = buffer.finish();
+        assert_eq!(partition.tenant_id, Some("tenant1".into()));
+
+        let mut buffer = new_buffer_with(&global_timestamps, OutOfOrderAction::Drop);
+        let _ = buffer.push(record("value1", 5, "other tenant"));
+        assert_eq!(buffer.num_items, 1);
+    }
+
+    #[test]
+    fn forgets_idle_streams() {
+        let global_timestamps = GlobalTimestamps::default();
+        let partition = PartitionKey::default();
+        let labels = |stream: &str| vec![("asdf".to_owned(), stream.to_owned())];
+        let now = Instant::now();
+
+        let timestamps = vec![(labels("idle"), 10)].into_iter().collect();
+        global_timestamps.insert_at(partition.clone(), timestamps, now);
+        let timestamps = vec![(labels("active"), 20)].into_iter().collect();
+        global_timestamps.insert_at(partition.clone(), timestamps, now + STREAM_TTL / 2);
+        assert_eq!(global_timestamps.get(&partition).len(), 2);
+
+        let timestamps = vec![(labels("active"), 30)].into_iter().collect();
+        global_timestamps.insert_at(partition.clone(), timestamps, now + STREAM_TTL);
+        let latest = global_timestamps.get(&partition);
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[&labels("active")], 30);
+
+        let other = PartitionKey {
+            tenant_id: Some("tenant1".into()),
+        };
+        global_timestamps.insert_at(other, HashMap::new(), now + STREAM_TTL * 3);
+        assert!(global_timestamps.map.lock().unwrap().is_empty());
+    }
 }
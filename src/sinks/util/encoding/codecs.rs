@@ -0,0 +1,516 @@
+use super::syslog::{self, Facility, Severity};
+use crate::event::{self, Event, LogEvent};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use snafu::{ResultExt, Snafu};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Write},
+    io, iter,
+};
+use string_cache::DefaultAtom as Atom;
+
+/// The encodings every sink that writes out whole events can offer through
+/// `encoding.codec`.
+///
+/// Encodings without options may be given by name alone, such as
+/// `codec = "json"`. The others are given as a table with a `type` key, such
+/// as `codec = { type = "csv", fields = ["host", "message"] }`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(remote = "Self", tag = "type", rename_all = "snake_case")]
+pub enum StandardEncodings {
+    /// The value of a single field, the message by default.
+    Text {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        field: Option<String>,
+    },
+    /// The whole event as a JSON object.
+    Json,
+    /// The whole event as a JSON object, one per line.
+    Ndjson,
+    /// Every field as `key=value` pairs.
+    Logfmt,
+    /// The given fields as one CSV record.
+    Csv { fields: Vec<String> },
+    /// The whole event as a MessagePack map.
+    Msgpack,
+    /// The whole event as an Avro single object of the given record schema.
+    Avro { schema: AvroSchema },
+    /// The message field as an RFC 5424 syslog message.
+    Syslog {
+        #[serde(default)]
+        facility: Facility,
+        #[serde(default)]
+        severity: Severity,
+        #[serde(default = "syslog::default_app_name")]
+        app_name: String,
+    },
+}
+
+#[derive(Debug, Snafu)]
+pub enum EncodeError {
+    #[snafu(display("Unable to encode event as JSON: {}", source))]
+    Json { source: serde_json::Error },
+    #[snafu(display("Unable to encode event as MessagePack: {}", source))]
+    Msgpack { source: rmp_serde::encode::Error },
+    #[snafu(display("Unable to encode event as CSV: {}", source))]
+    Csv { source: csv::Error },
+    #[snafu(display("Unable to encode event as Avro: {}", message))]
+    Avro { message: String },
+    #[snafu(display("Metric events can't be encoded as {}", encoding))]
+    UnsupportedMetric { encoding: &'static str },
+}
+
+impl StandardEncodings {
+    /// The `text` encoding of the message field.
+    pub fn text() -> Self {
+        Self::Text { field: None }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Text { .. } => "text",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Logfmt => "logfmt",
+            Self::Csv { .. } => "csv",
+            Self::Msgpack => "msgpack",
+            Self::Avro { .. } => "avro",
+            Self::Syslog { .. } => "syslog",
+        }
+    }
+
+    /// Binary encodings can't be separated by newlines or embedded in text.
+    pub fn is_binary(&self) -> bool {
+        match self {
+            Self::Msgpack | Self::Avro { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Text { .. } | Self::Logfmt | Self::Syslog { .. } => "text/plain",
+            Self::Json => "application/json",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Csv { .. } => "text/csv",
+            Self::Msgpack => "application/msgpack",
+            Self::Avro { .. } => "application/avro",
+        }
+    }
+
+    /// Encodes a single event, without any trailing delimiter.
+    ///
+    /// Metrics are only representable by the JSON and MessagePack encodings,
+    /// `text` falls back to JSON for them.
+    pub fn encode_event(&self, event: &Event) -> Result<Vec<u8>, EncodeError> {
+        let log = match event {
+            Event::Log(log) => log,
+            Event::Metric(metric) => {
+                return match self {
+                    Self::Text { .. } | Self::Json | Self::Ndjson => {
+                        serde_json::to_vec(metric).context(Json)
+                    }
+                    Self::Msgpack => rmp_serde::to_vec_named(metric).context(Msgpack),
+                    _ => Err(EncodeError::UnsupportedMetric {
+                        encoding: self.name(),
+                    }),
+                }
+            }
+        };
+
+        match self {
+            Self::Text { field } => {
+                let value = match field {
+                    Some(field) => log.get(&Atom::from(field.as_str())),
+                    None => log.get(&event::log_schema().message_key()),
+                };
+                Ok(value.map(|v| v.as_bytes().to_vec()).unwrap_or_default())
+            }
+            Self::Json | Self::Ndjson => serde_json::to_vec(log).context(Json),
+            Self::Logfmt => Ok(encode_logfmt(log).into_bytes()),
+            Self::Csv { fields } => encode_csv(log, fields),
+            Self::Msgpack => rmp_serde::to_vec_named(log).context(Msgpack),
+            Self::Avro { schema } => schema.encode(log),
+            Self::Syslog {
+                facility,
+                severity,
+                app_name,
+            } => Ok(syslog::encode_syslog(log, *facility, *severity, app_name)),
+        }
+    }
+
+    /// Encodes a single event followed by a newline, unless the encoding is
+    /// binary. Avro objects are preceded by their length as a 32-bit
+    /// big-endian integer instead, as unlike MessagePack they can't be read
+    /// back one by one without knowing the schema.
+    pub fn encode_delimited(&self, event: &Event) -> Result<Vec<u8>, EncodeError> {
+        let mut bytes = self.encode_event(event)?;
+        match self {
+            Self::Avro { .. } => {
+                let length = u32::try_from(bytes.len()).map_err(|_| EncodeError::Avro {
+                    message: "object is too large to be length prefixed".into(),
+                })?;
+                let mut framed = length.to_be_bytes().to_vec();
+                framed.extend(bytes);
+                bytes = framed;
+            }
+            _ if !self.is_binary() => bytes.push(b'\n'),
+            _ => (),
+        }
+        Ok(bytes)
+    }
+}
+
+impl Default for StandardEncodings {
+    fn default() -> Self {
+        Self::text()
+    }
+}
+
+impl Serialize for StandardEncodings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StandardEncodings::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StandardEncodings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NameOrTable;
+
+        impl<'de> Visitor<'de> for NameOrTable {
+            type Value = StandardEncodings;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an encoding name or a table with a `type` key")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                StandardEncodings::deserialize(de::value::MapDeserializer::new(iter::once((
+                    "type", value,
+                ))))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<Self::Value, M::Error> {
+                StandardEncodings::deserialize(de::value::MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(NameOrTable)
+    }
+}
+
+fn encode_logfmt(log: &LogEvent) -> String {
+    let mut output = String::new();
+    for (key, value) in log.all_fields() {
+        if !output.is_empty() {
+            output.push(' ');
+        }
+        let value = value.to_string_lossy();
+        let quote = value.is_empty()
+            || value
+                .chars()
+                .any(|c| c.is_whitespace() || c == '=' || c == '"');
+        if quote {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r");
+            let _ = write!(output, "{}=\"{}\"", key, value);
+        } else {
+            let _ = write!(output, "{}={}", key, value);
+        }
+    }
+    output
+}
+
+fn encode_csv(log: &LogEvent, fields: &[String]) -> Result<Vec<u8>, EncodeError> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    writer
+        .write_record(fields.iter().map(|field| {
+            log.get(&Atom::from(field.as_str()))
+                .map(|value| value.as_bytes().to_vec())
+                .unwrap_or_default()
+        }))
+        .context(Csv)?;
+    let mut record = writer
+        .into_inner()
+        .map_err(|error| csv::Error::from(io::Error::from(error.error().kind())))
+        .context(Csv)?;
+    // The writer always terminates the record, leave that to the sink.
+    if record.last() == Some(&b'\n') {
+        record.pop();
+    }
+    Ok(record)
+}
+
+/// The marker the Avro single object encoding starts with.
+const AVRO_OBJECT_MARKER: [u8; 2] = [0xc3, 0x01];
+
+/// An Avro schema, parsed when the configuration is loaded so that a broken
+/// schema is reported up front.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct AvroSchema {
+    source: String,
+    schema: avro_rs::Schema,
+    fingerprint: [u8; 8],
+}
+
+impl AvroSchema {
+    /// Encodes the event in the Avro single object encoding, so that readers
+    /// can tell which schema it was written with: the marker, the fingerprint
+    /// of the schema and then the datum.
+    fn encode(&self, log: &LogEvent) -> Result<Vec<u8>, EncodeError> {
+        let datum = avro_rs::to_value(log)
+            .and_then(|value| value.resolve(&self.schema))
+            .and_then(|value| avro_rs::to_avro_datum(&self.schema, value))
+            .map_err(|error| EncodeError::Avro {
+                message: error.to_string(),
+            })?;
+
+        let mut object = Vec::with_capacity(AVRO_OBJECT_MARKER.len() + 8 + datum.len());
+        object.extend_from_slice(&AVRO_OBJECT_MARKER);
+        object.extend_from_slice(&self.fingerprint);
+        object.extend(datum);
+        Ok(object)
+    }
+}
+
+/// The CRC-64-AVRO fingerprint of the given schema, as specified in
+/// https://avro.apache.org/docs/current/spec.html#schema_fingerprints
+fn avro_fingerprint(schema: &avro_rs::Schema) -> u64 {
+    const EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+    schema
+        .canonical_form()
+        .bytes()
+        .fold(EMPTY, |fingerprint, byte| {
+            (0..8).fold(fingerprint ^ u64::from(byte), |fingerprint, _| {
+                (fingerprint >> 1) ^ (EMPTY & (fingerprint & 1).wrapping_neg())
+            })
+        })
+}
+
+impl TryFrom<String> for AvroSchema {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let schema = avro_rs::Schema::parse_str(&source)
+            .map_err(|error| format!("Invalid Avro schema: {}", error))?;
+        let fingerprint = avro_fingerprint(&schema).to_le_bytes();
+        Ok(Self {
+            source,
+            schema,
+            fingerprint,
+        })
+    }
+}
+
+impl From<AvroSchema> for String {
+    fn from(schema: AvroSchema) -> Self {
+        schema.source
+    }
+}
+
+impl PartialEq for AvroSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for AvroSchema {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{
+        metric::{MetricKind, MetricValue},
+        Metric,
+    };
+
+    #[derive(Deserialize, Debug)]
+    struct Config {
+        codec: StandardEncodings,
+    }
+
+    fn parse(config: &str) -> StandardEncodings {
+        toml::from_str::<Config>(config).unwrap().codec
+    }
+
+    fn event() -> Event {
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("host", "example.com");
+        event.as_mut_log().insert("status", 200);
+        event
+    }
+
+    #[test]
+    fn parses_names_and_tables() {
+        assert_eq!(parse(r#"codec = "json""#), StandardEncodings::Json);
+        assert_eq!(parse(r#"codec = "text""#), StandardEncodings::text());
+        assert_eq!(
+            parse(r#"codec = { type = "text", field = "host" }"#),
+            StandardEncodings::Text {
+                field: Some("host".into())
+            }
+        );
+        assert_eq!(
+            parse(r#"codec = { type = "csv", fields = ["host", "message"] }"#),
+            StandardEncodings::Csv {
+                fields: vec!["host".into(), "message".into()]
+            }
+        );
+        assert_eq!(
+            parse(r#"codec = { type = "syslog", facility = "local0" }"#),
+            StandardEncodings::Syslog {
+                facility: Facility::Local0,
+                severity: Severity::Info,
+                app_name: "vector".into()
+            }
+        );
+        assert!(toml::from_str::<Config>(r#"codec = "csv""#).is_err());
+        assert!(toml::from_str::<Config>(r#"codec = "yaml""#).is_err());
+        assert!(toml::from_str::<Config>(r#"codec = { type = "avro", schema = "nope" }"#).is_err());
+    }
+
+    #[test]
+    fn encodes_text_field() {
+        let encoding = StandardEncodings::Text {
+            field: Some("host".into()),
+        };
+        assert_eq!(encoding.encode_event(&event()).unwrap(), b"example.com");
+        assert_eq!(
+            StandardEncodings::text().encode_event(&event()).unwrap(),
+            b"hello world"
+        );
+    }
+
+    #[test]
+    fn encodes_logfmt() {
+        let encoded = StandardEncodings::Logfmt.encode_event(&event()).unwrap();
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            r#"host=example.com message="hello world" status=200"#
+        );
+    }
+
+    #[test]
+    fn encodes_logfmt_escapes() {
+        let mut event = Event::new_empty_log();
+        event
+            .as_mut_log()
+            .insert("message", "say \"hi\"\tto C:\\ é");
+        let encoded = StandardEncodings::Logfmt.encode_event(&event).unwrap();
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "message=\"say \\\"hi\\\"\tto C:\\\\ é\""
+        );
+    }
+
+    #[test]
+    fn encodes_logfmt_escapes_line_breaks() {
+        let mut event = Event::new_empty_log();
+        event
+            .as_mut_log()
+            .insert("message", "first \"line\"\r\nsecond line\n");
+        let encoded = StandardEncodings::Logfmt.encode_delimited(&event).unwrap();
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "message=\"first \\\"line\\\"\\r\\nsecond line\\n\"\n"
+        );
+    }
+
+    #[test]
+    fn encodes_csv() {
+        let encoding = StandardEncodings::Csv {
+            fields: vec!["status".into(), "missing".into(), "message".into()],
+        };
+        let mut event = event();
+        event.as_mut_log().insert("message", "hello, \"world\"");
+        assert_eq!(
+            String::from_utf8(encoding.encode_event(&event).unwrap()).unwrap(),
+            r#"200,,"hello, ""world""""#
+        );
+    }
+
+    #[test]
+    fn encodes_msgpack() {
+        let encoded = StandardEncodings::Msgpack.encode_event(&event()).unwrap();
+        let decoded: serde_json::Value = rmp_serde::from_read_ref(&encoded).unwrap();
+        assert_eq!(decoded["host"], "example.com");
+        assert_eq!(decoded["status"], 200);
+    }
+
+    #[test]
+    fn encodes_avro() {
+        let schema = r#"{
+            "type": "record",
+            "name": "log",
+            "fields": [
+                {"name": "host", "type": "string"},
+                {"name": "message", "type": "string"},
+                {"name": "status", "type": "long"}
+            ]
+        }"#;
+        let schema = AvroSchema::try_from(schema.to_owned()).unwrap();
+        let encoding = StandardEncodings::Avro {
+            schema: schema.clone(),
+        };
+        let encoded = encoding.encode_event(&event()).unwrap();
+        assert_eq!(encoded[..2], AVRO_OBJECT_MARKER);
+        assert_eq!(encoded[2..10], schema.fingerprint);
+
+        // Streams of objects are length prefixed.
+        let delimited = encoding.encode_delimited(&event()).unwrap();
+        assert_eq!(delimited[..4], (encoded.len() as u32).to_be_bytes());
+        assert_eq!(delimited[4..], encoded[..]);
+
+        let decoded = avro_rs::from_avro_datum(&schema.schema, &mut &encoded[10..], None).unwrap();
+        assert_eq!(
+            decoded,
+            avro_rs::types::Value::Record(vec![
+                (
+                    "host".into(),
+                    avro_rs::types::Value::String("example.com".into())
+                ),
+                (
+                    "message".into(),
+                    avro_rs::types::Value::String("hello world".into())
+                ),
+                ("status".into(), avro_rs::types::Value::Long(200)),
+            ])
+        );
+    }
+
+    #[test]
+    fn fingerprints_avro_schemas() {
+        // The fingerprints from the test cases of the Avro specification.
+        let fingerprint =
+            |schema: &str| avro_fingerprint(&avro_rs::Schema::parse_str(schema).unwrap()) as i64;
+        assert_eq!(fingerprint(r#""null""#), 7195948357588979594);
+        assert_eq!(fingerprint(r#""int""#), 8247732601305521295);
+        assert_eq!(fingerprint(r#""long""#), -3434872931120570953);
+    }
+
+    #[test]
+    fn encodes_metrics_as_json_only() {
+        let metric = Event::Metric(Metric {
+            name: "hits".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.0 },
+        });
+        let encoded = StandardEncodings::text().encode_event(&metric).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&encoded).unwrap()["name"],
+            "hits"
+        );
+        assert!(StandardEncodings::Logfmt.encode_event(&metric).is_err());
+    }
+}
@@ -4,7 +4,9 @@
 //!   * `EncodingConfig<E>`: For sinks without a default `Encoding`.
 //!   * `EncodingConfigWithDefault<E: Default>`: For sinks that have a default `Encoding`.
 //!
-//! Your sink should define some `Encoding` enum that is used as the `E` parameter.
+//! Sinks that write out whole events should use `StandardEncodings` as the `E` parameter, so
+//! that every encoding it offers is available to them. Other sinks should define some `Encoding`
+//! enum of their own.
 //!
 //! You can use either of these for a sink! They both implement `EncodingConfiguration`, which you
 //! will need to import as well.
@@ -29,8 +31,11 @@
 //       `Encoder` that defines some `encode` function which this config then calls internally as
 //       part of it's own (yet to be written) `encode() -> Vec<u8>` function.
 
+mod codecs;
+pub use codecs::{AvroSchema, EncodeError, StandardEncodings};
 mod config;
 pub use config::EncodingConfig;
+pub mod syslog;
 mod with_default;
 pub use with_default::EncodingConfigWithDefault;
 
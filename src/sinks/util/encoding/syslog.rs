@@ -0,0 +1,185 @@
+//! The RFC 5424 syslog message format, for forwarding events to collectors
+//! that only accept syslog.
+
+use crate::event::{self, LogEvent, Value};
+use chrono::SecondsFormat;
+use serde::{
+    de::{value::StrDeserializer, IntoDeserializer},
+    Deserialize, Serialize,
+};
+use std::fmt::Write;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Facility {
+    Kern,
+    #[derivative(Default)]
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    Authpriv,
+    Ftp,
+    Ntp,
+    Audit,
+    Alert,
+    Clockd,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+#[derive(Serialize, Deserialize, Debug, Derivative, Copy, Clone, PartialEq, Eq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Emerg,
+    Alert,
+    Crit,
+    Err,
+    Warning,
+    Notice,
+    #[derivative(Default)]
+    Info,
+    Debug,
+}
+
+pub fn default_app_name() -> String {
+    "vector".into()
+}
+
+/// Parses the names used by the syslog source, ignoring anything else.
+fn from_name<'de, T: Deserialize<'de>>(name: &'de str) -> Option<T> {
+    let deserializer: StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
+
+/// Encodes the message field as an RFC 5424 message. The header is taken
+/// from the fields the syslog source parses, where present, so that
+/// forwarded messages keep their origin.
+pub fn encode_syslog(
+    log: &LogEvent,
+    facility: Facility,
+    severity: Severity,
+    app_name: &str,
+) -> Vec<u8> {
+    let field = |name: &str| log.get(&Atom::from(name)).map(Value::to_string_lossy);
+
+    let facility = field("facility")
+        .and_then(|name| from_name(&name))
+        .unwrap_or(facility);
+    let severity = field("severity")
+        .and_then(|name| from_name(&name))
+        .unwrap_or(severity);
+    let timestamp = match log.get(&event::log_schema().timestamp_key()) {
+        Some(Value::Timestamp(ts)) => ts.to_rfc3339_opts(SecondsFormat::Micros, true),
+        _ => "-".into(),
+    };
+    let hostname = field("hostname").or_else(|| field(&event::log_schema().host_key()));
+    let app_name = field("appname").unwrap_or_else(|| app_name.into());
+
+    let mut output = String::new();
+    let _ = write!(
+        output,
+        "<{}>1 {} {} {} {} {} -",
+        facility as u8 * 8 + severity as u8,
+        timestamp,
+        header_field(hostname, 255),
+        header_field(Some(app_name), 48),
+        header_field(field("procid"), 128),
+        header_field(field("msgid"), 32),
+    );
+    if let Some(message) = log.get(&event::log_schema().message_key()) {
+        output.push(' ');
+        output.push_str(&message.to_string_lossy());
+    }
+    output.into_bytes()
+}
+
+/// Header fields are limited to printable ASCII, with `-` standing in for
+/// missing values.
+fn header_field(value: Option<String>, max_len: usize) -> String {
+    let value = value
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| ('!'..='~').contains(c))
+        .take(max_len)
+        .collect::<String>();
+    if value.is_empty() {
+        "-".into()
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use chrono::{TimeZone, Utc};
+
+    fn event() -> Event {
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert(
+            "timestamp",
+            Utc.ymd(2020, 7, 1).and_hms_micro(12, 0, 0, 500),
+        );
+        event.as_mut_log().insert("host", "example.com");
+        event
+    }
+
+    fn encode(event: &Event) -> String {
+        let encoded = encode_syslog(event.as_log(), Facility::User, Severity::Info, "vector");
+        String::from_utf8(encoded).unwrap()
+    }
+
+    #[test]
+    fn encodes_defaults() {
+        assert_eq!(
+            encode(&event()),
+            "<14>1 2020-07-01T12:00:00.000500Z example.com vector - - - hello world"
+        );
+    }
+
+    #[test]
+    fn encodes_syslog_source_fields() {
+        let mut event = event();
+        event.as_mut_log().insert("hostname", "origin");
+        event.as_mut_log().insert("appname", "app name");
+        event.as_mut_log().insert("procid", 8449);
+        event.as_mut_log().insert("msgid", "ID47");
+        event.as_mut_log().insert("facility", "local4");
+        event.as_mut_log().insert("severity", "err");
+        assert_eq!(
+            encode(&event),
+            "<163>1 2020-07-01T12:00:00.000500Z origin appname 8449 ID47 - hello world"
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_facility_and_severity() {
+        let mut event = event();
+        event.as_mut_log().insert("facility", "nope");
+        event.as_mut_log().insert("severity", 3);
+        assert!(encode(&event).starts_with("<14>1 "));
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(from_name("local7"), Some(Facility::Local7));
+        assert_eq!(from_name("warning"), Some(Severity::Warning));
+        assert_eq!(from_name::<Severity>("warn"), None);
+    }
+}
@@ -2,6 +2,8 @@ pub mod batch;
 pub mod buffer;
 pub mod encoding;
 pub mod http;
+#[cfg(any(feature = "sinks-aws_s3", feature = "sinks-gcp"))]
+pub mod parquet;
 pub mod retries;
 pub mod retries2;
 #[cfg(feature = "rusoto_core")]
@@ -9,6 +11,7 @@ pub mod rusoto;
 pub mod service;
 pub mod service2;
 pub mod sink;
+pub mod socket;
 pub mod tcp;
 #[cfg(test)]
 pub mod test;
@@ -17,10 +20,9 @@ pub mod udp;
 pub mod unix;
 pub mod uri;
 
-use crate::event::{self, Event};
+use crate::event::Event;
 use bytes::Bytes;
-use encoding::{EncodingConfig, EncodingConfiguration};
-use serde::{Deserialize, Serialize};
+use encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings};
 use snafu::Snafu;
 
 pub use batch::{Batch, BatchConfig, BatchSettings, BatchSize, PushResult};
@@ -41,40 +43,21 @@ enum SinkBuildError {
     MissingPort,
 }
 
-/**
- * Enum representing different ways to encode events as they are sent into a Sink.
- */
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
-#[serde(rename_all = "snake_case")]
-pub enum Encoding {
-    Text,
-    Json,
-}
-
 /**
 * Encodes the given event into raw bytes that can be sent into a Sink, according to
-* the given encoding.  If there are any errors encoding the event, logs a warning
-* and returns None.
+* the given encoding, followed by a newline unless the encoding is binary.  If there
+* are any errors encoding the event, logs a warning and returns None.
 **/
-pub fn encode_event(mut event: Event, encoding: &EncodingConfig<Encoding>) -> Option<Bytes> {
+pub fn encode_event(
+    mut event: Event,
+    encoding: &EncodingConfig<StandardEncodings>,
+) -> Option<Bytes> {
     encoding.apply_rules(&mut event);
-    let log = event.into_log();
-
-    let b = match encoding.codec() {
-        Encoding::Json => serde_json::to_vec(&log),
-        Encoding::Text => {
-            let bytes = log
-                .get(&event::log_schema().message_key())
-                .map(|v| v.as_bytes().to_vec())
-                .unwrap_or_default();
-            Ok(bytes)
-        }
-    };
 
-    b.map(|mut b| {
-        b.push(b'\n');
-        Bytes::from(b)
-    })
-    .map_err(|error| error!(message = "Unable to encode.", %error))
-    .ok()
+    encoding
+        .codec()
+        .encode_delimited(&event)
+        .map(Bytes::from)
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()
 }
@@ -0,0 +1,441 @@
+//! Parquet output for the object store sinks.
+//!
+//! Unlike the `encoding` codecs, which encode each event on its own, Parquet
+//! lays out a whole batch column by column. Events are converted into rows
+//! following an explicit schema and written out when the batch is flushed.
+
+mod thrift;
+mod writer;
+
+use super::{
+    batch::{
+        err_event_too_large, Batch, BatchConfig, BatchError, BatchSettings, BatchSize, PushResult,
+    },
+    PartitionInnerBuffer,
+};
+use crate::event::{Event, Value};
+use chrono::{DateTime, Utc};
+use futures::future::{self, Either, Ready};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::HashSet,
+    io,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use string_cache::DefaultAtom as Atom;
+use tower03::Service;
+use writer::Column;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ParquetConfig {
+    /// The columns of the written files, in order.
+    pub schema: Vec<ParquetColumn>,
+    #[serde(default = "default_row_group_size")]
+    pub row_group_size: usize,
+    #[serde(default)]
+    pub compression: ParquetCompression,
+}
+
+fn default_row_group_size() -> usize {
+    10_000
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ParquetColumn {
+    pub name: String,
+    /// The event field the column is filled from, `name` if not set.
+    pub field: Option<String>,
+    #[serde(rename = "type")]
+    pub column_type: ParquetType,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParquetType {
+    String,
+    Int64,
+    Double,
+    Boolean,
+    /// Milliseconds since the Unix epoch.
+    Timestamp,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Derivative)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ParquetCompression {
+    None,
+    #[derivative(Default)]
+    Snappy,
+    Gzip,
+}
+
+#[derive(Debug, Snafu)]
+enum ParquetConfigError {
+    #[snafu(display("Parquet schema must have at least one column"))]
+    EmptySchema,
+    #[snafu(display("Parquet schema has more than one column named {:?}", name))]
+    DuplicateColumn { name: String },
+    #[snafu(display("Parquet row_group_size must be greater than zero"))]
+    ZeroRowGroupSize,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ParquetError {
+    #[snafu(display("Unable to write Parquet file: {}", source))]
+    WriteFile { source: io::Error },
+}
+
+/// A single value of a row, converted to the physical type of its column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParquetValue {
+    Boolean(bool),
+    Int64(i64),
+    Double(f64),
+    Bytes(Vec<u8>),
+}
+
+impl ParquetValue {
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::Boolean(_) => 1,
+            Self::Int64(_) | Self::Double(_) => 8,
+            Self::Bytes(bytes) => 4 + bytes.len(),
+        }
+    }
+}
+
+/// An event converted into the columns of the schema.
+#[derive(Debug, Clone)]
+pub struct ParquetRow {
+    values: Vec<Option<ParquetValue>>,
+    size: usize,
+}
+
+/// Turns events into rows, and batches of rows into Parquet files.
+#[derive(Debug)]
+pub struct ParquetEncoder {
+    columns: Vec<Column>,
+    fields: Vec<Atom>,
+    row_group_size: usize,
+    compression: ParquetCompression,
+}
+
+impl ParquetEncoder {
+    pub fn new(config: &ParquetConfig) -> crate::Result<Self> {
+        if config.schema.is_empty() {
+            return Err(ParquetConfigError::EmptySchema.into());
+        }
+        if config.row_group_size == 0 {
+            return Err(ParquetConfigError::ZeroRowGroupSize.into());
+        }
+        let mut names = HashSet::new();
+        for column in &config.schema {
+            if !names.insert(&column.name) {
+                return Err(ParquetConfigError::DuplicateColumn {
+                    name: column.name.clone(),
+                }
+                .into());
+            }
+        }
+
+        Ok(Self {
+            columns: config
+                .schema
+                .iter()
+                .map(|column| Column {
+                    name: column.name.clone(),
+                    column_type: column.column_type,
+                })
+                .collect(),
+            fields: config
+                .schema
+                .iter()
+                .map(|column| Atom::from(column.field.as_ref().unwrap_or(&column.name).as_str()))
+                .collect(),
+            row_group_size: config.row_group_size,
+            compression: config.compression,
+        })
+    }
+
+    /// Fields that are missing or can't be converted to the type of their
+    /// column are written as nulls.
+    pub fn encode_event(&self, event: &Event) -> ParquetRow {
+        let log = event.as_log();
+        let values = self
+            .columns
+            .iter()
+            .zip(&self.fields)
+            .map(|(column, field)| {
+                log.get(field)
+                    .and_then(|value| convert(value, column.column_type))
+            })
+            .collect::<Vec<_>>();
+        let size = values
+            .iter()
+            .map(|value| value.as_ref().map_or(0, ParquetValue::encoded_len))
+            .sum();
+        ParquetRow { values, size }
+    }
+
+    fn write(&self, rows: Vec<ParquetRow>) -> Result<Vec<u8>, ParquetError> {
+        let rows = rows.into_iter().map(|row| row.values).collect::<Vec<_>>();
+        writer::write_file(&self.columns, &rows, self.row_group_size, self.compression)
+            .context(WriteFile)
+    }
+}
+
+fn convert(value: &Value, column_type: ParquetType) -> Option<ParquetValue> {
+    match (column_type, value) {
+        (_, Value::Null) => None,
+        // Readers expect valid UTF-8 in string columns.
+        (ParquetType::String, Value::Bytes(bytes)) => Some(ParquetValue::Bytes(
+            String::from_utf8_lossy(bytes).into_owned().into_bytes(),
+        )),
+        (ParquetType::String, Value::Map(_)) | (ParquetType::String, Value::Array(_)) => {
+            serde_json::to_vec(value).ok().map(ParquetValue::Bytes)
+        }
+        (ParquetType::String, value) => {
+            Some(ParquetValue::Bytes(value.to_string_lossy().into_bytes()))
+        }
+        (ParquetType::Int64, Value::Integer(int)) => Some(ParquetValue::Int64(*int)),
+        (ParquetType::Int64, Value::Float(float)) => Some(ParquetValue::Int64(*float as i64)),
+        (ParquetType::Int64, Value::Bytes(bytes)) => parse(bytes).map(ParquetValue::Int64),
+        (ParquetType::Double, Value::Float(float)) => Some(ParquetValue::Double(*float)),
+        (ParquetType::Double, Value::Integer(int)) => Some(ParquetValue::Double(*int as f64)),
+        (ParquetType::Double, Value::Bytes(bytes)) => parse(bytes).map(ParquetValue::Double),
+        (ParquetType::Boolean, Value::Boolean(boolean)) => Some(ParquetValue::Boolean(*boolean)),
+        (ParquetType::Boolean, Value::Bytes(bytes)) => parse(bytes).map(ParquetValue::Boolean),
+        (ParquetType::Timestamp, Value::Timestamp(timestamp)) => {
+            Some(ParquetValue::Int64(timestamp.timestamp_millis()))
+        }
+        (ParquetType::Timestamp, Value::Integer(millis)) => Some(ParquetValue::Int64(*millis)),
+        (ParquetType::Timestamp, Value::Bytes(bytes)) => std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|timestamp| ParquetValue::Int64(timestamp.with_timezone(&Utc).timestamp_millis())),
+        _ => None,
+    }
+}
+
+fn parse<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.trim().parse().ok()
+}
+
+/// Batches rows up to the configured size, then writes them as one Parquet
+/// file. The size of a batch is the size of its plain encoded values, so the
+/// written files are usually smaller.
+#[derive(Debug)]
+pub struct ParquetBuffer {
+    rows: Vec<ParquetRow>,
+    num_bytes: usize,
+    settings: BatchSize<Self>,
+    encoder: Arc<ParquetEncoder>,
+}
+
+impl ParquetBuffer {
+    pub fn new(settings: BatchSize<Self>, encoder: Arc<ParquetEncoder>) -> Self {
+        Self {
+            rows: Vec::new(),
+            num_bytes: 0,
+            settings,
+            encoder,
+        }
+    }
+}
+
+impl Batch for ParquetBuffer {
+    type Input = ParquetRow;
+    type Output = Result<Vec<u8>, ParquetError>;
+
+    fn get_settings_defaults(
+        config: BatchConfig,
+        defaults: BatchSettings<Self>,
+    ) -> Result<BatchSettings<Self>, BatchError> {
+        Ok(config
+            .use_size_as_bytes()?
+            .get_settings_or_default(defaults))
+    }
+
+    fn push(&mut self, item: Self::Input) -> PushResult<Self::Input> {
+        let new_bytes = self.num_bytes + item.size;
+        if self.is_empty() && item.size > self.settings.bytes {
+            err_event_too_large(item.size)
+        } else if self.rows.len() >= self.settings.events || new_bytes > self.settings.bytes {
+            PushResult::Overflow(item)
+        } else {
+            self.rows.push(item);
+            self.num_bytes = new_bytes;
+            PushResult::Ok(
+                self.rows.len() >= self.settings.events || new_bytes >= self.settings.bytes,
+            )
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self::new(self.settings, Arc::clone(&self.encoder))
+    }
+
+    fn finish(self) -> Self::Output {
+        self.encoder.write(self.rows)
+    }
+
+    fn num_items(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/// Fails the request of a batch that couldn't be written as a Parquet file,
+/// and passes the files of the other batches on to the inner service.
+#[derive(Debug, Clone)]
+pub struct ParquetService<S> {
+    inner: S,
+}
+
+impl<S> ParquetService<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, K> Service<PartitionInnerBuffer<Result<Vec<u8>, ParquetError>, K>> for ParquetService<S>
+where
+    S: Service<PartitionInnerBuffer<Vec<u8>, K>, Error = crate::Error>,
+{
+    type Response = S::Response;
+    type Error = crate::Error;
+    type Future = Either<Ready<Result<S::Response, crate::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(
+        &mut self,
+        request: PartitionInnerBuffer<Result<Vec<u8>, ParquetError>, K>,
+    ) -> Self::Future {
+        let (file, key) = request.into_parts();
+        match file {
+            Ok(file) => Either::Right(self.inner.call(PartitionInnerBuffer::new(file, key))),
+            Err(error) => Either::Left(future::ready(Err(error.into()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event;
+
+    fn encoder() -> ParquetEncoder {
+        let config: ParquetConfig = toml::from_str(
+            r#"
+            schema = [
+                { name = "message", type = "string" },
+                { name = "ts", field = "timestamp", type = "timestamp" },
+                { name = "status", type = "int64" },
+                { name = "ok", type = "boolean" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.row_group_size, 10_000);
+        assert_eq!(config.compression, ParquetCompression::Snappy);
+        ParquetEncoder::new(&config).unwrap()
+    }
+
+    #[test]
+    fn converts_events_to_rows() {
+        let mut event = Event::from("hello");
+        event.as_mut_log().insert("status", "200");
+        event.as_mut_log().insert("ok", 1);
+        let timestamp = event.as_log()[&event::log_schema().timestamp_key()].clone();
+
+        let row = encoder().encode_event(&event);
+        assert_eq!(
+            row.values,
+            vec![
+                Some(ParquetValue::Bytes(b"hello".to_vec())),
+                Some(ParquetValue::Int64(match timestamp {
+                    Value::Timestamp(timestamp) => timestamp.timestamp_millis(),
+                    _ => unreachable!(),
+                })),
+                Some(ParquetValue::Int64(200)),
+                None,
+            ]
+        );
+        assert_eq!(row.size, 9 + 8 + 8);
+    }
+
+    #[test]
+    fn replaces_invalid_utf8_in_strings() {
+        let value = Value::from(&b"ok \xff"[..]);
+        assert_eq!(
+            convert(&value, ParquetType::String),
+            Some(ParquetValue::Bytes("ok \u{fffd}".as_bytes().to_vec()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        let column = ParquetColumn {
+            name: "message".into(),
+            field: None,
+            column_type: ParquetType::String,
+        };
+        let config = ParquetConfig {
+            schema: vec![],
+            row_group_size: 10,
+            compression: ParquetCompression::None,
+        };
+        assert!(ParquetEncoder::new(&config).is_err());
+
+        let config = ParquetConfig {
+            schema: vec![column.clone(), column],
+            ..config
+        };
+        assert!(ParquetEncoder::new(&config).is_err());
+    }
+
+    #[test]
+    fn buffer_writes_parquet_file() {
+        let encoder = Arc::new(encoder());
+        let mut buffer = ParquetBuffer::new(
+            BatchSettings::default().bytes(40).events(10).size,
+            Arc::clone(&encoder),
+        );
+        let row = encoder.encode_event(&Event::from("hello"));
+        assert!(matches!(buffer.push(row.clone()), PushResult::Ok(false)));
+        assert!(matches!(buffer.push(row.clone()), PushResult::Ok(false)));
+        assert!(matches!(buffer.push(row), PushResult::Overflow(_)));
+        assert_eq!(buffer.num_items(), 2);
+
+        let file = buffer.finish().unwrap();
+        assert_eq!(&file[..4], b"PAR1");
+        assert_eq!(&file[file.len() - 4..], b"PAR1");
+    }
+
+    #[tokio::test]
+    async fn service_fails_unwritten_batches() {
+        let mut service = ParquetService::new(tower03::service_fn(
+            |request: PartitionInnerBuffer<Vec<u8>, &str>| async move {
+                Ok::<_, crate::Error>(request.into_parts().0.len())
+            },
+        ));
+
+        let written = PartitionInnerBuffer::new(Ok(b"PAR1".to_vec()), "key");
+        assert_eq!(service.call(written).await.unwrap(), 4);
+
+        let source = io::Error::new(io::ErrorKind::InvalidInput, "too large");
+        let failed = PartitionInnerBuffer::new(Err(ParquetError::WriteFile { source }), "key");
+        assert!(service.call(failed).await.is_err());
+    }
+}
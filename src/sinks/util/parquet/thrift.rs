@@ -0,0 +1,192 @@
+//! Just enough of the Thrift compact protocol to write Parquet metadata.
+//!
+//! https://github.com/apache/thrift/blob/master/doc/specs/thrift-compact-protocol.md
+
+const TYPE_BOOLEAN_TRUE: u8 = 1;
+const TYPE_BOOLEAN_FALSE: u8 = 2;
+const TYPE_I32: u8 = 5;
+const TYPE_I64: u8 = 6;
+const TYPE_BINARY: u8 = 8;
+const TYPE_LIST: u8 = 9;
+const TYPE_STRUCT: u8 = 12;
+
+/// Writes the fields of one struct, in increasing field id order.
+pub struct StructWriter<'a> {
+    out: &'a mut Vec<u8>,
+    last_id: i16,
+}
+
+impl<'a> StructWriter<'a> {
+    pub fn new(out: &'a mut Vec<u8>) -> Self {
+        Self { out, last_id: 0 }
+    }
+
+    fn field_header(&mut self, id: i16, field_type: u8) {
+        let delta = id - self.last_id;
+        if delta > 0 && delta <= 15 {
+            self.out.push((delta as u8) << 4 | field_type);
+        } else {
+            self.out.push(field_type);
+            write_varint(self.out, zigzag(id as i64));
+        }
+        self.last_id = id;
+    }
+
+    pub fn bool(&mut self, id: i16, value: bool) -> &mut Self {
+        let field_type = if value {
+            TYPE_BOOLEAN_TRUE
+        } else {
+            TYPE_BOOLEAN_FALSE
+        };
+        self.field_header(id, field_type);
+        self
+    }
+
+    pub fn i32(&mut self, id: i16, value: i32) -> &mut Self {
+        self.field_header(id, TYPE_I32);
+        write_varint(self.out, zigzag(value as i64));
+        self
+    }
+
+    pub fn i64(&mut self, id: i16, value: i64) -> &mut Self {
+        self.field_header(id, TYPE_I64);
+        write_varint(self.out, zigzag(value));
+        self
+    }
+
+    pub fn binary(&mut self, id: i16, value: &[u8]) -> &mut Self {
+        self.field_header(id, TYPE_BINARY);
+        write_binary(self.out, value);
+        self
+    }
+
+    pub fn list_i32(&mut self, id: i16, values: &[i32]) -> &mut Self {
+        self.field_header(id, TYPE_LIST);
+        write_list_header(self.out, values.len(), TYPE_I32);
+        for value in values {
+            write_varint(self.out, zigzag(*value as i64));
+        }
+        self
+    }
+
+    pub fn list_binary<T: AsRef<[u8]>>(&mut self, id: i16, values: &[T]) -> &mut Self {
+        self.field_header(id, TYPE_LIST);
+        write_list_header(self.out, values.len(), TYPE_BINARY);
+        for value in values {
+            write_binary(self.out, value.as_ref());
+        }
+        self
+    }
+
+    pub fn list_struct<T>(
+        &mut self,
+        id: i16,
+        values: &[T],
+        mut write: impl FnMut(&mut StructWriter<'_>, &T),
+    ) -> &mut Self {
+        self.field_header(id, TYPE_LIST);
+        write_list_header(self.out, values.len(), TYPE_STRUCT);
+        for value in values {
+            let mut inner = StructWriter::new(self.out);
+            write(&mut inner, value);
+            inner.finish();
+        }
+        self
+    }
+
+    pub fn r#struct(&mut self, id: i16, write: impl FnOnce(&mut StructWriter<'_>)) -> &mut Self {
+        self.field_header(id, TYPE_STRUCT);
+        let mut inner = StructWriter::new(self.out);
+        write(&mut inner);
+        inner.finish();
+        self
+    }
+
+    /// Writes the stop field that ends the struct.
+    pub fn finish(&mut self) {
+        self.out.push(0);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_binary(out: &mut Vec<u8>, value: &[u8]) {
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value);
+}
+
+fn write_list_header(out: &mut Vec<u8>, len: usize, element_type: u8) {
+    if len < 15 {
+        out.push((len as u8) << 4 | element_type);
+    } else {
+        out.push(0xf0 | element_type);
+        write_varint(out, len as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_struct_fields() {
+        let mut out = Vec::new();
+        StructWriter::new(&mut out)
+            .i32(1, 1)
+            .binary(4, b"id")
+            .i64(20, -1)
+            .bool(21, true)
+            .finish();
+        assert_eq!(
+            out,
+            vec![
+                0x15, 0x02, // field 1, i32 zigzag(1)
+                0x38, 0x02, b'i', b'd', // field 4 (delta 3), binary
+                0x06, 0x28, 0x01, // field 20 (delta 16, long form), i64 zigzag(-1)
+                0x11, // field 21, true
+                0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_lists() {
+        let mut out = Vec::new();
+        StructWriter::new(&mut out)
+            .list_i32(1, &[0, 3])
+            .list_binary(2, &(0..15).map(|_| "a").collect::<Vec<_>>())
+            .finish();
+        assert_eq!(&out[..5], &[0x19, 0x25, 0x00, 0x06, 0x19]);
+        // Long list header, then 15 strings of length 1.
+        assert_eq!(&out[5..8], &[0xf8, 0x0f, 0x01]);
+        assert_eq!(out.len(), 5 + 2 + 15 * 2 + 1);
+    }
+
+    #[test]
+    fn writes_nested_structs() {
+        let mut out = Vec::new();
+        StructWriter::new(&mut out)
+            .i32(3, 7)
+            .r#struct(4, |inner| {
+                inner.i32(1, 1);
+            })
+            .i32(5, 1)
+            .finish();
+        // The nested struct starts numbering its fields from zero again,
+        // and the outer one carries on from field 4.
+        assert_eq!(
+            out,
+            vec![0x35, 0x0e, 0x1c, 0x15, 0x02, 0x00, 0x15, 0x02, 0x00]
+        );
+    }
+}
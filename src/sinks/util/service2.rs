@@ -1,9 +1,10 @@
 use super::retries2::{FixedRetryPolicy, RetryLogic};
 use super::sink::Response;
-use super::{Batch, BatchSink};
+use super::{Batch, BatchSink, Partition, PartitionBatchSink};
 use crate::buffers::Acker;
 use futures::TryFutureExt;
 use serde::{Deserialize, Serialize};
+use std::hash::Hash;
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
@@ -20,6 +21,8 @@ pub use compat::TowerCompat;
 
 pub type Svc<S, L> = RateLimit<Retry<FixedRetryPolicy<L>, ConcurrencyLimit<Timeout<S>>>>;
 pub type TowerBatchedSink<S, B, L, Request> = BatchSink<TowerCompat<Svc<S, L>>, B, Request>;
+pub type TowerPartitionSink<S, B, L, K, Request> =
+    PartitionBatchSink<B, TowerCompat<Svc<S, L>>, K, Request>;
 
 pub trait ServiceBuilderExt<L> {
     fn map<R1, R2, F>(self, f: F) -> ServiceBuilder<Stack<MapLayer<R1, R2>, L>>
@@ -151,6 +154,37 @@ impl TowerRequestSettings {
         let service = TowerCompat::new(service);
         BatchSink::new(service, batch, batch_timeout, acker)
     }
+
+    pub fn partition_sink<B, L, S, K, Request>(
+        &self,
+        retry_logic: L,
+        service: S,
+        batch: B,
+        batch_timeout: Duration,
+        acker: Acker,
+    ) -> TowerPartitionSink<S, B, L, K, Request>
+    where
+        L: RetryLogic<Response = S::Response> + Send + 'static,
+        S: Service<Request> + Clone + Send + 'static,
+        S::Error: Into<crate::Error> + Send + Sync + 'static,
+        S::Response: Send + Response,
+        S::Future: Send + 'static,
+        B: Batch<Output = Request>,
+        B::Input: Partition<K>,
+        K: Hash + Eq + Clone + Send + 'static,
+        Request: Send + Clone + 'static,
+    {
+        let policy = self.retry_policy(retry_logic);
+        let service = ServiceBuilder::new()
+            .rate_limit(self.rate_limit_num, self.rate_limit_duration)
+            .retry(policy)
+            .concurrency_limit(self.in_flight_limit)
+            .timeout(self.timeout)
+            .service(service);
+
+        let service = TowerCompat::new(service);
+        PartitionBatchSink::new(service, batch, batch_timeout, acker)
+    }
 }
 
 #[derive(Debug, Clone)]
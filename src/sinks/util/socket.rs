@@ -0,0 +1,179 @@
+//! Options shared by the sinks that write to stream sockets.
+
+use super::encoding::{EncodingConfig, EncodingConfiguration, StandardEncodings};
+use crate::event::Event;
+use bytes::Bytes;
+use bytes05::BytesMut;
+use codec::OctetCountingCodec;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, time::Duration};
+use tokio_util::codec::Encoder;
+
+/// How encoded events are delimited on the stream.
+#[derive(Deserialize, Serialize, Debug, Derivative, Copy, Clone, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    /// Each event is followed by a newline, unless the encoding is binary.
+    #[derivative(Default)]
+    NewlineDelimited,
+    /// Each event is preceded by its length as a 32-bit big-endian integer.
+    LengthPrefixed,
+    /// Each event is preceded by its length in ASCII digits and a space, as
+    /// in RFC 6587 and RFC 5425.
+    OctetCounting,
+}
+
+/// Encodes the given event and frames it for writing to a stream. If there
+/// are any errors encoding the event, or it is too large for a length
+/// prefix, logs an error and returns None.
+pub fn encode_framed_event(
+    mut event: Event,
+    encoding: &EncodingConfig<StandardEncodings>,
+    framing: Framing,
+) -> Option<Bytes> {
+    encoding.apply_rules(&mut event);
+
+    let codec = encoding.codec();
+    let encoded = match framing {
+        Framing::NewlineDelimited => codec.encode_delimited(&event),
+        Framing::LengthPrefixed | Framing::OctetCounting => codec.encode_event(&event),
+    };
+    let encoded = encoded
+        .map_err(|error| error!(message = "Unable to encode.", %error))
+        .ok()?;
+
+    Some(match framing {
+        Framing::NewlineDelimited => Bytes::from(encoded),
+        Framing::LengthPrefixed => {
+            let length = u32::try_from(encoded.len())
+                .map_err(|_| {
+                    error!(
+                        message = "Event is too large to be length prefixed.",
+                        length = encoded.len()
+                    )
+                })
+                .ok()?;
+            let mut framed = length.to_be_bytes().to_vec();
+            framed.extend_from_slice(&encoded);
+            Bytes::from(framed)
+        }
+        Framing::OctetCounting => {
+            let mut framed = BytesMut::new();
+            OctetCountingCodec::new()
+                .encode(&encoded, &mut framed)
+                .map_err(|error| error!(message = "Unable to frame.", %error))
+                .ok()?;
+            Bytes::from(&framed[..])
+        }
+    })
+}
+
+/// The delay between attempts to reconnect, doubling from
+/// `initial_backoff_ms` up to `max_backoff_secs`.
+#[derive(Deserialize, Serialize, Debug, Derivative, Copy, Clone, Eq, PartialEq)]
+#[derivative(Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    #[derivative(Default(value = "500"))]
+    pub initial_backoff_ms: u64,
+    #[derivative(Default(value = "60"))]
+    pub max_backoff_secs: u64,
+}
+
+impl ReconnectConfig {
+    pub fn backoff(&self) -> ReconnectBackoff {
+        ReconnectBackoff {
+            // Not reconnecting in a tight loop.
+            next: Duration::from_millis(self.initial_backoff_ms.max(1)),
+            max: Duration::from_secs(self.max_backoff_secs),
+        }
+    }
+}
+
+/// The delays of a `ReconnectConfig`.
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    next: Duration,
+    max: Duration,
+}
+
+impl Iterator for ReconnectBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let delay = self.next.min(self.max);
+        self.next = self.next.checked_mul(2).unwrap_or(self.max);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(framing: Framing) -> Bytes {
+        let encoding = EncodingConfig::from(StandardEncodings::text());
+        encode_framed_event(Event::from("hello"), &encoding, framing).unwrap()
+    }
+
+    #[test]
+    fn frames_events() {
+        assert_eq!(encode(Framing::NewlineDelimited), &b"hello\n"[..]);
+        assert_eq!(
+            encode(Framing::LengthPrefixed),
+            &b"\x00\x00\x00\x05hello"[..]
+        );
+        assert_eq!(encode(Framing::OctetCounting), &b"5 hello"[..]);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let config = ReconnectConfig {
+            initial_backoff_ms: 100,
+            max_backoff_secs: 1,
+        };
+        let delays = config.backoff().take(6).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000]
+                .iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn backoff_starts_at_odd_delays() {
+        let config = ReconnectConfig {
+            initial_backoff_ms: 1,
+            max_backoff_secs: 1,
+        };
+        let delays = config.backoff().take(3).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1, 2, 4]
+                .iter()
+                .map(|ms| Duration::from_millis(*ms))
+                .collect::<Vec<_>>()
+        );
+
+        let config = ReconnectConfig {
+            initial_backoff_ms: 3,
+            ..config
+        };
+        assert_eq!(config.backoff().next(), Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn default_backoff_is_unchanged() {
+        let delays = ReconnectConfig::default()
+            .backoff()
+            .take(2)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![Duration::from_millis(500), Duration::from_millis(1000)]
+        );
+    }
+}
@@ -5,7 +5,11 @@ use crate::{
         TcpConnectionDisconnected, TcpConnectionEstablished, TcpConnectionFailed,
         TcpConnectionShutdown, TcpEventSent, TcpFlushError,
     },
-    sinks::util::{encode_event, encoding::EncodingConfig, Encoding, SinkBuildError, StreamSink},
+    sinks::util::{
+        encoding::{EncodingConfig, StandardEncodings},
+        socket::{encode_framed_event, Framing, ReconnectBackoff, ReconnectConfig},
+        SinkBuildError, StreamSink,
+    },
     sinks::{Healthcheck, RouterSink},
     tls::{MaybeTlsConnector, MaybeTlsSettings, MaybeTlsStream, TlsConfig},
     topology::config::SinkContext,
@@ -27,7 +31,6 @@ use std::{
 };
 use tokio::time::{delay_for, Delay};
 use tokio01::net::tcp::TcpStream;
-use tokio_retry::strategy::ExponentialBackoff;
 use tokio_util::{
     codec::{BytesCodec, FramedWrite},
     compat::{Compat, FuturesAsyncWriteCompatExt},
@@ -37,16 +40,30 @@ use tokio_util::{
 #[serde(deny_unknown_fields)]
 pub struct TcpSinkConfig {
     pub address: String,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub framing: Framing,
     pub tls: Option<TlsConfig>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub reconnect: ReconnectConfig,
+    pub keepalive_secs: Option<u64>,
 }
 
 impl TcpSinkConfig {
-    pub fn new(address: String, encoding: EncodingConfig<Encoding>) -> Self {
+    pub fn new(address: String, encoding: EncodingConfig<StandardEncodings>) -> Self {
         Self {
             address,
             encoding,
+            framing: Framing::default(),
             tls: None,
+            reconnect: ReconnectConfig::default(),
+            keepalive_secs: None,
         }
     }
 
@@ -58,13 +75,17 @@ impl TcpSinkConfig {
 
         let tls = MaybeTlsSettings::from_config(&self.tls, false)?;
 
-        let tcp = TcpSink::new(host, port, cx.resolver(), tls);
+        let tcp = TcpSink::new(host, port, cx.resolver(), tls)
+            .with_reconnect(self.reconnect)
+            .with_keepalive(self.keepalive_secs.map(Duration::from_secs));
         let healthcheck = tcp.healthcheck();
 
         let encoding = self.encoding.clone();
+        let framing = self.framing;
         let sink = Box::new(
-            StreamSink::new(tcp, cx.acker())
-                .with_flat_map(move |event| iter_ok(encode_event(event, &encoding))),
+            StreamSink::new(tcp, cx.acker()).with_flat_map(move |event| {
+                iter_ok(encode_framed_event(event, &encoding, framing))
+            }),
         );
 
         Ok((sink, healthcheck))
@@ -77,7 +98,9 @@ pub struct TcpSink {
     resolver: Resolver,
     tls: MaybeTlsSettings,
     state: TcpSinkState,
-    backoff: ExponentialBackoff,
+    reconnect: ReconnectConfig,
+    backoff: ReconnectBackoff,
+    keepalive: Option<Duration>,
     span: tracing::Span,
 }
 
@@ -95,17 +118,33 @@ type TcpOrTlsStream =
 impl TcpSink {
     pub fn new(host: String, port: u16, resolver: Resolver, tls: MaybeTlsSettings) -> Self {
         let span = info_span!("connection", %host, %port);
+        let reconnect = ReconnectConfig::default();
         Self {
             host,
             port,
             resolver,
             tls,
             state: TcpSinkState::Disconnected,
-            backoff: Self::fresh_backoff(),
+            reconnect,
+            backoff: reconnect.backoff(),
+            keepalive: None,
             span,
         }
     }
 
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = reconnect;
+        self.backoff = reconnect.backoff();
+        self
+    }
+
+    /// Enables TCP keepalive on new connections, with the given idle time
+    /// before probes are sent.
+    pub fn with_keepalive(mut self, keepalive: Option<Duration>) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
     pub fn healthcheck(&self) -> Healthcheck {
         tcp_healthcheck(
             self.host.clone(),
@@ -115,13 +154,6 @@ impl TcpSink {
         )
     }
 
-    fn fresh_backoff() -> ExponentialBackoff {
-        // TODO: make configurable
-        ExponentialBackoff::from_millis(2)
-            .factor(250)
-            .max_delay(Duration::from_secs(60))
-    }
-
     fn next_delay(&mut self) -> Delay {
         delay_for(self.backoff.next().unwrap())
     }
@@ -178,7 +210,12 @@ impl TcpSink {
                         emit!(TcpConnectionEstablished {
                             peer_addr: stream.peer_addr().ok(),
                         });
-                        self.backoff = Self::fresh_backoff();
+                        if self.keepalive.is_some() {
+                            if let Err(error) = stream.set_keepalive(self.keepalive) {
+                                warn!(message = "Failed to enable TCP keepalive.", %error);
+                            }
+                        }
+                        self.backoff = self.reconnect.backoff();
                         let stream = Compat01As03::new(stream).compat_write();
                         let out = FramedWrite::new(stream, BytesCodec::new());
                         TcpSinkState::Connected(CompatSink::new(out))
@@ -1,4 +1,8 @@
-use super::{encode_event, encoding::EncodingConfig, Encoding, SinkBuildError, StreamSink};
+use super::{
+    encode_event,
+    encoding::{EncodingConfig, StandardEncodings},
+    SinkBuildError, StreamSink,
+};
 use crate::{
     dns::{Resolver, ResolverFuture},
     sinks::{Healthcheck, RouterSink},
@@ -8,6 +12,7 @@ use bytes::Bytes;
 use futures01::{future, stream::iter_ok, Async, AsyncSink, Future, Poll, Sink, StartSend};
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
+use socket2::Socket;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::time::{Duration, Instant};
@@ -19,18 +24,26 @@ use tracing::field;
 pub enum UdpBuildError {
     #[snafu(display("failed to create UDP listener socket, error = {:?}", source))]
     SocketBind { source: io::Error },
+    #[snafu(display("failed to set UDP send buffer size, error = {:?}", source))]
+    SendBufferSize { source: io::Error },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct UdpSinkConfig {
     pub address: String,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
+    /// The size of the socket's send buffer, the system default if not set.
+    pub send_buffer_bytes: Option<usize>,
 }
 
 impl UdpSinkConfig {
-    pub fn new(address: String, encoding: EncodingConfig<Encoding>) -> Self {
-        Self { address, encoding }
+    pub fn new(address: String, encoding: EncodingConfig<StandardEncodings>) -> Self {
+        Self {
+            address,
+            encoding,
+            send_buffer_bytes: None,
+        }
     }
 
     pub fn build(&self, cx: SinkContext) -> crate::Result<(RouterSink, Healthcheck)> {
@@ -39,7 +52,13 @@ impl UdpSinkConfig {
         let host = uri.host().ok_or(SinkBuildError::MissingHost)?.to_string();
         let port = uri.port_u16().ok_or(SinkBuildError::MissingPort)?;
 
-        let sink = raw_udp(host, port, self.encoding.clone(), cx)?;
+        let sink = raw_udp(
+            host,
+            port,
+            self.encoding.clone(),
+            self.send_buffer_bytes,
+            cx,
+        )?;
         let healthcheck = udp_healthcheck();
 
         Ok((sink, healthcheck))
@@ -49,10 +68,12 @@ impl UdpSinkConfig {
 pub fn raw_udp(
     host: String,
     port: u16,
-    encoding: EncodingConfig<Encoding>,
+    encoding: EncodingConfig<StandardEncodings>,
+    send_buffer_bytes: Option<usize>,
     cx: SinkContext,
 ) -> Result<RouterSink, UdpBuildError> {
-    let sink = UdpSink::new(host, port, cx.resolver())?;
+    let sink =
+        UdpSink::new(host, port, cx.resolver())?.with_send_buffer_bytes(send_buffer_bytes)?;
     let sink = StreamSink::new(sink, cx.acker());
     Ok(Box::new(sink.with_flat_map(move |event| {
         iter_ok(encode_event(event, &encoding))
@@ -95,6 +116,22 @@ impl UdpSink {
         })
     }
 
+    pub fn with_send_buffer_bytes(
+        self,
+        send_buffer_bytes: Option<usize>,
+    ) -> Result<Self, UdpBuildError> {
+        let size = match send_buffer_bytes {
+            Some(size) => size,
+            None => return Ok(self),
+        };
+        let socket = Socket::from(self.socket);
+        socket.set_send_buffer_size(size).context(SendBufferSize)?;
+        Ok(Self {
+            socket: socket.into_udp_socket(),
+            ..self
+        })
+    }
+
     fn fresh_backoff() -> ExponentialBackoff {
         // TODO: make configurable
         ExponentialBackoff::from_millis(2)
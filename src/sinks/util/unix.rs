@@ -3,8 +3,13 @@ use crate::{
         UnixSocketConnectionEstablished, UnixSocketConnectionFailure, UnixSocketError,
         UnixSocketEventSent,
     },
-    sinks::util::{encode_event, encoding::EncodingConfig, Encoding, StreamSink},
+    sinks::util::{
+        encoding::{EncodingConfig, StandardEncodings},
+        socket::{encode_framed_event, Framing, ReconnectBackoff, ReconnectConfig},
+        StreamSink,
+    },
     sinks::{Healthcheck, RouterSink},
+    tls::{MaybeTlsSettings, MaybeTlsUnixStream, TlsConfig, TlsError},
     topology::config::SinkContext,
 };
 use bytes05::Bytes;
@@ -12,12 +17,11 @@ use futures::{compat::CompatSink, FutureExt, TryFutureExt};
 use futures01::{stream::iter_ok, try_ready, Async, AsyncSink, Future, Poll, Sink, StartSend};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use std::{path::PathBuf, time::Duration};
+use std::path::PathBuf;
 use tokio::{
     net::UnixStream,
     time::{delay_for, Delay},
 };
-use tokio_retry::strategy::ExponentialBackoff;
 use tokio_util::codec::{BytesCodec, FramedWrite};
 use tracing::field;
 
@@ -25,21 +29,53 @@ use tracing::field;
 #[serde(deny_unknown_fields)]
 pub struct UnixSinkConfig {
     pub path: PathBuf,
-    pub encoding: EncodingConfig<Encoding>,
+    pub encoding: EncodingConfig<StandardEncodings>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub framing: Framing,
+    pub tls: Option<TlsConfig>,
+    /// The name the server certificate is verified against, as there is no
+    /// host name to use.
+    pub tls_server_name: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serde::skip_serializing_if_default"
+    )]
+    pub reconnect: ReconnectConfig,
 }
 
 impl UnixSinkConfig {
-    pub fn new(path: PathBuf, encoding: EncodingConfig<Encoding>) -> Self {
-        Self { path, encoding }
+    pub fn new(path: PathBuf, encoding: EncodingConfig<StandardEncodings>) -> Self {
+        Self {
+            path,
+            encoding,
+            framing: Framing::default(),
+            tls: None,
+            tls_server_name: None,
+            reconnect: ReconnectConfig::default(),
+        }
     }
 
     pub fn build(&self, cx: SinkContext) -> crate::Result<(RouterSink, Healthcheck)> {
+        let tls = MaybeTlsSettings::from_config(&self.tls, false)?;
+        let server_name = self
+            .tls_server_name
+            .clone()
+            .unwrap_or_else(|| "localhost".into());
+
         let encoding = self.encoding.clone();
-        let unix = UnixSink::new(self.path.clone());
+        let framing = self.framing;
+        let unix = UnixSink::new(self.path.clone())
+            .with_tls(tls, server_name)
+            .with_reconnect(self.reconnect);
         let sink = StreamSink::new(unix, cx.acker());
 
         let sink =
-            Box::new(sink.with_flat_map(move |event| iter_ok(encode_event(event, &encoding))));
+            Box::new(sink.with_flat_map(move |event| {
+                iter_ok(encode_framed_event(event, &encoding, framing))
+            }));
         let healthcheck = healthcheck(self.path.clone()).boxed().compat();
 
         Ok((sink, Box::new(healthcheck)))
@@ -61,31 +97,45 @@ async fn healthcheck(path: PathBuf) -> crate::Result<()> {
 
 pub struct UnixSink {
     path: PathBuf,
+    tls: MaybeTlsSettings,
+    server_name: String,
     state: UnixSinkState,
-    backoff: ExponentialBackoff,
+    reconnect: ReconnectConfig,
+    backoff: ReconnectBackoff,
 }
 
+type UnixOrTlsStream = CompatSink<FramedWrite<MaybeTlsUnixStream, BytesCodec>, Bytes>;
+
 enum UnixSinkState {
     Disconnected,
-    Creating(Box<dyn Future<Item = UnixStream, Error = tokio::io::Error> + Send + 'static>),
-    Open(CompatSink<FramedWrite<UnixStream, BytesCodec>, Bytes>),
+    Creating(Box<dyn Future<Item = MaybeTlsUnixStream, Error = TlsError> + Send + 'static>),
+    Open(UnixOrTlsStream),
     Backoff(Box<dyn Future<Item = (), Error = ()> + Send>),
 }
 
 impl UnixSink {
     pub fn new(path: PathBuf) -> Self {
+        let reconnect = ReconnectConfig::default();
         Self {
             path,
+            tls: MaybeTlsSettings::Raw(()),
+            server_name: "localhost".into(),
             state: UnixSinkState::Disconnected,
-            backoff: Self::fresh_backoff(),
+            reconnect,
+            backoff: reconnect.backoff(),
         }
     }
 
-    fn fresh_backoff() -> ExponentialBackoff {
-        // TODO: make configurable
-        ExponentialBackoff::from_millis(2)
-            .factor(250)
-            .max_delay(Duration::from_secs(60))
+    pub fn with_tls(mut self, tls: MaybeTlsSettings, server_name: String) -> Self {
+        self.tls = tls;
+        self.server_name = server_name;
+        self
+    }
+
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = reconnect;
+        self.backoff = reconnect.backoff();
+        self
     }
 
     fn next_delay(&mut self) -> Delay {
@@ -95,9 +145,7 @@ impl UnixSink {
     /**
      * Polls for whether the underlying UnixStream is connected and ready to receive writes.
      **/
-    fn poll_connection(
-        &mut self,
-    ) -> Poll<&mut CompatSink<FramedWrite<UnixStream, BytesCodec>, Bytes>, ()> {
+    fn poll_connection(&mut self) -> Poll<&mut UnixOrTlsStream, ()> {
         loop {
             self.state = match self.state {
                 UnixSinkState::Open(ref mut stream) => {
@@ -118,7 +166,7 @@ impl UnixSink {
                     }
                     Ok(Async::Ready(stream)) => {
                         emit!(UnixSocketConnectionEstablished { path: &self.path });
-                        self.backoff = Self::fresh_backoff();
+                        self.backoff = self.reconnect.backoff();
                         let out = FramedWrite::new(stream, BytesCodec::new());
                         UnixSinkState::Open(CompatSink::new(out))
                     }
@@ -133,7 +181,12 @@ impl UnixSink {
                         message = "connecting",
                         path = &field::display(self.path.to_str().unwrap())
                     );
-                    let connect_future = UnixStream::connect(self.path.clone()).boxed().compat();
+                    let tls = self.tls.clone();
+                    let server_name = self.server_name.clone();
+                    let path = self.path.clone();
+                    let connect_future = async move { tls.connect_unix(&server_name, &path).await }
+                        .boxed()
+                        .compat();
                     UnixSinkState::Creating(Box::new(connect_future))
                 }
             }
@@ -234,7 +287,7 @@ mod tests {
             let mut receiver = CountReceiver::receive_lines_unix(out_path.clone());
 
             // Set up Sink
-            let config = UnixSinkConfig::new(out_path, Encoding::Text.into());
+            let config = UnixSinkConfig::new(out_path, StandardEncodings::text().into());
             let cx = SinkContext::new_test();
             let (sink, _healthcheck) = config.build(cx).unwrap();
 
@@ -250,4 +303,56 @@ mod tests {
         });
         shutdown_on_idle(rt);
     }
+
+    #[cfg(feature = "sinks-socket")]
+    #[test]
+    fn tls_unix_sink() {
+        use crate::tls::{TlsOptions, TlsSettings};
+        use tokio::io::AsyncReadExt;
+
+        let mut rt = runtime();
+        rt.block_on_std(async move {
+            let out_path = temp_uds_path("tls_unix_test");
+
+            let acceptor = TlsSettings::from_options(&Some(TlsOptions {
+                crt_file: Some("tests/data/localhost.crt".into()),
+                key_file: Some("tests/data/localhost.key".into()),
+                ..Default::default()
+            }))
+            .unwrap()
+            .acceptor()
+            .unwrap();
+            let mut listener = UnixListener::bind(&out_path).unwrap();
+            let server = tokio::spawn(async move {
+                let (socket, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_openssl::accept(&acceptor, socket).await.unwrap();
+                let mut output = Vec::new();
+                while output.iter().filter(|b| **b == b'\n').count() < 10 {
+                    let mut buf = [0; 1024];
+                    let n = socket.read(&mut buf).await.unwrap();
+                    assert!(n > 0, "connection closed early");
+                    output.extend_from_slice(&buf[..n]);
+                }
+                String::from_utf8(output).unwrap()
+            });
+
+            let config = UnixSinkConfig {
+                tls: Some(TlsConfig {
+                    enabled: Some(true),
+                    options: TlsOptions {
+                        ca_file: Some("tests/data/Vector_CA.crt".into()),
+                        ..Default::default()
+                    },
+                }),
+                ..UnixSinkConfig::new(out_path, StandardEncodings::text().into())
+            };
+            let (sink, _healthcheck) = config.build(SinkContext::new_test()).unwrap();
+
+            let (input_lines, events) = random_lines_with_stream(100, 10);
+            let _ = sink.send_all(events).compat().await.unwrap();
+
+            let output = server.await.unwrap();
+            assert_eq!(input_lines, output.lines().collect::<Vec<_>>());
+        });
+    }
 }
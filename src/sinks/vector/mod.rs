@@ -0,0 +1,99 @@
+pub mod v1;
+pub mod v2;
+
+use crate::topology::config::{DataType, SinkConfig, SinkContext, SinkDescription};
+use serde::{Deserialize, Serialize};
+
+/// The protocol spoken to the downstream `vector` source. Version 1 writes
+/// length delimited events over a raw TCP stream, version 2 pushes
+/// compressed batches over gRPC and waits for them to be acknowledged.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum VectorSinkConfig {
+    V2(v2::VectorSinkConfig),
+    V1(v1::VectorSinkConfig),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum V1 {
+    #[serde(rename = "1")]
+    V1,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum V2 {
+    #[serde(rename = "2")]
+    V2,
+}
+
+impl VectorSinkConfig {
+    pub fn new(address: String) -> Self {
+        Self::V1(v1::VectorSinkConfig::new(address))
+    }
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<VectorSinkConfig>("vector")
+}
+
+#[typetag::serde(name = "vector")]
+impl SinkConfig for VectorSinkConfig {
+    fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        match self {
+            Self::V1(config) => config.build(cx),
+            Self::V2(config) => config.build(cx),
+        }
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "vector"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_one_by_default() {
+        let config: VectorSinkConfig = toml::from_str(r#"address = "127.0.0.1:9000""#).unwrap();
+        assert!(matches!(config, VectorSinkConfig::V1(_)));
+
+        let config: VectorSinkConfig = toml::from_str(
+            r#"
+            version = "1"
+            address = "127.0.0.1:9000"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(config, VectorSinkConfig::V1(_)));
+    }
+
+    #[test]
+    fn parses_version_two() {
+        let config: VectorSinkConfig = toml::from_str(
+            r#"
+            version = "2"
+            addresses = ["127.0.0.1:9000", "127.0.0.1:9001"]
+            compression = "zstd"
+            "#,
+        )
+        .unwrap();
+        match config {
+            VectorSinkConfig::V2(config) => {
+                assert_eq!(config.addresses.len(), 2);
+                assert_eq!(config.compression, v2::Compression::Zstd);
+            }
+            VectorSinkConfig::V1(_) => panic!("expected a version 2 config"),
+        }
+    }
+
+    #[test]
+    fn rejects_v2_options_without_version() {
+        assert!(toml::from_str::<VectorSinkConfig>(r#"addresses = ["127.0.0.1:9000"]"#).is_err());
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
     event::proto,
     internal_events::VectorEventSent,
-    sinks::util::{tcp::TcpSink, StreamSink},
+    sinks::{
+        util::{tcp::TcpSink, StreamSink},
+        Healthcheck, RouterSink,
+    },
     tls::{MaybeTlsSettings, TlsConfig},
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::SinkContext,
     Event,
 };
 use bytes::Bytes;
@@ -13,34 +16,25 @@ use prost::Message;
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct VectorSinkConfig {
+    #[serde(default)]
+    pub version: Option<super::V1>,
     pub address: String,
     pub tls: Option<TlsConfig>,
 }
 
 impl VectorSinkConfig {
     pub fn new(address: String) -> Self {
-        Self { address, tls: None }
+        Self {
+            version: None,
+            address,
+            tls: None,
+        }
     }
-}
-
-#[derive(Debug, Snafu)]
-enum BuildError {
-    #[snafu(display("Missing host in address field"))]
-    MissingHost,
-    #[snafu(display("Missing port in address field"))]
-    MissingPort,
-}
-
-inventory::submit! {
-    SinkDescription::new_without_default::<VectorSinkConfig>("vector")
-}
 
-#[typetag::serde(name = "vector")]
-impl SinkConfig for VectorSinkConfig {
-    fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+    pub(super) fn build(&self, cx: SinkContext) -> crate::Result<(RouterSink, Healthcheck)> {
         let uri = self.address.parse::<http::Uri>()?;
 
         let host = uri.host().ok_or(BuildError::MissingHost)?.to_string();
@@ -55,14 +49,14 @@ impl SinkConfig for VectorSinkConfig {
 
         Ok((Box::new(sink), healthcheck))
     }
+}
 
-    fn input_type(&self) -> DataType {
-        DataType::Any
-    }
-
-    fn sink_type(&self) -> &'static str {
-        "vector"
-    }
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Missing host in address field"))]
+    MissingHost,
+    #[snafu(display("Missing port in address field"))]
+    MissingPort,
 }
 
 #[derive(Debug, Snafu)]
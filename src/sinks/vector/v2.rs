@@ -0,0 +1,429 @@
+use crate::{
+    dns::Resolver,
+    event::proto as event_proto,
+    internal_events::VectorEventBatchSent,
+    proto::vector as proto,
+    sinks::{
+        util::{
+            retries2::RetryLogic, service2::TowerRequestConfig, sink::Response, BatchConfig,
+            BatchSettings, EncodedLength, VecBuffer,
+        },
+        Healthcheck, RouterSink,
+    },
+    tls::{tls_connector_builder, MaybeTlsSettings, TlsConfig},
+    topology::config::SinkContext,
+};
+use flate2::write::GzEncoder;
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+use futures01::{stream::iter_ok, Sink};
+use http::{uri::Scheme, Request, Uri};
+use hyper::client::HttpConnector;
+use hyper_openssl::HttpsConnector;
+use lazy_static::lazy_static;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tonic::{body::BoxBody, Code, Status};
+use tower03::Service;
+use tracing_futures::Instrument;
+
+type Client = proto::vector_client::VectorClient<
+    AddOrigin<hyper::Client<HttpsConnector<HttpConnector<Resolver>>, BoxBody>>,
+>;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VectorSinkConfig {
+    pub version: super::V2,
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::Gzip
+    }
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: Some(5),
+        rate_limit_num: Some(1000),
+        ..Default::default()
+    };
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("At least one address is required"))]
+    NoAddresses,
+    #[snafu(display("Missing host in address {:?}", address))]
+    MissingHost { address: String },
+    #[snafu(display("Missing port in address {:?}", address))]
+    MissingPort { address: String },
+}
+
+#[derive(Debug, Snafu)]
+enum HealthcheckError {
+    #[snafu(display("Health check request to {} failed: {}", uri, source))]
+    RequestFailed { uri: Uri, source: Status },
+    #[snafu(display("{} is not serving", uri))]
+    NotServing { uri: Uri },
+}
+
+impl VectorSinkConfig {
+    pub(super) fn build(&self, cx: SinkContext) -> crate::Result<(RouterSink, Healthcheck)> {
+        if self.addresses.is_empty() {
+            return Err(BuildError::NoAddresses.into());
+        }
+
+        let tls = MaybeTlsSettings::from_config(&self.tls, false)?;
+        let client = new_client(cx.resolver(), &tls)?;
+
+        let clients = self
+            .addresses
+            .iter()
+            .map(|address| {
+                let origin = origin(address, &tls)?;
+                Ok((
+                    origin.clone(),
+                    proto::vector_client::VectorClient::new(AddOrigin {
+                        inner: client.clone(),
+                        origin,
+                    }),
+                ))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        let healthcheck = healthcheck(clients.clone()).boxed().compat();
+
+        let batch = BatchSettings::default()
+            .bytes(4_000_000)
+            .events(1000)
+            .timeout(1)
+            .parse_config(self.batch)?;
+        let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
+
+        let service = VectorService {
+            clients: Arc::new(clients.into_iter().map(|(_, client)| client).collect()),
+            next: Arc::new(AtomicUsize::new(0)),
+            compression: self.compression,
+        };
+
+        let sink = request
+            .batch_sink(
+                VectorRetryLogic,
+                service,
+                VecBuffer::new(batch.size),
+                batch.timeout,
+                cx.acker(),
+            )
+            .sink_map_err(|error| error!("Fatal vector sink error: {}", error))
+            .with_flat_map(|event| iter_ok(Some(event_proto::EventWrapper::from(event))));
+
+        Ok((Box::new(sink), Box::new(healthcheck)))
+    }
+}
+
+fn new_client(
+    resolver: Resolver,
+    tls: &MaybeTlsSettings,
+) -> crate::Result<hyper::Client<HttpsConnector<HttpConnector<Resolver>>, BoxBody>> {
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+
+    let mut tls_builder = tls_connector_builder(tls)?;
+    // gRPC requires HTTP/2, so advertise it for any proxy in between.
+    tls_builder.set_alpn_protos(b"\x02h2")?;
+    let mut https = HttpsConnector::with_connector(http, tls_builder)?;
+
+    let settings = tls.tls().cloned();
+    https.set_callback(move |c, _uri| {
+        if let Some(settings) = &settings {
+            settings.apply_connect_configuration(c);
+        }
+
+        Ok(())
+    });
+
+    Ok(hyper::Client::builder().http2_only(true).build(https))
+}
+
+/// Builds the base URI requests to `address` are sent to, using the scheme
+/// implied by the TLS settings.
+fn origin(address: &str, tls: &MaybeTlsSettings) -> crate::Result<Uri> {
+    let uri = address.parse::<Uri>()?;
+    let authority = match uri.authority() {
+        Some(authority) if !authority.host().is_empty() => authority.clone(),
+        _ => {
+            return Err(BuildError::MissingHost {
+                address: address.into(),
+            }
+            .into())
+        }
+    };
+    if authority.port_u16().is_none() {
+        return Err(BuildError::MissingPort {
+            address: address.into(),
+        }
+        .into());
+    }
+
+    let scheme = if tls.is_tls() {
+        Scheme::HTTPS
+    } else {
+        Scheme::HTTP
+    };
+
+    Ok(Uri::builder()
+        .scheme(scheme)
+        .authority(authority)
+        .path_and_query("/")
+        .build()?)
+}
+
+async fn healthcheck(clients: Vec<(Uri, Client)>) -> crate::Result<()> {
+    for (uri, mut client) in clients {
+        let response = client
+            .health_check(proto::HealthCheckRequest {})
+            .await
+            .map_err(|source| HealthcheckError::RequestFailed {
+                uri: uri.clone(),
+                source,
+            })?
+            .into_inner();
+
+        if response.status() != proto::ServingStatus::Serving {
+            return Err(HealthcheckError::NotServing { uri }.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the scheme and authority of the path-only URIs produced by the
+/// generated gRPC client.
+#[derive(Clone, Debug)]
+struct AddOrigin<S> {
+    inner: S,
+    origin: Uri,
+}
+
+impl<S, B> Service<Request<B>> for AddOrigin<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let mut parts = self.origin.clone().into_parts();
+        parts.path_and_query = request.uri().path_and_query().cloned();
+        *request.uri_mut() = Uri::from_parts(parts).expect("Origin and path form a valid URI");
+        self.inner.call(request)
+    }
+}
+
+/// Pushes each batch to the next address in turn, so that batches are
+/// spread across all of the configured aggregators and retries land on a
+/// different one.
+#[derive(Clone)]
+struct VectorService {
+    clients: Arc<Vec<Client>>,
+    next: Arc<AtomicUsize>,
+    compression: Compression,
+}
+
+impl Service<Vec<event_proto::EventWrapper>> for VectorService {
+    type Response = proto::PushEventsResponse;
+    type Error = Status;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, events: Vec<event_proto::EventWrapper>) -> Self::Future {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        let mut client = self.clients[index].clone();
+        let count = events.len();
+        let request = encode_request(events, self.compression);
+
+        Box::pin(
+            async move {
+                let request = request.map_err(|error| {
+                    Status::internal(format!("Failed to compress events: {}", error))
+                })?;
+                let byte_size = request.encoded_len();
+
+                let response = client.push_events(request).await?.into_inner();
+                check_accepted(&response, count)?;
+                emit!(VectorEventBatchSent { count, byte_size });
+
+                Ok(response)
+            }
+            .instrument(info_span!("request")),
+        )
+    }
+}
+
+/// The source acknowledges the batch only once all of its events are in
+/// the pipeline, so anything short of that is retried as a whole.
+fn check_accepted(response: &proto::PushEventsResponse, count: usize) -> Result<(), Status> {
+    if response.accepted == count as u64 {
+        Ok(())
+    } else {
+        Err(Status::aborted(format!(
+            "Only {} of {} events were accepted",
+            response.accepted, count
+        )))
+    }
+}
+
+fn encode_request(
+    events: Vec<event_proto::EventWrapper>,
+    compression: Compression,
+) -> io::Result<proto::PushEventsRequest> {
+    let compression = match compression {
+        Compression::None => {
+            return Ok(proto::PushEventsRequest {
+                events,
+                ..Default::default()
+            })
+        }
+        Compression::Gzip => proto::Compression::Gzip,
+        Compression::Zstd => proto::Compression::Zstd,
+    };
+
+    let batch = proto::EventBatch { events };
+    let mut buffer = Vec::with_capacity(batch.encoded_len());
+    batch
+        .encode(&mut buffer)
+        .expect("Vec has enough capacity for the encoded batch");
+
+    let compressed_events = match compression {
+        proto::Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&buffer)?;
+            encoder.finish()?
+        }
+        _ => zstd::stream::encode_all(&buffer[..], 0)?,
+    };
+
+    let mut request = proto::PushEventsRequest {
+        compressed_events,
+        ..Default::default()
+    };
+    request.set_compression(compression);
+    Ok(request)
+}
+
+impl EncodedLength for event_proto::EventWrapper {
+    fn encoded_length(&self) -> usize {
+        self.encoded_len()
+    }
+}
+
+impl Response for proto::PushEventsResponse {}
+
+#[derive(Debug, Clone)]
+struct VectorRetryLogic;
+
+impl RetryLogic for VectorRetryLogic {
+    type Error = Status;
+    type Response = proto::PushEventsResponse;
+
+    fn is_retriable_error(&self, error: &Self::Error) -> bool {
+        match error.code() {
+            // Connection failures surface as `Unknown`. `ResourceExhausted`
+            // is returned for batches over the size limit of the source,
+            // which would never be accepted.
+            Code::Unknown | Code::Unavailable | Code::DeadlineExceeded | Code::Aborted => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> Vec<event_proto::EventWrapper> {
+        vec!["one", "two", "three"]
+            .into_iter()
+            .map(|message| event_proto::EventWrapper::from(crate::Event::from(message)))
+            .collect()
+    }
+
+    #[test]
+    fn encode_request_uncompressed() {
+        let request = encode_request(events(), Compression::None).unwrap();
+        assert_eq!(request.compression(), proto::Compression::None);
+        assert_eq!(request.events, events());
+        assert!(request.compressed_events.is_empty());
+    }
+
+    #[test]
+    fn encode_request_zstd() {
+        let request = encode_request(events(), Compression::Zstd).unwrap();
+        assert_eq!(request.compression(), proto::Compression::Zstd);
+        assert!(request.events.is_empty());
+
+        let decompressed = zstd::stream::decode_all(&request.compressed_events[..]).unwrap();
+        let batch = proto::EventBatch::decode(&decompressed[..]).unwrap();
+        assert_eq!(batch.events, events());
+    }
+
+    #[test]
+    fn partial_acceptance_is_retried() {
+        let response = proto::PushEventsResponse { accepted: 3 };
+        assert!(check_accepted(&response, 3).is_ok());
+
+        let error = check_accepted(&response, 5).unwrap_err();
+        assert_eq!(error.code(), Code::Aborted);
+        assert!(VectorRetryLogic.is_retriable_error(&error));
+    }
+
+    #[test]
+    fn origin_uses_tls_scheme() {
+        let raw = MaybeTlsSettings::Raw(());
+        assert_eq!(
+            origin("localhost:9000", &raw).unwrap(),
+            "http://localhost:9000/"
+        );
+        assert!(origin("localhost", &raw).is_err());
+
+        let tls = MaybeTlsSettings::enable_client().unwrap();
+        assert_eq!(
+            origin("10.0.0.1:9000", &tls).unwrap(),
+            "https://10.0.0.1:9000/"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::util::MultilineConfig;
 use crate::{
     event::merge_state::LogEventMergeState,
     event::{self, Event, LogEvent, Value},
@@ -6,6 +7,7 @@ use crate::{
         DockerContainerUnwatch, DockerContainerWatch, DockerEventReceived,
         DockerLoggingDriverUnsupported, DockerTimestampParseFailed,
     },
+    line_agg::{self, LineAgg},
     shutdown::ShutdownSignal,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Pipeline,
@@ -15,7 +17,7 @@ use bollard::{
     errors::{Error as DockerError, ErrorKind as DockerErrorKind},
     service::{ContainerInspectResponse, SystemEventsResponse},
     system::EventsOptions,
-    Docker,
+    Docker, API_DEFAULT_VERSION,
 };
 use bytes05::{Buf, Bytes};
 use chrono::{DateTime, FixedOffset, Local, NaiveTime, ParseError, Utc, MAX_DATE};
@@ -25,8 +27,12 @@ use futures::{
     sink::SinkExt,
     FutureExt, Stream, StreamExt, TryFutureExt,
 };
+use http::uri::{InvalidUri, Uri};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::convert::TryFrom;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::{collections::HashMap, env};
@@ -37,6 +43,10 @@ use tracing::field;
 /// The begining of image names of vector docker images packaged by vector.
 const VECTOR_IMAGE_NAME: &str = "timberio/vector";
 
+/// Timeout, in seconds, for requests to the docker daemon, same as the
+/// `bollard` defaults.
+const DOCKER_TIMEOUT: u64 = 120;
+
 lazy_static! {
     static ref STDERR: Bytes = "stderr".into();
     static ref STDOUT: Bytes = "stdout".into();
@@ -47,24 +57,70 @@ lazy_static! {
     static ref CONTAINER: Atom = Atom::from("container_id");
 }
 
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("docker_host {:?} is not a valid URI: {}", host, source))]
+    InvalidDockerHost { host: String, source: InvalidUri },
+    #[snafu(display("docker_host {:?} has an unsupported scheme", host))]
+    UnsupportedDockerHostScheme { host: String },
+    #[snafu(display(
+        "docker_host {:?} requires TLS, but neither tls nor DOCKER_CERT_PATH is set",
+        host
+    ))]
+    MissingDockerTls { host: String },
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields, default)]
 pub struct DockerConfig {
+    docker_host: Option<String>,
+    tls: Option<DockerTlsConfig>,
     include_containers: Option<Vec<String>>, // Starts with actually, not include
+    exclude_containers: Option<Vec<String>>, // Starts with actually, not exclude
     include_labels: Option<Vec<String>>,
+    exclude_labels: Option<Vec<String>>,
     include_images: Option<Vec<String>>,
+    exclude_images: Option<Vec<String>>,
     partial_event_marker_field: Option<Atom>,
     auto_partial_merge: bool,
+    multiline: Option<MultilineConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DockerTlsConfig {
+    ca_file: PathBuf,
+    crt_file: PathBuf,
+    key_file: PathBuf,
+}
+
+impl DockerTlsConfig {
+    /// Certificates in the directory given by `DOCKER_CERT_PATH`, named the
+    /// same way the docker CLI expects them.
+    fn from_env() -> Option<Self> {
+        let path = PathBuf::from(env::var("DOCKER_CERT_PATH").ok()?);
+        Some(DockerTlsConfig {
+            ca_file: path.join("ca.pem"),
+            crt_file: path.join("cert.pem"),
+            key_file: path.join("key.pem"),
+        })
+    }
 }
 
 impl Default for DockerConfig {
     fn default() -> Self {
         Self {
+            docker_host: None,
+            tls: None,
             include_containers: None,
+            exclude_containers: None,
             include_labels: None,
+            exclude_labels: None,
             include_images: None,
+            exclude_images: None,
             partial_event_marker_field: Some(event::PARTIAL.clone()),
             auto_partial_merge: true,
+            multiline: None,
         }
     }
 }
@@ -92,6 +148,42 @@ impl DockerConfig {
         }
     }
 
+    /// True if the container matches any of the exclude filters.
+    /// `labels` may contain other container attributes besides labels.
+    fn container_excluded<'a>(
+        &self,
+        id: &str,
+        names: impl IntoIterator<Item = &'a str>,
+        image: Option<&str>,
+        labels: Option<&HashMap<String, String>>,
+    ) -> bool {
+        let names = names.into_iter().collect::<Vec<_>>();
+        let name_excluded = self.exclude_containers.iter().flatten().any(|exclude| {
+            id.starts_with(exclude.as_str())
+                || names.iter().any(|name| name.starts_with(exclude.as_str()))
+        });
+
+        let image_excluded = image
+            .map(|image| {
+                self.exclude_images
+                    .iter()
+                    .flatten()
+                    .any(|exclude| image_matches(image, exclude))
+            })
+            .unwrap_or(false);
+
+        let label_excluded = labels
+            .map(|labels| {
+                self.exclude_labels
+                    .iter()
+                    .flatten()
+                    .any(|exclude| label_matches(labels, exclude))
+            })
+            .unwrap_or(false);
+
+        name_excluded || image_excluded || label_excluded
+    }
+
     fn with_empty_partial_event_marker_field_as_none(mut self) -> Self {
         if let Some(val) = &self.partial_event_marker_field {
             if val.is_empty() {
@@ -102,6 +194,23 @@ impl DockerConfig {
     }
 }
 
+/// True if `image` is the image `name`, with or without a tag or digest.
+fn image_matches(image: &str, name: &str) -> bool {
+    image == name
+        || (image.starts_with(name) && image[name.len()..].starts_with(|c| c == ':' || c == '@'))
+}
+
+/// True if `labels` match the filter, which is either a label `key` or a
+/// `key=value` pair, same as in the docker `label` filter.
+fn label_matches(labels: &HashMap<String, String>, filter: &str) -> bool {
+    let mut parts = filter.splitn(2, '=');
+    let key = parts.next().unwrap_or_default();
+    match parts.next() {
+        Some(value) => labels.get(key).map(|v| v == value).unwrap_or(false),
+        None => labels.contains_key(key),
+    }
+}
+
 inventory::submit! {
     SourceDescription::new::<DockerConfig>("docker")
 }
@@ -155,6 +264,8 @@ impl SourceConfig for DockerConfig {
 
 struct DockerSourceCore {
     config: DockerConfig,
+    /// Multiline aggregation applied to each container's log stream.
+    line_agg_config: Option<line_agg::Config>,
     docker: Docker,
     /// Only logs created at, or after this moment are logged.
     now_timestamp: DateTime<Utc>,
@@ -162,9 +273,16 @@ struct DockerSourceCore {
 
 impl DockerSourceCore {
     fn new(config: DockerConfig) -> crate::Result<Self> {
-        // ?NOTE: Constructs a new Docker instance for a docker host listening at url specified by an env var DOCKER_HOST.
+        // ?NOTE: Constructs a new Docker instance for a docker host listening at url specified by docker_host,
+        // ?      or an env var DOCKER_HOST.
         // ?      Otherwise connects to unix socket which requires sudo privileges, or docker group membership.
-        let docker = docker()?;
+        let docker = docker(config.docker_host.clone(), config.tls.clone())?;
+
+        let line_agg_config = config
+            .multiline
+            .as_ref()
+            .map(line_agg::Config::try_from)
+            .transpose()?;
 
         // Only log events created at-or-after this moment are logged.
         let now = Local::now();
@@ -175,6 +293,7 @@ impl DockerSourceCore {
 
         Ok(DockerSourceCore {
             config,
+            line_agg_config,
             docker,
             now_timestamp: now.into(),
         })
@@ -329,6 +448,7 @@ impl DockerSource {
                 let id = container.id.unwrap();
                 let names = container.names.unwrap();
                 let image = container.image.unwrap();
+                let labels = container.labels;
 
                 trace!(
                     message = "found already running container.",
@@ -340,18 +460,28 @@ impl DockerSource {
                     return;
                 }
 
-                if !self.esb.core.config.container_name_included(
-                    id.as_str(),
-                    names.iter().map(|s| {
-                        // In this case bollard / shiplift gives names with starting '/' so it needs to be removed.
+                // In this case bollard / shiplift gives names with starting '/' so it needs to be removed.
+                let names = names
+                    .iter()
+                    .map(|s| {
                         let s = s.as_str();
                         if s.starts_with('/') {
                             s.split_at('/'.len_utf8()).1
                         } else {
                             s
                         }
-                    }),
-                ) {
+                    })
+                    .collect::<Vec<_>>();
+
+                let config = &self.esb.core.config;
+                if !config.container_name_included(id.as_str(), names.iter().cloned())
+                    || config.container_excluded(
+                        id.as_str(),
+                        names.iter().cloned(),
+                        Some(image.as_str()),
+                        labels.as_ref(),
+                    )
+                {
                     trace!(message = "container excluded.", id = field::display(&id));
                     return;
                 }
@@ -427,12 +557,21 @@ impl DockerSource {
                                                 attributes.get("name").map(|s| s.as_str()),
                                             );
 
+                                        // Event attributes contain the container labels, besides its name and image.
+                                        let excluded =
+                                            self.esb.core.config.container_excluded(
+                                                id.as_str(),
+                                                attributes.get("name").map(|s| s.as_str()),
+                                                attributes.get("image").map(|s| s.as_str()),
+                                                Some(&attributes),
+                                            );
+
                                         let self_check = self.exclude_vector(
                                             id.as_str(),
                                             attributes.get("image").map(|s| s.as_str()),
                                         );
 
-                                        if include_name && self_check {
+                                        if include_name && !excluded && self_check {
                                             self.containers.insert(id.clone(), self.esb.start(id));
                                         }
                                     }
@@ -551,7 +690,7 @@ impl EventStreamBuilder {
         // Create event streamer
         let mut partial_event_merge_state = None;
 
-        stream
+        let events = stream
             .map(|value| {
                 match value {
                     Ok(message) => Ok(info.new_event(
@@ -582,7 +721,16 @@ impl EventStreamBuilder {
                 }
             })
             .take_while(|v| future::ready(v.is_ok()))
-            .filter_map(|v| future::ready(v.transpose()))
+            .filter_map(|v| future::ready(v.transpose()));
+
+        let events: Pin<Box<dyn Stream<Item = Result<Event, ()>> + Send + '_>> =
+            if let Some(config) = &self.core.line_agg_config {
+                Box::pin(line_agg_events(events, config.clone()))
+            } else {
+                Box::pin(events)
+            };
+
+        events
             .take_until(self.shutdown.clone().compat())
             .forward(self.out.clone().sink_compat().sink_map_err(|_| ()))
             .map(|_| {})
@@ -599,6 +747,35 @@ impl EventStreamBuilder {
     }
 }
 
+/// Aggregates multiline messages of the container, separately for stdout and
+/// stderr. Aggregated events keep the fields of their first line.
+fn line_agg_events(
+    events: impl Stream<Item = Result<Event, ()>> + Send,
+    config: line_agg::Config,
+) -> impl Stream<Item = Result<Event, ()>> + Send {
+    let lines = Box::pin(
+        events
+            .filter_map(|event| future::ready(event.ok()))
+            .map(|event| {
+                let mut log = event.into_log();
+                let message = log
+                    .remove(&event::log_schema().message_key())
+                    .map(|message| Bytes::copy_from_slice(&message.as_bytes()))
+                    .unwrap_or_default();
+                let stream = log
+                    .get(&STREAM)
+                    .map(|stream| Bytes::copy_from_slice(&stream.as_bytes()))
+                    .unwrap_or_default();
+                (message, stream, log)
+            }),
+    );
+
+    LineAgg::new(lines, line_agg::Logic::new(config)).map(|(message, _stream, mut log)| {
+        log.insert(event::log_schema().message_key().clone(), message);
+        Ok(Event::Log(log))
+    })
+}
+
 /// Container ID as assigned by Docker.
 /// Is actually a string.
 #[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -911,17 +1088,63 @@ impl ContainerMetadata {
     }
 }
 
-fn docker() -> Result<Docker, DockerError> {
-    let scheme = env::var("DOCKER_HOST").ok().and_then(|host| {
-        let uri = host.parse::<hyper::Uri>().expect("invalid url");
-        uri.into_parts().scheme
-    });
+/// Connects to the docker daemon at `host`, or at `DOCKER_HOST` if not given.
+/// Without either, connects to the local daemon socket.
+fn docker(host: Option<String>, tls: Option<DockerTlsConfig>) -> crate::Result<Docker> {
+    let host = match host.or_else(|| env::var("DOCKER_HOST").ok()) {
+        Some(host) => host,
+        None => return Ok(Docker::connect_with_local_defaults()?),
+    };
 
-    match scheme.as_ref().map(|s| s.as_str()) {
-        Some("http") => Docker::connect_with_http_defaults(),
-        Some("https") => Docker::connect_with_tls_defaults(),
-        _ => Docker::connect_with_local_defaults(),
+    // Socket paths have no authority, so they aren't parsed as a URI.
+    #[cfg(unix)]
+    {
+        if let Some(path) = host.strip_prefix("unix://") {
+            return Ok(Docker::connect_with_unix(
+                path,
+                DOCKER_TIMEOUT,
+                API_DEFAULT_VERSION,
+            )?);
+        }
     }
+
+    let uri = host
+        .parse::<Uri>()
+        .with_context(|| InvalidDockerHost { host: host.clone() })?;
+    let authority = uri
+        .authority()
+        .map(|authority| authority.as_str().to_owned());
+
+    let (scheme, authority) = match (uri.scheme_str(), authority) {
+        (Some(scheme @ "http"), Some(authority))
+        | (Some(scheme @ "tcp"), Some(authority))
+        | (Some(scheme @ "https"), Some(authority)) => (scheme, authority),
+        _ => return Err(BuildError::UnsupportedDockerHostScheme { host }.into()),
+    };
+
+    // An explicit `tls` configuration applies to any TCP host, while `https`
+    // hosts can also take their certificates from `DOCKER_CERT_PATH`.
+    let tls = match (scheme, tls) {
+        (_, Some(tls)) => Some(tls),
+        ("https", None) => Some(
+            DockerTlsConfig::from_env()
+                .ok_or_else(|| BuildError::MissingDockerTls { host: host.clone() })?,
+        ),
+        (_, None) => None,
+    };
+
+    let docker = match tls {
+        Some(tls) => Docker::connect_with_tls(
+            &authority,
+            &tls.key_file,
+            &tls.crt_file,
+            &tls.ca_file,
+            DOCKER_TIMEOUT,
+            API_DEFAULT_VERSION,
+        )?,
+        None => Docker::connect_with_http(&authority, DOCKER_TIMEOUT, API_DEFAULT_VERSION)?,
+    };
+    Ok(docker)
 }
 
 #[cfg(all(test, feature = "docker-integration-tests"))]
@@ -1184,7 +1407,7 @@ mod tests {
         let out = source_with(&[name], None, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id = container_log_n(1, name, Some(label), message, &docker).await;
             let events = collect_n(out, 1).compat().await.ok().unwrap();
@@ -1215,7 +1438,7 @@ mod tests {
         let out = source_with(&[name], None, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id = container_log_n(2, name, None, message, &docker).await;
             let events = collect_n(out, 2).compat().await.ok().unwrap();
@@ -1244,7 +1467,7 @@ mod tests {
         let out = source_with(&[name1], None, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id0 = container_log_n(1, name0, None, "13", &docker).await;
             let id1 = container_log_n(1, name1, None, message, &docker).await;
@@ -1272,7 +1495,7 @@ mod tests {
         let out = source_with(&[name0, name1], label, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id0 = container_log_n(1, name0, None, "13", &docker).await;
             let id1 = container_log_n(1, name1, Some(label), message, &docker).await;
@@ -1296,7 +1519,7 @@ mod tests {
         let label = "vector_test_label_currently_running";
 
         let mut rt = runtime();
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
         let id = running_container(name, Some(label), message, &docker, &mut rt);
         let out = source_with(&[name], None, &mut rt);
 
@@ -1335,7 +1558,7 @@ mod tests {
         let out = source_with_config(config, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id = container_log_n(1, name, None, message, &docker).await;
             let events = collect_n(out, 1).compat().await.ok().unwrap();
@@ -1363,7 +1586,7 @@ mod tests {
         let exclude_out = source_with_config(config_ex, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id = container_log_n(1, name, None, message, &docker).await;
             container_remove(&id, &docker).await;
@@ -1389,7 +1612,7 @@ mod tests {
         };
 
         let mut rt = runtime();
-        let docker = docker().unwrap();
+        let docker = docker(None, None).unwrap();
 
         let id = running_container(name, None, message, &docker, &mut rt);
         let exclude_out = source_with_config(config_ex, &mut rt);
@@ -1404,6 +1627,130 @@ mod tests {
         });
     }
 
+    #[test]
+    fn exclude_containers() {
+        trace_init();
+
+        let message = "18";
+        let name0 = "vector_test_exclude_containers_0";
+        let name1 = "vector_test_exclude_containers_1";
+        let config = DockerConfig {
+            include_containers: Some(vec!["vector_test_exclude_containers".to_owned()]),
+            exclude_containers: Some(vec![name0.to_owned()]),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = runtime();
+        let out = source_with_config(config, &mut rt);
+
+        rt.block_on_std(async move {
+            let docker = docker(None, None).unwrap();
+
+            let id0 = container_log_n(1, name0, None, "19", &docker).await;
+            let id1 = container_log_n(1, name1, None, message, &docker).await;
+            let events = collect_n(out, 1).compat().await.ok().unwrap();
+            container_remove(&id0, &docker).await;
+            container_remove(&id1, &docker).await;
+
+            assert_eq!(
+                events[0].as_log()[&event::log_schema().message_key()],
+                message.into()
+            );
+        });
+    }
+
+    #[test]
+    fn exclude_labels() {
+        trace_init();
+
+        let message = "20";
+        let name0 = "vector_test_exclude_labels_0";
+        let name1 = "vector_test_exclude_labels_1";
+        let label = "vector_test_exclude_label";
+        let config = DockerConfig {
+            include_containers: Some(vec![name0.to_owned(), name1.to_owned()]),
+            exclude_labels: Some(vec![label.to_owned()]),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = runtime();
+        let out = source_with_config(config, &mut rt);
+
+        rt.block_on_std(async move {
+            let docker = docker(None, None).unwrap();
+
+            let id0 = container_log_n(1, name0, Some(label), "21", &docker).await;
+            let id1 = container_log_n(1, name1, None, message, &docker).await;
+            let events = collect_n(out, 1).compat().await.ok().unwrap();
+            container_remove(&id0, &docker).await;
+            container_remove(&id1, &docker).await;
+
+            assert_eq!(
+                events[0].as_log()[&event::log_schema().message_key()],
+                message.into()
+            );
+        });
+    }
+
+    #[test]
+    fn multiline() {
+        trace_init();
+
+        let name = "vector_test_multiline";
+        let config = DockerConfig {
+            include_containers: Some(vec![name.to_owned()]),
+            multiline: Some(MultilineConfig {
+                start_pattern: "^[^\\s]".to_owned(),
+                condition_pattern: "^[\\s]+".to_owned(),
+                mode: line_agg::Mode::ContinueThrough,
+                timeout_ms: 1000,
+            }),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = runtime();
+        let out = source_with_config(config, &mut rt);
+
+        rt.block_on_std(async move {
+            let docker = docker(None, None).unwrap();
+
+            let command = "echo foo; echo '  bar'; echo baz";
+            let id = cmd_container(name, None, vec!["sh", "-c", command], &docker).await;
+            if let Err(error) = container_run(&id, &docker).await {
+                container_remove(&id, &docker).await;
+                panic!("Container failed to start with error: {:?}", error);
+            }
+            let events = collect_n(out, 2).compat().await.ok().unwrap();
+            container_remove(&id, &docker).await;
+
+            assert_eq!(
+                events[0].as_log()[&event::log_schema().message_key()],
+                "foo\n  bar".into()
+            );
+            assert_eq!(
+                events[1].as_log()[&event::log_schema().message_key()],
+                "baz".into()
+            );
+            assert_eq!(events[0].as_log()[&super::NAME], name.into());
+        });
+    }
+
+    #[test]
+    fn filter_matching() {
+        assert!(image_matches("busybox", "busybox"));
+        assert!(image_matches("busybox:latest", "busybox"));
+        assert!(image_matches("busybox@sha256:abc", "busybox"));
+        assert!(!image_matches("busybox-extra", "busybox"));
+
+        let labels = vec![("app".to_owned(), "web".to_owned())]
+            .into_iter()
+            .collect();
+        assert!(label_matches(&labels, "app"));
+        assert!(label_matches(&labels, "app=web"));
+        assert!(!label_matches(&labels, "app=db"));
+        assert!(!label_matches(&labels, "env"));
+    }
+
     #[test]
     fn log_longer_than_16kb() {
         trace_init();
@@ -1418,7 +1765,7 @@ mod tests {
         let out = source_with(&[name], None, &mut rt);
 
         rt.block_on_std(async move {
-            let docker = docker().unwrap();
+            let docker = docker(None, None).unwrap();
 
             let id = container_log_n(1, name, None, message.as_str(), &docker).await;
             let events = collect_n(out, 1).compat().await.ok().unwrap();
@@ -0,0 +1,575 @@
+use crate::{
+    event::{self, Event},
+    internal_events::{ExecCommandExited, ExecEventReceived, ExecFailedToSpawn, ExecWaitFailed},
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    Pipeline,
+};
+use bytes05::Bytes;
+use codec::BytesDelimitedCodec;
+use futures::{
+    compat::{Future01CompatExt, Sink01CompatExt},
+    future::{self, Either},
+    stream, FutureExt, SinkExt, StreamExt, TryFutureExt,
+};
+use futures01::Sink;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::{
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+use tokio::{process::Command, time::delay_for};
+use tokio_retry::strategy::ExponentialBackoff;
+use tokio_util::codec::FramedRead;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+// TODO: add back when serde-rs/serde#1358 is addressed
+// #[serde(deny_unknown_fields)]
+pub struct ExecConfig {
+    #[serde(flatten)]
+    pub mode: Mode,
+    pub command: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    #[serde(default = "default_include_stderr")]
+    pub include_stderr: bool,
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    pub host_key: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    /// Run the command to completion every `exec_interval_secs`.
+    Scheduled {
+        #[serde(default = "default_exec_interval_secs")]
+        exec_interval_secs: u64,
+    },
+    /// Run the command once and keep reading from it for as long as it lives.
+    Streaming {
+        #[serde(default = "default_respawn_on_exit")]
+        respawn_on_exit: bool,
+        #[serde(default = "default_respawn_interval_secs")]
+        respawn_interval_secs: u64,
+        #[serde(default = "default_max_respawn_interval_secs")]
+        max_respawn_interval_secs: u64,
+    },
+}
+
+fn default_include_stderr() -> bool {
+    true
+}
+
+fn default_max_length() -> usize {
+    bytesize::kib(100u64) as usize
+}
+
+fn default_exec_interval_secs() -> u64 {
+    60
+}
+
+fn default_respawn_on_exit() -> bool {
+    true
+}
+
+fn default_respawn_interval_secs() -> u64 {
+    1
+}
+
+fn default_max_respawn_interval_secs() -> u64 {
+    60
+}
+
+/// A process that stayed up this long is considered healthy, and the respawn
+/// backoff starts over the next time it exits.
+const HEALTHY_RUN_SECS: u64 = 60;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("`command` must not be empty"))]
+    EmptyCommand,
+    #[snafu(display("`{}` must be greater than zero", name))]
+    ZeroInterval { name: &'static str },
+}
+
+impl ExecConfig {
+    pub fn new(mode: Mode, command: Vec<String>) -> Self {
+        Self {
+            mode,
+            command,
+            working_directory: None,
+            include_stderr: default_include_stderr(),
+            max_length: default_max_length(),
+            host_key: None,
+        }
+    }
+
+    fn validate(&self) -> crate::Result<()> {
+        if self.command.is_empty() {
+            return Err(BuildError::EmptyCommand.into());
+        }
+        match self.mode {
+            Mode::Scheduled {
+                exec_interval_secs: 0,
+            } => Err(BuildError::ZeroInterval {
+                name: "exec_interval_secs",
+            }
+            .into()),
+            Mode::Streaming {
+                respawn_interval_secs: 0,
+                ..
+            } => Err(BuildError::ZeroInterval {
+                name: "respawn_interval_secs",
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<ExecConfig>("exec")
+}
+
+#[typetag::serde(name = "exec")]
+impl SourceConfig for ExecConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        self.validate()?;
+        let source = exec_source(self.clone(), shutdown, out);
+        Ok(Box::new(source.boxed().compat()))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "exec"
+    }
+}
+
+async fn exec_source(
+    config: ExecConfig,
+    shutdown: ShutdownSignal,
+    out: Pipeline,
+) -> Result<(), ()> {
+    let host_key = config
+        .host_key
+        .clone()
+        .unwrap_or_else(|| event::log_schema().host_key().to_string());
+    let hostname = hostname::get_hostname();
+
+    match config.mode {
+        Mode::Scheduled { exec_interval_secs } => {
+            let mut ticks = tokio::time::interval(Duration::from_secs(exec_interval_secs))
+                .take_until(shutdown.clone().compat());
+            while ticks.next().await.is_some() {
+                run_command(&config, &host_key, &hostname, shutdown.clone(), out.clone()).await;
+            }
+        }
+        Mode::Streaming {
+            respawn_on_exit,
+            respawn_interval_secs,
+            max_respawn_interval_secs,
+        } => {
+            // The delays are `base^n * factor` milliseconds, so a base of 2
+            // and a factor of half the interval in milliseconds start at
+            // `respawn_interval_secs` and double on every respawn.
+            let fresh_backoff = || {
+                ExponentialBackoff::from_millis(2)
+                    .factor(respawn_interval_secs * 500)
+                    .max_delay(Duration::from_secs(max_respawn_interval_secs))
+            };
+            let mut backoff = fresh_backoff();
+            let mut shutdown_done = shutdown.clone().compat();
+
+            loop {
+                let started = Instant::now();
+                run_command(&config, &host_key, &hostname, shutdown.clone(), out.clone()).await;
+
+                if !respawn_on_exit {
+                    break;
+                }
+                if started.elapsed() >= Duration::from_secs(HEALTHY_RUN_SECS) {
+                    backoff = fresh_backoff();
+                }
+
+                let delay = backoff.next().expect("backoff never ends");
+                debug!(message = "respawning command.", ?delay);
+                match future::select(delay_for(delay), &mut shutdown_done).await {
+                    Either::Left(_) => {}
+                    Either::Right(_) => break,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the command once, forwarding its output until it exits or shutdown
+/// begins, in which case the process is killed.
+async fn run_command(
+    config: &ExecConfig,
+    host_key: &str,
+    hostname: &Option<String>,
+    shutdown: ShutdownSignal,
+    out: Pipeline,
+) {
+    let mut command = Command::new(&config.command[0]);
+    command
+        .args(&config.command[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(if config.include_stderr {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .kill_on_drop(true);
+    if let Some(dir) = &config.working_directory {
+        command.current_dir(dir);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            emit!(ExecFailedToSpawn {
+                command: &config.command,
+                error,
+            });
+            return;
+        }
+    };
+    let pid = child.id();
+    debug!(message = "spawned command.", command = ?config.command, %pid);
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|stdout| output_lines(stdout, "stdout", config.max_length));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|stderr| output_lines(stderr, "stderr", config.max_length));
+    let lines = stream::select(
+        stream::iter(stdout).flatten(),
+        stream::iter(stderr).flatten(),
+    );
+
+    let command = config.command.clone();
+    let host_key = host_key.to_owned();
+    let hostname = hostname.clone();
+    let mut events = Box::pin(lines.take_until(shutdown.clone().compat()).map(
+        move |(line, stream)| {
+            Ok(build_event(
+                line, stream, pid, &command, &host_key, &hostname,
+            ))
+        },
+    ));
+
+    let mut out = out
+        .sink_map_err(|error| error!(message = "error sending event.", %error))
+        .sink_compat();
+    let _ = out.send_all(&mut events).await;
+
+    // Either the command closed its output or we are shutting down. Even in
+    // the former case it may keep running, so stop it once shutdown begins.
+    let status = match future::select(&mut child, shutdown.compat()).await {
+        Either::Left((status, _)) => status,
+        Either::Right(_) => {
+            let _ = child.kill();
+            child.await
+        }
+    };
+    match status {
+        Ok(status) => emit!(ExecCommandExited {
+            command: &config.command,
+            pid,
+            status: exit_code(status),
+        }),
+        Err(error) => emit!(ExecWaitFailed {
+            command: &config.command,
+            error,
+        }),
+    }
+}
+
+fn output_lines<R>(
+    reader: R,
+    stream: &'static str,
+    max_length: usize,
+) -> impl futures::Stream<Item = (Bytes, &'static str)>
+where
+    R: tokio::io::AsyncRead,
+{
+    FramedRead::new(
+        reader,
+        BytesDelimitedCodec::new_with_max_length(b'\n', max_length),
+    )
+    .filter_map(move |line| {
+        future::ready(match line {
+            Ok(line) => Some((line, stream)),
+            Err(error) => {
+                warn!(message = "error reading command output.", %error, %stream);
+                None
+            }
+        })
+    })
+}
+
+fn build_event(
+    line: Bytes,
+    stream: &'static str,
+    pid: u32,
+    command: &[String],
+    host_key: &str,
+    hostname: &Option<String>,
+) -> Event {
+    emit!(ExecEventReceived {
+        byte_size: line.len()
+    });
+
+    let mut event = Event::from(line);
+    let log = event.as_mut_log();
+    log.insert(event::log_schema().source_type_key(), "exec");
+    log.insert("command", command.join(" "));
+    log.insert("pid", pid as i64);
+    log.insert("stream", stream);
+    if let Some(hostname) = hostname {
+        log.insert(host_key, hostname.clone());
+    }
+    event
+}
+
+#[cfg(unix)]
+fn exit_code(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: ExitStatus) -> Option<i32> {
+    status.code()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_util::{collect_n, runtime};
+    use futures01::Stream;
+
+    fn shell(script: &str) -> Vec<String> {
+        vec!["sh".into(), "-c".into(), script.into()]
+    }
+
+    #[test]
+    fn parses_config() {
+        let config: ExecConfig = toml::from_str(
+            r#"
+            mode = "streaming"
+            command = ["tail", "-f", "/var/log/messages"]
+            respawn_interval_secs = 5
+          "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.mode,
+            Mode::Streaming {
+                respawn_on_exit: true,
+                respawn_interval_secs: 5,
+                max_respawn_interval_secs: 60,
+            }
+        );
+        assert!(config.include_stderr);
+
+        let config: ExecConfig = toml::from_str(
+            r#"
+            mode = "scheduled"
+            command = ["uptime"]
+          "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.mode,
+            Mode::Scheduled {
+                exec_interval_secs: 60
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_empty_command() {
+        let config = ExecConfig::new(
+            Mode::Scheduled {
+                exec_interval_secs: 1,
+            },
+            vec![],
+        );
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn captures_stdout_and_stderr() {
+        let (tx, rx) = Pipeline::new_test();
+        let config = ExecConfig::new(
+            Mode::Streaming {
+                respawn_on_exit: false,
+                respawn_interval_secs: 1,
+                max_respawn_interval_secs: 1,
+            },
+            shell("echo out; echo err 1>&2"),
+        );
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .unwrap();
+
+        let mut rt = runtime();
+        rt.spawn(source);
+        let mut events = rt.block_on(collect_n(rx, 2)).unwrap();
+        events.sort_by_key(|event| event.as_log()[&"stream".into()].to_string_lossy());
+
+        let stderr = events[0].as_log();
+        assert_eq!(stderr[&event::log_schema().message_key()], "err".into());
+        assert_eq!(stderr[&"stream".into()], "stderr".into());
+        assert_eq!(
+            stderr[&"command".into()],
+            "sh -c echo out; echo err 1>&2".into()
+        );
+        assert!(stderr.get(&"pid".into()).is_some());
+
+        let stdout = events[1].as_log();
+        assert_eq!(stdout[&event::log_schema().message_key()], "out".into());
+        assert_eq!(stdout[&"stream".into()], "stdout".into());
+        assert_eq!(
+            stdout[&event::log_schema().source_type_key()],
+            "exec".into()
+        );
+    }
+
+    #[test]
+    fn excludes_stderr() {
+        let (tx, rx) = Pipeline::new_test();
+        let mut config = ExecConfig::new(
+            Mode::Streaming {
+                respawn_on_exit: false,
+                respawn_interval_secs: 1,
+                max_respawn_interval_secs: 1,
+            },
+            shell("echo err 1>&2; echo out"),
+        );
+        config.include_stderr = false;
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .unwrap();
+
+        let mut rt = runtime();
+        rt.block_on(source).unwrap();
+        let events = rt.block_on(rx.collect()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_log()[&"stream".into()], "stdout".into());
+    }
+
+    #[test]
+    fn respawns_streaming_command() {
+        let (tx, rx) = Pipeline::new_test();
+        let config = ExecConfig::new(
+            Mode::Streaming {
+                respawn_on_exit: true,
+                respawn_interval_secs: 1,
+                max_respawn_interval_secs: 1,
+            },
+            shell("echo once"),
+        );
+        let source = config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .unwrap();
+
+        let mut rt = runtime();
+        rt.spawn(source);
+        let events = rt.block_on(collect_n(rx, 2)).unwrap();
+        assert_ne!(
+            events[0].as_log()[&"pid".into()],
+            events[1].as_log()[&"pid".into()]
+        );
+    }
+
+    #[test]
+    fn kills_command_on_shutdown() {
+        let (tx, rx) = Pipeline::new_test();
+        let (trigger, shutdown, shutdown_done) = ShutdownSignal::new_wired();
+        let config = ExecConfig::new(
+            Mode::Streaming {
+                respawn_on_exit: true,
+                respawn_interval_secs: 1,
+                max_respawn_interval_secs: 1,
+            },
+            shell("echo started; sleep 1000"),
+        );
+        let source = config
+            .build("default", &GlobalOptions::default(), shutdown, tx)
+            .unwrap();
+
+        let mut rt = runtime();
+        let source = rt.spawn_handle_std(source.compat());
+        let events = rt.block_on(collect_n(rx, 1)).unwrap();
+        assert_eq!(
+            events[0].as_log()[&event::log_schema().message_key()],
+            "started".into()
+        );
+
+        drop(trigger);
+        rt.block_on(shutdown_done).unwrap();
+        rt.block_on_std(source).unwrap().unwrap();
+    }
+
+    #[test]
+    fn kills_command_with_closed_output_on_shutdown() {
+        let (tx, rx) = Pipeline::new_test();
+        let (trigger, shutdown, shutdown_done) = ShutdownSignal::new_wired();
+        let config = ExecConfig::new(
+            Mode::Streaming {
+                respawn_on_exit: true,
+                respawn_interval_secs: 1,
+                max_respawn_interval_secs: 1,
+            },
+            shell("echo started; exec >&- 2>&-; sleep 1000"),
+        );
+        let source = config
+            .build("default", &GlobalOptions::default(), shutdown, tx)
+            .unwrap();
+
+        let mut rt = runtime();
+        let source = rt.spawn_handle_std(source.compat());
+        rt.block_on(collect_n(rx, 1)).unwrap();
+
+        drop(trigger);
+        rt.block_on(shutdown_done).unwrap();
+        rt.block_on_std(source).unwrap().unwrap();
+    }
+}
@@ -1,6 +1,8 @@
+use super::util::MultilineConfig;
 use crate::{
     event::{self, Event},
     internal_events::FileEventReceived,
+    line_agg::{self, LineAgg},
     shutdown::ShutdownSignal,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     trace::{current_span, Instrument},
@@ -9,7 +11,7 @@ use crate::{
 use bytes05::Bytes;
 use file_source::{
     paths_provider::glob::{Glob, MatchOptions},
-    FileServer, Fingerprinter,
+    FileFingerprint, FileServer, Fingerprinter, Line,
 };
 use futures::{
     compat::{Compat, Compat01As03, Compat01As03Sink, Future01CompatExt},
@@ -20,14 +22,11 @@ use futures01::{future, Future, Sink, Stream};
 use regex::bytes::Regex;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryInto;
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 use tokio::task::spawn_blocking;
 
-mod line_agg;
-use line_agg::LineAgg;
-
 #[derive(Debug, Snafu)]
 enum BuildError {
     #[snafu(display("data_dir option required, but not given here or globally"))]
@@ -55,22 +54,9 @@ enum BuildError {
         indicator: String,
         source: regex::Error,
     },
-    #[snafu(display(
-        "unable to parse multiline start pattern from {:?}: {}",
-        start_pattern,
-        source
-    ))]
-    InvalidMultilineStartPattern {
-        start_pattern: String,
-        source: regex::Error,
-    },
-    #[snafu(display(
-        "unable to parse multiline condition pattern from {:?}: {}",
-        condition_pattern,
-        source
-    ))]
-    InvalidMultilineConditionPattern {
-        condition_pattern: String,
+    #[snafu(display("path_regex {:?} is not a valid regex: {}", path_regex, source))]
+    InvalidPathRegex {
+        path_regex: String,
         source: regex::Error,
     },
 }
@@ -86,6 +72,11 @@ pub struct FileConfig {
     #[serde(default = "default_max_line_bytes")]
     pub max_line_bytes: usize,
     pub host_key: Option<String>,
+    pub offset_key: Option<String>,
+    pub inode_key: Option<String>,
+    pub device_key: Option<String>,
+    pub fingerprint_key: Option<String>,
+    pub path_regex: Option<String>,
     pub data_dir: Option<PathBuf>,
     pub glob_minimum_cooldown: u64, // millis
     pub fingerprinting: FingerprintingConfig,
@@ -97,42 +88,6 @@ pub struct FileConfig {
     pub remove_after: Option<u64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
-pub struct MultilineConfig {
-    pub start_pattern: String,
-    pub condition_pattern: String,
-    pub mode: line_agg::Mode,
-    pub timeout_ms: u64,
-}
-
-impl TryFrom<&MultilineConfig> for line_agg::Config {
-    type Error = crate::Error;
-
-    fn try_from(config: &MultilineConfig) -> crate::Result<Self> {
-        let MultilineConfig {
-            start_pattern,
-            condition_pattern,
-            mode,
-            timeout_ms,
-        } = config;
-
-        let start_pattern = Regex::new(start_pattern)
-            .with_context(|| InvalidMultilineStartPattern { start_pattern })?;
-        let condition_pattern = Regex::new(condition_pattern)
-            .with_context(|| InvalidMultilineConditionPattern { condition_pattern })?;
-        let mode = mode.clone();
-        let timeout = Duration::from_millis(*timeout_ms);
-
-        Ok(Self {
-            start_pattern,
-            condition_pattern,
-            mode,
-            timeout,
-        })
-    }
-}
-
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(tag = "strategy", rename_all = "snake_case")]
 pub enum FingerprintingConfig {
@@ -177,6 +132,11 @@ impl Default for FileConfig {
                 ignored_header_bytes: 0,
             },
             host_key: None,
+            offset_key: None,
+            inode_key: None,
+            device_key: None,
+            fingerprint_key: None,
+            path_regex: None,
             data_dir: None,
             glob_minimum_cooldown: 1000, // millis
             message_start_indicator: None,
@@ -216,7 +176,7 @@ impl SourceConfig for FileConfig {
             Regex::new(indicator).with_context(|| InvalidMessageStartIndicator { indicator })?;
         }
 
-        Ok(file_source(self, data_dir, shutdown, out))
+        file_source(self, data_dir, shutdown, out)
     }
 
     fn output_type(&self) -> DataType {
@@ -233,7 +193,14 @@ pub fn file_source(
     data_dir: PathBuf,
     shutdown: ShutdownSignal,
     out: Pipeline,
-) -> super::Source {
+) -> crate::Result<super::Source> {
+    let path_regex = match config.path_regex {
+        Some(ref path_regex) => {
+            Some(regex::Regex::new(path_regex).with_context(|| InvalidPathRegex { path_regex })?)
+        }
+        None => None,
+    };
+
     let ignore_before = config
         .ignore_older
         .map(|secs| SystemTime::now() - Duration::from_secs(secs));
@@ -255,23 +222,39 @@ pub fn file_source(
         remove_after: config.remove_after.map(Duration::from_secs),
     };
 
-    let file_key = config.file_key.clone();
-    let host_key = config
-        .host_key
-        .clone()
-        .unwrap_or_else(|| event::log_schema().host_key().to_string());
-    let hostname = hostname::get_hostname();
+    let fields = EventFields {
+        host_key: config
+            .host_key
+            .clone()
+            .unwrap_or_else(|| event::log_schema().host_key().to_string()),
+        hostname: hostname::get_hostname(),
+        file_key: config.file_key.clone(),
+        offset_key: config.offset_key.clone(),
+        inode_key: config.inode_key.clone(),
+        device_key: config.device_key.clone(),
+        fingerprint_key: config.fingerprint_key.clone(),
+        path_regex,
+    };
 
     let include = config.include.clone();
     let exclude = config.exclude.clone();
     let multiline_config = config.multiline.clone();
     let message_start_indicator = config.message_start_indicator.clone();
     let multi_line_timeout = config.multi_line_timeout;
-    Box::new(future::lazy(move || {
+    Ok(Box::new(future::lazy(move || {
         info!(message = "Starting file server.", ?include, ?exclude);
 
         // sizing here is just a guess
-        let (tx, rx) = futures01::sync::mpsc::channel(100);
+        let (tx, rx) = futures01::sync::mpsc::channel::<Line>(100);
+        let rx = rx.map(|line| {
+            let metadata = LineMetadata {
+                file_id: line.file_id,
+                offset: line.offset,
+                device: line.device,
+                inode: line.inode,
+            };
+            (line.text, line.filename, metadata)
+        });
 
         // This closure is overcomplicated because of the compatibility layer.
         let wrap_with_line_agg = |rx, config| {
@@ -281,7 +264,7 @@ pub fn file_source(
             let logic = line_agg::Logic::new(config);
             Box::new(Compat::new(LineAgg::new(rx, logic).map(Ok)))
         };
-        let messages: Box<dyn Stream<Item = (Bytes, String), Error = ()> + Send> =
+        let messages: Box<dyn Stream<Item = (Bytes, String, LineMetadata), Error = ()> + Send> =
             if let Some(ref multiline_config) = multiline_config {
                 wrap_with_line_agg(
                     rx,
@@ -305,14 +288,16 @@ pub fn file_source(
         let span2 = span.clone();
         tokio::spawn(
             messages
-                .map(move |(msg, file): (Bytes, String)| {
-                    let _enter = span2.enter();
-                    emit!(FileEventReceived {
-                        file: &file,
-                        byte_size: msg.len(),
-                    });
-                    create_event(msg, file, &host_key, &hostname, &file_key)
-                })
+                .map(
+                    move |(msg, file, metadata): (Bytes, String, LineMetadata)| {
+                        let _enter = span2.enter();
+                        emit!(FileEventReceived {
+                            file: &file,
+                            byte_size: msg.len(),
+                        });
+                        create_event(msg, file, &metadata, &fields)
+                    },
+                )
                 .forward(out.sink_map_err(|e| error!(%e)))
                 .map(|_| ())
                 .compat()
@@ -331,29 +316,73 @@ pub fn file_source(
         .boxed()
         .compat()
         .map_err(|error| error!(message="File server unexpectedly stopped.",%error))
-    }))
+    })))
+}
+
+/// Information about where a line was read from. For aggregated multi-line
+/// messages this refers to the first line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LineMetadata {
+    file_id: FileFingerprint,
+    offset: u64,
+    device: u64,
+    inode: u64,
 }
 
-fn create_event(
-    line: Bytes,
-    file: String,
-    host_key: &str,
-    hostname: &Option<String>,
-    file_key: &Option<String>,
-) -> Event {
+/// The fields to add to every event, as configured.
+struct EventFields {
+    host_key: String,
+    hostname: Option<String>,
+    file_key: Option<String>,
+    offset_key: Option<String>,
+    inode_key: Option<String>,
+    device_key: Option<String>,
+    fingerprint_key: Option<String>,
+    path_regex: Option<regex::Regex>,
+}
+
+fn create_event(line: Bytes, file: String, metadata: &LineMetadata, fields: &EventFields) -> Event {
     let mut event = Event::from(line);
+    let log = event.as_mut_log();
 
     // Add source type
-    event
-        .as_mut_log()
-        .insert(event::log_schema().source_type_key(), "file");
+    log.insert(event::log_schema().source_type_key(), "file");
+
+    if let Some(path_regex) = &fields.path_regex {
+        if let Some(captures) = path_regex.captures(&file) {
+            for name in path_regex.capture_names().flatten() {
+                if let Some(value) = captures.name(name) {
+                    log.insert(name, value.as_str());
+                }
+            }
+        }
+    }
+
+    if let Some(offset_key) = &fields.offset_key {
+        log.insert(offset_key.clone(), metadata.offset as i64);
+    }
 
-    if let Some(file_key) = &file_key {
-        event.as_mut_log().insert(file_key.clone(), file);
+    if let Some(inode_key) = &fields.inode_key {
+        log.insert(inode_key.clone(), metadata.inode as i64);
     }
 
-    if let Some(hostname) = &hostname {
-        event.as_mut_log().insert(host_key, hostname.clone());
+    if let Some(device_key) = &fields.device_key {
+        log.insert(device_key.clone(), metadata.device as i64);
+    }
+
+    if let Some(fingerprint_key) = &fields.fingerprint_key {
+        log.insert(
+            fingerprint_key.clone(),
+            format!("{:016x}", metadata.file_id),
+        );
+    }
+
+    if let Some(file_key) = &fields.file_key {
+        log.insert(file_key.clone(), file);
+    }
+
+    if let Some(hostname) = &fields.hostname {
+        log.insert(&fields.host_key, hostname.clone());
     }
 
     event
@@ -476,15 +505,34 @@ mod tests {
         assert_eq!(res, config.global.data_dir.unwrap());
     }
 
+    fn test_event_fields() -> EventFields {
+        EventFields {
+            host_key: "host".to_string(),
+            hostname: Some("Some.Machine".to_string()),
+            file_key: Some("file".to_string()),
+            offset_key: None,
+            inode_key: None,
+            device_key: None,
+            fingerprint_key: None,
+            path_regex: None,
+        }
+    }
+
+    fn test_line_metadata() -> LineMetadata {
+        LineMetadata {
+            file_id: 0x1234_5678_90ab_cdef,
+            offset: 42,
+            device: 7,
+            inode: 1234,
+        }
+    }
+
     #[test]
     fn file_create_event() {
         let line = Bytes::from("hello world");
         let file = "some_file.rs".to_string();
-        let host_key = "host".to_string();
-        let hostname = Some("Some.Machine".to_string());
-        let file_key = Some("file".to_string());
 
-        let event = create_event(line, file, &host_key, &hostname, &file_key);
+        let event = create_event(line, file, &test_line_metadata(), &test_event_fields());
         let log = event.into_log();
 
         assert_eq!(log[&"file".into()], "some_file.rs".into());
@@ -494,6 +542,52 @@ mod tests {
             "hello world".into()
         );
         assert_eq!(log[event::log_schema().source_type_key()], "file".into());
+        assert!(log.get(&"offset".into()).is_none());
+    }
+
+    #[test]
+    fn file_create_event_with_metadata_fields() {
+        let line = Bytes::from("hello world");
+        let file = "/var/log/my-app/current.log".to_string();
+        let fields = EventFields {
+            offset_key: Some("offset".to_string()),
+            inode_key: Some("inode".to_string()),
+            device_key: Some("device".to_string()),
+            fingerprint_key: Some("fingerprint".to_string()),
+            path_regex: Some(regex::Regex::new(r"/var/log/(?P<app>[^/]+)/.*\.log").unwrap()),
+            ..test_event_fields()
+        };
+
+        let event = create_event(line, file, &test_line_metadata(), &fields);
+        let log = event.into_log();
+
+        assert_eq!(log[&"offset".into()], 42.into());
+        assert_eq!(log[&"inode".into()], 1234.into());
+        assert_eq!(log[&"device".into()], 7.into());
+        assert_eq!(log[&"fingerprint".into()], "1234567890abcdef".into());
+        assert_eq!(log[&"app".into()], "my-app".into());
+        assert_eq!(log[&"file".into()], "/var/log/my-app/current.log".into());
+    }
+
+    #[test]
+    fn file_invalid_path_regex() {
+        let config = FileConfig {
+            path_regex: Some("(?P<app".to_string()),
+            ..Default::default()
+        };
+        let dir = tempdir().unwrap();
+        let globals = GlobalOptions {
+            data_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert!(config
+            .build(
+                "file",
+                &globals,
+                ShutdownSignal::noop(),
+                Pipeline::new_test().0
+            )
+            .is_err());
     }
 
     #[test]
@@ -508,7 +602,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -569,7 +664,8 @@ mod tests {
             include: vec![dir.path().join("*")],
             ..test_default_file_config(&dir)
         };
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -638,7 +734,8 @@ mod tests {
             include: vec![dir.path().join("*")],
             ..test_default_file_config(&dir)
         };
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -710,7 +807,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -772,7 +870,8 @@ mod tests {
                 ..test_default_file_config(&dir)
             };
 
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
             rt.spawn(source);
 
@@ -807,7 +906,8 @@ mod tests {
                 ..test_default_file_config(&dir)
             };
 
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
             rt.spawn(source);
 
@@ -842,7 +942,8 @@ mod tests {
                 ..test_default_file_config(&dir)
             };
 
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
             rt.spawn(source);
 
@@ -893,7 +994,8 @@ mod tests {
             let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
 
             let (tx, rx) = Pipeline::new_test();
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
             let mut rt = runtime();
             rt.spawn(source);
 
@@ -916,7 +1018,8 @@ mod tests {
             let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
 
             let (tx, rx) = Pipeline::new_test();
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
             let mut rt = runtime();
             rt.spawn(source);
 
@@ -944,7 +1047,8 @@ mod tests {
                 ..test_default_file_config(&dir)
             };
             let (tx, rx) = Pipeline::new_test();
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
             let mut rt = runtime();
             rt.spawn(source);
 
@@ -981,7 +1085,8 @@ mod tests {
             let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
 
             let (tx, rx) = Pipeline::new_test();
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
             let mut rt = runtime();
             rt.spawn(source);
 
@@ -1008,7 +1113,8 @@ mod tests {
             let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
 
             let (tx, rx) = Pipeline::new_test();
-            let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+            let source =
+                file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
             let mut rt = runtime();
             rt.spawn(source);
 
@@ -1046,7 +1152,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         rt.spawn(source);
 
@@ -1131,7 +1238,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -1192,7 +1300,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -1269,7 +1378,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
 
         let mut rt = runtime();
 
@@ -1361,7 +1471,8 @@ mod tests {
 
         sleep();
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
         let mut rt = runtime();
         rt.spawn(source);
 
@@ -1426,7 +1537,8 @@ mod tests {
 
         sleep();
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
         let mut rt = runtime();
         rt.spawn(source);
 
@@ -1470,7 +1582,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
         let mut rt = runtime();
         rt.spawn(source);
 
@@ -1518,7 +1631,8 @@ mod tests {
             ..test_default_file_config(&dir)
         };
 
-        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let source =
+            file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx).unwrap();
         let mut rt = runtime();
         rt.spawn(source);
 
@@ -0,0 +1,917 @@
+use super::util::{SocketListenAddr, TcpSource};
+use crate::{
+    event::{self, Event, LogEvent, Value},
+    internal_events::{FluentMessageDecodeError, FluentMessageReceived},
+    shutdown::ShutdownSignal,
+    tls::{MaybeTlsSettings, TlsConfig},
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    Pipeline,
+};
+use bytes05::{Buf, Bytes, BytesMut};
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::read::MultiGzDecoder;
+use rmpv::{decode, encode, Value as MsgPackValue};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::TryInto,
+    io::{self, Read},
+};
+use tokio_util::codec::Decoder;
+
+/// Messages nested deeper than this are rejected, as decoding them recurses
+/// once for every level.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FluentConfig {
+    pub address: SocketListenAddr,
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    pub host_key: Option<String>,
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_max_length() -> usize {
+    bytesize::mib(8u64) as usize
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+impl FluentConfig {
+    pub fn new(address: SocketListenAddr) -> Self {
+        Self {
+            address,
+            max_length: default_max_length(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            host_key: None,
+            tls: None,
+        }
+    }
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<FluentConfig>("fluent")
+}
+
+#[typetag::serde(name = "fluent")]
+impl SourceConfig for FluentConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let source = FluentSource {
+            max_length: self.max_length,
+            host_key: self
+                .host_key
+                .clone()
+                .unwrap_or_else(|| event::log_schema().host_key().to_string()),
+        };
+        let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
+        source.run(self.address, self.shutdown_timeout_secs, tls, shutdown, out)
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "fluent"
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FluentSource {
+    max_length: usize,
+    host_key: String,
+}
+
+impl TcpSource for FluentSource {
+    type Error = DecodeError;
+    type Decoder = FluentDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        FluentDecoder::new(self.max_length)
+    }
+
+    fn build_event(&self, frame: FluentFrame, host: Bytes) -> Option<Event> {
+        match frame {
+            FluentFrame::Entry(entry) => {
+                let mut event = Event::from(entry);
+                event.as_mut_log().insert(self.host_key.as_str(), host);
+                Some(event)
+            }
+            FluentFrame::Ack(_) => None,
+        }
+    }
+
+    fn build_acknowledgement(&self, frame: &FluentFrame) -> Option<Bytes> {
+        match frame {
+            FluentFrame::Ack(chunk) => Some(encode_ack(chunk)),
+            FluentFrame::Entry(_) => None,
+        }
+    }
+}
+
+/// A single item produced by the decoder. Every entry of a message is
+/// emitted on its own, followed by an `Ack` if the client asked for one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FluentFrame {
+    Entry(FluentEntry),
+    Ack(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FluentEntry {
+    tag: String,
+    timestamp: DateTime<Utc>,
+    record: BTreeMap<String, Value>,
+}
+
+impl From<FluentEntry> for Event {
+    fn from(entry: FluentEntry) -> Self {
+        let mut log = LogEvent::default();
+        for (key, value) in entry.record {
+            log.insert_flat(key, value);
+        }
+        log.insert("tag", entry.tag);
+        log.insert(event::log_schema().timestamp_key().clone(), entry.timestamp);
+        log.insert(event::log_schema().source_type_key().clone(), "fluent");
+        Event::Log(log)
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum DecodeError {
+    #[snafu(display("IO error: {}", source))]
+    Io { source: io::Error },
+    #[snafu(display("Invalid msgpack: {}", source))]
+    MsgPack { source: decode::Error },
+    #[snafu(display("Message exceeds the maximum length of {} bytes", max_length))]
+    TooLong { max_length: usize },
+    #[snafu(display("Message exceeds the maximum nesting depth of {}", max_depth))]
+    TooDeep { max_depth: usize },
+    #[snafu(display("Unexpected message structure: {}", reason))]
+    UnexpectedValue { reason: &'static str },
+    #[snafu(display("Unsupported compression: {}", compression))]
+    UnsupportedCompression { compression: String },
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(source: io::Error) -> Self {
+        DecodeError::Io { source }
+    }
+}
+
+/// Decodes the Fluent Forward protocol v1.
+///
+/// https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1
+#[derive(Debug)]
+pub struct FluentDecoder {
+    max_length: usize,
+    queued: VecDeque<FluentFrame>,
+    scanner: MessageScanner,
+}
+
+impl FluentDecoder {
+    fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            queued: VecDeque::new(),
+            scanner: MessageScanner::default(),
+        }
+    }
+
+    fn queue_message(&mut self, message: MsgPackValue) -> Result<(), DecodeError> {
+        let mut message = match message {
+            MsgPackValue::Array(message) if message.len() >= 2 => message.into_iter(),
+            _ => {
+                return Err(DecodeError::UnexpectedValue {
+                    reason: "message must be an array of at least two elements",
+                })
+            }
+        };
+
+        let tag = match message.next() {
+            Some(MsgPackValue::String(tag)) => {
+                tag.into_str().ok_or(DecodeError::UnexpectedValue {
+                    reason: "tag must be valid UTF-8",
+                })?
+            }
+            _ => {
+                return Err(DecodeError::UnexpectedValue {
+                    reason: "tag must be a string",
+                })
+            }
+        };
+
+        match message.next() {
+            // Forward mode: [tag, [[time, record], ...], option]
+            Some(MsgPackValue::Array(entries)) => {
+                let options = Options::parse(message.next())?;
+                for entry in entries {
+                    let entry = decode_entry(&tag, entry)?;
+                    self.queued.push_back(FluentFrame::Entry(entry));
+                }
+                self.queue_ack(options);
+            }
+            // (Compressed)PackedForward mode: [tag, msgpack stream of [time, record], option]
+            Some(MsgPackValue::Binary(packed)) => {
+                let options = Options::parse(message.next())?;
+                self.queue_packed(&tag, &packed, &options)?;
+                self.queue_ack(options);
+            }
+            Some(MsgPackValue::String(packed)) => {
+                let options = Options::parse(message.next())?;
+                self.queue_packed(&tag, packed.as_bytes(), &options)?;
+                self.queue_ack(options);
+            }
+            // Message mode: [tag, time, record, option]
+            Some(time) => {
+                let record = message.next().ok_or(DecodeError::UnexpectedValue {
+                    reason: "message mode requires a record",
+                })?;
+                let options = Options::parse(message.next())?;
+                let entry = FluentEntry {
+                    tag,
+                    timestamp: decode_timestamp(time)?,
+                    record: decode_record(record)?,
+                };
+                self.queued.push_back(FluentFrame::Entry(entry));
+                self.queue_ack(options);
+            }
+            None => unreachable!("length checked above"),
+        }
+
+        Ok(())
+    }
+
+    fn queue_packed(
+        &mut self,
+        tag: &str,
+        packed: &[u8],
+        options: &Options,
+    ) -> Result<(), DecodeError> {
+        let decompressed;
+        let packed = match options.compressed.as_deref() {
+            None | Some("text") => packed,
+            Some("gzip") => {
+                // Bounded, so a small message can't decompress into more
+                // than the maximum length.
+                let mut buf = Vec::new();
+                MultiGzDecoder::new(packed)
+                    .take(self.max_length as u64 + 1)
+                    .read_to_end(&mut buf)?;
+                if buf.len() > self.max_length {
+                    return Err(DecodeError::TooLong {
+                        max_length: self.max_length,
+                    });
+                }
+                decompressed = buf;
+                &decompressed[..]
+            }
+            Some(compression) => {
+                return Err(DecodeError::UnsupportedCompression {
+                    compression: compression.to_owned(),
+                })
+            }
+        };
+
+        let mut packed = packed;
+        while !packed.is_empty() {
+            let byte_size =
+                MessageScanner::default()
+                    .scan(packed)?
+                    .ok_or(DecodeError::UnexpectedValue {
+                        reason: "packed entries are truncated",
+                    })?;
+            let entry = decode::read_value(&mut &packed[..byte_size]).context(MsgPack)?;
+            let entry = decode_entry(tag, entry)?;
+            self.queued.push_back(FluentFrame::Entry(entry));
+            packed = &packed[byte_size..];
+        }
+        Ok(())
+    }
+
+    fn queue_ack(&mut self, options: Options) {
+        if let Some(chunk) = options.chunk {
+            self.queued.push_back(FluentFrame::Ack(chunk));
+        }
+    }
+}
+
+impl Decoder for FluentDecoder {
+    type Item = FluentFrame;
+    type Error = DecodeError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(frame) = self.queued.pop_front() {
+                return Ok(Some(frame));
+            }
+
+            if src.is_empty() {
+                return Ok(None);
+            }
+
+            let byte_size = match self.scanner.scan(src)? {
+                Some(byte_size) => byte_size,
+                None if src.len() > self.max_length => {
+                    return Err(DecodeError::TooLong {
+                        max_length: self.max_length,
+                    });
+                }
+                None => return Ok(None),
+            };
+            let message = match decode::read_value(&mut &src[..byte_size]) {
+                Ok(message) => message,
+                Err(source) => {
+                    emit!(FluentMessageDecodeError { error: &source });
+                    return Err(DecodeError::MsgPack { source });
+                }
+            };
+            src.advance(byte_size);
+
+            emit!(FluentMessageReceived { byte_size });
+            self.queue_message(message)?;
+        }
+    }
+}
+
+/// Options map trailing every mode of the protocol.
+#[derive(Debug, Default)]
+struct Options {
+    chunk: Option<String>,
+    compressed: Option<String>,
+}
+
+impl Options {
+    fn parse(value: Option<MsgPackValue>) -> Result<Self, DecodeError> {
+        let mut options = Options::default();
+        match value {
+            None | Some(MsgPackValue::Nil) => {}
+            Some(MsgPackValue::Map(entries)) => {
+                for (key, value) in entries {
+                    match (key.as_str(), value.as_str()) {
+                        (Some("chunk"), Some(chunk)) => options.chunk = Some(chunk.to_owned()),
+                        (Some("compressed"), Some(compressed)) => {
+                            options.compressed = Some(compressed.to_owned())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(DecodeError::UnexpectedValue {
+                    reason: "options must be a map",
+                })
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Finds where a MessagePack value ends without decoding it, checking that
+/// it isn't nested deeper than `MAX_DEPTH`. The position is kept between
+/// calls, so a message arriving in pieces is only scanned once.
+#[derive(Debug, Default)]
+struct MessageScanner {
+    /// Bytes of the current message scanned so far.
+    position: usize,
+    /// Values left to scan at each level of nesting of the current message.
+    remaining: Vec<usize>,
+}
+
+impl MessageScanner {
+    /// Returns the length of the message at the start of `src`, once all of
+    /// it has been buffered.
+    fn scan(&mut self, src: &[u8]) -> Result<Option<usize>, DecodeError> {
+        if self.remaining.is_empty() {
+            self.position = 0;
+            self.remaining.push(1);
+        }
+        while let Some(&remaining) = self.remaining.last() {
+            if remaining == 0 {
+                self.remaining.pop();
+                continue;
+            }
+
+            let (header, payload, children) = match value_header(&src[self.position..]) {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+            if src.len() - self.position < header + payload {
+                return Ok(None);
+            }
+            self.position += header + payload;
+            *self.remaining.last_mut().expect("checked above") -= 1;
+            if children > 0 {
+                if self.remaining.len() > MAX_DEPTH {
+                    return Err(DecodeError::TooDeep {
+                        max_depth: MAX_DEPTH,
+                    });
+                }
+                self.remaining.push(children);
+            }
+        }
+        Ok(Some(self.position))
+    }
+}
+
+/// Returns the header length, payload length and number of nested values of
+/// the MessagePack value at the start of `buf`, if the header is complete.
+fn value_header(buf: &[u8]) -> Option<(usize, usize, usize)> {
+    let length = |width: usize| {
+        buf.get(1..=width)
+            .map(|bytes| bytes.iter().fold(0, |n, b| n << 8 | *b as usize))
+    };
+    let marker = *buf.first()?;
+    Some(match marker {
+        0x00..=0x7f | 0xc0..=0xc3 | 0xe0..=0xff => (1, 0, 0),
+        0x80..=0x8f => (1, 0, 2 * (marker & 0x0f) as usize),
+        0x90..=0x9f => (1, 0, (marker & 0x0f) as usize),
+        0xa0..=0xbf => (1, (marker & 0x1f) as usize, 0),
+        0xc4 | 0xd9 => (2, length(1)?, 0),
+        0xc5 | 0xda => (3, length(2)?, 0),
+        0xc6 | 0xdb => (5, length(4)?, 0),
+        0xc7 => (3, length(1)?, 0),
+        0xc8 => (4, length(2)?, 0),
+        0xc9 => (6, length(4)?, 0),
+        0xcc | 0xd0 => (1, 1, 0),
+        0xcd | 0xd1 => (1, 2, 0),
+        0xca | 0xce | 0xd2 => (1, 4, 0),
+        0xcb | 0xcf | 0xd3 => (1, 8, 0),
+        0xd4..=0xd8 => (2, 1 << (marker - 0xd4), 0),
+        0xdc => (3, 0, length(2)?),
+        0xdd => (5, 0, length(4)?),
+        0xde => (3, 0, 2 * length(2)?),
+        0xdf => (5, 0, 2 * length(4)?),
+    })
+}
+
+fn decode_entry(tag: &str, entry: MsgPackValue) -> Result<FluentEntry, DecodeError> {
+    match entry {
+        MsgPackValue::Array(entry) if entry.len() == 2 => {
+            let mut entry = entry.into_iter();
+            let timestamp = decode_timestamp(entry.next().expect("length checked"))?;
+            let record = decode_record(entry.next().expect("length checked"))?;
+            Ok(FluentEntry {
+                tag: tag.to_owned(),
+                timestamp,
+                record,
+            })
+        }
+        _ => Err(DecodeError::UnexpectedValue {
+            reason: "entry must be an array of time and record",
+        }),
+    }
+}
+
+/// Time is either an integer number of seconds, or the `EventTime` extension
+/// type carrying seconds and nanoseconds as big endian 32 bit integers.
+fn decode_timestamp(time: MsgPackValue) -> Result<DateTime<Utc>, DecodeError> {
+    let timestamp = match time {
+        MsgPackValue::Integer(secs) => secs
+            .as_i64()
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single()),
+        MsgPackValue::F64(secs) => Utc
+            .timestamp_opt(
+                secs.trunc() as i64,
+                (secs.fract() * 1_000_000_000f64) as u32,
+            )
+            .single(),
+        MsgPackValue::Ext(0, ref data) if data.len() == 8 => {
+            let secs = u32::from_be_bytes(data[0..4].try_into().expect("length checked"));
+            let nanos = u32::from_be_bytes(data[4..8].try_into().expect("length checked"));
+            Utc.timestamp_opt(i64::from(secs), nanos).single()
+        }
+        _ => {
+            return Err(DecodeError::UnexpectedValue {
+                reason: "time must be an integer or EventTime",
+            })
+        }
+    };
+    timestamp.ok_or(DecodeError::UnexpectedValue {
+        reason: "time is out of range",
+    })
+}
+
+fn decode_record(record: MsgPackValue) -> Result<BTreeMap<String, Value>, DecodeError> {
+    match decode_value(record) {
+        Value::Map(record) => Ok(record),
+        _ => Err(DecodeError::UnexpectedValue {
+            reason: "record must be a map",
+        }),
+    }
+}
+
+/// Recurses for every level of nesting, which `MessageScanner` bounds by
+/// `MAX_DEPTH` before the value is read.
+fn decode_value(value: MsgPackValue) -> Value {
+    match value {
+        MsgPackValue::Nil => Value::Null,
+        MsgPackValue::Boolean(b) => Value::Boolean(b),
+        MsgPackValue::Integer(i) => match i.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(i.as_f64().unwrap_or(std::f64::NAN)),
+        },
+        MsgPackValue::F32(f) => Value::from(f),
+        MsgPackValue::F64(f) => Value::from(f),
+        MsgPackValue::String(s) => Value::from(s.as_bytes()),
+        MsgPackValue::Binary(b) => Value::from(b),
+        MsgPackValue::Array(array) => Value::Array(array.into_iter().map(decode_value).collect()),
+        MsgPackValue::Map(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        MsgPackValue::String(key) => {
+                            String::from_utf8_lossy(key.as_bytes()).into_owned()
+                        }
+                        key => key.to_string(),
+                    };
+                    (key, decode_value(value))
+                })
+                .collect(),
+        ),
+        MsgPackValue::Ext(_, data) => Value::from(data),
+    }
+}
+
+fn encode_ack(chunk: &str) -> Bytes {
+    let ack = MsgPackValue::Map(vec![(MsgPackValue::from("ack"), MsgPackValue::from(chunk))]);
+    let mut buf = Vec::new();
+    encode::write_value(&mut buf, &ack).expect("writing to a Vec can't fail");
+    buf.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{collect_n, next_addr, runtime, wait_for_tcp};
+    use std::io::Write;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    fn msgpack(value: &MsgPackValue) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode::write_value(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn record(message: &str) -> MsgPackValue {
+        MsgPackValue::Map(vec![(
+            MsgPackValue::from("message"),
+            MsgPackValue::from(message),
+        )])
+    }
+
+    fn entry(secs: i64, message: &str) -> MsgPackValue {
+        MsgPackValue::Array(vec![MsgPackValue::from(secs), record(message)])
+    }
+
+    fn options(chunk: &str) -> MsgPackValue {
+        MsgPackValue::Map(vec![(
+            MsgPackValue::from("chunk"),
+            MsgPackValue::from(chunk),
+        )])
+    }
+
+    fn decode_all(input: &[u8]) -> Vec<FluentFrame> {
+        let mut decoder = FluentDecoder::new(default_max_length());
+        let mut buf = BytesMut::from(input);
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.decode(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+        assert!(buf.is_empty());
+        frames
+    }
+
+    fn messages(frames: &[FluentFrame]) -> Vec<(String, i64, String)> {
+        frames
+            .iter()
+            .filter_map(|frame| match frame {
+                FluentFrame::Entry(entry) => Some((
+                    entry.tag.clone(),
+                    entry.timestamp.timestamp(),
+                    entry.record["message"].to_string_lossy(),
+                )),
+                FluentFrame::Ack(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decode_message_mode() {
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::from(1_441_588_984),
+            record("hello"),
+        ]);
+
+        let frames = decode_all(&msgpack(&message));
+        assert_eq!(
+            messages(&frames),
+            vec![("tag.name".into(), 1_441_588_984, "hello".into())]
+        );
+    }
+
+    #[test]
+    fn decode_message_mode_event_time() {
+        let mut time = Vec::new();
+        time.extend_from_slice(&1_441_588_984u32.to_be_bytes());
+        time.extend_from_slice(&500_000_000u32.to_be_bytes());
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Ext(0, time),
+            record("hello"),
+        ]);
+
+        match &decode_all(&msgpack(&message))[..] {
+            [FluentFrame::Entry(entry)] => {
+                assert_eq!(entry.timestamp, Utc.timestamp(1_441_588_984, 500_000_000))
+            }
+            frames => panic!("unexpected frames {:?}", frames),
+        }
+    }
+
+    #[test]
+    fn decode_forward_mode_with_ack() {
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Array(vec![entry(1, "one"), entry(2, "two")]),
+            options("p8n9gmxTQVC8/nh2wlKKeQ=="),
+        ]);
+
+        let frames = decode_all(&msgpack(&message));
+        assert_eq!(
+            messages(&frames),
+            vec![
+                ("tag.name".into(), 1, "one".into()),
+                ("tag.name".into(), 2, "two".into())
+            ]
+        );
+        assert_eq!(
+            frames.last(),
+            Some(&FluentFrame::Ack("p8n9gmxTQVC8/nh2wlKKeQ==".into()))
+        );
+    }
+
+    #[test]
+    fn decode_packed_forward_mode() {
+        let mut packed = msgpack(&entry(1, "one"));
+        packed.extend(msgpack(&entry(2, "two")));
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Binary(packed),
+        ]);
+
+        let frames = decode_all(&msgpack(&message));
+        assert_eq!(
+            messages(&frames),
+            vec![
+                ("tag.name".into(), 1, "one".into()),
+                ("tag.name".into(), 2, "two".into())
+            ]
+        );
+        assert_eq!(frames.len(), 2);
+    }
+
+    #[test]
+    fn decode_compressed_packed_forward_mode() {
+        let mut packed = msgpack(&entry(1, "one"));
+        packed.extend(msgpack(&entry(2, "two")));
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&packed).unwrap();
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Binary(encoder.finish().unwrap()),
+            MsgPackValue::Map(vec![(
+                MsgPackValue::from("compressed"),
+                MsgPackValue::from("gzip"),
+            )]),
+        ]);
+
+        let frames = decode_all(&msgpack(&message));
+        assert_eq!(
+            messages(&frames),
+            vec![
+                ("tag.name".into(), 1, "one".into()),
+                ("tag.name".into(), 2, "two".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_partial_messages() {
+        let message = msgpack(&MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::from(1),
+            record("hello"),
+        ]));
+
+        let mut decoder = FluentDecoder::new(default_max_length());
+        let mut buf = BytesMut::from(&message[..message.len() - 3]);
+        assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(&message[message.len() - 3..]);
+        assert!(decoder.decode(&mut buf).unwrap().is_some());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn scans_messages_byte_by_byte() {
+        let message = msgpack(&MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Array(vec![entry(1, "hello"), entry(2, &"x".repeat(300))]),
+            MsgPackValue::Map(vec![
+                (MsgPackValue::from("chunk"), MsgPackValue::from(1.5)),
+                (
+                    MsgPackValue::from(u64::max_value()),
+                    MsgPackValue::Ext(1, vec![0; 4]),
+                ),
+            ]),
+        ]));
+
+        let mut scanner = MessageScanner::default();
+        for end in 0..message.len() {
+            assert_eq!(scanner.scan(&message[..end]).unwrap(), None);
+        }
+        assert_eq!(scanner.scan(&message).unwrap(), Some(message.len()));
+
+        let mut twice = message.clone();
+        twice.extend_from_slice(&message);
+        assert_eq!(scanner.scan(&twice).unwrap(), Some(message.len()));
+    }
+
+    fn nested(depth: usize) -> MsgPackValue {
+        (0..depth).fold(MsgPackValue::from(1), |value, _| {
+            MsgPackValue::Array(vec![value])
+        })
+    }
+
+    #[test]
+    fn scanner_rejects_deep_nesting() {
+        let mut scanner = MessageScanner::default();
+        let message = msgpack(&nested(MAX_DEPTH));
+        assert_eq!(scanner.scan(&message).unwrap(), Some(message.len()));
+
+        // Only the header is needed to reject it, not the whole message.
+        let mut scanner = MessageScanner::default();
+        let message = [0x91; 100_000];
+        assert!(matches!(
+            scanner.scan(&message),
+            Err(DecodeError::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_deeply_nested_packed_entries() {
+        let packed = msgpack(&MsgPackValue::Array(vec![
+            MsgPackValue::from(1),
+            MsgPackValue::Map(vec![(MsgPackValue::from("a"), nested(MAX_DEPTH))]),
+        ]));
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Binary(packed),
+        ]);
+
+        let mut decoder = FluentDecoder::new(default_max_length());
+        let mut buf = BytesMut::from(&msgpack(&message)[..]);
+        assert!(matches!(
+            decoder.decode(&mut buf),
+            Err(DecodeError::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_limits_decompressed_size() {
+        let packed = msgpack(&entry(1, &"x".repeat(10_000)));
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&packed).unwrap();
+        let message = MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Binary(encoder.finish().unwrap()),
+            MsgPackValue::Map(vec![(
+                MsgPackValue::from("compressed"),
+                MsgPackValue::from("gzip"),
+            )]),
+        ]);
+
+        let mut decoder = FluentDecoder::new(1_000);
+        let mut buf = BytesMut::from(&msgpack(&message)[..]);
+        assert!(matches!(
+            decoder.decode(&mut buf),
+            Err(DecodeError::TooLong { max_length: 1_000 })
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_out_of_range_time() {
+        let mut time = Vec::new();
+        time.extend_from_slice(&1u32.to_be_bytes());
+        time.extend_from_slice(&u32::max_value().to_be_bytes());
+
+        for time in vec![
+            MsgPackValue::Ext(0, time),
+            MsgPackValue::from(i64::max_value()),
+            MsgPackValue::from(std::f64::MAX),
+        ] {
+            let message =
+                MsgPackValue::Array(vec![MsgPackValue::from("tag.name"), time, record("hello")]);
+            let mut decoder = FluentDecoder::new(default_max_length());
+            let mut buf = BytesMut::from(&msgpack(&message)[..]);
+            assert!(matches!(
+                decoder.decode(&mut buf),
+                Err(DecodeError::UnexpectedValue {
+                    reason: "time is out of range"
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn decode_rejects_garbage() {
+        let mut decoder = FluentDecoder::new(default_max_length());
+        let mut buf = BytesMut::from(&msgpack(&MsgPackValue::from("not an array"))[..]);
+        assert!(decoder.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn builds_event() {
+        let source = FluentSource {
+            max_length: default_max_length(),
+            host_key: "host".into(),
+        };
+        let entry = FluentEntry {
+            tag: "tag.name".into(),
+            timestamp: Utc.timestamp(1, 0),
+            record: vec![("a.b".to_string(), Value::from("c"))]
+                .into_iter()
+                .collect(),
+        };
+
+        let event = source
+            .build_event(FluentFrame::Entry(entry), "127.0.0.1".into())
+            .unwrap();
+        let log = event.as_log();
+        assert_eq!(log.get_flat("a.b"), Some(&"c".into()));
+        assert_eq!(log[&"tag".into()], "tag.name".into());
+        assert_eq!(log[&"host".into()], "127.0.0.1".into());
+        assert_eq!(
+            log[&event::log_schema().timestamp_key()],
+            Utc.timestamp(1, 0).into()
+        );
+        assert_eq!(log[&event::log_schema().source_type_key()], "fluent".into());
+    }
+
+    #[test]
+    fn receives_and_acknowledges() {
+        let (tx, rx) = Pipeline::new_test();
+        let addr = next_addr();
+
+        let server = FluentConfig::new(addr.into())
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .unwrap();
+        let mut rt = runtime();
+        rt.spawn(server);
+        wait_for_tcp(addr);
+
+        let message = msgpack(&MsgPackValue::Array(vec![
+            MsgPackValue::from("tag.name"),
+            MsgPackValue::Array(vec![entry(1, "one"), entry(2, "two")]),
+            options("chunk-id"),
+        ]));
+        let expected = encode_ack("chunk-id");
+
+        let ack = rt.block_on_std(async move {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            socket.write_all(&message).await.unwrap();
+
+            let mut ack = vec![0u8; expected.len()];
+            socket.read_exact(&mut ack).await.unwrap();
+            ack
+        });
+        assert_eq!(&ack[..], &encode_ack("chunk-id")[..]);
+
+        let events = rt.block_on(collect_n(rx, 2)).unwrap();
+        assert_eq!(events[0].as_log()[&"message".into()], "one".into());
+        assert_eq!(events[1].as_log()[&"message".into()], "two".into());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::event::{
+    metric::{Metric, MetricKind, MetricValue, StatisticKind},
+    Event,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const HOSTS: &[&str] = &["web-01", "web-02", "api-01", "db-01", "cache-01"];
+const USERS: &[&str] = &["-", "alice", "bob", "carol", "dave", "eve"];
+const METHODS: &[&str] = &["GET", "GET", "GET", "POST", "PUT", "DELETE", "HEAD"];
+const PATHS: &[&str] = &[
+    "/",
+    "/index.html",
+    "/login",
+    "/api/v1/users",
+    "/api/v1/orders",
+    "/static/app.js",
+    "/static/style.css",
+    "/favicon.ico",
+];
+const STATUSES: &[u16] = &[
+    200, 200, 200, 200, 201, 204, 301, 304, 400, 403, 404, 500, 503,
+];
+const REFERERS: &[&str] = &[
+    "-",
+    "https://www.google.com/",
+    "https://example.com/",
+    "https://example.com/login",
+];
+const AGENTS: &[&str] = &[
+    "Mozilla/5.0 (X11; Linux x86_64; rv:78.0) Gecko/20100101 Firefox/78.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_5) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/13.1.1 Safari/605.1.15",
+    "curl/7.68.0",
+    "Go-http-client/1.1",
+];
+const APPS: &[&str] = &["nginx", "sshd", "cron", "kernel", "billing", "checkout"];
+const LEVELS: &[&str] = &["debug", "info", "info", "info", "warn", "error"];
+const MESSAGES: &[&str] = &[
+    "Connection accepted",
+    "Connection closed by peer",
+    "Request completed",
+    "Cache miss, fetching from upstream",
+    "Retrying request after timeout",
+    "Failed to authenticate user",
+    "Configuration reloaded",
+    "Queue is backing up",
+];
+
+/// Generates synthetic, but realistic looking, events. Everything except the
+/// timestamps is derived from the seed, so the same seed produces the same
+/// events.
+pub(super) struct Fake {
+    rng: StdRng,
+}
+
+impl Fake {
+    pub(super) fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[self.rng.gen_range(0, values.len())]
+    }
+
+    fn ip(&mut self) -> String {
+        format!(
+            "{}.{}.{}.{}",
+            self.rng.gen_range(1, 224),
+            self.rng.gen::<u8>(),
+            self.rng.gen::<u8>(),
+            self.rng.gen_range(1, 255)
+        )
+    }
+
+    fn priority(&mut self) -> u8 {
+        // Facility 0-23, severity 0-7.
+        self.rng.gen_range(0, 24) * 8 + self.rng.gen_range(0, 8)
+    }
+
+    pub(super) fn apache_common(&mut self, now: DateTime<Utc>) -> String {
+        format!(
+            "{} - {} [{}] \"{} {} HTTP/1.1\" {} {}",
+            self.ip(),
+            self.pick(USERS),
+            now.format("%d/%b/%Y:%T %z"),
+            self.pick(METHODS),
+            self.pick(PATHS),
+            self.pick(STATUSES),
+            self.rng.gen_range(0, 50_000)
+        )
+    }
+
+    pub(super) fn apache_combined(&mut self, now: DateTime<Utc>) -> String {
+        format!(
+            "{} \"{}\" \"{}\"",
+            self.apache_common(now),
+            self.pick(REFERERS),
+            self.pick(AGENTS)
+        )
+    }
+
+    pub(super) fn syslog_3164(&mut self, now: DateTime<Utc>) -> String {
+        format!(
+            "<{}>{} {} {}[{}]: {}",
+            self.priority(),
+            now.format("%b %e %T"),
+            self.pick(HOSTS),
+            self.pick(APPS),
+            self.rng.gen_range(1, 65_536),
+            self.pick(MESSAGES)
+        )
+    }
+
+    pub(super) fn syslog_5424(&mut self, now: DateTime<Utc>) -> String {
+        format!(
+            "<{}>1 {} {} {} {} ID{} - {}",
+            self.priority(),
+            now.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.pick(HOSTS),
+            self.pick(APPS),
+            self.rng.gen_range(1, 65_536),
+            self.rng.gen_range(0, 1000),
+            self.pick(MESSAGES)
+        )
+    }
+
+    pub(super) fn json(&mut self, now: DateTime<Utc>) -> String {
+        json!({
+            "timestamp": now.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "level": self.pick(LEVELS),
+            "host": self.pick(HOSTS),
+            "service": self.pick(APPS),
+            "user": self.pick(USERS),
+            "client_ip": self.ip(),
+            "duration_ms": self.rng.gen_range(0, 5_000),
+            "message": self.pick(MESSAGES),
+        })
+        .to_string()
+    }
+
+    pub(super) fn metric(&mut self, now: DateTime<Utc>) -> Event {
+        let mut tags = BTreeMap::new();
+        tags.insert("host".to_owned(), self.pick(HOSTS).to_owned());
+
+        let (name, kind, value) = match self.rng.gen_range(0, 3) {
+            0 => {
+                tags.insert("method".to_owned(), self.pick(METHODS).to_owned());
+                tags.insert("status".to_owned(), self.pick(STATUSES).to_string());
+                (
+                    "http_requests_total",
+                    MetricKind::Incremental,
+                    MetricValue::Counter {
+                        value: self.rng.gen_range(1, 100) as f64,
+                    },
+                )
+            }
+            1 => (
+                "memory_used_bytes",
+                MetricKind::Absolute,
+                MetricValue::Gauge {
+                    value: self.rng.gen_range(1u64 << 20, 1u64 << 34) as f64,
+                },
+            ),
+            _ => {
+                let values = (0..self.rng.gen_range(1, 10))
+                    .map(|_| self.rng.gen_range(0.0, 2.5))
+                    .collect::<Vec<f64>>();
+                (
+                    "http_request_duration_seconds",
+                    MetricKind::Incremental,
+                    MetricValue::Distribution {
+                        sample_rates: vec![1; values.len()],
+                        values,
+                        statistic: StatisticKind::Histogram,
+                    },
+                )
+            }
+        };
+
+        Event::Metric(Metric {
+            name: name.to_owned(),
+            timestamp: Some(now),
+            tags: Some(tags),
+            kind,
+            value,
+        })
+    }
+}
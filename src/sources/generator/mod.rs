@@ -0,0 +1,423 @@
+use crate::{
+    event::Event,
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    Pipeline,
+};
+use chrono::Utc;
+use futures::{
+    compat::Future01CompatExt,
+    future::{FutureExt, TryFutureExt},
+    stream::StreamExt,
+};
+use futures01::{future::Future, stream::iter_ok, Sink};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::time::Duration;
+use tokio::time::interval;
+
+mod fake;
+
+#[derive(Debug, PartialEq, Snafu)]
+enum BuildError {
+    #[snafu(display("The `lines` format requires at least one line"))]
+    EmptyLines,
+    #[snafu(display(
+        "The rate must be a positive number of at most {} events per second, got {}",
+        MAX_RATE,
+        rate
+    ))]
+    InvalidRate { rate: f64 },
+}
+
+/// One event per nanosecond, beyond which the period between events can't
+/// be represented.
+const MAX_RATE: f64 = 1e9;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GeneratorConfig {
+    #[serde(default)]
+    format: OutputFormat,
+    #[serde(default)]
+    sequence: bool,
+    #[serde(default)]
+    lines: Vec<String>,
+    #[serde(default)]
+    batch_interval: Option<f64>,
+    #[serde(default = "usize::max_value")]
+    count: usize,
+    #[serde(default)]
+    rate: Option<f64>,
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OutputFormat {
+    /// Cycle over the configured `lines`.
+    Lines,
+    ApacheCommon,
+    ApacheCombined,
+    Syslog3164,
+    Syslog5424,
+    Json,
+    Metrics,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Lines
+    }
+}
+
+impl GeneratorConfig {
+    #[allow(dead_code)] // to make check-component-features pass
+    #[cfg(test)]
+    pub fn repeat(lines: Vec<String>, count: usize) -> Self {
+        Self {
+            lines,
+            count,
+            ..Self::default()
+        }
+    }
+}
+
+inventory::submit! {
+    SourceDescription::new::<GeneratorConfig>("generator")
+}
+
+#[typetag::serde(name = "generator")]
+impl SourceConfig for GeneratorConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        if self.format == OutputFormat::Lines && self.lines.is_empty() {
+            return Err(BuildError::EmptyLines.into());
+        }
+        if let Some(rate) = self.rate {
+            if !(rate > 0.0 && rate <= MAX_RATE) {
+                return Err(BuildError::InvalidRate { rate }.into());
+            }
+        }
+        Ok(self.clone().generator(shutdown, out))
+    }
+
+    fn output_type(&self) -> DataType {
+        match self.format {
+            OutputFormat::Metrics => DataType::Metric,
+            _ => DataType::Log,
+        }
+    }
+
+    fn source_type(&self) -> &'static str {
+        "generator"
+    }
+}
+
+impl GeneratorConfig {
+    pub(self) fn generator(self, shutdown: ShutdownSignal, out: Pipeline) -> super::Source {
+        Box::new(self.inner(shutdown, out).boxed().compat())
+    }
+
+    async fn inner(self, mut shutdown: ShutdownSignal, mut out: Pipeline) -> Result<(), ()> {
+        let mut batch_interval = self
+            .batch_interval
+            .map(|i| interval(Duration::from_secs_f64(i)));
+        // Ticks once per event. Late ticks fire immediately, so the
+        // average rate is kept even when single ticks are too short to sleep.
+        // The rate is at most `MAX_RATE`, but the period could still round
+        // down to zero, which `interval` rejects.
+        let mut rate_interval = self.rate.map(|rate| {
+            let period = Duration::from_secs_f64(1.0 / rate);
+            interval(period.max(Duration::from_nanos(1)))
+        });
+        let mut fake = fake::Fake::new(self.seed.unwrap_or_else(rand::random));
+        let mut number: usize = 0;
+
+        for _ in 0..self.count {
+            if shutdown.poll().expect("polling shutdown").is_ready() {
+                break;
+            }
+
+            if let Some(batch_interval) = &mut batch_interval {
+                batch_interval.next().await;
+            }
+
+            let batch_size = match self.format {
+                OutputFormat::Lines => self.lines.len(),
+                _ => 1,
+            };
+            let mut events = Vec::with_capacity(batch_size);
+            for index in 0..batch_size {
+                if let Some(rate_interval) = &mut rate_interval {
+                    rate_interval.next().await;
+                }
+                events.push(self.generate(index, &mut number, &mut fake));
+            }
+
+            let (sink, _) = out
+                .send_all(iter_ok(events))
+                .compat()
+                .await
+                .map_err(|error| error!(message="error sending generated lines", %error))?;
+            out = sink;
+        }
+        Ok(())
+    }
+
+    /// Generate the event at `index` of the current batch.
+    fn generate(&self, index: usize, number: &mut usize, fake: &mut fake::Fake) -> Event {
+        let now = Utc::now();
+        let line = match self.format {
+            OutputFormat::Lines => {
+                let line = &self.lines[index];
+                if self.sequence {
+                    *number += 1;
+                    format!("{} {}", number, line)
+                } else {
+                    line.clone()
+                }
+            }
+            OutputFormat::ApacheCommon => fake.apache_common(now),
+            OutputFormat::ApacheCombined => fake.apache_combined(now),
+            OutputFormat::Syslog3164 => fake.syslog_3164(now),
+            OutputFormat::Syslog5424 => fake.syslog_5424(now),
+            OutputFormat::Json => fake.json(now),
+            OutputFormat::Metrics => return fake.metric(now),
+        };
+        Event::from(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event, shutdown::ShutdownSignal, test_util::runtime, Pipeline};
+    use futures01::{stream::Stream, sync::mpsc, Async::*};
+    use std::time::{Duration, Instant};
+
+    fn runit(config: &str) -> mpsc::Receiver<Event> {
+        let (tx, rx) = Pipeline::new_test();
+        let mut rt = runtime();
+        let config: GeneratorConfig = toml::from_str(config).unwrap();
+        let source = config.generator(ShutdownSignal::noop(), tx);
+        rt.block_on(source).unwrap();
+        rx
+    }
+
+    fn messages(rx: mpsc::Receiver<Event>) -> Vec<String> {
+        let message_key = event::log_schema().message_key();
+        rx.wait()
+            .map(|event| event.unwrap().as_log()[&message_key].to_string_lossy())
+            .collect()
+    }
+
+    #[test]
+    fn copies_lines() {
+        let message_key = event::log_schema().message_key();
+        let mut rx = runit(
+            r#"lines = ["one", "two"]
+               count = 1"#,
+        );
+
+        for line in &["one", "two"] {
+            let event = rx.poll().unwrap();
+            match event {
+                Ready(Some(event)) => {
+                    let log = event.as_log();
+                    let message = log[&message_key].to_string_lossy();
+                    assert_eq!(message, *line);
+                }
+                Ready(None) => panic!("premature end of input"),
+                NotReady => panic!("generator was not ready"),
+            }
+        }
+
+        assert_eq!(rx.poll().unwrap(), Ready(None));
+    }
+
+    #[test]
+    fn limits_count() {
+        let mut rx = runit(
+            r#"lines = ["one", "two"]
+               count = 5"#,
+        );
+
+        for _ in 0..10 {
+            assert!(matches!(rx.poll().unwrap(), Ready(Some(_))));
+        }
+        assert_eq!(rx.poll().unwrap(), Ready(None));
+    }
+
+    #[test]
+    fn adds_sequence() {
+        let message_key = event::log_schema().message_key();
+        let mut rx = runit(
+            r#"lines = ["one", "two"]
+               count = 2
+               sequence = true"#,
+        );
+
+        for line in &["1 one", "2 two", "3 one", "4 two"] {
+            let event = rx.poll().unwrap();
+            match event {
+                Ready(Some(event)) => {
+                    let log = event.as_log();
+                    let message = log[&message_key].to_string_lossy();
+                    assert_eq!(message, *line);
+                }
+                Ready(None) => panic!("premature end of input"),
+                NotReady => panic!("generator was not ready"),
+            }
+        }
+
+        assert_eq!(rx.poll().unwrap(), Ready(None));
+    }
+
+    #[test]
+    fn obeys_batch_interval() {
+        let start = Instant::now();
+        let mut rx = runit(
+            r#"lines = ["one", "two"]
+               count = 3
+               batch_interval = 1.0"#,
+        );
+
+        for _ in 0..6 {
+            assert!(matches!(rx.poll().unwrap(), Ready(Some(_))));
+        }
+        assert_eq!(rx.poll().unwrap(), Ready(None));
+        let duration = start.elapsed();
+        assert!(duration >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn requires_lines() {
+        let config: GeneratorConfig = toml::from_str("count = 1").unwrap();
+        let (tx, _rx) = Pipeline::new_test();
+        assert!(config
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn generates_apache_common() {
+        let messages = messages(runit(
+            r#"format = "apache_common"
+               count = 5"#,
+        ));
+        assert_eq!(messages.len(), 5);
+        let regex = regex::Regex::new(
+            r#"^\d+\.\d+\.\d+\.\d+ - \S+ \[[^\]]+\] "[A-Z]+ \S+ HTTP/1.1" \d{3} \d+$"#,
+        )
+        .unwrap();
+        for message in messages {
+            assert!(regex.is_match(&message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn generates_syslog_5424() {
+        let messages = messages(runit(
+            r#"format = "syslog_5424"
+               count = 5"#,
+        ));
+        assert_eq!(messages.len(), 5);
+        let regex = regex::Regex::new(
+            r#"^<\d{1,3}>1 \d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d{3}Z \S+ \S+ \d+ ID\d+ - .+$"#,
+        )
+        .unwrap();
+        for message in messages {
+            assert!(regex.is_match(&message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn generates_json() {
+        for message in messages(runit(
+            r#"format = "json"
+               count = 5"#,
+        )) {
+            let value: serde_json::Value = serde_json::from_str(&message).unwrap();
+            assert!(value["message"].is_string());
+            assert!(value["level"].is_string());
+        }
+    }
+
+    #[test]
+    fn generates_metrics() {
+        let events = runit(
+            r#"format = "metrics"
+               count = 5"#,
+        )
+        .wait()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(events.len(), 5);
+        assert!(events.iter().all(|event| matches!(event, Event::Metric(_))));
+    }
+
+    #[test]
+    fn same_seed_same_data() {
+        let config = r#"format = "syslog_3164"
+                        count = 10
+                        seed = 42"#;
+        // Strip the timestamps, which aren't seeded.
+        let strip = |messages: Vec<String>| {
+            messages
+                .into_iter()
+                .map(|message| {
+                    message
+                        .split_at(message.find('>').unwrap() + 16)
+                        .1
+                        .to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            strip(messages(runit(config))),
+            strip(messages(runit(config)))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in &[0.0, -1.0, 2e9, f64::INFINITY, f64::NAN] {
+            let config = GeneratorConfig {
+                lines: vec!["one".into()],
+                rate: Some(*rate),
+                ..GeneratorConfig::default()
+            };
+            let built = config.build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                Pipeline::new_test().0,
+            );
+            assert!(built.is_err(), "rate {} was accepted", rate);
+        }
+    }
+
+    #[test]
+    fn obeys_rate() {
+        let start = Instant::now();
+        let rx = runit(
+            r#"lines = ["one"]
+               count = 11
+               rate = 10.0"#,
+        );
+        assert_eq!(messages(rx).len(), 11);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,814 @@
+use super::util::GlobFilter;
+use crate::{
+    event::metric::{Metric, MetricKind, MetricValue},
+    internal_events::{HostMetricsCollectFailed, HostMetricsEventsReceived},
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    Event, Pipeline,
+};
+use chrono::{DateTime, Utc};
+use futures::{
+    compat::Future01CompatExt,
+    future::{FutureExt, TryFutureExt},
+    stream::StreamExt,
+};
+use futures01::Sink;
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::interval;
+
+/// `/proc/stat` reports CPU time in `USER_HZ`, which the kernel fixes at 100
+/// for userspace regardless of its internal tick rate.
+const USER_HZ: f64 = 100.0;
+
+/// `/proc/diskstats` always counts in 512 byte sectors.
+const SECTOR_SIZE: f64 = 512.0;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HostMetricsConfig {
+    #[serde(default = "default_scrape_interval")]
+    pub scrape_interval_secs: u64,
+    #[serde(default = "default_collectors")]
+    pub collectors: Vec<Collector>,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    #[serde(default = "default_proc_root")]
+    pub proc_root: PathBuf,
+    #[serde(default)]
+    pub disk: DiskConfig,
+    #[serde(default)]
+    pub filesystem: FilesystemConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Collector {
+    Cpu,
+    Memory,
+    Swap,
+    Load,
+    Disk,
+    Filesystem,
+    Network,
+}
+
+impl Collector {
+    fn as_str(self) -> &'static str {
+        match self {
+            Collector::Cpu => "cpu",
+            Collector::Memory => "memory",
+            Collector::Swap => "swap",
+            Collector::Load => "load",
+            Collector::Disk => "disk",
+            Collector::Filesystem => "filesystem",
+            Collector::Network => "network",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DiskConfig {
+    #[serde(default)]
+    pub devices: FilterList,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FilesystemConfig {
+    #[serde(default)]
+    pub devices: FilterList,
+    #[serde(default)]
+    pub mountpoints: FilterList,
+    /// Where the host's root filesystem is mounted. Mountpoints are looked
+    /// up under it, but reported as they're listed in `mounts`.
+    pub root_path: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub interfaces: FilterList,
+}
+
+/// Glob patterns selecting which devices, mountpoints or interfaces are
+/// reported. An empty `includes` matches everything.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FilterList {
+    #[serde(default)]
+    pub includes: Vec<String>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+}
+
+fn default_scrape_interval() -> u64 {
+    15
+}
+
+fn default_collectors() -> Vec<Collector> {
+    vec![
+        Collector::Cpu,
+        Collector::Memory,
+        Collector::Swap,
+        Collector::Load,
+        Collector::Disk,
+        Collector::Filesystem,
+        Collector::Network,
+    ]
+}
+
+fn default_namespace() -> String {
+    "host".into()
+}
+
+fn default_proc_root() -> PathBuf {
+    "/proc".into()
+}
+
+impl Default for HostMetricsConfig {
+    fn default() -> Self {
+        Self {
+            scrape_interval_secs: default_scrape_interval(),
+            collectors: default_collectors(),
+            namespace: default_namespace(),
+            proc_root: default_proc_root(),
+            disk: DiskConfig::default(),
+            filesystem: FilesystemConfig::default(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+enum CollectError {
+    #[snafu(display("Unable to read {:?}: {}", path, source))]
+    Read { path: PathBuf, source: io::Error },
+    #[snafu(display("Unable to parse {:?}: {}", path, reason))]
+    Parse { path: PathBuf, reason: String },
+}
+
+inventory::submit! {
+    SourceDescription::new::<HostMetricsConfig>("host_metrics")
+}
+
+#[typetag::serde(name = "host_metrics")]
+impl SourceConfig for HostMetricsConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let host_metrics = Arc::new(HostMetrics::new(self.clone())?);
+        let duration = Duration::from_secs(self.scrape_interval_secs);
+        Ok(Box::new(
+            run(host_metrics, duration, out, shutdown).boxed().compat(),
+        ))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn source_type(&self) -> &'static str {
+        "host_metrics"
+    }
+}
+
+async fn run(
+    host_metrics: Arc<HostMetrics>,
+    duration: Duration,
+    mut out: Pipeline,
+    shutdown: ShutdownSignal,
+) -> Result<(), ()> {
+    let mut interval = interval(duration).take_until(shutdown.compat());
+
+    while interval.next().await.is_some() {
+        let host_metrics = Arc::clone(&host_metrics);
+        let metrics = tokio::task::spawn_blocking(move || host_metrics.capture_metrics())
+            .await
+            .map_err(|error| error!(message = "host metrics collection panicked.", %error))?;
+        emit!(HostMetricsEventsReceived {
+            count: metrics.len()
+        });
+
+        let (sink, _) = out
+            .send_all(futures01::stream::iter_ok(metrics))
+            .compat()
+            .await
+            .map_err(|error| error!(message = "error sending host metrics.", %error))?;
+        out = sink;
+    }
+
+    Ok(())
+}
+
+impl FilterList {
+    fn compile(&self) -> crate::Result<GlobFilter> {
+        Ok(GlobFilter::new(&self.includes, &self.excludes)?)
+    }
+}
+
+#[derive(Debug)]
+struct HostMetrics {
+    config: HostMetricsConfig,
+    hostname: Option<String>,
+    disk_devices: GlobFilter,
+    filesystem_devices: GlobFilter,
+    filesystem_mountpoints: GlobFilter,
+    network_interfaces: GlobFilter,
+}
+
+impl HostMetrics {
+    fn new(config: HostMetricsConfig) -> crate::Result<Self> {
+        Ok(Self {
+            hostname: hostname::get_hostname(),
+            disk_devices: config.disk.devices.compile()?,
+            filesystem_devices: config.filesystem.devices.compile()?,
+            filesystem_mountpoints: config.filesystem.mountpoints.compile()?,
+            network_interfaces: config.network.interfaces.compile()?,
+            config,
+        })
+    }
+
+    fn capture_metrics(&self) -> Vec<Event> {
+        let mut buffer = MetricsBuffer::new(&self.config.namespace, self.hostname.as_deref());
+
+        for &collector in &self.config.collectors {
+            let result = match collector {
+                Collector::Cpu => self.cpu_metrics(&mut buffer),
+                Collector::Memory => self.memory_metrics(&mut buffer),
+                Collector::Swap => self.swap_metrics(&mut buffer),
+                Collector::Load => self.load_metrics(&mut buffer),
+                Collector::Disk => self.disk_metrics(&mut buffer),
+                Collector::Filesystem => self.filesystem_metrics(&mut buffer),
+                Collector::Network => self.network_metrics(&mut buffer),
+            };
+            if let Err(error) = result {
+                emit!(HostMetricsCollectFailed {
+                    collector: collector.as_str(),
+                    error: &error,
+                });
+            }
+        }
+
+        buffer.metrics
+    }
+
+    fn read(&self, name: &str) -> Result<(PathBuf, String), CollectError> {
+        let path = self.config.proc_root.join(name);
+        let contents = fs::read_to_string(&path).context(Read { path: &path })?;
+        Ok((path, contents))
+    }
+
+    fn cpu_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        const MODES: [&str; 8] = [
+            "user", "nice", "system", "idle", "iowait", "irq", "softirq", "steal",
+        ];
+
+        let (path, contents) = self.read("stat")?;
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let cpu = match fields.next() {
+                // The aggregated `cpu` line is left for the consumer to compute.
+                Some(name) if name.starts_with("cpu") && name.len() > 3 => &name[3..],
+                _ => continue,
+            };
+            for (mode, value) in MODES.iter().zip(fields) {
+                let jiffies = parse_number(&path, value)?;
+                buffer.counter(
+                    "cpu_seconds_total",
+                    jiffies / USER_HZ,
+                    &[("cpu", cpu), ("mode", *mode)],
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn memory_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        let (path, contents) = self.read("meminfo")?;
+        let meminfo = parse_meminfo(&path, &contents)?;
+        let get = |key: &str| meminfo.get(key).copied().unwrap_or(0.0);
+
+        let total = get("MemTotal");
+        let free = get("MemFree");
+        let buffers = get("Buffers");
+        let cached = get("Cached");
+        buffer.gauge("memory_total_bytes", total, &[]);
+        buffer.gauge("memory_free_bytes", free, &[]);
+        buffer.gauge("memory_available_bytes", get("MemAvailable"), &[]);
+        buffer.gauge("memory_buffers_bytes", buffers, &[]);
+        buffer.gauge("memory_cached_bytes", cached, &[]);
+        buffer.gauge("memory_shared_bytes", get("Shmem"), &[]);
+        buffer.gauge("memory_active_bytes", get("Active"), &[]);
+        buffer.gauge("memory_inactive_bytes", get("Inactive"), &[]);
+        buffer.gauge(
+            "memory_used_bytes",
+            (total - free - buffers - cached).max(0.0),
+            &[],
+        );
+        Ok(())
+    }
+
+    fn swap_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        let (path, contents) = self.read("meminfo")?;
+        let meminfo = parse_meminfo(&path, &contents)?;
+        let total = meminfo.get("SwapTotal").copied().unwrap_or(0.0);
+        let free = meminfo.get("SwapFree").copied().unwrap_or(0.0);
+        buffer.gauge("memory_swap_total_bytes", total, &[]);
+        buffer.gauge("memory_swap_free_bytes", free, &[]);
+        buffer.gauge("memory_swap_used_bytes", total - free, &[]);
+
+        let (path, contents) = self.read("vmstat")?;
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let name = match fields.next() {
+                Some("pswpin") => "memory_swapped_in_pages_total",
+                Some("pswpout") => "memory_swapped_out_pages_total",
+                _ => continue,
+            };
+            if let Some(value) = fields.next() {
+                buffer.counter(name, parse_number(&path, value)?, &[]);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        let (path, contents) = self.read("loadavg")?;
+        let mut fields = contents.split_whitespace();
+        for name in &["load1", "load5", "load15"] {
+            let value = fields.next().ok_or_else(|| CollectError::Parse {
+                path: path.clone(),
+                reason: "missing load average".into(),
+            })?;
+            buffer.gauge(name, parse_number(&path, value)?, &[]);
+        }
+        Ok(())
+    }
+
+    fn disk_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        let (path, contents) = self.read("diskstats")?;
+        for line in contents.lines() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 14 {
+                continue;
+            }
+            let device = fields[2];
+            if !self.disk_devices.matches(device) {
+                continue;
+            }
+            let field = |index: usize| parse_number(&path, fields[index]);
+            let tags = [("device", device)];
+
+            buffer.counter("disk_reads_completed_total", field(3)?, &tags);
+            buffer.counter("disk_read_bytes_total", field(5)? * SECTOR_SIZE, &tags);
+            buffer.counter("disk_writes_completed_total", field(7)?, &tags);
+            buffer.counter("disk_written_bytes_total", field(9)? * SECTOR_SIZE, &tags);
+            buffer.counter("disk_io_time_seconds_total", field(12)? / 1000.0, &tags);
+        }
+        Ok(())
+    }
+
+    fn filesystem_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        let (_, contents) = self.read("mounts")?;
+        for line in contents.lines() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 3 {
+                continue;
+            }
+            let device = unescape_mount_field(fields[0]);
+            let mountpoint = unescape_mount_field(fields[1]);
+            let filesystem = fields[2];
+            if !self.filesystem_devices.matches(&device)
+                || !self.filesystem_mountpoints.matches(&mountpoint)
+            {
+                continue;
+            }
+
+            let path = match &self.config.filesystem.root_path {
+                Some(root) => root.join(mountpoint.trim_start_matches('/')),
+                None => PathBuf::from(&mountpoint),
+            };
+            let stat = match nix::sys::statvfs::statvfs(&path) {
+                Ok(stat) => stat,
+                Err(error) => {
+                    debug!(message = "unable to stat filesystem.", %mountpoint, %error);
+                    continue;
+                }
+            };
+            // Pseudo filesystems such as `proc` and `sysfs` have no size.
+            if stat.blocks() == 0 {
+                continue;
+            }
+            let fragment_size = stat.fragment_size() as f64;
+            let total = stat.blocks() as f64 * fragment_size;
+            let free = stat.blocks_free() as f64 * fragment_size;
+            let available = stat.blocks_available() as f64 * fragment_size;
+
+            let tags = [
+                ("device", device.as_str()),
+                ("mountpoint", mountpoint.as_str()),
+                ("filesystem", filesystem),
+            ];
+            buffer.gauge("filesystem_total_bytes", total, &tags);
+            buffer.gauge("filesystem_free_bytes", free, &tags);
+            buffer.gauge("filesystem_available_bytes", available, &tags);
+            buffer.gauge("filesystem_used_bytes", total - free, &tags);
+        }
+        Ok(())
+    }
+
+    fn network_metrics(&self, buffer: &mut MetricsBuffer) -> Result<(), CollectError> {
+        const COUNTERS: [(usize, &str); 8] = [
+            (0, "network_receive_bytes_total"),
+            (1, "network_receive_packets_total"),
+            (2, "network_receive_errs_total"),
+            (3, "network_receive_drop_total"),
+            (8, "network_transmit_bytes_total"),
+            (9, "network_transmit_packets_total"),
+            (10, "network_transmit_errs_total"),
+            (11, "network_transmit_drop_total"),
+        ];
+
+        let (path, contents) = self.read("net/dev")?;
+        // The first two lines are headers.
+        for line in contents.lines().skip(2) {
+            let mut parts = line.splitn(2, ':');
+            let (interface, stats) = match (parts.next(), parts.next()) {
+                (Some(interface), Some(stats)) => (interface.trim(), stats),
+                _ => continue,
+            };
+            if !self.network_interfaces.matches(interface) {
+                continue;
+            }
+            let fields = stats.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 16 {
+                continue;
+            }
+            let tags = [("device", interface)];
+            for &(index, name) in COUNTERS.iter() {
+                buffer.counter(name, parse_number(&path, fields[index])?, &tags);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the metrics of a single scrape, all sharing one timestamp.
+struct MetricsBuffer<'a> {
+    namespace: &'a str,
+    hostname: Option<&'a str>,
+    timestamp: DateTime<Utc>,
+    metrics: Vec<Event>,
+}
+
+impl<'a> MetricsBuffer<'a> {
+    fn new(namespace: &'a str, hostname: Option<&'a str>) -> Self {
+        Self {
+            namespace,
+            hostname,
+            timestamp: Utc::now(),
+            metrics: Vec::new(),
+        }
+    }
+
+    fn counter(&mut self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.push(name, MetricValue::Counter { value }, tags);
+    }
+
+    fn gauge(&mut self, name: &str, value: f64, tags: &[(&str, &str)]) {
+        self.push(name, MetricValue::Gauge { value }, tags);
+    }
+
+    fn push(&mut self, name: &str, value: MetricValue, tags: &[(&str, &str)]) {
+        let mut tags = tags
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        if let Some(hostname) = self.hostname {
+            tags.insert("host".into(), hostname.into());
+        }
+
+        let name = if self.namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}_{}", self.namespace, name)
+        };
+
+        self.metrics.push(Event::Metric(Metric {
+            name,
+            timestamp: Some(self.timestamp),
+            tags: if tags.is_empty() { None } else { Some(tags) },
+            kind: MetricKind::Absolute,
+            value,
+        }));
+    }
+}
+
+fn parse_number(path: &Path, value: &str) -> Result<f64, CollectError> {
+    value.parse().map_err(|_| CollectError::Parse {
+        path: path.into(),
+        reason: format!("{:?} is not a number", value),
+    })
+}
+
+/// Parses `/proc/meminfo` into bytes.
+fn parse_meminfo(path: &Path, contents: &str) -> Result<HashMap<String, f64>, CollectError> {
+    let mut meminfo = HashMap::new();
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+            let scale = match fields.next() {
+                Some("kB") => 1024.0,
+                _ => 1.0,
+            };
+            let key = key.trim_end_matches(':').to_string();
+            meminfo.insert(key, parse_number(path, value)? * scale);
+        }
+    }
+    Ok(meminfo)
+}
+
+/// `/proc/mounts` escapes whitespace and backslashes as octal sequences.
+fn unescape_mount_field(field: &str) -> String {
+    field
+        .replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempfile::tempdir;
+
+    const STAT: &str = "\
+cpu  200 0 100 1000 0 0 0 0 0 0
+cpu0 100 0 50 500 10 1 2 3 0 0
+cpu1 100 0 50 500 0 0 0 0 0 0
+intr 12345
+ctxt 678
+";
+
+    const MEMINFO: &str = "\
+MemTotal:       16000 kB
+MemFree:         4000 kB
+MemAvailable:    8000 kB
+Buffers:         1000 kB
+Cached:          2000 kB
+SwapCached:         0 kB
+Active:          5000 kB
+Inactive:        3000 kB
+Shmem:            100 kB
+SwapTotal:       2000 kB
+SwapFree:        1500 kB
+";
+
+    const VMSTAT: &str = "\
+nr_free_pages 1000
+pswpin 12
+pswpout 34
+";
+
+    const DISKSTATS: &str = "\
+   8       0 sda 100 0 2000 50 200 0 4000 60 0 1500 110 0 0 0 0
+   8       1 sda1 10 0 200 5 20 0 400 6 0 150 11 0 0 0 0
+   7       0 loop0 1 0 2 0 0 0 0 0 0 0 0 0 0 0 0
+";
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:     100       1    0    0    0     0          0         0      100       1    0    0    0     0       0          0
+  eth0:    2000      20    1    2    0     0          0         0     3000      30    3    4    0     0       0          0
+";
+
+    fn proc_root() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        write(dir.path().join("stat"), STAT).unwrap();
+        write(dir.path().join("meminfo"), MEMINFO).unwrap();
+        write(dir.path().join("vmstat"), VMSTAT).unwrap();
+        write(dir.path().join("loadavg"), "0.50 0.25 0.10 1/100 1234\n").unwrap();
+        write(dir.path().join("diskstats"), DISKSTATS).unwrap();
+        create_dir_all(dir.path().join("net")).unwrap();
+        write(dir.path().join("net/dev"), NET_DEV).unwrap();
+        write(
+            dir.path().join("mounts"),
+            format!(
+                "/dev/test {} ext4 rw 0 0\nproc /proc proc rw 0 0\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+        dir
+    }
+
+    fn collect(config: HostMetricsConfig) -> BTreeMap<String, Vec<Metric>> {
+        let mut metrics = BTreeMap::new();
+        for event in HostMetrics::new(config).unwrap().capture_metrics() {
+            let metric = event.into_metric();
+            metrics
+                .entry(metric.name.clone())
+                .or_insert_with(Vec::new)
+                .push(metric);
+        }
+        metrics
+    }
+
+    fn config(proc_root: &Path, collector: Collector) -> HostMetricsConfig {
+        HostMetricsConfig {
+            collectors: vec![collector],
+            proc_root: proc_root.into(),
+            ..HostMetricsConfig::default()
+        }
+    }
+
+    fn tag<'a>(metric: &'a Metric, key: &str) -> &'a str {
+        &metric.tags.as_ref().unwrap()[key]
+    }
+
+    fn single_value(metrics: &BTreeMap<String, Vec<Metric>>, name: &str) -> MetricValue {
+        assert_eq!(metrics[name].len(), 1, "{}", name);
+        metrics[name][0].value.clone()
+    }
+
+    #[test]
+    fn parses_config() {
+        let config: HostMetricsConfig = toml::from_str(
+            r#"
+            collectors = ["cpu", "network"]
+            scrape_interval_secs = 5
+
+            [network.interfaces]
+            excludes = ["lo"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.collectors, vec![Collector::Cpu, Collector::Network]);
+        assert_eq!(config.scrape_interval_secs, 5);
+        assert_eq!(config.network.interfaces.excludes, vec!["lo".to_string()]);
+        assert_eq!(config.proc_root, PathBuf::from("/proc"));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let mut config = HostMetricsConfig::default();
+        config.disk.devices.includes = vec!["[".into()];
+        assert!(HostMetrics::new(config).is_err());
+    }
+
+    #[test]
+    fn collects_cpu_metrics() {
+        let root = proc_root();
+        let metrics = collect(config(root.path(), Collector::Cpu));
+
+        let cpu = &metrics["host_cpu_seconds_total"];
+        assert_eq!(cpu.len(), 16);
+        let user0 = cpu
+            .iter()
+            .find(|m| tag(m, "cpu") == "0" && tag(m, "mode") == "user")
+            .unwrap();
+        assert_eq!(user0.value, MetricValue::Counter { value: 1.0 });
+        assert_eq!(user0.kind, MetricKind::Absolute);
+    }
+
+    #[test]
+    fn collects_memory_metrics() {
+        let root = proc_root();
+        let metrics = collect(config(root.path(), Collector::Memory));
+
+        assert_eq!(
+            single_value(&metrics, "host_memory_total_bytes"),
+            MetricValue::Gauge {
+                value: 16000.0 * 1024.0
+            }
+        );
+        assert_eq!(
+            single_value(&metrics, "host_memory_used_bytes"),
+            MetricValue::Gauge {
+                value: 9000.0 * 1024.0
+            }
+        );
+    }
+
+    #[test]
+    fn collects_swap_metrics() {
+        let root = proc_root();
+        let metrics = collect(config(root.path(), Collector::Swap));
+
+        assert_eq!(
+            single_value(&metrics, "host_memory_swap_used_bytes"),
+            MetricValue::Gauge {
+                value: 500.0 * 1024.0
+            }
+        );
+        assert_eq!(
+            single_value(&metrics, "host_memory_swapped_out_pages_total"),
+            MetricValue::Counter { value: 34.0 }
+        );
+    }
+
+    #[test]
+    fn collects_load_metrics() {
+        let root = proc_root();
+        let metrics = collect(config(root.path(), Collector::Load));
+
+        assert_eq!(
+            single_value(&metrics, "host_load5"),
+            MetricValue::Gauge { value: 0.25 }
+        );
+    }
+
+    #[test]
+    fn collects_disk_metrics_with_filter() {
+        let root = proc_root();
+        let mut config = config(root.path(), Collector::Disk);
+        config.disk.devices.excludes = vec!["loop*".into()];
+        let metrics = collect(config);
+
+        let reads = &metrics["host_disk_read_bytes_total"];
+        assert_eq!(reads.len(), 2);
+        let sda = reads.iter().find(|m| tag(m, "device") == "sda").unwrap();
+        assert_eq!(
+            sda.value,
+            MetricValue::Counter {
+                value: 2000.0 * 512.0
+            }
+        );
+        assert!(!reads.iter().any(|m| tag(m, "device") == "loop0"));
+    }
+
+    #[test]
+    fn collects_filesystem_metrics() {
+        let root = proc_root();
+        let metrics = collect(config(root.path(), Collector::Filesystem));
+
+        let total = &metrics["host_filesystem_total_bytes"];
+        assert_eq!(total.len(), 1);
+        assert_eq!(tag(&total[0], "device"), "/dev/test");
+        assert_eq!(tag(&total[0], "filesystem"), "ext4");
+
+        let mut config = config(root.path(), Collector::Filesystem);
+        config.filesystem.devices.excludes = vec!["/dev/*".into()];
+        assert!(collect(config).is_empty());
+    }
+
+    #[test]
+    fn looks_up_mountpoints_under_root_path() {
+        let root = proc_root();
+        write(root.path().join("mounts"), "/dev/test / ext4 rw 0 0\n").unwrap();
+        let mut config = config(root.path(), Collector::Filesystem);
+        config.filesystem.root_path = Some(root.path().into());
+
+        let metrics = collect(config);
+        let total = &metrics["host_filesystem_total_bytes"];
+        assert_eq!(total.len(), 1);
+        assert_eq!(tag(&total[0], "mountpoint"), "/");
+    }
+
+    #[test]
+    fn collects_network_metrics_with_filter() {
+        let root = proc_root();
+        let mut config = config(root.path(), Collector::Network);
+        config.network.interfaces.excludes = vec!["lo".into()];
+        let metrics = collect(config);
+
+        assert_eq!(
+            single_value(&metrics, "host_network_receive_bytes_total"),
+            MetricValue::Counter { value: 2000.0 }
+        );
+        assert_eq!(
+            single_value(&metrics, "host_network_transmit_drop_total"),
+            MetricValue::Counter { value: 4.0 }
+        );
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let root = tempdir().unwrap();
+        let metrics = collect(config(root.path(), Collector::Cpu));
+        assert!(metrics.is_empty());
+    }
+
+    #[test]
+    fn unescapes_mount_fields() {
+        assert_eq!(unescape_mount_field("/mnt/my\\040disk"), "/mnt/my disk");
+    }
+}
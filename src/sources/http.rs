@@ -1,7 +1,7 @@
 use crate::{
     event::{self, Event},
     shutdown::ShutdownSignal,
-    sources::util::{ErrorMessage, HttpSource},
+    sources::util::{decoding::Decoder, DecodingConfig, ErrorMessage, FramingConfig, HttpSource},
     tls::TlsConfig,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Pipeline,
@@ -23,6 +23,9 @@ pub struct SimpleHttpConfig {
     #[serde(default)]
     headers: Vec<String>,
     tls: Option<TlsConfig>,
+    /// Takes precedence over `encoding` when set.
+    decoding: Option<DecodingConfig>,
+    framing: Option<FramingConfig>,
 }
 
 inventory::submit! {
@@ -32,6 +35,7 @@ inventory::submit! {
 #[derive(Clone)]
 struct SimpleHttpSource {
     encoding: Encoding,
+    decoder: Option<Decoder>,
     headers: Vec<String>,
 }
 
@@ -47,16 +51,19 @@ pub enum Encoding {
 
 impl HttpSource for SimpleHttpSource {
     fn build_event(&self, body: Bytes, header_map: HeaderMap) -> Result<Vec<Event>, ErrorMessage> {
-        decode_body(body, self.encoding)
-            .map(|events| add_headers(events, &self.headers, header_map))
-            .map(|mut events| {
-                // Add source type
-                let key = event::log_schema().source_type_key();
-                for event in events.iter_mut() {
-                    event.as_mut_log().try_insert(key, "http");
-                }
-                events
-            })
+        match &self.decoder {
+            Some(decoder) => decode_frames(decoder, &body),
+            None => decode_body(body, self.encoding),
+        }
+        .map(|events| add_headers(events, &self.headers, header_map))
+        .map(|mut events| {
+            // Add source type
+            let key = event::log_schema().source_type_key();
+            for event in events.iter_mut() {
+                event.as_mut_log().try_insert(key, "http");
+            }
+            events
+        })
     }
 }
 
@@ -69,8 +76,19 @@ impl SourceConfig for SimpleHttpConfig {
         shutdown: ShutdownSignal,
         out: Pipeline,
     ) -> crate::Result<super::Source> {
+        let decoder = match &self.decoding {
+            Some(decoding) => {
+                let framing = self
+                    .framing
+                    .clone()
+                    .unwrap_or(FramingConfig::NewlineDelimited);
+                Some(Decoder::new(framing.build(usize::MAX)?, decoding.build()?))
+            }
+            None => None,
+        };
         let source = SimpleHttpSource {
             encoding: self.encoding,
+            decoder,
             headers: self.headers.clone(),
         };
         source.run(self.address, "", &self.tls, out, shutdown)
@@ -125,6 +143,18 @@ fn body_to_lines(buf: Bytes) -> impl Iterator<Item = Result<Bytes, ErrorMessage>
     })
 }
 
+fn decode_frames(decoder: &Decoder, body: &[u8]) -> Result<Vec<Event>, ErrorMessage> {
+    decoder
+        .decode_payload(body)
+        .into_iter()
+        .map(|event| {
+            event.map_err(|error| {
+                ErrorMessage::new(StatusCode::BAD_REQUEST, format!("Bad request: {}", error))
+            })
+        })
+        .collect()
+}
+
 fn decode_body(body: Bytes, enc: Encoding) -> Result<Vec<Event>, ErrorMessage> {
     match enc {
         Encoding::Text => body_to_lines(body)
@@ -198,6 +228,7 @@ fn json_value_to_type_string(value: &JsonValue) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::{Encoding, SimpleHttpConfig};
+    use crate::sources::util::{DecodingConfig, FramingConfig};
 
     use crate::shutdown::ShutdownSignal;
     use crate::{
@@ -218,6 +249,16 @@ mod tests {
         rt: &mut Runtime,
         encoding: Encoding,
         headers: Vec<String>,
+    ) -> (mpsc::Receiver<Event>, SocketAddr) {
+        source_with_decoding(rt, encoding, headers, None, None)
+    }
+
+    fn source_with_decoding(
+        rt: &mut Runtime,
+        encoding: Encoding,
+        headers: Vec<String>,
+        decoding: Option<DecodingConfig>,
+        framing: Option<FramingConfig>,
     ) -> (mpsc::Receiver<Event>, SocketAddr) {
         test_util::trace_init();
         let (sender, recv) = Pipeline::new_test();
@@ -228,6 +269,8 @@ mod tests {
                 encoding,
                 headers,
                 tls: None,
+                decoding,
+                framing,
             }
             .build(
                 "default",
@@ -410,6 +453,37 @@ mod tests {
         });
     }
 
+    #[test]
+    fn http_decoding_json() {
+        let mut rt = runtime();
+        let (rx, addr) = source_with_decoding(
+            &mut rt,
+            Encoding::Text,
+            vec![],
+            Some(DecodingConfig::Json),
+            Some(FramingConfig::CharacterDelimited { delimiter: ';' }),
+        );
+
+        rt.block_on_std(async move {
+            assert_eq!(400, send(addr, r#"{"key":"value"};[1]"#).await);
+            assert_eq!(200, send(addr, r#"{"key":"one"};{"key":"two"}"#).await);
+
+            let mut events = collect_n(rx, 2).compat().await.unwrap();
+            {
+                let event = events.remove(0);
+                let log = event.as_log();
+                assert_eq!(log[&Atom::from("key")], "one".into());
+                assert!(log.get(&event::log_schema().timestamp_key()).is_some());
+                assert_eq!(log[event::log_schema().source_type_key()], "http".into());
+            }
+            {
+                let event = events.remove(0);
+                let log = event.as_log();
+                assert_eq!(log[&Atom::from("key")], "two".into());
+            }
+        });
+    }
+
     #[test]
     fn http_headers() {
         let mut headers = HeaderMap::new();
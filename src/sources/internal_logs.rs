@@ -0,0 +1,153 @@
+use crate::{
+    event::{self, Event, LogEvent},
+    internal_events::InternalLogsEventsLagged,
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    trace, Pipeline,
+};
+use futures::{
+    compat::Future01CompatExt,
+    future::{FutureExt, TryFutureExt},
+};
+use futures01::Sink;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{Receiver, RecvError};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct InternalLogsConfig {}
+
+inventory::submit! {
+    SourceDescription::new::<InternalLogsConfig>("internal_logs")
+}
+
+#[typetag::serde(name = "internal_logs")]
+impl SourceConfig for InternalLogsConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        let receiver = trace::subscribe();
+        Ok(Box::new(run(receiver, out, shutdown).boxed().compat()))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "internal_logs"
+    }
+}
+
+async fn run(
+    mut receiver: Receiver<LogEvent>,
+    mut out: Pipeline,
+    shutdown: ShutdownSignal,
+) -> Result<(), ()> {
+    let mut shutdown = shutdown.compat();
+
+    loop {
+        let received = tokio::select! {
+            _ = &mut shutdown => break,
+            received = receiver.recv() => received,
+        };
+
+        let mut log = match received {
+            Ok(log) => log,
+            Err(RecvError::Lagged(count)) => {
+                emit!(InternalLogsEventsLagged { count });
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        log.insert(event::log_schema().source_type_key(), "internal_logs");
+
+        out = out
+            .send(Event::Log(log))
+            .compat()
+            .await
+            .map_err(|error| error!(message = "Error sending log.", %error))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::BroadcastLayer;
+    use futures01::Stream;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    #[tokio::test]
+    async fn receives_logs() {
+        let (tx, rx) = Pipeline::new_test();
+        let source = InternalLogsConfig {}
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                tx,
+            )
+            .unwrap();
+        tokio::spawn(source.compat());
+
+        let subscriber = Registry::default().with(BroadcastLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("source", name = "in", r#type = "internal_logs");
+            let _enter = span.enter();
+            error!(target: "vector::test", message = "Internal log.", answer = 42);
+        });
+
+        // Other tests may be logging through the global subscriber too.
+        let (event, _) = rx
+            .filter(|event| {
+                event.as_log().get(&event::log_schema().message_key())
+                    == Some(&"Internal log.".into())
+            })
+            .into_future()
+            .compat()
+            .await
+            .map_err(|_| ())
+            .unwrap();
+        let log = event.unwrap().into_log();
+
+        assert_eq!(log[&"answer".into()], 42.into());
+        assert_eq!(log[&"metadata.level".into()], "ERROR".into());
+        assert_eq!(log[&"metadata.target".into()], "vector::test".into());
+        assert_eq!(log[&"component_kind".into()], "source".into());
+        assert_eq!(log[&"component_name".into()], "in".into());
+        assert_eq!(log[&"component_type".into()], "internal_logs".into());
+        assert_eq!(
+            log[event::log_schema().source_type_key()],
+            "internal_logs".into()
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_own_logs() {
+        let mut rx = crate::trace::subscribe();
+
+        let subscriber = Registry::default().with(BroadcastLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            error!(message = "Error sending log.");
+            error!(target: "vector::pipeline", message = "Pipeline error.");
+            error!(target: "vector::test", message = "Other log.");
+        });
+
+        // Other tests may be logging through the global subscriber too.
+        loop {
+            let log = rx.recv().await.unwrap();
+            let target = log[&"metadata.target".into()].to_string_lossy();
+            assert!(!target.starts_with("vector::sources::internal_logs"));
+            assert_ne!(target, "vector::pipeline");
+            if target == "vector::test" {
+                break;
+            }
+        }
+    }
+}
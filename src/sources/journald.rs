@@ -21,6 +21,7 @@ use nix::{
 use serde::{Deserialize, Serialize};
 use serde_json::{Error as JsonError, Value as JsonValue};
 use snafu::{ResultExt, Snafu};
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
@@ -40,6 +41,7 @@ lazy_static! {
     static ref HOSTNAME: Atom = Atom::from("_HOSTNAME");
     static ref MESSAGE: Atom = Atom::from("MESSAGE");
     static ref SYSTEMD_UNIT: Atom = Atom::from("_SYSTEMD_UNIT");
+    static ref PRIORITY: Atom = Atom::from("PRIORITY");
     static ref SOURCE_TIMESTAMP: Atom = Atom::from("_SOURCE_REALTIME_TIMESTAMP");
     static ref RECEIVED_TIMESTAMP: Atom = Atom::from("__REALTIME_TIMESTAMP");
     static ref JOURNALCTL: PathBuf = "journalctl".into();
@@ -56,6 +58,11 @@ enum BuildError {
         unit
     ))]
     DuplicatedUnit { unit: String },
+    #[snafu(display(
+        "Invalid field match {:?}, expected `FIELD<op>VALUE` with one of =, !=, <, <=, >, >=",
+        expression
+    ))]
+    InvalidMatch { expression: String },
 }
 
 #[derive(Deserialize, Serialize, Debug, Default)]
@@ -65,9 +72,14 @@ pub struct JournaldConfig {
     pub units: Vec<String>,
     pub include_units: Vec<String>,
     pub exclude_units: Vec<String>,
+    pub include_matches: Vec<String>,
+    pub exclude_matches: Vec<String>,
     pub data_dir: Option<PathBuf>,
     pub batch_size: Option<usize>,
     pub journalctl_path: Option<PathBuf>,
+    pub journal_directory: Option<PathBuf>,
+    pub namespace: Option<String>,
+    pub since_now: bool,
     #[serde(default)]
     pub remap_priority: bool,
 }
@@ -110,6 +122,8 @@ impl SourceConfig for JournaldConfig {
             return Err(BuildError::DuplicatedUnit { unit }.into());
         }
 
+        let matches = Matches::new(&self.include_matches, &self.exclude_matches)?;
+
         let checkpointer = Checkpointer::new(data_dir)
             .map_err(|err| format!("Unable to open checkpoint file: {}", err))?;
 
@@ -119,6 +133,7 @@ impl SourceConfig for JournaldConfig {
             checkpointer,
             include_units,
             exclude_units,
+            matches,
             batch_size,
             self.remap_priority,
         )
@@ -141,6 +156,7 @@ impl JournaldConfig {
         mut checkpointer: Checkpointer,
         include_units: HashSet<String>,
         exclude_units: HashSet<String>,
+        matches: Matches,
         batch_size: usize,
         remap_priority: bool,
     ) -> crate::Result<super::Source>
@@ -163,7 +179,7 @@ impl JournaldConfig {
             }
         };
 
-        let (journal, close) = J::new(self, cursor)?;
+        let (journal, close) = J::new(self, &matches, cursor)?;
 
         Ok(Box::new(future::lazy(move || {
             info!(message = "Starting journald server.",);
@@ -172,6 +188,7 @@ impl JournaldConfig {
                 journal,
                 include_units,
                 exclude_units,
+                matches,
                 channel: out,
                 shutdown: shutdown.clone(),
                 checkpointer,
@@ -244,6 +261,7 @@ trait JournalSource: Iterator<Item = Result<String, io::Error>> + Sized {
     /// (source, close_underlying_stream)
     fn new(
         config: &JournaldConfig,
+        matches: &Matches,
         cursor: Option<String>,
     ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)>;
 }
@@ -257,6 +275,7 @@ struct Journalctl {
 impl JournalSource for Journalctl {
     fn new(
         config: &JournaldConfig,
+        matches: &Matches,
         cursor: Option<String>,
     ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)> {
         let journalctl = config.journalctl_path.as_ref().unwrap_or(&JOURNALCTL);
@@ -267,6 +286,14 @@ impl JournalSource for Journalctl {
         command.arg("--show-cursor");
         command.arg("--output=json");
 
+        if let Some(directory) = &config.journal_directory {
+            command.arg(format!("--directory={}", directory.display()));
+        }
+
+        if let Some(namespace) = &config.namespace {
+            command.arg(format!("--namespace={}", namespace));
+        }
+
         let current_boot = config.current_boot_only.unwrap_or(true);
         if current_boot {
             command.arg("--boot");
@@ -274,11 +301,15 @@ impl JournalSource for Journalctl {
 
         if let Some(cursor) = cursor {
             command.arg(format!("--after-cursor={}", cursor));
+        } else if config.since_now {
+            command.arg("--since=now");
         } else {
             // journalctl --follow only outputs a few lines without a starting point
             command.arg("--since=2000-01-01");
         }
 
+        command.args(matches.journalctl_args());
+
         let mut child = command.spawn().context(JournalctlSpawn)?;
         let stdout = child.stdout.take().unwrap();
         let stdout = BufReader::new(stdout);
@@ -312,6 +343,7 @@ struct JournaldServer<J, T> {
     journal: J,
     include_units: HashSet<String>,
     exclude_units: HashSet<String>,
+    matches: Matches,
     channel: T,
     shutdown: ShutdownSignal,
     checkpointer: Checkpointer,
@@ -350,7 +382,7 @@ where
                     }
                 };
 
-                let mut record = match decode_record(&text, self.remap_priority) {
+                let mut record = match decode_record(&text) {
                     Ok(record) => record,
                     Err(error) => {
                         emit!(JournaldInvalidRecord { error, text });
@@ -367,6 +399,15 @@ where
                 if filter_unit(unit, &self.include_units, &self.exclude_units) {
                     continue;
                 }
+                if self.matches.filter(&record) {
+                    continue;
+                }
+
+                if self.remap_priority {
+                    if let Some(priority) = record.get_mut(&PRIORITY) {
+                        remap_priority(priority);
+                    }
+                }
 
                 emit!(JournaldEventReceived {
                     byte_size: text.len()
@@ -404,7 +445,7 @@ where
     }
 }
 
-fn decode_record(text: &str, remap: bool) -> Result<Record, JsonError> {
+fn decode_record(text: &str) -> Result<Record, JsonError> {
     let mut record = serde_json::from_str::<JsonValue>(&text)?;
     // journalctl will output non-ASCII messages using an array
     // of integers. Look for those messages and re-parse them.
@@ -414,9 +455,6 @@ fn decode_record(text: &str, remap: bool) -> Result<Record, JsonError> {
             .and_then(|v| decode_array(&v))
             .map(|decoded| *message = decoded)
     });
-    if remap {
-        record.get_mut("PRIORITY").map(remap_priority);
-    }
     serde_json::from_value(record)
 }
 
@@ -436,8 +474,8 @@ fn decode_array(array: &[JsonValue]) -> Option<JsonValue> {
         .map(|array| String::from_utf8_lossy(&array).into())
 }
 
-fn remap_priority(priority: &mut JsonValue) {
-    if let Some(num) = priority.as_str().and_then(|s| usize::from_str(s).ok()) {
+fn remap_priority(priority: &mut String) {
+    if let Ok(num) = usize::from_str(priority) {
         let text = match num {
             0 => "EMERG",
             1 => "ALERT",
@@ -449,7 +487,7 @@ fn remap_priority(priority: &mut JsonValue) {
             7 => "DEBUG",
             _ => "UNKNOWN",
         };
-        *priority = JsonValue::String(text.into());
+        *priority = text.into();
     }
 }
 
@@ -468,6 +506,141 @@ fn filter_unit(
     }
 }
 
+/// The comparison operator of a field match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MatchOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A single journal field match, like `_TRANSPORT=kernel` or `PRIORITY<=4`.
+#[derive(Debug, Clone, PartialEq)]
+struct FieldMatch {
+    field: Atom,
+    op: MatchOp,
+    value: String,
+}
+
+impl FromStr for FieldMatch {
+    type Err = BuildError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = || BuildError::InvalidMatch {
+            expression: expression.into(),
+        };
+        let index = expression
+            .find(|c| "=!<>".contains(c))
+            .ok_or_else(invalid)?;
+        let (field, rest) = expression.split_at(index);
+        let (op, value) = [
+            ("!=", MatchOp::Ne),
+            ("<=", MatchOp::Le),
+            (">=", MatchOp::Ge),
+            ("=", MatchOp::Eq),
+            ("<", MatchOp::Lt),
+            (">", MatchOp::Gt),
+        ]
+        .iter()
+        .find(|(token, _)| rest.starts_with(token))
+        .map(|(token, op)| (*op, &rest[token.len()..]))
+        .ok_or_else(invalid)?;
+        if field.is_empty() {
+            return Err(invalid());
+        }
+        Ok(FieldMatch {
+            field: field.into(),
+            op,
+            value: value.into(),
+        })
+    }
+}
+
+impl FieldMatch {
+    /// Does the given record match? Ordering comparisons are numeric when
+    /// both sides are integers, and lexicographic otherwise.
+    fn matches(&self, record: &Record) -> bool {
+        let value = match record.get(&self.field) {
+            Some(value) => value,
+            None => return self.op == MatchOp::Ne,
+        };
+        let ordering = match (value.parse::<i64>(), self.value.parse::<i64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => value.as_str().cmp(self.value.as_str()),
+        };
+        match self.op {
+            MatchOp::Eq => value == &self.value,
+            MatchOp::Ne => value != &self.value,
+            MatchOp::Lt => ordering == Ordering::Less,
+            MatchOp::Le => ordering != Ordering::Greater,
+            MatchOp::Gt => ordering == Ordering::Greater,
+            MatchOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// The configured field matches. Like with `journalctl`, include matches for
+/// the same field are ORed together and matches for different fields are
+/// ANDed; a record matching any of the exclude matches is dropped.
+#[derive(Debug, Default)]
+struct Matches {
+    includes: HashMap<Atom, Vec<FieldMatch>>,
+    excludes: Vec<FieldMatch>,
+}
+
+impl Matches {
+    fn new(includes: &[String], excludes: &[String]) -> Result<Self, BuildError> {
+        let mut matches = Matches::default();
+        for expression in includes {
+            let field_match = expression.parse::<FieldMatch>()?;
+            matches
+                .includes
+                .entry(field_match.field.clone())
+                .or_default()
+                .push(field_match);
+        }
+        for expression in excludes {
+            matches.excludes.push(expression.parse()?);
+        }
+        Ok(matches)
+    }
+
+    /// The include matches `journalctl` can apply itself, so that it doesn't
+    /// output entries only to have them dropped. These are the fields whose
+    /// matches are all equality matches, which `journalctl` combines the same
+    /// way. All of the matches are still evaluated on the records read.
+    fn journalctl_args(&self) -> Vec<String> {
+        let mut args = self
+            .includes
+            .values()
+            .filter(|group| {
+                group
+                    .iter()
+                    .all(|field_match| field_match.op == MatchOp::Eq)
+            })
+            .flatten()
+            .map(|field_match| format!("{}={}", field_match.field, field_match.value))
+            .collect::<Vec<_>>();
+        args.sort();
+        args
+    }
+
+    /// Should the given record be filtered (excluded)?
+    fn filter(&self, record: &Record) -> bool {
+        !self
+            .includes
+            .values()
+            .all(|group| group.iter().any(|field_match| field_match.matches(record)))
+            || self
+                .excludes
+                .iter()
+                .any(|field_match| field_match.matches(record))
+    }
+}
+
 const CHECKPOINT_FILENAME: &str = "checkpoint.txt";
 
 struct Checkpointer {
@@ -594,6 +767,7 @@ mod tests {
     impl JournalSource for FakeJournal {
         fn new(
             _: &JournaldConfig,
+            _: &Matches,
             checkpoint: Option<String>,
         ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)> {
             let cursor = Cursor::new(FAKE_JOURNAL);
@@ -613,6 +787,15 @@ mod tests {
     }
 
     fn run_journal(iunits: &[&str], xunits: &[&str], cursor: Option<&str>) -> Vec<Event> {
+        run_journal_with_matches(iunits, xunits, Matches::default(), cursor)
+    }
+
+    fn run_journal_with_matches(
+        iunits: &[&str],
+        xunits: &[&str],
+        matches: Matches,
+        cursor: Option<&str>,
+    ) -> Vec<Event> {
         let (tx, rx) = Pipeline::new_test();
         let (trigger, shutdown, _) = ShutdownSignal::new_wired();
         let tempdir = tempdir().unwrap();
@@ -633,6 +816,7 @@ mod tests {
                 checkpointer,
                 include_units,
                 exclude_units,
+                matches,
                 DEFAULT_BATCH_SIZE,
                 true,
             )
@@ -721,6 +905,86 @@ mod tests {
         assert_eq!(timestamp(&received[1]), value_ts(1578529839, 140005000));
     }
 
+    fn make_matches(includes: &[&str], excludes: &[&str]) -> Matches {
+        let to_owned = |v: &[&str]| v.iter().map(|&s| s.into()).collect::<Vec<String>>();
+        Matches::new(&to_owned(includes), &to_owned(excludes)).unwrap()
+    }
+
+    #[test]
+    fn includes_matches() {
+        let matches = make_matches(&["PRIORITY<=3"], &[]);
+        let received = run_journal_with_matches(&[], &[], matches, None);
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            message(&received[0]),
+            Value::Bytes("Missing timestamp".into())
+        );
+        // Priority is remapped after matching.
+        assert_eq!(priority(&received[0]), Value::Bytes("CRIT".into()));
+        assert_eq!(priority(&received[1]), Value::Bytes("ERR".into()));
+    }
+
+    #[test]
+    fn includes_matches_ors_same_field() {
+        let matches = make_matches(
+            &["PRIORITY=6", "PRIORITY=7", "_SYSTEMD_UNIT!=sysinit.target"],
+            &[],
+        );
+        let received = run_journal_with_matches(&[], &[], matches, None);
+        assert_eq!(received.len(), 1);
+        assert_eq!(message(&received[0]), Value::Bytes("unit message".into()));
+    }
+
+    #[test]
+    fn excludes_matches() {
+        let matches = make_matches(&[], &["_SYSTEMD_UNIT=stdout", "PRIORITY>6"]);
+        let received = run_journal_with_matches(&[], &[], matches, None);
+        assert_eq!(received.len(), 2);
+        assert_eq!(
+            message(&received[0]),
+            Value::Bytes("System Initialization".into())
+        );
+        assert_eq!(message(&received[1]), Value::Bytes("¿Hello?".into()));
+    }
+
+    #[test]
+    fn passes_equality_matches_to_journalctl() {
+        let matches = make_matches(
+            &[
+                "_TRANSPORT=kernel",
+                "_TRANSPORT=syslog",
+                "PRIORITY=3",
+                "PRIORITY<=1",
+                "_SYSTEMD_UNIT!=sysinit.target",
+            ],
+            &["_SYSTEMD_UNIT=stdout"],
+        );
+        assert_eq!(
+            matches.journalctl_args(),
+            vec!["_TRANSPORT=kernel", "_TRANSPORT=syslog"]
+        );
+    }
+
+    #[test]
+    fn parses_field_matches() {
+        let field_match = "PRIORITY<=4".parse::<FieldMatch>().unwrap();
+        assert_eq!(field_match.field, Atom::from("PRIORITY"));
+        assert_eq!(field_match.op, MatchOp::Le);
+        assert_eq!(field_match.value, "4");
+
+        let field_match = "_TRANSPORT!=kernel".parse::<FieldMatch>().unwrap();
+        assert_eq!(field_match.op, MatchOp::Ne);
+        assert_eq!(field_match.value, "kernel");
+
+        let field_match = "MESSAGE=a=b".parse::<FieldMatch>().unwrap();
+        assert_eq!(field_match.op, MatchOp::Eq);
+        assert_eq!(field_match.value, "a=b");
+
+        assert!("PRIORITY".parse::<FieldMatch>().is_err());
+        assert!("=4".parse::<FieldMatch>().is_err());
+        assert!("PRIORITY!4".parse::<FieldMatch>().is_err());
+    }
+
     #[test]
     fn filter_unit_works_correctly() {
         let empty: HashSet<String> = vec![].into_iter().collect();
@@ -1,26 +1,39 @@
 use crate::{
-    event::{self, Event},
-    internal_events::{KafkaEventFailed, KafkaEventReceived, KafkaOffsetUpdateFailed},
+    event::{self, Event, Value},
+    internal_events::{
+        DecoderParseFailed, KafkaEventFailed, KafkaEventReceived, KafkaOffsetUpdateFailed,
+        KafkaStartOffsetLookupFailed,
+    },
     kafka::KafkaAuthConfig,
     shutdown::ShutdownSignal,
+    sources::util::{decoding::Decoder, DecodingConfig, FramingConfig},
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Pipeline,
 };
-use bytes::Bytes;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use futures::{
     compat::{Compat, Future01CompatExt},
+    future::{self, Either},
     FutureExt, StreamExt,
 };
-use futures01::Sink;
+use futures01::{stream, Sink};
 use rdkafka::{
+    client::{ClientContext, NativeClient},
     config::ClientConfig,
-    consumer::{Consumer, StreamConsumer},
-    message::Message,
+    consumer::{Consumer, ConsumerContext, DefaultConsumerContext, StreamConsumer},
+    error::KafkaResult,
+    message::{BorrowedMessage, Headers, Message},
+    topic_partition_list::{Offset, TopicPartitionList},
+    types::RDKafkaRespErr,
 };
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::spawn_blocking;
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -38,6 +51,7 @@ pub struct KafkaSourceConfig {
     group_id: String,
     #[serde(default = "default_auto_offset_reset")]
     auto_offset_reset: String,
+    start_from_timestamp: Option<DateTime<Utc>>,
     #[serde(default = "default_session_timeout_ms")]
     session_timeout_ms: u64,
     #[serde(default = "default_socket_timeout_ms")]
@@ -47,9 +61,17 @@ pub struct KafkaSourceConfig {
     #[serde(default = "default_commit_interval_ms")]
     commit_interval_ms: u64,
     key_field: Option<String>,
+    topic_field: Option<String>,
+    partition_field: Option<String>,
+    offset_field: Option<String>,
+    timestamp_field: Option<String>,
+    headers_field: Option<String>,
     librdkafka_options: Option<HashMap<String, String>>,
     #[serde(flatten)]
     auth: KafkaAuthConfig,
+    framing: Option<FramingConfig>,
+    #[serde(default)]
+    decoding: DecodingConfig,
 }
 
 fn default_session_timeout_ms() -> u64 {
@@ -97,95 +119,286 @@ impl SourceConfig for KafkaSourceConfig {
     }
 }
 
+/// Names of the optional log fields the Kafka message metadata is stored under.
+#[derive(Clone, Debug, Default)]
+struct MetadataFields {
+    key: Option<String>,
+    topic: Option<String>,
+    partition: Option<String>,
+    offset: Option<String>,
+    timestamp: Option<String>,
+    headers: Option<String>,
+}
+
+impl MetadataFields {
+    fn new(config: &KafkaSourceConfig) -> Self {
+        Self {
+            key: config.key_field.clone(),
+            topic: config.topic_field.clone(),
+            partition: config.partition_field.clone(),
+            offset: config.offset_field.clone(),
+            timestamp: config.timestamp_field.clone(),
+            headers: config.headers_field.clone(),
+        }
+    }
+}
+
 fn kafka_source(
     config: &KafkaSourceConfig,
     shutdown: ShutdownSignal,
     out: Pipeline,
 ) -> crate::Result<super::Source> {
-    let key_field = config.key_field.clone();
-    let consumer = Arc::new(create_consumer(config)?);
+    let fields = MetadataFields::new(config);
+    let start_from = config.start_from_timestamp;
+    let topics = config.topics.clone();
+    let timeout = Duration::from_millis(config.socket_timeout_ms);
+    let start_offsets = StartOffsets::default();
+    let context = KafkaSourceContext {
+        start_offsets: Arc::clone(&start_offsets),
+    };
+    let consumer = Arc::new(create_consumer(config, context)?);
+    let framing = config.framing.clone().unwrap_or(FramingConfig::Bytes);
+    let decoder = Decoder::new(framing.build(usize::MAX)?, config.decoding.build()?);
 
     let fut = async move {
-        Arc::clone(&consumer)
-            .start()
-            .take_until(shutdown.clone().compat())
-            .then(move |message| {
-                let key_field = key_field.clone();
-                let consumer = Arc::clone(&consumer);
-
-                async move {
-                    match message {
-                        Err(error) => {
-                            emit!(KafkaEventFailed { error });
-                            Err(())
-                        }
-                        Ok(msg) => {
-                            emit!(KafkaEventReceived {
-                                byte_size: msg.payload_len()
-                            });
-
-                            let payload = match msg.payload() {
-                                None => return Err(()), // skip messages with empty payload
-                                Some(payload) => Bytes::from(payload),
-                            };
-                            let mut event = Event::new_empty_log();
-                            let log = event.as_mut_log();
-
-                            log.insert(event::log_schema().message_key().clone(), payload);
-
-                            // Extract timestamp from kafka message
-                            let timestamp = msg
-                                .timestamp()
-                                .to_millis()
-                                .and_then(|millis| Utc.timestamp_millis_opt(millis).latest())
-                                .unwrap_or_else(Utc::now);
-                            log.insert(event::log_schema().timestamp_key().clone(), timestamp);
-
-                            // Add source type
-                            log.insert(event::log_schema().source_type_key(), "kafka");
-
-                            if let Some(key_field) = &key_field {
-                                match msg.key() {
-                                    None => (),
-                                    Some(key) => {
-                                        log.insert(key_field.clone(), key);
-                                    }
-                                }
-                            }
-
-                            consumer.store_offset(&msg).map_err(|error| {
-                                emit!(KafkaOffsetUpdateFailed { error });
-                            })?;
-
-                            Ok(event)
-                        }
-                    }
+        if let Some(timestamp) = start_from {
+            // The lookups block for up to the socket timeout each, and have
+            // to finish before the stream is started, as polling it is what
+            // assigns the partitions.
+            let lookup_consumer = Arc::clone(&consumer);
+            let lookup = spawn_blocking(move || {
+                lookup_start_offsets(&lookup_consumer, &topics, timestamp, timeout)
+            });
+            match future::select(lookup, shutdown.clone().compat()).await {
+                Either::Left((Ok(Ok(offsets)), _)) => *start_offsets.lock().unwrap() = offsets,
+                Either::Left((Ok(Err(error)), _)) => emit!(KafkaStartOffsetLookupFailed { error }),
+                Either::Left((Err(error), _)) => panic!("Start offset lookup failed: {}", error),
+                Either::Right(_) => return Ok(()),
+            }
+        }
+
+        let mut stream = Box::pin(consumer.start().take_until(shutdown.compat()));
+
+        while let Some(message) = stream.next().await {
+            let msg = match message {
+                Err(error) => {
+                    emit!(KafkaEventFailed { error });
+                    continue;
                 }
-            })
-            // Try `forward` after removing old futures.
-            // Error: implementation of `futures_core::stream::Stream` is not general enough
-            // .forward(
-            //     out.sink_compat()
-            //         .sink_map_err(|e| error!(message = "Error sending to sink", error = ?e)),
-            // )
-            .for_each(|item| {
-                let out = out.clone();
-                async move {
-                    if let Ok(item) = item {
-                        if let Err(e) = out.send(item).compat().await {
-                            error!(message = "Error sending to sink", error = ?e);
+                Ok(msg) => msg,
+            };
+
+            emit!(KafkaEventReceived {
+                byte_size: msg.payload_len()
+            });
+
+            let events = msg_to_events(&msg, &fields, &decoder);
+            if events.is_empty() {
+                // Skip messages with an empty payload or that failed to decode,
+                // their offset is stored along with the next message.
+                continue;
+            }
+
+            let events = stream::iter_ok(events);
+            match out.clone().send_all(events).compat().await {
+                // Offsets are only stored once the event has been handed off
+                // downstream, so anything not yet forwarded is replayed on restart.
+                Ok(_) => match consumer.store_offset(&msg) {
+                    // Once an offset is stored it is committed, even when the
+                    // partition is revoked, so its start offset is done with.
+                    Ok(()) => {
+                        let mut start_offsets = start_offsets.lock().unwrap();
+                        if !start_offsets.is_empty() {
+                            start_offsets.remove(&(msg.topic().to_string(), msg.partition()));
                         }
                     }
+                    Err(error) => emit!(KafkaOffsetUpdateFailed { error }),
+                },
+                Err(error) => {
+                    error!(message = "Error sending to sink", ?error);
+                    break;
                 }
-            })
-            .await;
+            }
+        }
+
         Ok(())
     };
 
     Ok(Box::new(Compat::new(fut.boxed())))
 }
 
-fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer> {
+fn msg_to_events(
+    msg: &BorrowedMessage<'_>,
+    fields: &MetadataFields,
+    decoder: &Decoder,
+) -> Vec<Event> {
+    let payload = match msg.payload() {
+        Some(payload) => payload,
+        None => return Vec::new(),
+    };
+
+    // Extract timestamp from kafka message
+    let msg_timestamp = msg
+        .timestamp()
+        .to_millis()
+        .and_then(|millis| Utc.timestamp_millis_opt(millis).latest());
+
+    let mut headers = BTreeMap::new();
+    if fields.headers.is_some() {
+        if let Some(msg_headers) = msg.headers() {
+            for index in 0..msg_headers.count() {
+                if let Some((key, value)) = msg_headers.get(index) {
+                    headers.insert(key.to_string(), Value::from(value));
+                }
+            }
+        }
+    }
+
+    decoder
+        .decode_payload(payload)
+        .into_iter()
+        .filter_map(|event| match event {
+            Ok(event) => Some(event),
+            Err(error) => {
+                emit!(DecoderParseFailed {
+                    component_type: "kafka",
+                    error: &error,
+                });
+                None
+            }
+        })
+        .map(|mut event| {
+            let log = event.as_mut_log();
+
+            if let Some(timestamp) = msg_timestamp {
+                log.insert(event::log_schema().timestamp_key().clone(), timestamp);
+            }
+
+            // Add source type
+            log.insert(event::log_schema().source_type_key(), "kafka");
+
+            if let (Some(key_field), Some(key)) = (&fields.key, msg.key()) {
+                log.insert(key_field.clone(), key);
+            }
+
+            if let Some(topic_field) = &fields.topic {
+                log.insert(topic_field.clone(), msg.topic());
+            }
+
+            if let Some(partition_field) = &fields.partition {
+                log.insert(partition_field.clone(), msg.partition());
+            }
+
+            if let Some(offset_field) = &fields.offset {
+                log.insert(offset_field.clone(), msg.offset());
+            }
+
+            if let (Some(timestamp_field), Some(timestamp)) = (&fields.timestamp, msg_timestamp) {
+                log.insert(timestamp_field.clone(), timestamp);
+            }
+
+            if let Some(headers_field) = &fields.headers {
+                log.insert(headers_field.clone(), headers.clone());
+            }
+
+            event
+        })
+        .collect()
+}
+
+/// Offsets to start the partitions that have no committed offset for the
+/// consumer group at, from the first message at or after the configured
+/// timestamp.
+type StartOffsets = Arc<Mutex<HashMap<(String, i32), Offset>>>;
+
+/// Applies the start offsets when partitions are assigned to the consumer,
+/// so that nothing is fetched from them at other offsets beforehand.
+struct KafkaSourceContext {
+    start_offsets: StartOffsets,
+}
+
+impl ClientContext for KafkaSourceContext {}
+
+impl ConsumerContext for KafkaSourceContext {
+    fn rebalance(
+        &self,
+        native_client: &NativeClient,
+        err: RDKafkaRespErr,
+        tpl: &mut TopicPartitionList,
+    ) {
+        if let RDKafkaRespErr::RD_KAFKA_RESP_ERR__ASSIGN_PARTITIONS = err {
+            apply_start_offsets(&self.start_offsets.lock().unwrap(), tpl);
+        }
+        // The default context assigns the partitions at the offsets in `tpl`.
+        DefaultConsumerContext.rebalance(native_client, err, tpl)
+    }
+}
+
+fn apply_start_offsets(
+    start_offsets: &HashMap<(String, i32), Offset>,
+    tpl: &mut TopicPartitionList,
+) {
+    for ((topic, partition), offset) in start_offsets {
+        if tpl.find_partition(topic, *partition).is_some() {
+            debug!(message = "starting partition at offset.", %topic, %partition, ?offset);
+            tpl.set_partition_offset(topic, *partition, *offset)
+                .expect("Partition is in the list");
+        }
+    }
+}
+
+/// Looks up the start offsets of the partitions of the topics that have no
+/// committed offset for the consumer group.
+fn lookup_start_offsets<C: ConsumerContext>(
+    consumer: &StreamConsumer<C>,
+    topics: &[String],
+    timestamp: DateTime<Utc>,
+    timeout: Duration,
+) -> KafkaResult<HashMap<(String, i32), Offset>> {
+    let mut partitions = TopicPartitionList::new();
+    for topic in topics {
+        let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+        for topic in metadata.topics() {
+            for partition in topic.partitions() {
+                partitions.add_partition(topic.name(), partition.id());
+            }
+        }
+    }
+
+    let committed = consumer.committed_offsets(partitions, timeout)?;
+    let mut timestamps = TopicPartitionList::new();
+    for elem in committed.elements() {
+        if !matches!(elem.offset(), Offset::Offset(_)) {
+            timestamps.add_partition_offset(
+                elem.topic(),
+                elem.partition(),
+                Offset::Offset(timestamp.timestamp_millis()),
+            );
+        }
+    }
+    if timestamps.count() == 0 {
+        return Ok(HashMap::new());
+    }
+
+    let offsets = consumer.offsets_for_times(timestamps, timeout)?;
+    Ok(offsets
+        .elements()
+        .into_iter()
+        .map(|elem| {
+            let offset = match elem.offset() {
+                Offset::Offset(offset) => Offset::Offset(offset),
+                // No message at or after the timestamp, start from the end.
+                _ => Offset::End,
+            };
+            ((elem.topic().to_string(), elem.partition()), offset)
+        })
+        .collect())
+}
+
+fn create_consumer(
+    config: &KafkaSourceConfig,
+    context: KafkaSourceContext,
+) -> crate::Result<StreamConsumer<KafkaSourceContext>> {
     let mut client_config = ClientConfig::new();
     client_config
         .set("group.id", &config.group_id)
@@ -211,7 +424,9 @@ fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer>
         }
     }
 
-    let consumer: StreamConsumer = client_config.create().context(KafkaCreateError)?;
+    let consumer: StreamConsumer<KafkaSourceContext> = client_config
+        .create_with_context(context)
+        .context(KafkaCreateError)?;
     let topics: Vec<&str> = config.topics.iter().map(|s| s.as_str()).collect();
     consumer.subscribe(&topics).context(KafkaSubscribeError)?;
 
@@ -220,8 +435,15 @@ fn create_consumer(config: &KafkaSourceConfig) -> crate::Result<StreamConsumer>
 
 #[cfg(test)]
 mod test {
-    use super::{kafka_source, KafkaSourceConfig};
-    use crate::{shutdown::ShutdownSignal, Pipeline};
+    use super::{apply_start_offsets, kafka_source, KafkaSourceConfig};
+    use crate::{
+        shutdown::ShutdownSignal,
+        sources::util::{DecodingConfig, FramingConfig},
+        Pipeline,
+    };
+    use chrono::{TimeZone, Utc};
+    use rdkafka::topic_partition_list::{Offset, TopicPartitionList};
+    use std::collections::HashMap;
 
     fn make_config() -> KafkaSourceConfig {
         KafkaSourceConfig {
@@ -252,6 +474,67 @@ mod test {
         };
         assert!(kafka_source(&config, ShutdownSignal::noop(), Pipeline::new_test().0).is_err());
     }
+
+    #[test]
+    fn kafka_source_parse_metadata_fields() {
+        let config: KafkaSourceConfig = toml::from_str(
+            r#"
+            bootstrap_servers = "localhost:9092"
+            topics = ["my-topic"]
+            group_id = "group-id"
+            start_from_timestamp = "2020-08-01T12:00:00Z"
+            topic_field = "topic"
+            partition_field = "partition"
+            offset_field = "offset"
+            timestamp_field = "kafka_timestamp"
+            headers_field = "headers"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.start_from_timestamp,
+            Some(Utc.ymd(2020, 8, 1).and_hms(12, 0, 0))
+        );
+        assert_eq!(config.headers_field, Some("headers".to_string()));
+        assert!(kafka_source(&config, ShutdownSignal::noop(), Pipeline::new_test().0).is_ok());
+    }
+
+    #[test]
+    fn kafka_source_applies_start_offsets_on_assignment() {
+        let mut start_offsets = HashMap::new();
+        start_offsets.insert(("my-topic".to_string(), 0), Offset::Offset(42));
+        start_offsets.insert(("my-topic".to_string(), 1), Offset::End);
+        start_offsets.insert(("other-topic".to_string(), 0), Offset::Offset(7));
+
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition("my-topic", 0);
+        tpl.add_partition("my-topic", 1);
+        tpl.add_partition("my-topic", 2);
+        apply_start_offsets(&start_offsets, &mut tpl);
+
+        let offset = |partition| tpl.find_partition("my-topic", partition).unwrap().offset();
+        assert_eq!(offset(0), Offset::Offset(42));
+        assert_eq!(offset(1), Offset::End);
+        assert_eq!(offset(2), Offset::Invalid);
+        assert!(tpl.find_partition("other-topic", 0).is_none());
+    }
+
+    #[test]
+    fn kafka_source_parse_decoding() {
+        let config: KafkaSourceConfig = toml::from_str(
+            r#"
+            bootstrap_servers = "localhost:9092"
+            topics = ["my-topic"]
+            group_id = "group-id"
+            framing.method = "newline_delimited"
+            decoding.codec = "json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.framing, Some(FramingConfig::NewlineDelimited));
+        assert_eq!(config.decoding, DecodingConfig::Json);
+        assert!(kafka_source(&config, ShutdownSignal::noop(), Pipeline::new_test().0).is_ok());
+    }
 }
 
 #[cfg(feature = "kafka-integration-tests")]
@@ -264,12 +547,14 @@ mod integration_test {
         test_util::{collect_n, random_string, runtime},
         Pipeline,
     };
-    use chrono::Utc;
+    use chrono::{Duration, Utc};
     use rdkafka::{
         config::ClientConfig,
+        message::OwnedHeaders,
         producer::{FutureProducer, FutureRecord},
         util::Timeout,
     };
+    use std::collections::BTreeMap;
     use string_cache::DefaultAtom as Atom;
 
     const BOOTSTRAP_SERVER: &str = "localhost:9092";
@@ -285,7 +570,8 @@ mod integration_test {
         let record = FutureRecord::to(&topic)
             .payload(text)
             .key(key)
-            .timestamp(timestamp);
+            .timestamp(timestamp)
+            .headers(OwnedHeaders::new().add("my header", "my header value"));
 
         if let Err(err) = producer.send(record, Timeout::Never).await {
             panic!("Cannot send event to Kafka: {:?}", err);
@@ -308,6 +594,10 @@ mod integration_test {
             session_timeout_ms: 6000,
             commit_interval_ms: 5000,
             key_field: Some("message_key".to_string()),
+            topic_field: Some("topic".to_string()),
+            partition_field: Some("partition".to_string()),
+            offset_field: Some("offset".to_string()),
+            headers_field: Some("headers".to_string()),
             socket_timeout_ms: 60000,
             fetch_wait_max_ms: 100,
             ..Default::default()
@@ -341,5 +631,56 @@ mod integration_test {
             events[0].as_log()[event::log_schema().timestamp_key()],
             now.into()
         );
+        assert_eq!(events[0].as_log()[&Atom::from("topic")], topic.into());
+        assert_eq!(events[0].as_log()[&Atom::from("partition")], 0.into());
+        assert_eq!(events[0].as_log()[&Atom::from("offset")], 0.into());
+        let mut headers = BTreeMap::new();
+        headers.insert("my header".to_string(), "my header value".into());
+        assert_eq!(events[0].as_log()[&Atom::from("headers")], headers.into());
+    }
+
+    #[test]
+    #[ignore]
+    fn kafka_source_starts_from_timestamp() {
+        let topic = format!("test-topic-{}", random_string(10));
+        let group_id = format!("test-group-{}", random_string(10));
+        let now = Utc::now();
+
+        let mut rt = runtime();
+        for i in 0..10 {
+            // The first half of the messages are from before the start timestamp.
+            let timestamp = if i < 5 { now - Duration::hours(1) } else { now };
+            rt.block_on_std(send_event(
+                topic.clone(),
+                "key",
+                &format!("message {}", i),
+                timestamp.timestamp_millis(),
+            ));
+        }
+
+        // Without a start offset the consumer would fetch the earlier
+        // messages first, as it resets to the beginning of the partition.
+        let config = KafkaSourceConfig {
+            bootstrap_servers: BOOTSTRAP_SERVER.into(),
+            topics: vec![topic.clone()],
+            group_id,
+            auto_offset_reset: "beginning".into(),
+            start_from_timestamp: Some(now - Duration::minutes(30)),
+            session_timeout_ms: 6000,
+            commit_interval_ms: 5000,
+            offset_field: Some("offset".to_string()),
+            socket_timeout_ms: 60000,
+            fetch_wait_max_ms: 100,
+            ..Default::default()
+        };
+
+        let (tx, rx) = Pipeline::new_test();
+        rt.spawn(kafka_source(&config, ShutdownSignal::noop(), tx).unwrap());
+        let events = rt.block_on(collect_n(rx, 5)).ok().unwrap();
+        let offsets = events
+            .iter()
+            .map(|event| event.as_log()[&Atom::from("offset")].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, (5i64..10).map(Into::into).collect::<Vec<_>>());
     }
 }
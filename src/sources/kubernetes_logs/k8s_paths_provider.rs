@@ -2,23 +2,62 @@
 
 #![deny(missing_docs)]
 
-use super::path_helpers::build_pod_logs_directory;
-use crate::kubernetes as k8s;
+use super::path_helpers::{build_pod_logs_directory, parse_log_file_path};
+use crate::{kubernetes as k8s, sources::util::GlobFilter};
 use evmap10::ReadHandle;
 use file_source::paths_provider::PathsProvider;
 use k8s_openapi::api::core::v1::Pod;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// The annotation that allows opting individual pods out of log collection.
+const EXCLUDE_ANNOTATION: &str = "vector.dev/exclude";
+
+/// Filters applied to pods and containers before their log files are
+/// provided.
+#[derive(Debug, Clone, Default)]
+pub struct PathsFilter {
+    /// Filter for the pod namespaces.
+    pub namespaces: GlobFilter,
+    /// Filter for the pod names.
+    pub pod_names: GlobFilter,
+    /// Filter for the container names.
+    pub container_names: GlobFilter,
+}
+
+impl PathsFilter {
+    fn pod_matches(&self, pod: &Pod) -> bool {
+        let metadata = &pod.metadata;
+        let namespace = metadata.namespace.as_deref().unwrap_or_default();
+        let name = metadata.name.as_deref().unwrap_or_default();
+        self.namespaces.matches(namespace) && self.pod_names.matches(name)
+    }
+
+    fn path_matches(&self, path: &Path) -> bool {
+        path.to_str()
+            .and_then(parse_log_file_path)
+            .map_or(false, |info| {
+                self.container_names.matches(info.container_name)
+            })
+    }
+}
 
 /// A paths provider implementation that uses the state obtained from the
 /// the k8s API.
 pub struct K8sPathsProvider {
     pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+    filter: PathsFilter,
 }
 
 impl K8sPathsProvider {
     /// Create a new [`K8sPathsProvider`].
-    pub fn new(pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>) -> Self {
-        Self { pods_state_reader }
+    pub fn new(
+        pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+        filter: PathsFilter,
+    ) -> Self {
+        Self {
+            pods_state_reader,
+            filter,
+        }
     }
 }
 
@@ -45,13 +84,29 @@ impl PathsProvider for K8sPathsProvider {
                 let pod = values
                     .get_one()
                     .expect("we are supposed to be working with single-item values only");
+                if is_excluded(pod) || !self.filter.pod_matches(pod) {
+                    trace!(message = "skipping excluded pod", ?uid);
+                    return None;
+                }
                 trace!(message = "providing log paths for pod", ?uid);
-                list_pod_log_paths(real_glob, pod)
+                Some(list_pod_log_paths(real_glob, pod))
             })
+            .flatten()
+            .filter(|path| self.filter.path_matches(path))
             .collect()
     }
 }
 
+/// Checks whether the pod has opted out of log collection via the
+/// [`EXCLUDE_ANNOTATION`] annotation.
+fn is_excluded(pod: &Pod) -> bool {
+    pod.metadata
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(EXCLUDE_ANNOTATION))
+        .map_or(false, |value| value == "true")
+}
+
 fn extract_pod_logs_directory(pod: &Pod) -> Option<PathBuf> {
     let metadata = &pod.metadata;
     let namespace = metadata.namespace.as_ref()?;
@@ -93,7 +148,9 @@ fn real_glob(pattern: &str) -> impl Iterator<Item = PathBuf> {
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_pod_logs_directory, list_pod_log_paths};
+    use super::{
+        extract_pod_logs_directory, is_excluded, list_pod_log_paths, GlobFilter, PathsFilter,
+    };
     use k8s_openapi::{api::core::v1::Pod, apimachinery::pkg::apis::meta::v1::ObjectMeta};
     use std::path::PathBuf;
 
@@ -156,6 +213,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_excluded() {
+        let cases = vec![
+            (None, false),
+            (Some(vec![]), false),
+            (Some(vec![("vector.dev/exclude", "false")]), false),
+            (Some(vec![("vector.dev/exclude", "true")]), true),
+            (Some(vec![("some.other/annotation", "true")]), false),
+        ];
+
+        for (annotations, expected) in cases {
+            let pod = Pod {
+                metadata: ObjectMeta {
+                    annotations: annotations.map(|annotations| {
+                        annotations
+                            .into_iter()
+                            .map(|(key, val)| (key.to_owned(), val.to_owned()))
+                            .collect()
+                    }),
+                    ..ObjectMeta::default()
+                },
+                ..Pod::default()
+            };
+            assert_eq!(is_excluded(&pod), expected);
+        }
+    }
+
+    fn make_filter(includes: &[&str], excludes: &[&str]) -> GlobFilter {
+        let to_owned = |patterns: &[&str]| -> Vec<String> {
+            patterns
+                .iter()
+                .map(|pattern| (*pattern).to_owned())
+                .collect()
+        };
+        GlobFilter::new(&to_owned(includes), &to_owned(excludes)).unwrap()
+    }
+
+    #[test]
+    fn test_paths_filter() {
+        let filter = PathsFilter {
+            namespaces: make_filter(&[], &["kube-system"]),
+            pod_names: make_filter(&["sandbox*"], &[]),
+            container_names: make_filter(&[], &["istio-proxy"]),
+        };
+
+        let make_pod = |namespace: &str, name: &str| Pod {
+            metadata: ObjectMeta {
+                namespace: Some(namespace.to_owned()),
+                name: Some(name.to_owned()),
+                ..ObjectMeta::default()
+            },
+            ..Pod::default()
+        };
+
+        assert!(filter.pod_matches(&make_pod("sandbox0-ns", "sandbox0-name")));
+        assert!(!filter.pod_matches(&make_pod("kube-system", "sandbox0-name")));
+        assert!(!filter.pod_matches(&make_pod("sandbox0-ns", "other-name")));
+
+        assert!(filter.path_matches(&PathBuf::from(
+            "/var/log/pods/sandbox0-ns_sandbox0-name_sandbox0-uid/container1/0.log"
+        )));
+        assert!(!filter.path_matches(&PathBuf::from(
+            "/var/log/pods/sandbox0-ns_sandbox0-name_sandbox0-uid/istio-proxy/0.log"
+        )));
+    }
+
     #[test]
     fn test_list_pod_log_paths() {
         let cases = vec![
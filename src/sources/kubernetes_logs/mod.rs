@@ -11,16 +11,16 @@ use crate::kubernetes as k8s;
 use crate::{
     dns::Resolver,
     shutdown::ShutdownSignal,
-    sources,
+    sources::{self, util::GlobFilter},
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     transforms::Transform,
     Pipeline,
 };
 use bytes05::Bytes;
 use evmap10::{self as evmap};
-use file_source::{FileServer, FileServerShutdown, Fingerprinter};
+use file_source::{FileServer, FileServerShutdown, Fingerprinter, Line};
 use futures::{future::FutureExt, sink::Sink, stream::StreamExt};
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{Namespace, Pod};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
@@ -34,7 +34,7 @@ mod pod_metadata_annotator;
 mod transform_utils;
 mod util;
 
-use k8s_paths_provider::K8sPathsProvider;
+use k8s_paths_provider::{K8sPathsProvider, PathsFilter};
 use lifecycle::Lifecycle;
 use pod_metadata_annotator::PodMetadataAnnotator;
 
@@ -60,6 +60,35 @@ pub struct Config {
 
     /// Specifies the field names for metadata annotation.
     annotation_fields: pod_metadata_annotator::FieldsSpec,
+
+    /// Specifies the label selector to filter `Pod`s with, to be used in
+    /// addition to the built-in `vector.dev/exclude` filter.
+    label_selector: Option<String>,
+
+    /// Specifies the field selector to filter `Pod`s with, to be used in
+    /// addition to the built-in `Node` filter.
+    field_selector: Option<String>,
+
+    /// A list of glob patterns for the `Pod` namespaces to collect logs from.
+    /// If empty, all namespaces are included.
+    include_namespaces: Vec<String>,
+
+    /// A list of glob patterns for the `Pod` namespaces to skip.
+    exclude_namespaces: Vec<String>,
+
+    /// A list of glob patterns for the `Pod` names to collect logs from.
+    /// If empty, all pods are included.
+    include_pod_names: Vec<String>,
+
+    /// A list of glob patterns for the `Pod` names to skip.
+    exclude_pod_names: Vec<String>,
+
+    /// A list of glob patterns for the container names to collect logs from.
+    /// If empty, all containers are included.
+    include_container_names: Vec<String>,
+
+    /// A list of glob patterns for the container names to skip.
+    exclude_container_names: Vec<String>,
 }
 
 inventory::submit! {
@@ -107,10 +136,12 @@ impl SourceConfig for Config {
 #[derive(Clone)]
 struct Source {
     client: k8s::client::Client,
-    self_node_name: String,
     data_dir: PathBuf,
     auto_partial_merge: bool,
     fields_spec: pod_metadata_annotator::FieldsSpec,
+    field_selector: String,
+    label_selector: String,
+    paths_filter: PathsFilter,
 }
 
 impl Source {
@@ -142,12 +173,18 @@ impl Source {
 
         let data_dir = globals.resolve_and_make_data_subdir(None, name)?;
 
+        let field_selector = prepare_field_selector(&self_node_name, config);
+        let label_selector = prepare_label_selector(config);
+        let paths_filter = prepare_paths_filter(config)?;
+
         Ok(Self {
             client,
-            self_node_name,
             data_dir,
             auto_partial_merge: config.auto_partial_merge,
             fields_spec: config.annotation_fields.clone(),
+            field_selector,
+            label_selector,
+            paths_filter,
         })
     }
 
@@ -158,16 +195,16 @@ impl Source {
     {
         let Self {
             client,
-            self_node_name,
             data_dir,
             auto_partial_merge,
             fields_spec,
+            field_selector,
+            label_selector,
+            paths_filter,
         } = self;
 
-        let field_selector = format!("spec.nodeName={}", self_node_name);
-        let label_selector = "vector.dev/exclude!=true".to_owned();
-
-        let watcher = k8s::api_watcher::ApiWatcher::new(client, Pod::watch_pod_for_all_namespaces);
+        let watcher =
+            k8s::api_watcher::ApiWatcher::new(client.clone(), Pod::watch_pod_for_all_namespaces);
         let watcher = k8s::instrumenting_watcher::InstrumentingWatcher::new(watcher);
         let (state_reader, state_writer) = evmap::new();
         let state_writer =
@@ -185,8 +222,34 @@ impl Source {
         );
         let reflector_process = reflector.run();
 
-        let paths_provider = K8sPathsProvider::new(state_reader.clone());
-        let annotator = PodMetadataAnnotator::new(state_reader, fields_spec);
+        let namespace_watcher =
+            k8s::api_watcher::ApiWatcher::new(client, Namespace::watch_namespace);
+        let namespace_watcher =
+            k8s::instrumenting_watcher::InstrumentingWatcher::new(namespace_watcher);
+        let (namespace_state_reader, namespace_state_writer) = evmap::new();
+        let namespace_state_writer = k8s::state::evmap::Writer::with_key(
+            namespace_state_writer,
+            Some(Duration::from_millis(10)),
+            k8s::state::evmap::by_name,
+        );
+        let namespace_state_writer = k8s::state::instrumenting::Writer::new(namespace_state_writer);
+        let namespace_state_writer = k8s::state::delayed_delete::Writer::new(
+            namespace_state_writer,
+            Duration::from_secs(60),
+        );
+
+        let mut namespace_reflector = k8s::reflector::Reflector::new(
+            namespace_watcher,
+            namespace_state_writer,
+            None,
+            None,
+            Duration::from_secs(1),
+        );
+        let namespace_reflector_process = namespace_reflector.run();
+
+        let paths_provider = K8sPathsProvider::new(state_reader.clone(), paths_filter);
+        let annotator =
+            PodMetadataAnnotator::new(state_reader, namespace_state_reader, fields_spec);
 
         // TODO: maybe some of the parameters have to be configurable.
         let max_line_bytes = 32 * 1024; // 32 KiB
@@ -205,19 +268,18 @@ impl Source {
             remove_after: None,
         };
 
-        let (file_source_tx, file_source_rx) =
-            futures::channel::mpsc::channel::<(Bytes, String)>(100);
+        let (file_source_tx, file_source_rx) = futures::channel::mpsc::channel::<Line>(100);
 
         let mut parser = parser::build();
         let mut partial_events_merger = partial_events_merger::build(auto_partial_merge);
 
-        let events = file_source_rx.map(move |(bytes, file)| {
+        let events = file_source_rx.map(move |line| {
             emit!(KubernetesLogsEventReceived {
-                file: &file,
-                byte_size: bytes.len(),
+                file: &line.filename,
+                byte_size: line.text.len(),
             });
-            let mut event = create_event(bytes, &file);
-            if annotator.annotate(&mut event, &file).is_none() {
+            let mut event = create_event(line.text, &line.filename);
+            if annotator.annotate(&mut event, &line.filename).is_none() {
                 emit!(KubernetesLogsEventAnnotationFailed { event: &event });
             }
             event
@@ -242,6 +304,19 @@ impl Source {
                 });
             slot.bind(Box::pin(fut));
         }
+        {
+            let (slot, shutdown) = lifecycle.add();
+            let fut = util::cancel_on_signal(namespace_reflector_process, shutdown).map(|result| {
+                match result {
+                    Ok(()) => info!(message = "namespace reflector process completed gracefully"),
+                    Err(error) => error!(
+                        message = "namespace reflector process exited with an error",
+                        ?error
+                    ),
+                }
+            });
+            slot.bind(Box::pin(fut));
+        }
         {
             let (slot, shutdown) = lifecycle.add();
             let fut = util::run_file_server(file_server, file_source_tx, shutdown).map(|result| {
@@ -295,8 +370,119 @@ fn create_event(line: Bytes, file: &str) -> Event {
     event
 }
 
+/// This function construct the effective field selector to use, based on
+/// the specified configuration.
+fn prepare_field_selector(self_node_name: &str, config: &Config) -> String {
+    let base = format!("spec.nodeName={}", self_node_name);
+    match &config.field_selector {
+        Some(extra) if !extra.is_empty() => format!("{},{}", base, extra),
+        _ => base,
+    }
+}
+
+/// This function construct the effective label selector to use, based on
+/// the specified configuration.
+fn prepare_label_selector(config: &Config) -> String {
+    let base = "vector.dev/exclude!=true";
+    match &config.label_selector {
+        Some(extra) if !extra.is_empty() => format!("{},{}", base, extra),
+        _ => base.to_owned(),
+    }
+}
+
+/// This function compiles the include and exclude glob patterns from the
+/// configuration into a [`PathsFilter`].
+fn prepare_paths_filter(config: &Config) -> crate::Result<PathsFilter> {
+    let compile = |kind: &str, includes: &[String], excludes: &[String]| {
+        GlobFilter::new(includes, excludes)
+            .map_err(|error| format!("invalid {} filter: {}", kind, error))
+    };
+    Ok(PathsFilter {
+        namespaces: compile(
+            "namespace",
+            &config.include_namespaces,
+            &config.exclude_namespaces,
+        )?,
+        pod_names: compile(
+            "pod name",
+            &config.include_pod_names,
+            &config.exclude_pod_names,
+        )?,
+        container_names: compile(
+            "container name",
+            &config.include_container_names,
+            &config.exclude_container_names,
+        )?,
+    })
+}
+
 /// This function returns the default value for `self_node_name` variable
 /// as it should be at the generated config file.
 fn default_self_node_name_env_template() -> String {
     format!("${{{}}}", SELF_NODE_NAME_ENV_KEY)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare_field_selector, prepare_label_selector, prepare_paths_filter, Config};
+
+    #[test]
+    fn test_prepare_field_selector() {
+        let cases = vec![
+            (None, "spec.nodeName=qwe"),
+            (Some(""), "spec.nodeName=qwe"),
+            (
+                Some("metadata.namespace!=kube-system"),
+                "spec.nodeName=qwe,metadata.namespace!=kube-system",
+            ),
+        ];
+
+        for (field_selector, expected) in cases {
+            let config = Config {
+                field_selector: field_selector.map(ToOwned::to_owned),
+                ..Config::default()
+            };
+            assert_eq!(prepare_field_selector("qwe", &config), expected);
+        }
+    }
+
+    #[test]
+    fn test_prepare_label_selector() {
+        let cases = vec![
+            (None, "vector.dev/exclude!=true"),
+            (Some(""), "vector.dev/exclude!=true"),
+            (
+                Some("app=my-app,tier!=cache"),
+                "vector.dev/exclude!=true,app=my-app,tier!=cache",
+            ),
+        ];
+
+        for (label_selector, expected) in cases {
+            let config = Config {
+                label_selector: label_selector.map(ToOwned::to_owned),
+                ..Config::default()
+            };
+            assert_eq!(prepare_label_selector(&config), expected);
+        }
+    }
+
+    #[test]
+    fn test_prepare_paths_filter() {
+        let config = Config {
+            exclude_namespaces: vec!["kube-*".to_owned()],
+            exclude_container_names: vec!["istio-proxy".to_owned()],
+            ..Config::default()
+        };
+        let filter = prepare_paths_filter(&config).unwrap();
+        assert!(!filter.namespaces.matches("kube-system"));
+        assert!(filter.namespaces.matches("default"));
+        assert!(filter.pod_names.matches("any-pod"));
+        assert!(!filter.container_names.matches("istio-proxy"));
+
+        let config = Config {
+            include_pod_names: vec!["[".to_owned()],
+            ..Config::default()
+        };
+        assert!(prepare_paths_filter(&config).is_err());
+    }
+}
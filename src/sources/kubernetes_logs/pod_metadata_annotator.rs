@@ -3,10 +3,17 @@
 #![deny(missing_docs)]
 
 use super::path_helpers::parse_log_file_path;
-use crate::{event::LogEvent, kubernetes as k8s, Event};
+use crate::{
+    event::{LogEvent, Value},
+    kubernetes as k8s, Event,
+};
 use evmap10::ReadHandle;
-use k8s_openapi::{api::core::v1::Pod, apimachinery::pkg::apis::meta::v1::ObjectMeta};
+use k8s_openapi::{
+    api::core::v1::{Namespace, Pod, PodSpec},
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields, default)]
@@ -15,6 +22,13 @@ pub struct FieldsSpec {
     pub pod_namespace: String,
     pub pod_uid: String,
     pub pod_labels: String,
+    /// The pod annotations are only added when a field is given for them,
+    /// as they often hold large values, like the last applied configuration.
+    pub pod_annotations: Option<String>,
+    pub pod_node_name: String,
+    pub container_name: String,
+    pub container_image: String,
+    pub namespace_labels: String,
 }
 
 impl Default for FieldsSpec {
@@ -24,6 +38,11 @@ impl Default for FieldsSpec {
             pod_namespace: "kubernetes.pod_namespace".to_owned(),
             pod_uid: "kubernetes.pod_uid".to_owned(),
             pod_labels: "kubernetes.pod_labels".to_owned(),
+            pod_annotations: None,
+            pod_node_name: "kubernetes.pod_node_name".to_owned(),
+            container_name: "kubernetes.container_name".to_owned(),
+            container_image: "kubernetes.container_image".to_owned(),
+            namespace_labels: "kubernetes.namespace_labels".to_owned(),
         }
     }
 }
@@ -31,6 +50,7 @@ impl Default for FieldsSpec {
 /// Annotate the event with pod metadata.
 pub struct PodMetadataAnnotator {
     pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+    namespaces_state_reader: ReadHandle<String, k8s::state::evmap::Value<Namespace>>,
     fields_spec: FieldsSpec,
 }
 
@@ -38,28 +58,53 @@ impl PodMetadataAnnotator {
     /// Create a new [`PodMetadataAnnotator`].
     pub fn new(
         pods_state_reader: ReadHandle<String, k8s::state::evmap::Value<Pod>>,
+        namespaces_state_reader: ReadHandle<String, k8s::state::evmap::Value<Namespace>>,
         fields_spec: FieldsSpec,
     ) -> Self {
         Self {
             pods_state_reader,
+            namespaces_state_reader,
             fields_spec,
         }
     }
 }
 
 impl PodMetadataAnnotator {
-    /// Annotates an event with the information from the [`Pod::metadata`].
+    /// Annotates an event with the information from the [`Pod::metadata`],
+    /// the [`Pod::spec`] and the [`Namespace::metadata`] of the pod namespace.
     /// The event has to be obtained from kubernetes log file, and have a
     /// [`FILE_KEY`] field set with a file that the line came from.
     pub fn annotate(&self, event: &mut Event, file: &str) -> Option<()> {
         let log = event.as_mut_log();
-        let uid = parse_log_file_path(file)?.pod_uid;
-        let guard = self.pods_state_reader.get(uid)?;
+        let file_info = parse_log_file_path(file)?;
+        let guard = self.pods_state_reader.get(file_info.pod_uid)?;
         let entry = guard.get_one()?;
         let pod: &Pod = entry.as_ref();
         annotate_from_metadata(log, &self.fields_spec, &pod.metadata);
+        annotate_from_container(log, &self.fields_spec, file_info.container_name);
+        if let Some(spec) = &pod.spec {
+            annotate_from_pod_spec(log, &self.fields_spec, spec, file_info.container_name);
+        }
+        if let Some(namespace) = &pod.metadata.namespace {
+            self.annotate_from_namespace(log, namespace);
+        }
         Some(())
     }
+
+    /// Annotates an event with the labels of the [`Namespace`] with the
+    /// specified name, if it's known.
+    fn annotate_from_namespace(&self, log: &mut LogEvent, namespace: &str) {
+        // The namespace state is keyed by name.
+        let guard = match self.namespaces_state_reader.get(namespace) {
+            Some(guard) => guard,
+            None => return,
+        };
+        let metadata = guard.get_one().map(|value| &value.as_ref().metadata);
+
+        if let Some(metadata) = metadata {
+            annotate_from_namespace_metadata(log, &self.fields_spec, metadata);
+        }
+    }
 }
 
 fn annotate_from_metadata(log: &mut LogEvent, fields_spec: &FieldsSpec, metadata: &ObjectMeta) {
@@ -76,15 +121,63 @@ fn annotate_from_metadata(log: &mut LogEvent, fields_spec: &FieldsSpec, metadata
     }
 
     if let Some(labels) = &metadata.labels {
-        for (key, val) in labels.iter() {
-            log.insert(format!("{}.{}", fields_spec.pod_labels, key), val);
-        }
+        insert_map(log, &fields_spec.pod_labels, labels);
+    }
+
+    if let (Some(key), Some(annotations)) = (&fields_spec.pod_annotations, &metadata.annotations) {
+        insert_map(log, key, annotations);
+    }
+}
+
+/// Inserts the labels or annotations as a map under the given field. Their
+/// keys are kept as is, rather than splitting them into nested fields at
+/// dots, as in `app.kubernetes.io/name`.
+fn insert_map(log: &mut LogEvent, key: &str, map: &BTreeMap<String, String>) {
+    let map = map
+        .iter()
+        .map(|(key, val)| (key.clone(), Value::from(val.as_str())))
+        .collect::<BTreeMap<_, _>>();
+    log.insert(key, map);
+}
+
+fn annotate_from_container(log: &mut LogEvent, fields_spec: &FieldsSpec, container_name: &str) {
+    log.insert(&fields_spec.container_name, container_name);
+}
+
+fn annotate_from_pod_spec(
+    log: &mut LogEvent,
+    fields_spec: &FieldsSpec,
+    spec: &PodSpec,
+    container_name: &str,
+) {
+    if let Some(node_name) = &spec.node_name {
+        log.insert(&fields_spec.pod_node_name, node_name);
+    }
+
+    let container = spec
+        .containers
+        .iter()
+        .chain(spec.init_containers.iter().flatten())
+        .find(|container| container.name == container_name);
+    if let Some(image) = container.and_then(|container| container.image.as_ref()) {
+        log.insert(&fields_spec.container_image, image);
+    }
+}
+
+fn annotate_from_namespace_metadata(
+    log: &mut LogEvent,
+    fields_spec: &FieldsSpec,
+    metadata: &ObjectMeta,
+) {
+    if let Some(labels) = &metadata.labels {
+        insert_map(log, &fields_spec.namespace_labels, labels);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use k8s_openapi::api::core::v1::Container;
 
     #[test]
     fn test_annotate_from_metadata() {
@@ -108,6 +201,11 @@ mod tests {
                         .into_iter()
                         .collect(),
                     ),
+                    annotations: Some(
+                        vec![("sandbox0-annotation0".to_owned(), "val0".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
                     ..ObjectMeta::default()
                 },
                 {
@@ -126,6 +224,8 @@ mod tests {
                     pod_namespace: "ns".to_owned(),
                     pod_uid: "uid".to_owned(),
                     pod_labels: "labels".to_owned(),
+                    pod_annotations: Some("annotations".to_owned()),
+                    ..FieldsSpec::default()
                 },
                 ObjectMeta {
                     name: Some("sandbox0-name".to_owned()),
@@ -139,6 +239,11 @@ mod tests {
                         .into_iter()
                         .collect(),
                     ),
+                    annotations: Some(
+                        vec![("sandbox0-annotation0".to_owned(), "val0".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
                     ..ObjectMeta::default()
                 },
                 {
@@ -148,6 +253,42 @@ mod tests {
                     log.insert("uid", "sandbox0-uid");
                     log.insert("labels.sandbox0-label0", "val0");
                     log.insert("labels.sandbox0-label1", "val1");
+                    log.insert("annotations.sandbox0-annotation0", "val0");
+                    log
+                },
+            ),
+            (
+                FieldsSpec {
+                    pod_annotations: Some("kubernetes.pod_annotations".to_owned()),
+                    ..FieldsSpec::default()
+                },
+                ObjectMeta {
+                    labels: Some(
+                        vec![("app.kubernetes.io/name".to_owned(), "val0".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                    annotations: Some(
+                        vec![(
+                            "deployment.kubernetes.io/revision".to_owned(),
+                            "1".to_owned(),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    ..ObjectMeta::default()
+                },
+                {
+                    let mut labels = BTreeMap::new();
+                    labels.insert("app.kubernetes.io/name".to_owned(), Value::from("val0"));
+                    let mut annotations = BTreeMap::new();
+                    annotations.insert(
+                        "deployment.kubernetes.io/revision".to_owned(),
+                        Value::from("1"),
+                    );
+                    let mut log = LogEvent::default();
+                    log.insert("kubernetes.pod_labels", labels);
+                    log.insert("kubernetes.pod_annotations", annotations);
                     log
                 },
             ),
@@ -159,4 +300,69 @@ mod tests {
             assert_eq!(log, expected);
         }
     }
+
+    #[test]
+    fn test_annotate_from_pod_spec() {
+        let spec = PodSpec {
+            node_name: Some("sandbox0-node".to_owned()),
+            containers: vec![Container {
+                name: "sandbox0-container0".to_owned(),
+                image: Some("sandbox0-image0".to_owned()),
+                ..Container::default()
+            }],
+            init_containers: Some(vec![Container {
+                name: "sandbox0-init0".to_owned(),
+                image: Some("sandbox0-init-image0".to_owned()),
+                ..Container::default()
+            }]),
+            ..PodSpec::default()
+        };
+
+        let cases = vec![
+            ("sandbox0-container0", Some("sandbox0-image0")),
+            ("sandbox0-init0", Some("sandbox0-init-image0")),
+            ("sandbox0-unknown", None),
+        ];
+
+        for (container_name, expected_image) in cases {
+            let mut log = LogEvent::default();
+            annotate_from_pod_spec(&mut log, &FieldsSpec::default(), &spec, container_name);
+
+            let mut expected = LogEvent::default();
+            expected.insert("kubernetes.pod_node_name", "sandbox0-node");
+            if let Some(image) = expected_image {
+                expected.insert("kubernetes.container_image", image);
+            }
+            assert_eq!(log, expected);
+        }
+    }
+
+    #[test]
+    fn test_annotate_from_namespace_metadata() {
+        let cases = vec![
+            (ObjectMeta::default(), LogEvent::default()),
+            (
+                ObjectMeta {
+                    name: Some("sandbox0-ns".to_owned()),
+                    labels: Some(
+                        vec![("sandbox0-label0".to_owned(), "val0".to_owned())]
+                            .into_iter()
+                            .collect(),
+                    ),
+                    ..ObjectMeta::default()
+                },
+                {
+                    let mut log = LogEvent::default();
+                    log.insert("kubernetes.namespace_labels.sandbox0-label0", "val0");
+                    log
+                },
+            ),
+        ];
+
+        for (metadata, expected) in cases {
+            let mut log = LogEvent::default();
+            annotate_from_namespace_metadata(&mut log, &FieldsSpec::default(), &metadata);
+            assert_eq!(log, expected);
+        }
+    }
 }
@@ -1,5 +1,4 @@
-use bytes05::Bytes;
-use file_source::{paths_provider::PathsProvider, FileServer, FileServerShutdown};
+use file_source::{paths_provider::PathsProvider, FileServer, FileServerShutdown, Line};
 use futures::future::{select, Either};
 use futures::{pin_mut, Sink};
 use std::convert::Infallible;
@@ -16,8 +15,8 @@ pub async fn run_file_server<PP, C, S>(
 ) -> Result<FileServerShutdown, tokio::task::JoinError>
 where
     PP: PathsProvider + Send + 'static,
-    C: Sink<(Bytes, String)> + Unpin + Send + 'static,
-    <C as Sink<(Bytes, String)>>::Error: Error + Send,
+    C: Sink<Line> + Unpin + Send + 'static,
+    <C as Sink<Line>>::Error: Error + Send,
     S: Future + Unpin + Send + 'static,
 {
     let span = info_span!("file_server");
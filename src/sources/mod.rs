@@ -3,12 +3,20 @@ use snafu::Snafu;
 
 #[cfg(feature = "sources-docker")]
 pub mod docker;
+#[cfg(feature = "sources-exec")]
+pub mod exec;
 #[cfg(feature = "sources-file")]
 pub mod file;
+#[cfg(feature = "sources-fluent")]
+pub mod fluent;
 #[cfg(feature = "sources-generator")]
 pub mod generator;
+#[cfg(all(target_os = "linux", feature = "sources-host_metrics"))]
+pub mod host_metrics;
 #[cfg(feature = "sources-http")]
 pub mod http;
+#[cfg(feature = "sources-internal_logs")]
+pub mod internal_logs;
 #[cfg(feature = "sources-internal_metrics")]
 pub mod internal_metrics;
 #[cfg(all(unix, feature = "sources-journald"))]
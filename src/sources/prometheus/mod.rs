@@ -130,7 +130,7 @@ mod test {
     use super::*;
     use crate::{
         hyper::body_to_bytes,
-        sinks::prometheus::PrometheusSinkConfig,
+        sinks::prometheus::{default_ttl_secs, PrometheusSinkConfig},
         test_util::next_addr,
         topology::{self, config},
         Error,
@@ -202,7 +202,10 @@ mod test {
                 address: out_addr,
                 namespace: "vector".into(),
                 buckets: vec![1.0, 2.0, 4.0],
-                flush_period_secs: 1,
+                quantiles: vec![],
+                flush_period_secs: 2,
+                ttl_secs: default_ttl_secs(),
+                tls: None,
             },
         );
 
@@ -78,6 +78,7 @@ impl SourceConfig for SocketConfig {
         match self.mode.clone() {
             Mode::Tcp(config) => {
                 let tcp = tcp::RawTcpSource {
+                    decoder: config.decoder()?,
                     config: config.clone(),
                 };
                 let tls = MaybeTlsSettings::from_config(&config.tls, true)?;
@@ -98,6 +99,7 @@ impl SourceConfig for SocketConfig {
                     config.address,
                     config.max_length,
                     host_key,
+                    config.decoder()?,
                     shutdown,
                     out,
                 ))
@@ -109,9 +111,9 @@ impl SourceConfig for SocketConfig {
                     .clone()
                     .unwrap_or_else(|| event::log_schema().host_key().to_string());
                 Ok(unix::unix(
-                    config.path,
-                    config.max_length,
+                    config.path.clone(),
                     host_key,
+                    config.decoder()?,
                     shutdown,
                     out,
                 ))
@@ -140,6 +142,7 @@ mod test {
     use crate::runtime::Runtime;
     use crate::shutdown::{ShutdownSignal, SourceShutdownCoordinator};
     use crate::sinks::util::tcp::TcpSink;
+    use crate::sources::util::{DecodingConfig, FramingConfig};
     use crate::test_util::{
         block_on, collect_n, next_addr, runtime, send_lines, send_lines_tls, wait_for_tcp, CollectN,
     };
@@ -223,6 +226,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn tcp_decodes_json() {
+        let (tx, rx) = Pipeline::new_test();
+
+        let addr = next_addr();
+
+        let server = SocketConfig::from(TcpConfig {
+            decoding: DecodingConfig::Json,
+            ..TcpConfig::new(addr.into())
+        })
+        .build(
+            "default",
+            &GlobalOptions::default(),
+            ShutdownSignal::noop(),
+            tx,
+        )
+        .unwrap();
+        let mut rt = runtime();
+        rt.spawn(server);
+        wait_for_tcp(addr);
+
+        let lines = vec!["not json", r#"{"message":"test","code":7}"#];
+        rt.block_on_std(send_lines(addr, lines.into_iter().map(String::from)))
+            .unwrap();
+
+        let event = rx.wait().next().unwrap().unwrap();
+        let log = event.as_log();
+        assert_eq!(log[&event::log_schema().message_key()], "test".into());
+        assert_eq!(log[&"code".into()], 7.into());
+        assert_eq!(log[&event::log_schema().host_key()], "127.0.0.1".into());
+        assert_eq!(log[event::log_schema().source_type_key()], "socket".into());
+    }
+
     #[test]
     fn tcp_continue_after_long_line() {
         let (tx, rx) = Pipeline::new_test();
@@ -460,10 +496,19 @@ mod test {
         sender: Pipeline,
         source_name: &str,
         shutdown_signal: ShutdownSignal,
+    ) -> (SocketAddr, Runtime, oneshot::SpawnHandle<(), ()>) {
+        init_udp_with_config(sender, source_name, shutdown_signal, UdpConfig::new)
+    }
+
+    fn init_udp_with_config(
+        sender: Pipeline,
+        source_name: &str,
+        shutdown_signal: ShutdownSignal,
+        config: impl FnOnce(SocketAddr) -> UdpConfig,
     ) -> (SocketAddr, Runtime, oneshot::SpawnHandle<(), ()>) {
         let addr = next_addr();
 
-        let server = SocketConfig::from(UdpConfig::new(addr))
+        let server = SocketConfig::from(config(addr))
             .build(
                 source_name,
                 &GlobalOptions::default(),
@@ -514,6 +559,30 @@ mod test {
         );
     }
 
+    #[test]
+    fn udp_character_delimited() {
+        let (tx, rx) = Pipeline::new_test();
+
+        let (address, mut rt, handle) =
+            init_udp_with_config(tx, "default", ShutdownSignal::noop(), |addr| UdpConfig {
+                framing: Some(FramingConfig::CharacterDelimited { delimiter: ',' }),
+                ..UdpConfig::new(addr)
+            });
+        handle.forget();
+
+        send_lines_udp(address, vec!["test,test2".to_string()]);
+        let events = rt.block_on(collect_n(rx, 2)).ok().unwrap();
+
+        assert_eq!(
+            events[0].as_log()[&event::log_schema().message_key()],
+            "test".into()
+        );
+        assert_eq!(
+            events[1].as_log()[&event::log_schema().message_key()],
+            "test2".into()
+        );
+    }
+
     #[test]
     fn udp_multiple_packets() {
         let (tx, rx) = Pipeline::new_test();
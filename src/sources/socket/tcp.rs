@@ -1,11 +1,13 @@
 use crate::{
     event::{self, Event},
-    internal_events::TcpEventReceived,
-    sources::util::{SocketListenAddr, TcpSource},
+    internal_events::{DecoderParseFailed, TcpEventReceived},
+    sources::util::{
+        decoding::{Decoder, Framer},
+        DecodingConfig, FramingConfig, SocketListenAddr, TcpSource,
+    },
     tls::TlsConfig,
 };
 use bytes05::Bytes;
-use codec::BytesDelimitedCodec;
 use serde::{Deserialize, Serialize};
 use string_cache::DefaultAtom as Atom;
 use tracing::field;
@@ -20,6 +22,9 @@ pub struct TcpConfig {
     pub shutdown_timeout_secs: u64,
     pub host_key: Option<Atom>,
     pub tls: Option<TlsConfig>,
+    pub framing: Option<FramingConfig>,
+    #[serde(default)]
+    pub decoding: DecodingConfig,
 }
 
 fn default_max_length() -> usize {
@@ -38,26 +43,49 @@ impl TcpConfig {
             host_key: None,
             shutdown_timeout_secs: default_shutdown_timeout_secs(),
             tls: Default::default(),
+            framing: None,
+            decoding: Default::default(),
         }
     }
+
+    pub(super) fn decoder(&self) -> crate::Result<Decoder> {
+        let framing = self
+            .framing
+            .clone()
+            .unwrap_or(FramingConfig::NewlineDelimited);
+        Ok(Decoder::new(
+            framing.build(self.max_length)?,
+            self.decoding.build()?,
+        ))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct RawTcpSource {
     pub config: TcpConfig,
+    pub decoder: Decoder,
 }
 
 impl TcpSource for RawTcpSource {
     type Error = std::io::Error;
-    type Decoder = BytesDelimitedCodec;
+    type Decoder = Framer;
 
     fn decoder(&self) -> Self::Decoder {
-        BytesDelimitedCodec::new_with_max_length(b'\n', self.config.max_length)
+        self.decoder.framer()
     }
 
     fn build_event(&self, frame: Bytes, host: Bytes) -> Option<Event> {
         let byte_size = frame.len();
-        let mut event = Event::from(frame);
+        let mut event = self
+            .decoder
+            .parse(frame)
+            .map_err(|error| {
+                emit!(DecoderParseFailed {
+                    component_type: "socket",
+                    error: &error
+                })
+            })
+            .ok()?;
 
         event
             .as_mut_log()
@@ -1,21 +1,23 @@
 use crate::{
-    event::{self, Event},
-    internal_events::{UdpEventReceived, UdpSocketError},
+    event,
+    internal_events::{DecoderParseFailed, UdpEventReceived, UdpSocketError},
     shutdown::ShutdownSignal,
-    sources::Source,
+    sources::{
+        util::{decoding::Decoder, DecodingConfig, FramingConfig},
+        Source,
+    },
     Pipeline,
 };
 use bytes05::BytesMut;
-use codec::BytesDelimitedCodec;
 use futures::{compat::Future01CompatExt, FutureExt, TryFutureExt};
 use futures01::Sink;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use string_cache::DefaultAtom as Atom;
 use tokio::net::UdpSocket;
-use tokio_util::codec::Decoder;
 
-/// UDP processes messages per packet, where messages are separated by newline.
+/// UDP processes messages per packet, where messages are separated by newline
+/// unless configured otherwise.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct UdpConfig {
@@ -23,6 +25,9 @@ pub struct UdpConfig {
     #[serde(default = "default_max_length")]
     pub max_length: usize,
     pub host_key: Option<Atom>,
+    pub framing: Option<FramingConfig>,
+    #[serde(default)]
+    pub decoding: DecodingConfig,
 }
 
 fn default_max_length() -> usize {
@@ -35,14 +40,28 @@ impl UdpConfig {
             address,
             max_length: default_max_length(),
             host_key: None,
+            framing: None,
+            decoding: Default::default(),
         }
     }
+
+    pub(super) fn decoder(&self) -> crate::Result<Decoder> {
+        let framing = self
+            .framing
+            .clone()
+            .unwrap_or(FramingConfig::NewlineDelimited);
+        Ok(Decoder::new(
+            framing.build(self.max_length)?,
+            self.decoding.build()?,
+        ))
+    }
 }
 
 pub fn udp(
     address: SocketAddr,
     max_length: usize,
     host_key: Atom,
+    decoder: Decoder,
     shutdown: ShutdownSignal,
     out: Pipeline,
 ) -> Source {
@@ -65,13 +84,21 @@ pub fn udp(
                             emit!(UdpSocketError { error });
                         })?;
 
-                        let mut payload = buf.split_to(byte_size);
+                        let payload = buf.split_to(byte_size);
 
-                        // UDP processes messages per payload, where messages are separated by newline
-                        // and stretch to end of payload.
-                        let mut decoder = BytesDelimitedCodec::new(b'\n');
-                        while let Ok(Some(line)) = decoder.decode_eof(&mut payload) {
-                            let mut event = Event::from(line);
+                        // UDP processes messages per payload, where frames stretch to the end
+                        // of the payload.
+                        for event in decoder.decode_payload(&payload) {
+                            let mut event = match event {
+                                Ok(event) => event,
+                                Err(error) => {
+                                    emit!(DecoderParseFailed {
+                                        component_type: "socket",
+                                        error: &error
+                                    });
+                                    continue;
+                                }
+                            };
 
                             event
                                 .as_mut_log()
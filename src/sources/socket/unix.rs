@@ -1,14 +1,16 @@
 use crate::{
     event::{self, Event},
-    internal_events::UnixSocketEventReceived,
+    internal_events::{DecoderParseFailed, UnixSocketEventReceived},
     shutdown::ShutdownSignal,
-    sources::{util::build_unix_source, Source},
+    sources::{
+        util::{build_unix_source, decoding::Decoder, DecodingConfig, FramingConfig},
+        Source,
+    },
     Pipeline,
 };
 use bytes05::Bytes;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use tokio_util::codec::LinesCodec;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
@@ -17,6 +19,9 @@ pub struct UnixConfig {
     #[serde(default = "default_max_length")]
     pub max_length: usize,
     pub host_key: Option<String>,
+    pub framing: Option<FramingConfig>,
+    #[serde(default)]
+    pub decoding: DecodingConfig,
 }
 
 fn default_max_length() -> usize {
@@ -29,17 +34,43 @@ impl UnixConfig {
             path,
             max_length: default_max_length(),
             host_key: None,
+            framing: None,
+            decoding: Default::default(),
         }
     }
+
+    pub(super) fn decoder(&self) -> crate::Result<Decoder> {
+        let framing = self
+            .framing
+            .clone()
+            .unwrap_or(FramingConfig::NewlineDelimited);
+        Ok(Decoder::new(
+            framing.build(self.max_length)?,
+            self.decoding.build()?,
+        ))
+    }
 }
 
 /**
 * Function to pass to build_unix_source, specific to the basic unix source.
-* Takes a single line of a received message and builds an Event object.
+* Takes a single frame of a received message and builds an Event object.
 **/
-fn build_event(host_key: &str, received_from: Option<Bytes>, line: &str) -> Option<Event> {
-    let byte_size = line.len();
-    let mut event = Event::from(line);
+fn build_event(
+    decoder: &Decoder,
+    host_key: &str,
+    received_from: Option<Bytes>,
+    frame: Bytes,
+) -> Option<Event> {
+    let byte_size = frame.len();
+    let mut event = decoder
+        .parse(frame)
+        .map_err(|error| {
+            emit!(DecoderParseFailed {
+                component_type: "socket",
+                error: &error
+            })
+        })
+        .ok()?;
     event
         .as_mut_log()
         .insert(event::log_schema().source_type_key(), "socket");
@@ -52,17 +83,17 @@ fn build_event(host_key: &str, received_from: Option<Bytes>, line: &str) -> Opti
 
 pub fn unix(
     path: PathBuf,
-    max_length: usize,
     host_key: String,
+    decoder: Decoder,
     shutdown: ShutdownSignal,
     out: Pipeline,
 ) -> Source {
     build_unix_source(
         path,
-        LinesCodec::new_with_max_length(max_length),
+        decoder.framer(),
         host_key,
         shutdown,
         out,
-        build_event,
+        move |host_key, received_from, frame| build_event(&decoder, host_key, received_from, frame),
     )
 }
@@ -105,7 +105,7 @@ fn statsd(addr: SocketAddr, shutdown: ShutdownSignal, out: Pipeline) -> super::S
 mod test {
     use super::StatsdConfig;
     use crate::{
-        sinks::prometheus::PrometheusSinkConfig,
+        sinks::prometheus::{default_ttl_secs, PrometheusSinkConfig},
         test_util::next_addr,
         topology::{self, config},
     };
@@ -137,7 +137,10 @@ mod test {
                 address: out_addr,
                 namespace: "vector".into(),
                 buckets: vec![1.0, 2.0, 4.0],
+                quantiles: vec![],
                 flush_period_secs: 1,
+                ttl_secs: default_ttl_secs(),
+                tls: None,
             },
         );
 
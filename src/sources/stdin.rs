@@ -1,14 +1,18 @@
 use crate::{
     event::{self, Event},
-    internal_events::{StdinEventReceived, StdinReadFailed},
+    internal_events::{DecoderParseFailed, StdinEventReceived, StdinReadFailed},
     shutdown::ShutdownSignal,
+    sources::util::{
+        decoding::{Decoder, Framer},
+        DecodingConfig, FramingConfig,
+    },
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Pipeline,
 };
-use bytes::Bytes;
+use bytes05::{Bytes, BytesMut};
 use futures::{
     compat::{Future01CompatExt, Sink01CompatExt},
-    FutureExt, StreamExt, TryFutureExt, TryStreamExt,
+    future, FutureExt, StreamExt, TryFutureExt,
 };
 use futures01::Sink;
 use lazy_static::lazy_static;
@@ -16,6 +20,7 @@ use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 use std::{io, sync::Mutex, thread};
 use tokio::sync::broadcast::{channel, Sender};
+use tokio_util::codec::Decoder as _;
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -33,6 +38,8 @@ pub struct StdinConfig {
     #[serde(default = "default_max_length")]
     pub max_length: usize,
     pub host_key: Option<String>,
+    pub framing: Option<FramingConfig>,
+    pub decoding: DecodingConfig,
 }
 
 impl Default for StdinConfig {
@@ -40,6 +47,8 @@ impl Default for StdinConfig {
         StdinConfig {
             max_length: default_max_length(),
             host_key: None,
+            framing: None,
+            decoding: DecodingConfig::default(),
         }
     }
 }
@@ -83,7 +92,8 @@ where
     R: Send + io::BufRead + 'static,
 {
     // The idea is to have one dedicated future for reading stdin running in the background,
-    // and the sources would recieve the lines thorugh a multi consumer channel.
+    // and the sources would recieve the frames thorugh a multi consumer channel. The frames are
+    // split up with the framing of the source that started the background thread.
     //
     // Implemented solution relies on having a copy of optional sender behind a global mutex,
     // and have stdin sources and background thread synchronize on it.
@@ -108,6 +118,12 @@ where
     // this should be easier to reason about and performance shouldn't suffer since this procedure
     // is cold compared to the rest of the source.
 
+    let framing = config
+        .framing
+        .clone()
+        .unwrap_or(FramingConfig::NewlineDelimited);
+    let strip_cr = framing == FramingConfig::NewlineDelimited;
+    let decoder = Decoder::new(framing.build(config.max_length)?, config.decoding.build()?);
     let host_key = config
         .host_key
         .unwrap_or_else(|| event::log_schema().host_key().to_string());
@@ -123,40 +139,35 @@ where
             *guard = Some(sender.clone());
 
             // Start the background thread
+            let framer = decoder.framer();
             thread::spawn(move || {
                 info!("Capturing STDIN.");
 
-                for line in stdin.lines() {
-                    match line {
-                        Err(error) => {
-                            emit!(StdinReadFailed { error });
-                            break;
-                        }
-                        Ok(line) => {
-                            if sender.send(Bytes::from(line)).is_err() {
-                                // There are no active receivers.
-                                // Try to stop.
-                                let mut guard =
-                                    CRITICAL_SECTION.lock().expect("CRITICAL_SECTION poisoned");
-
-                                if sender.receiver_count() == 0 {
-                                    guard.take();
-                                    return;
-                                }
-
-                                // A new receiver has shown up.
-
-                                // It's fine not to resend the line since it came from
-                                // before this new receiver has shown up.
-                            }
+                let finished = read_frames(stdin, framer, strip_cr, |frame| {
+                    if sender.send(frame).is_err() {
+                        // There are no active receivers.
+                        // Try to stop.
+                        let mut guard = CRITICAL_SECTION.lock().expect("CRITICAL_SECTION poisoned");
+
+                        if sender.receiver_count() == 0 {
+                            guard.take();
+                            return false;
                         }
+
+                        // A new receiver has shown up.
+
+                        // It's fine not to resend the frame since it came from
+                        // before this new receiver has shown up.
                     }
+                    true
+                });
+
+                if finished {
+                    CRITICAL_SECTION
+                        .lock()
+                        .expect("CRITICAL_SECTION poisoned")
+                        .take();
                 }
-
-                CRITICAL_SECTION
-                    .lock()
-                    .expect("CRITICAL_SECTION poisoned")
-                    .take();
             });
 
             receiver
@@ -166,12 +177,28 @@ where
 
     let fut = receiver
         .take_until(shutdown.compat())
-        .map_err(|error| error!("error reading line: {:?}", error))
-        .map_ok(move |line| {
-            emit!(StdinEventReceived {
-                byte_size: line.len()
-            });
-            create_event(line, &host_key, &hostname)
+        .filter_map(move |frame| {
+            future::ready(match frame {
+                Ok(frame) => {
+                    emit!(StdinEventReceived {
+                        byte_size: frame.len()
+                    });
+                    match decoder.parse(frame) {
+                        Ok(event) => Some(Ok(create_event(event, &host_key, &hostname))),
+                        Err(error) => {
+                            emit!(DecoderParseFailed {
+                                component_type: "stdin",
+                                error: &error
+                            });
+                            None
+                        }
+                    }
+                }
+                Err(error) => {
+                    error!("error reading stdin: {:?}", error);
+                    None
+                }
+            })
         })
         .forward(
             out.sink_map_err(|error| error!(message = "Unable to send event to out.", %error))
@@ -181,9 +208,60 @@ where
     Ok(Box::new(fut.boxed().compat()))
 }
 
-fn create_event(line: Bytes, host_key: &str, hostname: &Option<String>) -> Event {
-    let mut event = Event::from(line);
+/// Reads until the end of the input, handing the frames it is split up into
+/// to `send` for as long as it returns true. With `strip_cr`, lines ending in
+/// CRLF are stripped of the carriage return, like `BufRead::lines` does.
+///
+/// Returns false if reading was stopped by `send`.
+fn read_frames<R: io::BufRead>(
+    mut stdin: R,
+    mut framer: Framer,
+    strip_cr: bool,
+    mut send: impl FnMut(Bytes) -> bool,
+) -> bool {
+    let mut buf = BytesMut::new();
+    let mut eof = false;
+    while !eof {
+        match stdin.fill_buf() {
+            Ok(chunk) if chunk.is_empty() => eof = true,
+            Ok(chunk) => {
+                buf.extend_from_slice(chunk);
+                let length = chunk.len();
+                stdin.consume(length);
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => {
+                emit!(StdinReadFailed { error });
+                eof = true;
+            }
+        }
+
+        loop {
+            let frame = if eof {
+                framer.decode_eof(&mut buf)
+            } else {
+                framer.decode(&mut buf)
+            };
+            let mut frame = match frame {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(error) => {
+                    emit!(StdinReadFailed { error });
+                    return true;
+                }
+            };
+            if strip_cr && frame.ends_with(b"\r") {
+                frame.truncate(frame.len() - 1);
+            }
+            if !send(frame) {
+                return false;
+            }
+        }
+    }
+    true
+}
 
+fn create_event(mut event: Event, host_key: &str, hostname: &Option<String>) -> Event {
     // Add source type
     event
         .as_mut_log()
@@ -209,7 +287,7 @@ mod tests {
         let host_key = "host".to_string();
         let hostname = Some("Some.Machine".to_string());
 
-        let event = create_event(line, &host_key, &hostname);
+        let event = create_event(Event::from(line), &host_key, &hostname);
         let log = event.into_log();
 
         assert_eq!(log[&"host".into()], "Some.Machine".into());
@@ -253,4 +331,27 @@ mod tests {
         assert!(event.is_ready());
         assert_eq!(Ready(None), event);
     }
+
+    #[test]
+    fn stdin_strips_crlf() {
+        let mut frames = Vec::new();
+        let finished = read_frames(
+            Cursor::new("hello world\r\nhello\rworld\nhello world again\r\n"),
+            FramingConfig::NewlineDelimited.build(100).unwrap(),
+            true,
+            |frame| {
+                frames.push(frame);
+                true
+            },
+        );
+        assert!(finished);
+        assert_eq!(
+            frames,
+            vec![
+                Bytes::from("hello world"),
+                Bytes::from("hello\rworld"),
+                Bytes::from("hello world again"),
+            ]
+        );
+    }
 }
@@ -1,16 +1,23 @@
-use super::util::{SocketListenAddr, TcpSource};
+pub use super::util::decoding::StructuredData;
+use super::util::{
+    decoding::{insert_syslog_fields, parse_syslog_message},
+    SocketListenAddr, TcpSource,
+};
 #[cfg(unix)]
 use crate::sources::util::build_unix_source;
 use crate::{
-    event::{self, Event, Value},
-    internal_events::{SyslogEventReceived, SyslogUdpReadError, SyslogUdpUtf8Error},
+    event::{self, Event},
+    internal_events::{
+        SyslogEventReceived, SyslogParseFailed, SyslogUdpReadError, SyslogUdpUtf8Error,
+    },
     shutdown::ShutdownSignal,
     tls::{MaybeTlsSettings, TlsConfig},
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Pipeline,
 };
-use bytes05::{Buf, Bytes, BytesMut};
-use chrono::{Datelike, Utc};
+use bytes05::{Bytes, BytesMut};
+use chrono::Utc;
+use codec::OctetCountingCodec;
 use derive_is_enum_variant::is_enum_variant;
 use futures::{
     compat::{Future01CompatExt, Sink01CompatExt},
@@ -22,7 +29,6 @@ use std::io;
 use std::net::SocketAddr;
 #[cfg(unix)]
 use std::path::PathBuf;
-use syslog_loose::{IncompleteDate, Message, ProcId, Protocol};
 use tokio::net::UdpSocket;
 use tokio_util::{
     codec::{BytesCodec, Decoder, LinesCodec, LinesCodecError},
@@ -40,6 +46,15 @@ pub struct SyslogConfig {
     pub max_length: usize,
     /// The host key of the log. (This differs from `hostname`)
     pub host_key: Option<String>,
+    /// How messages are delimited on stream based modes (`tcp` and `unix`).
+    #[serde(default)]
+    pub framing: Framing,
+    /// Whether RFC 5424 structured data is nested under its SD-ID or flattened.
+    #[serde(default)]
+    pub structured_data: StructuredData,
+    /// What to do with lines that can't be parsed as syslog.
+    #[serde(default)]
+    pub on_parse_failure: ParseFailure,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, is_enum_variant)]
@@ -58,6 +73,41 @@ pub enum Mode {
     },
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, is_enum_variant)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    /// Detect octet counting per message and fall back to newline delimiting.
+    Auto,
+    /// Messages are terminated by a newline.
+    NewlineDelimited,
+    /// Messages are prefixed by their length, as described in RFC 6587 and RFC 5425.
+    OctetCounting,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::Auto
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, is_enum_variant)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseFailure {
+    /// Emit the raw line as the message, tagged with `syslog_parse_failure`.
+    Passthrough,
+    /// Discard the line.
+    Drop,
+}
+
+impl Default for ParseFailure {
+    fn default() -> Self {
+        ParseFailure::Passthrough
+    }
+}
+
+/// Field set on events whose line couldn't be parsed as syslog.
+pub const PARSE_FAILURE_KEY: &str = "syslog_parse_failure";
+
 pub fn default_max_length() -> usize {
     bytesize::kib(100u64) as usize
 }
@@ -68,6 +118,9 @@ impl SyslogConfig {
             mode,
             host_key: None,
             max_length: default_max_length(),
+            framing: Framing::default(),
+            structured_data: StructuredData::default(),
+            on_parse_failure: ParseFailure::default(),
         }
     }
 }
@@ -89,26 +142,41 @@ impl SourceConfig for SyslogConfig {
             .host_key
             .clone()
             .unwrap_or_else(|| event::log_schema().host_key().to_string());
+        let options = ParseOptions {
+            structured_data: self.structured_data,
+            on_parse_failure: self.on_parse_failure,
+        };
 
         match self.mode.clone() {
             Mode::Tcp { address, tls } => {
                 let source = SyslogTcpSource {
                     max_length: self.max_length,
                     host_key,
+                    framing: self.framing,
+                    options,
                 };
                 let shutdown_secs = 30;
                 let tls = MaybeTlsSettings::from_config(&tls, true)?;
                 source.run(address, shutdown_secs, tls, shutdown, out)
             }
-            Mode::Udp { address } => Ok(udp(address, self.max_length, host_key, shutdown, out)),
+            Mode::Udp { address } => Ok(udp(
+                address,
+                self.max_length,
+                host_key,
+                options,
+                shutdown,
+                out,
+            )),
             #[cfg(unix)]
             Mode::Unix { path } => Ok(build_unix_source(
                 path,
-                SyslogDecoder::new(self.max_length),
+                SyslogDecoder::new(self.max_length, self.framing),
                 host_key,
                 shutdown,
                 out,
-                event_from_str,
+                move |host_key, default_host, line: String| {
+                    event_from_str(host_key, default_host, &line, &options)
+                },
             )),
         }
     }
@@ -122,10 +190,19 @@ impl SourceConfig for SyslogConfig {
     }
 }
 
+/// Options controlling how a single syslog line is turned into an event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    pub structured_data: StructuredData,
+    pub on_parse_failure: ParseFailure,
+}
+
 #[derive(Debug, Clone)]
 struct SyslogTcpSource {
     max_length: usize,
     host_key: String,
+    framing: Framing,
+    options: ParseOptions,
 }
 
 impl TcpSource for SyslogTcpSource {
@@ -133,90 +210,42 @@ impl TcpSource for SyslogTcpSource {
     type Decoder = SyslogDecoder;
 
     fn decoder(&self) -> Self::Decoder {
-        SyslogDecoder::new(self.max_length)
+        SyslogDecoder::new(self.max_length, self.framing)
     }
 
     fn build_event(&self, frame: String, host: Bytes) -> Option<Event> {
-        event_from_str(&self.host_key, Some(host), &frame)
+        event_from_str(&self.host_key, Some(host), &frame, &self.options)
     }
 }
 
-/// Decodes according to `Octet Counting` in https://tools.ietf.org/html/rfc6587
+/// Splits syslog messages on newlines, or by `Octet Counting` as described
+/// in https://tools.ietf.org/html/rfc6587.
 #[derive(Clone, Debug)]
 struct SyslogDecoder {
-    other: LinesCodec,
+    lines: LinesCodec,
+    octet_counting: OctetCountingCodec,
+    framing: Framing,
 }
 
 impl SyslogDecoder {
-    fn new(max_length: usize) -> Self {
+    fn new(max_length: usize, framing: Framing) -> Self {
         Self {
-            other: LinesCodec::new_with_max_length(max_length),
-        }
-    }
-
-    fn octet_decode(&self, src: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
-        // Encoding scheme:
-        //
-        // len ' ' data
-        // |    |  | len number of bytes that contain syslog message
-        // |    |
-        // |    | Separating whitespace
-        // |
-        // | ASCII decimal number of unknown length
-
-        if let Some(i) = src.iter().position(|&b| b == b' ') {
-            let len: usize = std::str::from_utf8(&src[..i])
-                .map_err(|_| ())
-                .and_then(|num| num.parse().map_err(|_| ()))
-                .map_err(|_| {
-                    LinesCodecError::Io(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "Unable to decode message len as number",
-                    ))
-                })?;
-
-            let from = i + 1;
-            let to = from + len;
-
-            if let Some(msg) = src.get(from..to) {
-                let s = std::str::from_utf8(msg)
-                    .map_err(|_| {
-                        LinesCodecError::Io(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "Unable to decode message as UTF8",
-                        ))
-                    })?
-                    .to_string();
-                src.advance(to);
-                Ok(Some(s))
-            } else {
-                Ok(None)
-            }
-        } else if src.len() < self.other.max_length() {
-            Ok(None)
-        } else {
-            // This is certainly mallformed, and there is no recovering from this.
-            Err(LinesCodecError::Io(io::Error::new(
-                io::ErrorKind::Other,
-                "Frame length limit exceeded",
-            )))
+            lines: LinesCodec::new_with_max_length(max_length),
+            octet_counting: OctetCountingCodec::new_with_max_length(max_length),
+            framing,
         }
     }
 
-    /// None if this is not octet counting encoded
-    fn checked_decode(
-        &self,
-        src: &mut BytesMut,
-    ) -> Option<Result<Option<String>, LinesCodecError>> {
-        if let Some(&first_byte) = src.get(0) {
-            if 49 <= first_byte && first_byte <= 57 {
-                // First character is non zero number so we can assume that
-                // octet count framing is used.
-                trace!("Octet counting encoded event detected.");
-                return Some(self.octet_decode(src));
+    fn is_octet_counted(&self, src: &BytesMut) -> bool {
+        match self.framing {
+            Framing::NewlineDelimited => false,
+            Framing::OctetCounting => true,
+            // First character is non zero number so we can assume that
+            // octet count framing is used.
+            Framing::Auto => {
+                self.octet_counting.is_discarding() || matches!(src.first(), Some(b'1'..=b'9'))
             }
         }
-        None
     }
 }
 
@@ -225,28 +254,38 @@ impl Decoder for SyslogDecoder {
     type Error = LinesCodecError;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if let Some(ret) = self.checked_decode(src) {
-            ret
+        if self.is_octet_counted(src) {
+            let frame = self.octet_counting.decode(src)?;
+            frame.map(frame_to_string).transpose()
         } else {
-            // Octet counting isn't used so fallback to newline codec.
-            self.other.decode(src)
+            self.lines.decode(src)
         }
     }
 
     fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if let Some(ret) = self.checked_decode(buf) {
-            ret
+        if self.is_octet_counted(buf) {
+            let frame = self.octet_counting.decode_eof(buf)?;
+            frame.map(frame_to_string).transpose()
         } else {
-            // Octet counting isn't used so fallback to newline codec.
-            self.other.decode_eof(buf)
+            self.lines.decode_eof(buf)
         }
     }
 }
 
+fn frame_to_string(frame: Bytes) -> Result<String, LinesCodecError> {
+    String::from_utf8(frame.to_vec()).map_err(|_| {
+        LinesCodecError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unable to decode message as UTF8",
+        ))
+    })
+}
+
 pub fn udp(
     addr: SocketAddr,
     _max_length: usize,
     host_key: String,
+    options: ParseOptions,
     shutdown: ShutdownSignal,
     out: Pipeline,
 ) -> super::Source {
@@ -276,7 +315,8 @@ pub fn udp(
                                     .map_err(|error| emit!(SyslogUdpUtf8Error { error }))
                                     .ok()
                                     .and_then(|s| {
-                                        event_from_str(&host_key, Some(received_from), s).map(Ok)
+                                        event_from_str(&host_key, Some(received_from), s, &options)
+                                            .map(Ok)
                                     })
                             }
                             Err(error) => {
@@ -297,29 +337,34 @@ pub fn udp(
     )
 }
 
-/// Function used to resolve the year for syslog messages that don't include the year.
-/// If the current month is January, and the syslog message is for December, it will take the previous year.
-/// Otherwise, take the current year.
-fn resolve_year((month, _date, _hour, _min, _sec): IncompleteDate) -> i32 {
-    let now = Utc::now();
-    if now.month() == 1 && month == 12 {
-        now.year() - 1
-    } else {
-        now.year()
-    }
-}
-
 /**
 * Function to pass to build_unix_source, specific to the Unix mode of the syslog source.
 * Handles the logic of parsing and decoding the syslog message format.
 **/
 // TODO: many more cases to handle:
-// octet framing (i.e. num bytes as ascii string prefix) with and without delimiters
 // null byte delimiter in place of newline
-fn event_from_str(host_key: &str, default_host: Option<Bytes>, line: &str) -> Option<Event> {
+fn event_from_str(
+    host_key: &str,
+    default_host: Option<Bytes>,
+    line: &str,
+    options: &ParseOptions,
+) -> Option<Event> {
     let line = line.trim();
-    let parsed = syslog_loose::parse_message_with_year(line, resolve_year);
-    let mut event = Event::from(&parsed.msg[..]);
+    let parsed = parse_syslog_message(line);
+
+    if parsed.is_none() {
+        emit!(SyslogParseFailed {
+            byte_size: line.len(),
+            dropped: options.on_parse_failure.is_drop(),
+        });
+        if options.on_parse_failure.is_drop() {
+            return None;
+        }
+    }
+
+    // Lines that failed to parse are passed through untouched, so that
+    // nothing a partial parse guessed at ends up in the event.
+    let mut event = Event::from(parsed.as_ref().map_or(line, |parsed| parsed.msg));
 
     // Add source type
     event
@@ -330,20 +375,31 @@ fn event_from_str(host_key: &str, default_host: Option<Bytes>, line: &str) -> Op
         event.as_mut_log().insert("source_ip", default_host);
     }
 
-    let parsed_hostname = parsed.hostname.map(|x| Bytes::from(x.to_owned()));
-    if let Some(parsed_host) = parsed_hostname.or(default_host) {
-        event.as_mut_log().insert(host_key, parsed_host);
-    }
+    match parsed {
+        None => {
+            let log = event.as_mut_log();
+            log.insert(PARSE_FAILURE_KEY, true);
+            if let Some(default_host) = default_host {
+                log.insert(host_key, default_host);
+            }
+            log.insert(event::log_schema().timestamp_key().clone(), Utc::now());
+        }
+        Some(parsed) => {
+            let log = event.as_mut_log();
+            let parsed_hostname = parsed.hostname.map(|x| Bytes::from(x.to_owned()));
+            if let Some(parsed_host) = parsed_hostname.or(default_host) {
+                log.insert(host_key, parsed_host);
+            }
 
-    let timestamp = parsed
-        .timestamp
-        .map(|ts| ts.into())
-        .unwrap_or_else(Utc::now);
-    event
-        .as_mut_log()
-        .insert(event::log_schema().timestamp_key().clone(), timestamp);
+            let timestamp = parsed
+                .timestamp
+                .map(|ts| ts.into())
+                .unwrap_or_else(Utc::now);
+            log.insert(event::log_schema().timestamp_key().clone(), timestamp);
 
-    insert_fields_from_syslog(&mut event, parsed);
+            insert_syslog_fields(log, &parsed, options.structured_data);
+        }
+    }
 
     emit!(SyslogEventReceived {
         byte_size: line.len()
@@ -357,48 +413,20 @@ fn event_from_str(host_key: &str, default_host: Option<Bytes>, line: &str) -> Op
     Some(event)
 }
 
-fn insert_fields_from_syslog(event: &mut Event, parsed: Message<&str>) {
-    let log = event.as_mut_log();
-
-    if let Some(host) = parsed.hostname {
-        log.insert("hostname", host);
-    }
-    if let Some(severity) = parsed.severity {
-        log.insert("severity", severity.as_str());
-    }
-    if let Some(facility) = parsed.facility {
-        log.insert("facility", facility.as_str());
-    }
-    if let Protocol::RFC5424(version) = parsed.protocol {
-        log.insert("version", version as i64);
-    }
-    if let Some(app_name) = parsed.appname {
-        log.insert("appname", app_name);
-    }
-    if let Some(msg_id) = parsed.msgid {
-        log.insert("msgid", msg_id);
-    }
-    if let Some(procid) = parsed.procid {
-        let value: Value = match procid {
-            ProcId::PID(pid) => pid.into(),
-            ProcId::Name(name) => name.into(),
-        };
-        log.insert("procid", value);
-    }
-
-    for element in parsed.structured_data.iter() {
-        for (name, value) in element.params.iter() {
-            let key = format!("{}.{}", element.id, name);
-            log.insert(key, *value);
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
-    use super::{event_from_str, SyslogConfig};
+    use super::{
+        Framing, ParseFailure, ParseOptions, StructuredData, SyslogConfig, SyslogDecoder,
+        PARSE_FAILURE_KEY,
+    };
     use crate::event::{self, Event};
+    use bytes05::{Bytes, BytesMut};
     use chrono::TimeZone;
+    use tokio_util::codec::Decoder;
+
+    fn event_from_str(host_key: &str, default_host: Option<Bytes>, line: &str) -> Option<Event> {
+        super::event_from_str(host_key, default_host, line, &ParseOptions::default())
+    }
 
     #[test]
     fn config_tcp() {
@@ -425,6 +453,37 @@ mod test {
         assert!(config.mode.is_udp());
     }
 
+    #[test]
+    fn config_framing_and_parsing() {
+        let config: SyslogConfig = toml::from_str(
+            r#"
+            mode = "tcp"
+            address = "127.0.0.1:1235"
+            framing = "octet_counting"
+            structured_data = "flat"
+            on_parse_failure = "drop"
+          "#,
+        )
+        .unwrap();
+        assert_eq!(config.framing, Framing::OctetCounting);
+        assert_eq!(config.structured_data, StructuredData::Flat);
+        assert_eq!(config.on_parse_failure, ParseFailure::Drop);
+    }
+
+    #[test]
+    fn config_framing_defaults() {
+        let config: SyslogConfig = toml::from_str(
+            r#"
+            mode = "tcp"
+            address = "127.0.0.1:1235"
+          "#,
+        )
+        .unwrap();
+        assert_eq!(config.framing, Framing::Auto);
+        assert_eq!(config.structured_data, StructuredData::Nested);
+        assert_eq!(config.on_parse_failure, ParseFailure::Passthrough);
+    }
+
     #[cfg(unix)]
     #[test]
     fn config_unix() {
@@ -668,4 +727,90 @@ mod test {
             expected
         );
     }
+
+    fn decode_all(framing: Framing, input: &str) -> Vec<String> {
+        let mut decoder = SyslogDecoder::new(1024, framing);
+        let mut buf = BytesMut::from(input);
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.decode_eof(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn decodes_octet_counting_auto() {
+        let frames = decode_all(Framing::Auto, "5 hello6 world!<13>plain\n");
+        assert_eq!(frames, vec!["hello", "world!", "<13>plain"]);
+    }
+
+    #[test]
+    fn decodes_octet_counting_explicit() {
+        let frames = decode_all(Framing::OctetCounting, "5 hello\n6 world!\r\n");
+        assert_eq!(frames, vec!["hello", "world!"]);
+
+        let mut decoder = SyslogDecoder::new(1024, Framing::OctetCounting);
+        let mut buf = BytesMut::from("<13>not counted\n");
+        assert!(decoder.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn decodes_newline_delimited_explicit() {
+        let frames = decode_all(Framing::NewlineDelimited, "5 hello\n6 world!\n");
+        assert_eq!(frames, vec!["5 hello", "6 world!"]);
+    }
+
+    #[test]
+    fn discards_oversized_octet_count() {
+        let mut decoder = SyslogDecoder::new(16, Framing::Auto);
+        let mut buf = BytesMut::from("21 <13>this is too long");
+        assert_eq!(decoder.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b"!5 hello");
+        assert_eq!(decoder.decode(&mut buf).unwrap(), Some("hello".into()));
+    }
+
+    #[test]
+    fn flattens_structured_data() {
+        let raw = r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [origin ip="192.168.0.1" software="test"] hi"#;
+        let options = ParseOptions {
+            structured_data: StructuredData::Flat,
+            ..ParseOptions::default()
+        };
+
+        let event = super::event_from_str("host", None, raw, &options).unwrap();
+        let log = event.as_log();
+        assert_eq!(log.get_flat("origin.ip"), Some(&"192.168.0.1".into()));
+        assert_eq!(log.get_flat("origin.software"), Some(&"test".into()));
+        assert!(log.get_flat("origin").is_none());
+    }
+
+    #[test]
+    fn passes_through_unparsable_lines() {
+        let raw = "this is not syslog";
+
+        let event = event_from_str("host", None, raw).unwrap();
+        let log = event.as_log();
+        assert_eq!(
+            log[&event::log_schema().message_key()],
+            "this is not syslog".into()
+        );
+        assert_eq!(log[&PARSE_FAILURE_KEY.into()], true.into());
+
+        let parsed = event_from_str("host", None, "<13>Feb 13 20:07:26 host root: hi").unwrap();
+        assert!(parsed.as_log().get(&PARSE_FAILURE_KEY.into()).is_none());
+    }
+
+    #[test]
+    fn drops_unparsable_lines() {
+        let options = ParseOptions {
+            on_parse_failure: ParseFailure::Drop,
+            ..ParseOptions::default()
+        };
+
+        assert!(super::event_from_str("host", None, "this is not syslog", &options).is_none());
+        assert!(
+            super::event_from_str("host", None, "<13>Feb 13 20:07:26 host root: hi", &options)
+                .is_some()
+        );
+    }
 }
@@ -0,0 +1,239 @@
+use bytes05::{Buf, Bytes, BytesMut};
+use codec::{BytesDelimitedCodec, OctetCountingCodec};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use std::io;
+use tokio_util::codec::Decoder;
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display("Frame delimiter {:?} must be a single ASCII character", delimiter))]
+    NonAsciiDelimiter { delimiter: char },
+}
+
+/// How the bytes read by a source are split up into frames, each of which
+/// becomes one event.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum FramingConfig {
+    /// The whole payload, such as a datagram, HTTP body or Kafka message, or
+    /// everything read from a stream until it is closed, is one frame.
+    Bytes,
+    NewlineDelimited,
+    CharacterDelimited {
+        delimiter: char,
+    },
+    /// Octet counting as described in RFC 6587, used by syslog over TCP.
+    OctetCounting,
+    /// Each frame is prefixed by its length as a 4 byte big-endian integer.
+    LengthDelimited,
+}
+
+impl FramingConfig {
+    pub fn build(&self, max_length: usize) -> crate::Result<Framer> {
+        Ok(match self {
+            Self::Bytes => Framer::Bytes(BytesFramer { max_length }),
+            Self::NewlineDelimited => {
+                Framer::Delimited(BytesDelimitedCodec::new_with_max_length(b'\n', max_length))
+            }
+            Self::CharacterDelimited { delimiter } => {
+                if !delimiter.is_ascii() {
+                    return Err(BuildError::NonAsciiDelimiter {
+                        delimiter: *delimiter,
+                    }
+                    .into());
+                }
+                Framer::Delimited(BytesDelimitedCodec::new_with_max_length(
+                    *delimiter as u8,
+                    max_length,
+                ))
+            }
+            Self::OctetCounting => {
+                Framer::OctetCounting(OctetCountingCodec::new_with_max_length(max_length))
+            }
+            Self::LengthDelimited => Framer::LengthDelimited(LengthDelimitedFramer { max_length }),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Framer {
+    Bytes(BytesFramer),
+    Delimited(BytesDelimitedCodec),
+    OctetCounting(OctetCountingCodec),
+    LengthDelimited(LengthDelimitedFramer),
+}
+
+impl Decoder for Framer {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        match self {
+            Self::Bytes(framer) => framer.decode(buf),
+            Self::Delimited(codec) => codec.decode(buf),
+            Self::OctetCounting(codec) => codec.decode(buf),
+            Self::LengthDelimited(framer) => framer.decode(buf),
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        match self {
+            Self::Bytes(framer) => framer.decode_eof(buf),
+            Self::Delimited(codec) => codec.decode_eof(buf),
+            Self::OctetCounting(codec) => codec.decode_eof(buf),
+            Self::LengthDelimited(framer) => framer.decode_eof(buf),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BytesFramer {
+    max_length: usize,
+}
+
+impl Decoder for BytesFramer {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        if buf.len() > self.max_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Frame length limit exceeded",
+            ));
+        }
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        self.decode(buf)?;
+        if buf.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(buf.split_to(buf.len()).freeze()))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedFramer {
+    max_length: usize,
+}
+
+impl Decoder for LengthDelimitedFramer {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        if buf.len() < 4 {
+            return Ok(None);
+        }
+
+        let mut header = [0; 4];
+        header.copy_from_slice(&buf[..4]);
+        let len = u32::from_be_bytes(header) as usize;
+        if len > self.max_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Frame length limit exceeded",
+            ));
+        }
+
+        if buf.len() < 4 + len {
+            return Ok(None);
+        }
+
+        buf.advance(4);
+        Ok(Some(buf.split_to(len).freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(config: FramingConfig, input: &[u8]) -> Vec<Bytes> {
+        let mut framer = config.build(100).unwrap();
+        let mut buf = BytesMut::from(input);
+        let mut frames = Vec::new();
+        while let Some(frame) = framer.decode_eof(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn parse_framing_config() {
+        let config: FramingConfig = toml::from_str(
+            r#"
+            method = "character_delimited"
+            delimiter = ","
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config, FramingConfig::CharacterDelimited { delimiter: ',' });
+
+        let config: FramingConfig = toml::from_str(r#"method = "octet_counting""#).unwrap();
+        assert_eq!(config, FramingConfig::OctetCounting);
+    }
+
+    #[test]
+    fn rejects_non_ascii_delimiter() {
+        assert!(FramingConfig::CharacterDelimited { delimiter: 'é' }
+            .build(100)
+            .is_err());
+    }
+
+    #[test]
+    fn frames_bytes() {
+        assert_eq!(
+            frames(FramingConfig::Bytes, b"one\ntwo"),
+            vec![Bytes::from("one\ntwo")]
+        );
+        assert!(FramingConfig::Bytes
+            .build(2)
+            .unwrap()
+            .decode(&mut BytesMut::from(&b"one"[..]))
+            .is_err());
+    }
+
+    #[test]
+    fn frames_delimited() {
+        assert_eq!(
+            frames(FramingConfig::NewlineDelimited, b"one\ntwo"),
+            vec![Bytes::from("one"), Bytes::from("two")]
+        );
+        assert_eq!(
+            frames(
+                FramingConfig::CharacterDelimited { delimiter: ',' },
+                b"one,two,"
+            ),
+            vec![Bytes::from("one"), Bytes::from("two")]
+        );
+    }
+
+    #[test]
+    fn frames_octet_counting() {
+        assert_eq!(
+            frames(FramingConfig::OctetCounting, b"3 one5 three"),
+            vec![Bytes::from("one"), Bytes::from("three")]
+        );
+    }
+
+    #[test]
+    fn frames_length_delimited() {
+        assert_eq!(
+            frames(
+                FramingConfig::LengthDelimited,
+                b"\x00\x00\x00\x03one\x00\x00\x00\x05three"
+            ),
+            vec![Bytes::from("one"), Bytes::from("three")]
+        );
+
+        let mut framer = FramingConfig::LengthDelimited.build(100).unwrap();
+        let mut buf = BytesMut::from(&b"\x00\x00\x00\x05thr"[..]);
+        assert!(framer.decode(&mut buf).unwrap().is_none());
+        assert!(framer.decode_eof(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! Decoding related code shared by sources.
+//!
+//! Sources that read raw bytes offer two options: `framing` splits what was
+//! read into frames, and `decoding` parses each frame into an event. A
+//! source picks the framing that suits its transport by default, `decoding`
+//! defaults to `bytes`, which keeps the frame as the `message` field.
+
+mod framing;
+mod protobuf;
+
+pub use framing::{Framer, FramingConfig};
+
+use crate::event::{self, Event, LogEvent, Value};
+use bytes05::{Bytes, BytesMut};
+use chrono::{Datelike, Utc};
+use protobuf::{ProtobufDecoder, ProtobufError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use snafu::{ResultExt, Snafu};
+use std::{path::PathBuf, sync::Arc};
+use syslog_loose::{IncompleteDate, Message, ProcId, Protocol};
+use tokio_util::codec::Decoder as _;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "codec", rename_all = "snake_case")]
+pub enum DecodingConfig {
+    Bytes,
+    Json,
+    Syslog,
+    Logfmt,
+    Protobuf {
+        desc_file: PathBuf,
+        message_type: String,
+    },
+}
+
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        Self::Bytes
+    }
+}
+
+impl DecodingConfig {
+    pub fn build(&self) -> crate::Result<Deserializer> {
+        Ok(match self {
+            Self::Bytes => Deserializer::Bytes,
+            Self::Json => Deserializer::Json,
+            Self::Syslog => Deserializer::Syslog,
+            Self::Logfmt => Deserializer::Logfmt,
+            Self::Protobuf {
+                desc_file,
+                message_type,
+            } => Deserializer::Protobuf(Arc::new(ProtobufDecoder::load(desc_file, message_type)?)),
+        })
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum DecodeError {
+    #[snafu(display("Unable to split input into frames: {}", source))]
+    Framing { source: std::io::Error },
+    #[snafu(display("Invalid JSON: {}", source))]
+    Json { source: serde_json::Error },
+    #[snafu(display("Expected a JSON object, got {}", kind))]
+    NotAnObject { kind: &'static str },
+    #[snafu(display("Frame is not valid UTF-8: {}", source))]
+    Utf8 { source: std::str::Utf8Error },
+    #[snafu(display("Frame is not a syslog message"))]
+    Syslog,
+    #[snafu(display("Invalid protobuf message: {}", source))]
+    Protobuf { source: ProtobufError },
+}
+
+/// Parses a single frame into an event.
+#[derive(Debug, Clone)]
+pub enum Deserializer {
+    Bytes,
+    Json,
+    Syslog,
+    Logfmt,
+    Protobuf(Arc<ProtobufDecoder>),
+}
+
+impl Deserializer {
+    pub fn parse(&self, frame: Bytes) -> Result<Event, DecodeError> {
+        match self {
+            Self::Bytes => Ok(Event::from(frame)),
+            Self::Json => parse_json(&frame),
+            Self::Syslog => parse_syslog(std::str::from_utf8(&frame).context(Utf8)?),
+            Self::Logfmt => {
+                let line = std::str::from_utf8(&frame).context(Utf8)?;
+                Ok(new_event(logfmt::parse(line).into_iter().map(
+                    |logfmt::Pair { key, val }| {
+                        // Bare keys are flags.
+                        let value = val.map_or(Value::Boolean(true), Value::from);
+                        (key, value)
+                    },
+                )))
+            }
+            Self::Protobuf(decoder) => decoder.decode(&frame).map(new_event).context(Protobuf),
+        }
+    }
+}
+
+/// Splits payloads into frames and parses each of them into an event.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    framer: Framer,
+    deserializer: Deserializer,
+}
+
+impl Decoder {
+    pub fn new(framer: Framer, deserializer: Deserializer) -> Self {
+        Self {
+            framer,
+            deserializer,
+        }
+    }
+
+    pub fn framer(&self) -> Framer {
+        self.framer.clone()
+    }
+
+    pub fn parse(&self, frame: Bytes) -> Result<Event, DecodeError> {
+        self.deserializer.parse(frame)
+    }
+
+    /// Decodes a payload that is complete in itself, such as a datagram or
+    /// an HTTP body. Framing errors end the payload, as the rest of it can't
+    /// be split up reliably.
+    pub fn decode_payload(&self, payload: &[u8]) -> Vec<Result<Event, DecodeError>> {
+        let mut framer = self.framer();
+        let mut buf = BytesMut::from(payload);
+        let mut events = Vec::new();
+        loop {
+            match framer.decode_eof(&mut buf) {
+                Ok(Some(frame)) => events.push(self.parse(frame)),
+                Ok(None) => break,
+                Err(source) => {
+                    events.push(Err(DecodeError::Framing { source }));
+                    break;
+                }
+            }
+        }
+        events
+    }
+}
+
+/// Creates an event of the decoded fields. Their keys are taken as is, a
+/// dot in one doesn't make it a path into nested fields.
+fn new_event<K: Into<String>>(fields: impl IntoIterator<Item = (K, Value)>) -> Event {
+    let mut event = Event::new_empty_log();
+    let log = event.as_mut_log();
+    log.insert(event::log_schema().timestamp_key().clone(), Utc::now());
+    for (key, value) in fields {
+        log.insert_flat(key, value);
+    }
+    event
+}
+
+fn parse_json(frame: &[u8]) -> Result<Event, DecodeError> {
+    match serde_json::from_slice(frame).context(Json)? {
+        JsonValue::Object(map) => Ok(new_event(
+            map.into_iter()
+                .map(|(key, value)| (key, Value::from(value))),
+        )),
+        other => Err(DecodeError::NotAnObject {
+            kind: match other {
+                JsonValue::Array(_) => "an array",
+                JsonValue::String(_) => "a string",
+                JsonValue::Number(_) => "a number",
+                JsonValue::Bool(_) => "a boolean",
+                _ => "null",
+            },
+        }),
+    }
+}
+
+fn parse_syslog(line: &str) -> Result<Event, DecodeError> {
+    let parsed = parse_syslog_message(line).ok_or(DecodeError::Syslog)?;
+
+    let mut event = Event::from(parsed.msg);
+    let log = event.as_mut_log();
+    if let Some(timestamp) = parsed.timestamp {
+        log.insert(
+            event::log_schema().timestamp_key().clone(),
+            chrono::DateTime::<Utc>::from(timestamp),
+        );
+    }
+    insert_syslog_fields(log, &parsed, StructuredData::Nested);
+    Ok(event)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuredData {
+    /// `[origin ip="1.2.3.4"]` becomes `{"origin": {"ip": "1.2.3.4"}}`.
+    Nested,
+    /// `[origin ip="1.2.3.4"]` becomes `{"origin.ip": "1.2.3.4"}`.
+    Flat,
+}
+
+impl Default for StructuredData {
+    fn default() -> Self {
+        StructuredData::Nested
+    }
+}
+
+/// Parses a syslog line, or returns None if it isn't syslog at all.
+pub fn parse_syslog_message(line: &str) -> Option<Message<&str>> {
+    let parsed = syslog_loose::parse_message_with_year(line.trim(), resolve_year);
+
+    // `syslog_loose` never fails outright, it hands back the whole line as
+    // the message instead. A line that yielded neither a priority nor a
+    // timestamp wasn't syslog to begin with.
+    if parsed.facility.is_none() && parsed.severity.is_none() && parsed.timestamp.is_none() {
+        None
+    } else {
+        Some(parsed)
+    }
+}
+
+/// Resolves the year for syslog messages that don't include it. If the
+/// current month is January, and the message is for December, it takes the
+/// previous year. Otherwise, it takes the current year.
+fn resolve_year((month, _date, _hour, _min, _sec): IncompleteDate) -> i32 {
+    let now = Utc::now();
+    if now.month() == 1 && month == 12 {
+        now.year() - 1
+    } else {
+        now.year()
+    }
+}
+
+/// Inserts the header fields and structured data of a parsed syslog message.
+pub fn insert_syslog_fields(
+    log: &mut LogEvent,
+    parsed: &Message<&str>,
+    structured_data: StructuredData,
+) {
+    if let Some(host) = parsed.hostname {
+        log.insert("hostname", host);
+    }
+    if let Some(severity) = &parsed.severity {
+        log.insert("severity", severity.as_str());
+    }
+    if let Some(facility) = &parsed.facility {
+        log.insert("facility", facility.as_str());
+    }
+    if let Protocol::RFC5424(version) = parsed.protocol {
+        log.insert("version", version as i64);
+    }
+    if let Some(app_name) = parsed.appname {
+        log.insert("appname", app_name);
+    }
+    if let Some(msg_id) = parsed.msgid {
+        log.insert("msgid", msg_id);
+    }
+    if let Some(procid) = &parsed.procid {
+        let value: Value = match procid {
+            ProcId::PID(pid) => (*pid).into(),
+            ProcId::Name(name) => (*name).into(),
+        };
+        log.insert("procid", value);
+    }
+    for element in parsed.structured_data.iter() {
+        for (name, value) in element.params.iter() {
+            let key = format!("{}.{}", element.id, name);
+            match structured_data {
+                StructuredData::Nested => {
+                    log.insert(key, *value);
+                }
+                StructuredData::Flat => log.insert_flat(key, *value),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(config: DecodingConfig, frame: &str) -> Result<Event, DecodeError> {
+        config.build().unwrap().parse(Bytes::from(frame.to_owned()))
+    }
+
+    #[test]
+    fn parse_decoding_config() {
+        let config: DecodingConfig = toml::from_str(r#"codec = "json""#).unwrap();
+        assert_eq!(config, DecodingConfig::Json);
+
+        let config: DecodingConfig = toml::from_str(
+            r#"
+            codec = "protobuf"
+            desc_file = "/etc/vector/logs.desc"
+            message_type = "logs.Log"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            DecodingConfig::Protobuf {
+                desc_file: "/etc/vector/logs.desc".into(),
+                message_type: "logs.Log".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_bytes() {
+        let event = parse(DecodingConfig::Bytes, "hello world").unwrap();
+        assert_eq!(
+            event.as_log()[&event::log_schema().message_key()],
+            "hello world".into()
+        );
+    }
+
+    #[test]
+    fn decodes_json() {
+        let event = parse(DecodingConfig::Json, r#"{"message":"hi","count":3}"#).unwrap();
+        let log = event.as_log();
+        assert_eq!(log[&"message".into()], "hi".into());
+        assert_eq!(log[&"count".into()], Value::Integer(3));
+        assert!(log.contains(&event::log_schema().timestamp_key()));
+
+        let event = parse(DecodingConfig::Json, r#"{"http.status":200}"#).unwrap();
+        let log = event.as_log();
+        assert_eq!(log.get_flat("http.status"), Some(&Value::Integer(200)));
+        assert!(!log.contains(&"http".into()));
+
+        assert!(parse(DecodingConfig::Json, "[1, 2]").is_err());
+        assert!(parse(DecodingConfig::Json, "{").is_err());
+    }
+
+    #[test]
+    fn decodes_syslog() {
+        let event = parse(
+            DecodingConfig::Syslog,
+            r#"<34>1 2020-03-13T20:45:38.119Z dynamicwireless.name non 2426 ID931 [exampleSDID@32473 iut="3"] Try to override"#,
+        )
+        .unwrap();
+        let log = event.as_log();
+        assert_eq!(
+            log[&event::log_schema().message_key()],
+            "Try to override".into()
+        );
+        assert_eq!(log[&"hostname".into()], "dynamicwireless.name".into());
+        assert_eq!(log[&"severity".into()], "crit".into());
+        assert_eq!(log[&"facility".into()], "auth".into());
+        assert_eq!(log[&"procid".into()], Value::Integer(2426));
+        assert_eq!(log[&"exampleSDID@32473.iut".into()], "3".into());
+
+        assert!(parse(DecodingConfig::Syslog, "just some text").is_err());
+    }
+
+    #[test]
+    fn decodes_logfmt() {
+        let event = parse(
+            DecodingConfig::Logfmt,
+            "level=info msg=\"hello world\" debug",
+        )
+        .unwrap();
+        let log = event.as_log();
+        assert_eq!(log[&"level".into()], "info".into());
+        assert_eq!(log[&"msg".into()], "hello world".into());
+        assert_eq!(log[&"debug".into()], Value::Boolean(true));
+    }
+
+    #[test]
+    fn decodes_payload_frames() {
+        let decoder = Decoder::new(
+            FramingConfig::NewlineDelimited.build(100).unwrap(),
+            DecodingConfig::Json.build().unwrap(),
+        );
+        let events = decoder.decode_payload(b"{\"a\":1}\nnot json\n{\"a\":2}");
+        assert_eq!(events.len(), 3);
+        assert!(events[0].is_ok());
+        assert!(events[1].is_err());
+        assert!(events[2].is_ok());
+    }
+}
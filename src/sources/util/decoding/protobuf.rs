@@ -0,0 +1,523 @@
+use crate::event::Value;
+use prost::Message;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorSet,
+};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Snafu)]
+pub enum BuildError {
+    #[snafu(display("Unable to read descriptor file {:?}: {}", path, source))]
+    ReadDescriptors {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Invalid descriptor file {:?}: {}", path, source))]
+    ParseDescriptors {
+        path: PathBuf,
+        source: prost::DecodeError,
+    },
+    #[snafu(display("Message type {:?} not found in descriptor file", message_type))]
+    UnknownMessageType { message_type: String },
+}
+
+#[derive(Debug, Snafu)]
+pub enum ProtobufError {
+    #[snafu(display("unexpected end of message"))]
+    Truncated,
+    #[snafu(display("invalid varint"))]
+    InvalidVarint,
+    #[snafu(display("field {:?} has unexpected wire type {}", field, wire_type))]
+    WireType { field: String, wire_type: u64 },
+    #[snafu(display("field {:?} uses groups, which are not supported", field))]
+    Group { field: String },
+    #[snafu(display("field {:?} is not valid UTF-8", field))]
+    Utf8 { field: String },
+    #[snafu(display("unknown message type {:?}", message_type))]
+    MissingType { message_type: String },
+    #[snafu(display("messages are nested more than {} levels deep", MAX_DEPTH))]
+    TooDeep,
+}
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LENGTH_DELIMITED: u64 = 2;
+const FIXED32: u64 = 5;
+
+/// How deep messages may be nested, so that malicious input can't overflow
+/// the stack.
+const MAX_DEPTH: usize = 100;
+
+/// Decodes messages of a single type using the descriptor set written by
+/// `protoc --descriptor_set_out`, so no generated code is needed.
+#[derive(Debug)]
+pub struct ProtobufDecoder {
+    messages: HashMap<String, DescriptorProto>,
+    enums: HashMap<String, EnumDescriptorProto>,
+    message_type: String,
+}
+
+impl ProtobufDecoder {
+    pub fn load(path: &Path, message_type: &str) -> Result<Self, BuildError> {
+        let buf = std::fs::read(path).context(ReadDescriptors { path })?;
+        let descriptors = FileDescriptorSet::decode(&buf[..]).context(ParseDescriptors { path })?;
+
+        let mut decoder = Self {
+            messages: HashMap::new(),
+            enums: HashMap::new(),
+            // Types are referenced by their fully qualified name, which
+            // starts with a dot.
+            message_type: format!(".{}", message_type.trim_start_matches('.')),
+        };
+
+        for file in descriptors.file {
+            let scope = match file.package() {
+                "" => String::new(),
+                package => format!(".{}", package),
+            };
+            for message in file.message_type {
+                decoder.register_message(&scope, message);
+            }
+            for enumeration in file.enum_type {
+                decoder
+                    .enums
+                    .insert(format!("{}.{}", scope, enumeration.name()), enumeration);
+            }
+        }
+
+        if !decoder.messages.contains_key(&decoder.message_type) {
+            return Err(BuildError::UnknownMessageType {
+                message_type: message_type.into(),
+            });
+        }
+
+        Ok(decoder)
+    }
+
+    fn register_message(&mut self, scope: &str, mut message: DescriptorProto) {
+        let name = format!("{}.{}", scope, message.name());
+        for nested in std::mem::take(&mut message.nested_type) {
+            self.register_message(&name, nested);
+        }
+        for enumeration in std::mem::take(&mut message.enum_type) {
+            self.enums
+                .insert(format!("{}.{}", name, enumeration.name()), enumeration);
+        }
+        self.messages.insert(name, message);
+    }
+
+    pub fn decode(&self, buf: &[u8]) -> Result<BTreeMap<String, Value>, ProtobufError> {
+        self.decode_message(&self.message_type, buf, 0)
+    }
+
+    fn decode_message(
+        &self,
+        message_type: &str,
+        buf: &[u8],
+        depth: usize,
+    ) -> Result<BTreeMap<String, Value>, ProtobufError> {
+        if depth > MAX_DEPTH {
+            return Err(ProtobufError::TooDeep);
+        }
+
+        let descriptor =
+            self.messages
+                .get(message_type)
+                .ok_or_else(|| ProtobufError::MissingType {
+                    message_type: message_type.into(),
+                })?;
+
+        let mut reader = Reader { buf };
+        let mut fields = BTreeMap::new();
+        while !reader.buf.is_empty() {
+            let key = reader.varint()?;
+            let (number, wire_type) = (key >> 3, key & 0x7);
+
+            let field = match descriptor
+                .field
+                .iter()
+                .find(|field| field.number() as u64 == number)
+            {
+                Some(field) => field,
+                None => {
+                    // Unknown fields are skipped, as generated code would.
+                    reader.skip(wire_type)?;
+                    continue;
+                }
+            };
+
+            let name = field.name().to_string();
+            let values = self.decode_field(field, wire_type, &mut reader, depth)?;
+
+            if field.label() != Label::Repeated {
+                // The last occurrence of a singular field wins.
+                if let Some(value) = values.into_iter().last() {
+                    fields.insert(name, value);
+                }
+            } else if self.is_map_entry(field) {
+                let map = fields
+                    .entry(name)
+                    .or_insert_with(|| Value::Map(BTreeMap::new()));
+                if let Value::Map(map) = map {
+                    for entry in values {
+                        if let Value::Map(mut entry) = entry {
+                            let key = entry
+                                .remove("key")
+                                .map(|key| key.to_string_lossy())
+                                .unwrap_or_default();
+                            map.insert(key, entry.remove("value").unwrap_or(Value::Null));
+                        }
+                    }
+                }
+            } else {
+                let array = fields
+                    .entry(name)
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(array) = array {
+                    array.extend(values);
+                }
+            }
+        }
+
+        Ok(fields)
+    }
+
+    fn is_map_entry(&self, field: &FieldDescriptorProto) -> bool {
+        field.r#type() == Type::Message
+            && self
+                .messages
+                .get(field.type_name())
+                .and_then(|message| message.options.as_ref())
+                .map_or(false, |options| options.map_entry())
+    }
+
+    /// Decodes a single occurrence of `field`, which holds several values
+    /// when it is a packed repeated field.
+    fn decode_field(
+        &self,
+        field: &FieldDescriptorProto,
+        wire_type: u64,
+        reader: &mut Reader<'_>,
+        depth: usize,
+    ) -> Result<Vec<Value>, ProtobufError> {
+        let field_type = field.r#type();
+        let expected = match field_type {
+            Type::Double | Type::Fixed64 | Type::Sfixed64 => FIXED64,
+            Type::Float | Type::Fixed32 | Type::Sfixed32 => FIXED32,
+            Type::Int32
+            | Type::Int64
+            | Type::Uint32
+            | Type::Uint64
+            | Type::Sint32
+            | Type::Sint64
+            | Type::Bool
+            | Type::Enum => VARINT,
+            Type::String | Type::Bytes | Type::Message => LENGTH_DELIMITED,
+            Type::Group => {
+                return Err(ProtobufError::Group {
+                    field: field.name().into(),
+                })
+            }
+        };
+
+        if wire_type == expected {
+            return self
+                .decode_value(field, reader, depth)
+                .map(|value| vec![value]);
+        }
+
+        if wire_type == LENGTH_DELIMITED && expected != LENGTH_DELIMITED {
+            let mut packed = Reader {
+                buf: reader.length_delimited()?,
+            };
+            let mut values = Vec::new();
+            while !packed.buf.is_empty() {
+                values.push(self.decode_value(field, &mut packed, depth)?);
+            }
+            return Ok(values);
+        }
+
+        Err(ProtobufError::WireType {
+            field: field.name().into(),
+            wire_type,
+        })
+    }
+
+    fn decode_value(
+        &self,
+        field: &FieldDescriptorProto,
+        reader: &mut Reader<'_>,
+        depth: usize,
+    ) -> Result<Value, ProtobufError> {
+        Ok(match field.r#type() {
+            Type::Double => f64::from_bits(reader.fixed64()?).into(),
+            Type::Float => (f32::from_bits(reader.fixed32()?) as f64).into(),
+            Type::Int32 => (reader.varint()? as i32).into(),
+            Type::Int64 => (reader.varint()? as i64).into(),
+            Type::Uint32 => (reader.varint()? as u32 as i64).into(),
+            Type::Uint64 => (reader.varint()? as i64).into(),
+            Type::Sint32 | Type::Sint64 => {
+                let value = reader.varint()?;
+                (((value >> 1) as i64) ^ -((value & 1) as i64)).into()
+            }
+            Type::Fixed32 => (reader.fixed32()? as i64).into(),
+            Type::Sfixed32 => (reader.fixed32()? as i32).into(),
+            Type::Fixed64 | Type::Sfixed64 => (reader.fixed64()? as i64).into(),
+            Type::Bool => (reader.varint()? != 0).into(),
+            Type::Enum => {
+                let number = reader.varint()? as i32;
+                self.enums
+                    .get(field.type_name())
+                    .and_then(|enumeration| {
+                        enumeration
+                            .value
+                            .iter()
+                            .find(|value| value.number() == number)
+                    })
+                    .map_or_else(|| number.into(), |value| value.name().into())
+            }
+            Type::String => std::str::from_utf8(reader.length_delimited()?)
+                .map_err(|_| ProtobufError::Utf8 {
+                    field: field.name().into(),
+                })?
+                .into(),
+            Type::Bytes => reader.length_delimited()?.into(),
+            Type::Message => Value::Map(self.decode_message(
+                field.type_name(),
+                reader.length_delimited()?,
+                depth + 1,
+            )?),
+            Type::Group => {
+                return Err(ProtobufError::Group {
+                    field: field.name().into(),
+                })
+            }
+        })
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProtobufError> {
+        if self.buf.len() < len {
+            return Err(ProtobufError::Truncated);
+        }
+        let (head, tail) = self.buf.split_at(len);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u64, ProtobufError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(ProtobufError::InvalidVarint)
+    }
+
+    fn fixed32(&mut self) -> Result<u32, ProtobufError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn fixed64(&mut self) -> Result<u64, ProtobufError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn length_delimited(&mut self) -> Result<&'a [u8], ProtobufError> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    fn skip(&mut self, wire_type: u64) -> Result<(), ProtobufError> {
+        match wire_type {
+            VARINT => self.varint().map(|_| ()),
+            FIXED64 => self.take(8).map(|_| ()),
+            LENGTH_DELIMITED => self.length_delimited().map(|_| ()),
+            FIXED32 => self.take(4).map(|_| ()),
+            _ => Err(ProtobufError::WireType {
+                field: String::new(),
+                wire_type,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_types::{EnumValueDescriptorProto, FileDescriptorProto, MessageOptions};
+    use std::io::Write;
+
+    fn field(name: &str, number: i32, r#type: Type, label: Label) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.into()),
+            number: Some(number),
+            r#type: Some(r#type as i32),
+            label: Some(label as i32),
+            ..Default::default()
+        }
+    }
+
+    fn typed(mut field: FieldDescriptorProto, type_name: &str) -> FieldDescriptorProto {
+        field.type_name = Some(type_name.into());
+        field
+    }
+
+    fn descriptors() -> FileDescriptorSet {
+        let entry = DescriptorProto {
+            name: Some("LabelsEntry".into()),
+            field: vec![
+                field("key", 1, Type::String, Label::Optional),
+                field("value", 2, Type::String, Label::Optional),
+            ],
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let level = EnumDescriptorProto {
+            name: Some("Level".into()),
+            value: vec![
+                EnumValueDescriptorProto {
+                    name: Some("INFO".into()),
+                    number: Some(0),
+                    ..Default::default()
+                },
+                EnumValueDescriptorProto {
+                    name: Some("ERROR".into()),
+                    number: Some(1),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let log = DescriptorProto {
+            name: Some("Log".into()),
+            field: vec![
+                field("message", 1, Type::String, Label::Optional),
+                field("code", 2, Type::Sint32, Label::Optional),
+                field("ratio", 3, Type::Double, Label::Optional),
+                field("ids", 4, Type::Int64, Label::Repeated),
+                typed(
+                    field("labels", 5, Type::Message, Label::Repeated),
+                    ".test.Log.LabelsEntry",
+                ),
+                typed(
+                    field("level", 6, Type::Enum, Label::Optional),
+                    ".test.Log.Level",
+                ),
+                typed(
+                    field("parent", 7, Type::Message, Label::Optional),
+                    ".test.Log",
+                ),
+            ],
+            nested_type: vec![entry],
+            enum_type: vec![level],
+            ..Default::default()
+        };
+
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("test.proto".into()),
+                package: Some("test".into()),
+                message_type: vec![log],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn decoder(message_type: &str) -> Result<ProtobufDecoder, BuildError> {
+        let mut buf = Vec::new();
+        descriptors().encode(&mut buf).unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&buf).unwrap();
+        ProtobufDecoder::load(file.path(), message_type)
+    }
+
+    #[test]
+    fn protobuf_decodes_message() {
+        let decoder = decoder("test.Log").unwrap();
+
+        let mut message = vec![0x0a, 5];
+        message.extend_from_slice(b"hello");
+        // code = -2, zigzag encoded
+        message.extend_from_slice(&[0x10, 3]);
+        message.push(0x19);
+        message.extend_from_slice(&0.5f64.to_le_bytes());
+        // packed ids = [1, 300]
+        message.extend_from_slice(&[0x22, 3, 1, 0xac, 0x02]);
+        // labels = { "a": "b" }
+        message.extend_from_slice(&[0x2a, 6, 0x0a, 1, b'a', 0x12, 1, b'b']);
+        message.extend_from_slice(&[0x30, 1]);
+        // unknown field 15
+        message.extend_from_slice(&[0x78, 7]);
+
+        let fields = decoder.decode(&message).unwrap();
+        assert_eq!(fields["message"], "hello".into());
+        assert_eq!(fields["code"], Value::Integer(-2));
+        assert_eq!(fields["ratio"], Value::Float(0.5));
+        assert_eq!(
+            fields["ids"],
+            Value::Array(vec![Value::Integer(1), Value::Integer(300)])
+        );
+        let mut labels = BTreeMap::new();
+        labels.insert("a".to_string(), Value::from("b"));
+        assert_eq!(fields["labels"], Value::Map(labels));
+        assert_eq!(fields["level"], "ERROR".into());
+        assert_eq!(fields.len(), 6);
+    }
+
+    #[test]
+    fn protobuf_rejects_truncated_message() {
+        let decoder = decoder(".test.Log").unwrap();
+        assert!(decoder.decode(&[0x0a, 5, b'h']).is_err());
+    }
+
+    #[test]
+    fn protobuf_limits_nesting() {
+        let decoder = decoder("test.Log").unwrap();
+
+        let nested = |depth: usize| {
+            (0..depth).fold(Vec::new(), |inner, _| {
+                let mut message = vec![0x3a];
+                let mut len = inner.len();
+                while len >= 0x80 {
+                    message.push(len as u8 | 0x80);
+                    len >>= 7;
+                }
+                message.push(len as u8);
+                message.extend(inner);
+                message
+            })
+        };
+
+        assert!(decoder.decode(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            decoder.decode(&nested(MAX_DEPTH + 1)),
+            Err(ProtobufError::TooDeep)
+        ));
+    }
+
+    #[test]
+    fn protobuf_rejects_unknown_message_type() {
+        assert!(decoder("test.Missing").is_err());
+    }
+}
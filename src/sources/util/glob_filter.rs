@@ -0,0 +1,81 @@
+use glob::Pattern;
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum GlobFilterError {
+    #[snafu(display("Invalid glob pattern {:?}: {}", pattern, source))]
+    InvalidPattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+}
+
+/// A pair of include and exclude glob pattern lists.
+#[derive(Debug, Clone, Default)]
+pub struct GlobFilter {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+}
+
+impl GlobFilter {
+    /// Compiles the include and exclude patterns.
+    pub fn new(includes: &[String], excludes: &[String]) -> Result<Self, GlobFilterError> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern).context(InvalidPattern { pattern }))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            includes: compile(includes)?,
+            excludes: compile(excludes)?,
+        })
+    }
+
+    /// Checks whether the value passes the filter: it has to match at least
+    /// one of the include patterns (if there are any), and none of the
+    /// exclude patterns.
+    pub fn matches(&self, value: &str) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|pattern| pattern.matches(value)))
+            && !self.excludes.iter().any(|pattern| pattern.matches(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_filter(includes: &[&str], excludes: &[&str]) -> GlobFilter {
+        let to_owned = |patterns: &[&str]| -> Vec<String> {
+            patterns
+                .iter()
+                .map(|pattern| (*pattern).to_owned())
+                .collect()
+        };
+        GlobFilter::new(&to_owned(includes), &to_owned(excludes)).unwrap()
+    }
+
+    #[test]
+    fn matches() {
+        let cases = vec![
+            (make_filter(&[], &[]), "kube-system", true),
+            (make_filter(&["kube-*"], &[]), "kube-system", true),
+            (make_filter(&["kube-*"], &[]), "default", false),
+            (make_filter(&[], &["kube-*"]), "kube-system", false),
+            (make_filter(&[], &["kube-*"]), "default", true),
+            (make_filter(&["sd*"], &["sd?1"]), "sda", true),
+            (make_filter(&["sd*"], &["sd?1"]), "sda1", false),
+            (make_filter(&["sd*"], &["sd?1"]), "loop0", false),
+        ];
+
+        for (filter, value, expected) in cases {
+            assert_eq!(filter.matches(value), expected, "value: {}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let error = GlobFilter::new(&["[".to_owned()], &[]).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid glob pattern \"[\""));
+    }
+}
@@ -1,12 +1,24 @@
+#[cfg(feature = "sources-decoding")]
+pub mod decoding;
+#[cfg(any(feature = "sources-host_metrics", feature = "sources-kubernetes-logs"))]
+mod glob_filter;
 #[cfg(all(feature = "sources-tls", feature = "warp"))]
 mod http;
+#[cfg(any(feature = "sources-file", feature = "sources-docker"))]
+mod multiline_config;
 #[cfg(all(feature = "sources-tls", feature = "listenfd"))]
 mod tcp;
 #[cfg(all(unix, any(feature = "sources-socket", feature = "sources-syslog")))]
 mod unix;
 
+#[cfg(feature = "sources-decoding")]
+pub use self::decoding::{DecodingConfig, FramingConfig};
 #[cfg(all(feature = "sources-tls", feature = "warp"))]
 pub use self::http::{ErrorMessage, HttpSource};
+#[cfg(any(feature = "sources-host_metrics", feature = "sources-kubernetes-logs"))]
+pub use glob_filter::GlobFilter;
+#[cfg(any(feature = "sources-file", feature = "sources-docker"))]
+pub use multiline_config::MultilineConfig;
 #[cfg(all(feature = "sources-tls", feature = "listenfd"))]
 pub use tcp::{SocketListenAddr, TcpSource};
 #[cfg(all(unix, any(feature = "sources-socket", feature = "sources-syslog")))]
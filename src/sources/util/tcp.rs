@@ -4,16 +4,17 @@ use crate::{
     tls::{MaybeTlsIncomingStream, MaybeTlsListener, MaybeTlsSettings},
     Event, Pipeline,
 };
-use bytes05::Bytes;
+use bytes05::{Buf, Bytes, BytesMut};
 use futures::{
     compat::{Compat, Compat01As03, Future01CompatExt, Stream01CompatExt},
     StreamExt, TryStreamExt,
 };
-use futures01::{future, stream, Async, Future, Sink, Stream};
+use futures01::{future, try_ready, Async, AsyncSink, Future, Sink, Stream};
 use listenfd::ListenFd;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::{
-    fmt, io,
+    fmt,
+    io::{self, Write},
     net::{Shutdown, SocketAddr},
     time::{Duration, Instant},
 };
@@ -72,6 +73,13 @@ pub trait TcpSource: Clone + Send + 'static {
 
     fn build_event(&self, frame: <Self::Decoder as Decoder>::Item, host: Bytes) -> Option<Event>;
 
+    /// Bytes to write back to the peer once `frame`, and every event read
+    /// before it, has been accepted by the pipeline, for protocols where the
+    /// client waits on an acknowledgement.
+    fn build_acknowledgement(&self, _frame: &<Self::Decoder as Decoder>::Item) -> Option<Bytes> {
+        None
+    }
+
     fn run(
         self,
         addr: SocketListenAddr,
@@ -172,7 +180,13 @@ fn handle_stream(
     let mut _token = None;
     let socket = Compat01As03::new(socket).compat();
     let mut reader = Compat::new(FramedRead::new(socket, source.decoder()));
-    let handler = stream::poll_fn(move || {
+    let mut out = out;
+    let mut tripwire = tripwire;
+    let mut pending_event = None;
+    let mut unconfirmed_ack: Option<Bytes> = None;
+    let mut pending_ack = BytesMut::new();
+    let mut closing = false;
+    let handler = future::poll_fn(move || -> Result<Async<()>, ()> {
         // Gracefull shutdown procedure
         if let Some(future) = shutdown.as_mut() {
             match future.poll() {
@@ -188,7 +202,7 @@ fn handle_stream(
                     } else {
                         // Connection hasn't yet been established so we are done here.
                         debug!("Closing connection that hasn't yet been fully established.");
-                        return Ok(Async::Ready(None));
+                        return Ok(Async::Ready(()));
                     }
                     _token = Some(tk);
                     shutdown = None;
@@ -198,20 +212,68 @@ fn handle_stream(
             }
         }
 
-        // Actual work
-        reader.poll()
-    })
-    .compat()
-    .take_until(tripwire.compat())
-    .compat()
-    .filter_map(move |frame| {
-        let host = host.clone();
-        source.build_event(frame, host)
-    })
-    .map_err(|error| {
-        emit!(TcpConnectionError { error });
+        loop {
+            if closing {
+                return out.close();
+            }
+
+            if let Some(event) = pending_event.take() {
+                if let AsyncSink::NotReady(event) = out.start_send(event)? {
+                    pending_event = Some(event);
+                    return Ok(Async::NotReady);
+                }
+            }
+
+            // Only acknowledge once everything read before the acknowledgement
+            // has been accepted by the pipeline.
+            if unconfirmed_ack.is_some() {
+                try_ready!(out.poll_complete());
+                pending_ack.extend_from_slice(&unconfirmed_ack.take().unwrap());
+            }
+
+            // Flush acknowledgements owed to the peer before reading any further.
+            while !pending_ack.is_empty() {
+                let socket = reader.get_mut().get_mut().get_mut().get_mut();
+                match socket.write(&pending_ack) {
+                    Ok(0) => {
+                        let error = io::Error::from(io::ErrorKind::WriteZero);
+                        emit!(TcpConnectionError { error });
+                        return Err(());
+                    }
+                    Ok(written) => pending_ack.advance(written),
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        return Ok(Async::NotReady)
+                    }
+                    Err(error) => {
+                        emit!(TcpConnectionError { error });
+                        return Err(());
+                    }
+                }
+            }
+
+            if let Ok(Async::Ready(())) | Err(()) = tripwire.poll() {
+                closing = true;
+                continue;
+            }
+
+            // Actual work
+            match reader.poll() {
+                Ok(Async::Ready(Some(frame))) => {
+                    unconfirmed_ack = source.build_acknowledgement(&frame);
+                    pending_event = source.build_event(frame, host.clone());
+                }
+                Ok(Async::Ready(None)) => closing = true,
+                Ok(Async::NotReady) => {
+                    out.poll_complete()?;
+                    return Ok(Async::NotReady);
+                }
+                Err(error) => {
+                    emit!(TcpConnectionError { error });
+                    return Err(());
+                }
+            }
+        }
     })
-    .forward(out)
     .map(|_| debug!("connection closed."))
     .map_err(|_| warn!("Error received while processing TCP source"));
     tokio01::spawn(handler.instrument(span));
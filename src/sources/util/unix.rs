@@ -25,10 +25,10 @@ pub fn build_unix_source<D, E>(
     host_key: String,
     shutdown: ShutdownSignal,
     out: Pipeline,
-    build_event: impl Fn(&str, Option<Bytes>, &str) -> Option<Event> + Clone + Send + Sync + 'static,
+    build_event: impl Fn(&str, Option<Bytes>, D::Item) -> Option<Event> + Clone + Send + Sync + 'static,
 ) -> Source
 where
-    D: Decoder<Item = String, Error = E> + Clone + Send + 'static,
+    D: Decoder<Error = E> + Clone + Send + 'static,
     E: From<std::io::Error> + std::fmt::Debug + std::fmt::Display,
 {
     let out = out.sink_map_err(|e| error!("error sending line: {:?}", e));
@@ -68,9 +68,9 @@ where
                 path.map(|p| p.to_string_lossy().into_owned().into());
 
             let stream = socket.allow_read_until(shutdown.clone().compat().map(|_| ()));
-            let mut stream = FramedRead::new(stream, decoder.clone()).filter_map(move |line| {
-                future::ready(match line {
-                    Ok(line) => build_event(&host_key, received_from.clone(), &line).map(Ok),
+            let mut stream = FramedRead::new(stream, decoder.clone()).filter_map(move |frame| {
+                future::ready(match frame {
+                    Ok(frame) => build_event(&host_key, received_from.clone(), frame).map(Ok),
                     Err(error) => {
                         emit!(UnixSocketError {
                             error,
@@ -0,0 +1,86 @@
+pub mod v1;
+pub mod v2;
+
+use crate::{
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    Pipeline,
+};
+use serde::{Deserialize, Serialize};
+
+/// The protocol accepted from upstream `vector` sinks. See the `vector` sink
+/// for the differences between the versions.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum VectorConfig {
+    V2(v2::VectorConfig),
+    V1(v1::VectorConfig),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum V1 {
+    #[serde(rename = "1")]
+    V1,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub enum V2 {
+    #[serde(rename = "2")]
+    V2,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<VectorConfig>("vector")
+}
+
+#[typetag::serde(name = "vector")]
+impl SourceConfig for VectorConfig {
+    fn build(
+        &self,
+        _name: &str,
+        _globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: Pipeline,
+    ) -> crate::Result<super::Source> {
+        match self {
+            Self::V1(config) => config.build(shutdown, out),
+            Self::V2(config) => config.build(shutdown, out),
+        }
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn source_type(&self) -> &'static str {
+        "vector"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions() {
+        let config: VectorConfig = toml::from_str(r#"address = "0.0.0.0:9000""#).unwrap();
+        assert!(matches!(config, VectorConfig::V1(_)));
+
+        let config: VectorConfig = toml::from_str(
+            r#"
+            version = "2"
+            address = "0.0.0.0:9000"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(config, VectorConfig::V2(_)));
+
+        assert!(toml::from_str::<VectorConfig>(
+            r#"
+            version = "3"
+            address = "0.0.0.0:9000"
+            "#,
+        )
+        .is_err());
+    }
+}
@@ -1,10 +1,12 @@
-use super::util::{SocketListenAddr, TcpSource};
 use crate::{
     event::proto,
     internal_events::{VectorEventReceived, VectorProtoDecodeError},
     shutdown::ShutdownSignal,
+    sources::{
+        util::{SocketListenAddr, TcpSource},
+        Source,
+    },
     tls::{MaybeTlsSettings, TlsConfig},
-    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     Event, Pipeline,
 };
 use bytes05::{Bytes, BytesMut};
@@ -15,6 +17,8 @@ use tokio_util::codec::LengthDelimitedCodec;
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct VectorConfig {
+    #[serde(default)]
+    pub version: Option<super::V1>,
     pub address: SocketListenAddr,
     #[serde(default = "default_shutdown_timeout_secs")]
     pub shutdown_timeout_secs: u64,
@@ -29,6 +33,7 @@ fn default_shutdown_timeout_secs() -> u64 {
 impl VectorConfig {
     pub fn new(address: SocketListenAddr, tls: Option<TlsConfig>) -> Self {
         Self {
+            version: None,
             address,
             shutdown_timeout_secs: default_shutdown_timeout_secs(),
             tls,
@@ -36,31 +41,12 @@ impl VectorConfig {
     }
 }
 
-inventory::submit! {
-    SourceDescription::new_without_default::<VectorConfig>("vector")
-}
-
-#[typetag::serde(name = "vector")]
-impl SourceConfig for VectorConfig {
-    fn build(
-        &self,
-        _name: &str,
-        _globals: &GlobalOptions,
-        shutdown: ShutdownSignal,
-        out: Pipeline,
-    ) -> crate::Result<super::Source> {
+impl VectorConfig {
+    pub(super) fn build(&self, shutdown: ShutdownSignal, out: Pipeline) -> crate::Result<Source> {
         let vector = VectorSource;
         let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
         vector.run(self.address, self.shutdown_timeout_secs, tls, shutdown, out)
     }
-
-    fn output_type(&self) -> DataType {
-        DataType::Any
-    }
-
-    fn source_type(&self) -> &'static str {
-        "vector"
-    }
 }
 
 #[derive(Debug, Clone)]
@@ -99,7 +85,8 @@ mod test {
             metric::{MetricKind, MetricValue},
             Metric,
         },
-        sinks::vector::VectorSinkConfig,
+        sinks::vector::{v1::VectorSinkConfig as VectorSinkConfigV1, VectorSinkConfig},
+        sources::vector::VectorConfig as VectorSourceConfig,
         test_util::{next_addr, runtime, wait_for_tcp, CollectCurrent},
         tls::{TlsConfig, TlsOptions},
         topology::config::{GlobalOptions, SinkConfig, SinkContext, SourceConfig},
@@ -111,7 +98,7 @@ mod test {
     fn stream_test(addr: SocketAddr, source: VectorConfig, sink: VectorSinkConfig) {
         let (tx, rx) = Pipeline::new_test();
 
-        let server = source
+        let server = VectorSourceConfig::V1(source)
             .build(
                 "default",
                 &GlobalOptions::default(),
@@ -160,10 +147,10 @@ mod test {
         stream_test(
             addr,
             VectorConfig::new(addr.into(), None),
-            VectorSinkConfig {
-                address: format!("localhost:{}", addr.port()),
-                tls: None,
-            },
+            VectorSinkConfig::V1(VectorSinkConfigV1::new(format!(
+                "localhost:{}",
+                addr.port()
+            ))),
         );
     }
 
@@ -183,8 +170,7 @@ mod test {
                     },
                 }),
             ),
-            VectorSinkConfig {
-                address: format!("localhost:{}", addr.port()),
+            VectorSinkConfig::V1(VectorSinkConfigV1 {
                 tls: Some(TlsConfig {
                     enabled: Some(true),
                     options: TlsOptions {
@@ -192,7 +178,8 @@ mod test {
                         ..Default::default()
                     },
                 }),
-            },
+                ..VectorSinkConfigV1::new(format!("localhost:{}", addr.port()))
+            }),
         );
     }
 }
@@ -0,0 +1,422 @@
+use crate::{
+    event::proto as event_proto,
+    internal_events::{VectorDecompressionError, VectorEventBatchReceived, VectorProtoDecodeError},
+    proto::vector as proto,
+    shutdown::ShutdownSignal,
+    sources::Source,
+    tls::{MaybeTlsSettings, TlsConfig},
+    Event, Pipeline,
+};
+use flate2::read::GzDecoder;
+use futures::{
+    compat::{AsyncRead01CompatExt, Future01CompatExt, Stream01CompatExt},
+    future, FutureExt, StreamExt, TryFutureExt,
+};
+use futures01::Sink;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Read},
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+use tonic::{
+    transport::{server::Connected, Server},
+    Request, Response, Status,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VectorConfig {
+    pub version: super::V2,
+    pub address: SocketAddr,
+    #[serde(default = "default_max_length")]
+    pub max_length: usize,
+    pub tls: Option<TlsConfig>,
+}
+
+fn default_max_length() -> usize {
+    16 * 1024 * 1024
+}
+
+impl VectorConfig {
+    pub(super) fn build(&self, shutdown: ShutdownSignal, out: Pipeline) -> crate::Result<Source> {
+        let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
+        let incoming = tls.bind(&self.address)?.incoming();
+        let address = self.address;
+
+        // A failed accept must not take the whole server down with it.
+        let incoming = incoming.compat().filter_map(|connection| {
+            future::ready(match connection {
+                Ok(connection) => {
+                    let peer_addr = connection.peer_addr();
+                    Some(Ok::<_, io::Error>(Connection {
+                        inner: connection.compat().compat(),
+                        peer_addr,
+                    }))
+                }
+                Err(error) => {
+                    warn!(message = "failed to accept connection.", %error);
+                    None
+                }
+            })
+        });
+
+        let service = proto::vector_server::VectorServer::new(Service {
+            pipeline: out,
+            max_length: self.max_length,
+        });
+
+        let fut = async move {
+            info!(message = "building gRPC server.", %address);
+
+            let result = Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, shutdown.clone().compat().map(|_| ()))
+                .await;
+            // We need to drop the last copy of ShutdownSignalToken only after server has shut down.
+            drop(shutdown);
+
+            result.map_err(|error| error!(message = "gRPC server failed.", %error))
+        };
+        Ok(Box::new(fut.boxed().compat()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Service {
+    pipeline: Pipeline,
+    max_length: usize,
+}
+
+#[tonic::async_trait]
+impl proto::vector_server::Vector for Service {
+    async fn push_events(
+        &self,
+        request: Request<proto::PushEventsRequest>,
+    ) -> Result<Response<proto::PushEventsResponse>, Status> {
+        let request = request.into_inner();
+        let byte_size = request.encoded_len();
+        let events = decode_events(request, self.max_length)?;
+        let count = events.len();
+
+        emit!(VectorEventBatchReceived { count, byte_size });
+
+        // Only acknowledge the batch once every event has been handed to
+        // the pipeline.
+        self.pipeline
+            .clone()
+            .send_all(futures01::stream::iter_ok(
+                events.into_iter().map(Event::from),
+            ))
+            .compat()
+            .await
+            .map_err(|_| {
+                error!("Failed to forward events, downstream is closed");
+                Status::unavailable("Downstream is closed")
+            })?;
+
+        Ok(Response::new(proto::PushEventsResponse {
+            accepted: count as u64,
+        }))
+    }
+
+    async fn health_check(
+        &self,
+        _request: Request<proto::HealthCheckRequest>,
+    ) -> Result<Response<proto::HealthCheckResponse>, Status> {
+        let mut response = proto::HealthCheckResponse::default();
+        response.set_status(proto::ServingStatus::Serving);
+        Ok(Response::new(response))
+    }
+}
+
+/// Decompresses the events of the request, up to `max_length` bytes of
+/// them, so that a small request can't expand into an unbounded one.
+fn decode_events(
+    request: proto::PushEventsRequest,
+    max_length: usize,
+) -> Result<Vec<event_proto::EventWrapper>, Status> {
+    let compression = proto::Compression::from_i32(request.compression).ok_or_else(|| {
+        Status::invalid_argument(format!("Unknown compression {}", request.compression))
+    })?;
+
+    let compressed = &request.compressed_events[..];
+    let limit = max_length as u64 + 1;
+    let mut decompressed = Vec::new();
+    match compression {
+        proto::Compression::None => return Ok(request.events),
+        proto::Compression::Gzip => GzDecoder::new(compressed)
+            .take(limit)
+            .read_to_end(&mut decompressed),
+        proto::Compression::Zstd => zstd::stream::read::Decoder::new(compressed)
+            .and_then(|decoder| decoder.take(limit).read_to_end(&mut decompressed)),
+    }
+    .map_err(|error| {
+        let status = Status::invalid_argument(format!("Failed to decompress events: {}", error));
+        emit!(VectorDecompressionError { error });
+        status
+    })?;
+
+    if decompressed.len() > max_length {
+        return Err(Status::resource_exhausted(format!(
+            "Decompressed events exceed the maximum length of {} bytes",
+            max_length
+        )));
+    }
+
+    proto::EventBatch::decode(&decompressed[..])
+        .map(|batch| batch.events)
+        .map_err(|error| {
+            let status = Status::invalid_argument(format!("Failed to decode events: {}", error));
+            emit!(VectorProtoDecodeError { error });
+            status
+        })
+}
+
+/// An accepted connection that remembers its peer address, which tonic
+/// exposes on each request.
+struct Connection<T> {
+    inner: T,
+    peer_addr: SocketAddr,
+}
+
+impl<T> Connected for Connection<T> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        Some(self.peer_addr)
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Connection<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Connection<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "sinks-vector")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{
+            metric::{MetricKind, MetricValue},
+            Metric,
+        },
+        sinks::vector::{v2 as sink, VectorSinkConfig},
+        sources::vector::{self as source, VectorConfig as VectorSourceConfig},
+        test_util::{next_addr, runtime, wait_for_tcp, CollectCurrent},
+        tls::TlsOptions,
+        topology::config::{GlobalOptions, SinkConfig, SinkContext, SourceConfig},
+    };
+    use futures01::{stream, Future};
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::from("test"),
+            Event::from("events"),
+            Event::from("to roundtrip"),
+            Event::Metric(Metric {
+                name: String::from("also test a metric"),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Absolute,
+                value: MetricValue::Counter { value: 1.0 },
+            }),
+        ]
+    }
+
+    fn sink_config(
+        addresses: Vec<SocketAddr>,
+        compression: sink::Compression,
+        tls: Option<TlsConfig>,
+    ) -> VectorSinkConfig {
+        VectorSinkConfig::V2(sink::VectorSinkConfig {
+            version: crate::sinks::vector::V2::V2,
+            addresses: addresses
+                .into_iter()
+                .map(|addr| format!("localhost:{}", addr.port()))
+                .collect(),
+            compression,
+            batch: Default::default(),
+            request: Default::default(),
+            tls,
+        })
+    }
+
+    fn round_trip(compression: sink::Compression, server_tls: Option<TlsConfig>) {
+        let client_tls = server_tls.as_ref().map(|_| TlsConfig {
+            enabled: Some(true),
+            options: TlsOptions {
+                verify_certificate: Some(false),
+                ..Default::default()
+            },
+        });
+
+        let addr = next_addr();
+        let (tx, rx) = Pipeline::new_test();
+        let server = VectorSourceConfig::V2(VectorConfig {
+            version: source::V2::V2,
+            address: addr,
+            max_length: default_max_length(),
+            tls: server_tls,
+        })
+        .build(
+            "default",
+            &GlobalOptions::default(),
+            ShutdownSignal::noop(),
+            tx,
+        )
+        .unwrap();
+
+        let mut rt = runtime();
+        rt.spawn(server);
+        wait_for_tcp(addr);
+
+        let (sink, healthcheck) = sink_config(vec![addr], compression, client_tls)
+            .build(SinkContext::new_test())
+            .unwrap();
+        rt.block_on(healthcheck).unwrap();
+
+        let events = events();
+        let _ = rt
+            .block_on(sink.send_all(stream::iter_ok(events.clone().into_iter())))
+            .unwrap();
+
+        // The sink only finishes once the batch has been acknowledged, at
+        // which point the events are already in the pipeline.
+        let (_, output) = CollectCurrent::new(rx).wait().unwrap();
+        assert_eq!(events, output);
+    }
+
+    #[test]
+    fn round_trip_uncompressed() {
+        round_trip(sink::Compression::None, None);
+    }
+
+    #[test]
+    fn round_trip_gzip() {
+        round_trip(sink::Compression::Gzip, None);
+    }
+
+    #[test]
+    fn round_trip_zstd() {
+        round_trip(sink::Compression::Zstd, None);
+    }
+
+    #[test]
+    fn round_trip_tls() {
+        round_trip(
+            sink::Compression::Gzip,
+            Some(TlsConfig {
+                enabled: Some(true),
+                options: TlsOptions {
+                    crt_file: Some("tests/data/localhost.crt".into()),
+                    key_file: Some("tests/data/localhost.key".into()),
+                    ..Default::default()
+                },
+            }),
+        );
+    }
+
+    #[test]
+    fn healthcheck_fails_without_server() {
+        let mut rt = runtime();
+        let (_, healthcheck) = sink_config(vec![next_addr()], sink::Compression::None, None)
+            .build(SinkContext::new_test())
+            .unwrap();
+        assert!(rt.block_on(healthcheck).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_compression() {
+        let request = proto::PushEventsRequest {
+            compression: 42,
+            ..Default::default()
+        };
+        assert_eq!(
+            decode_events(request, default_max_length())
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_payload() {
+        let mut request = proto::PushEventsRequest {
+            compressed_events: b"not gzip".to_vec(),
+            ..Default::default()
+        };
+        request.set_compression(proto::Compression::Gzip);
+        assert_eq!(
+            decode_events(request, default_max_length())
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn limits_decompressed_size() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let batch = proto::EventBatch {
+            events: vec![event_proto::EventWrapper::from(Event::from(
+                "x".repeat(10_000).as_str(),
+            ))],
+        };
+        let mut encoded = Vec::new();
+        batch.encode(&mut encoded).unwrap();
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&encoded).unwrap();
+        let compressed = vec![
+            (proto::Compression::Gzip, gzip.finish().unwrap()),
+            (
+                proto::Compression::Zstd,
+                zstd::stream::encode_all(&encoded[..], 0).unwrap(),
+            ),
+        ];
+
+        for (compression, compressed_events) in compressed {
+            let mut request = proto::PushEventsRequest {
+                compressed_events,
+                ..Default::default()
+            };
+            request.set_compression(compression);
+
+            let events = decode_events(request.clone(), encoded.len()).unwrap();
+            assert_eq!(events, batch.events);
+            assert_eq!(
+                decode_events(request, 1_000).unwrap_err().code(),
+                tonic::Code::ResourceExhausted
+            );
+        }
+    }
+}
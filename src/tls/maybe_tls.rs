@@ -1,6 +1,8 @@
 use futures01::Poll;
 use std::fmt::{self, Debug, Formatter};
 use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::task::Context;
 use tokio01::io::{AsyncRead, AsyncWrite};
 
 /// A type wrapper for objects that can exist in either a raw state or
@@ -98,3 +100,37 @@ impl<R: AsyncWrite, T: AsyncWrite> AsyncWrite for MaybeTls<R, T> {
         }
     }
 }
+
+impl<R, T> tokio::io::AsyncWrite for MaybeTls<R, T>
+where
+    R: tokio::io::AsyncWrite + Unpin,
+    T: tokio::io::AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tls(s) => tokio::io::AsyncWrite::poll_write(Pin::new(s), cx, buf),
+            Self::Raw(s) => tokio::io::AsyncWrite::poll_write(Pin::new(s), cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> std::task::Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tls(s) => tokio::io::AsyncWrite::poll_flush(Pin::new(s), cx),
+            Self::Raw(s) => tokio::io::AsyncWrite::poll_flush(Pin::new(s), cx),
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tls(s) => tokio::io::AsyncWrite::poll_shutdown(Pin::new(s), cx),
+            Self::Raw(s) => tokio::io::AsyncWrite::poll_shutdown(Pin::new(s), cx),
+        }
+    }
+}
@@ -7,6 +7,7 @@ use std::fmt::Debug;
 use std::io::Error as IoError;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio01::net::TcpStream;
 use tokio_openssl03::SslStream;
 
@@ -20,6 +21,8 @@ mod settings;
 pub(crate) use incoming::{MaybeTlsIncomingStream, MaybeTlsListener};
 pub(crate) use maybe_tls::MaybeTls;
 pub(crate) use outgoing::MaybeTlsConnector;
+#[cfg(unix)]
+pub use outgoing::MaybeTlsUnixStream;
 pub use settings::{MaybeTlsSettings, TlsConfig, TlsOptions, TlsSettings};
 
 pub type Result<T> = std::result::Result<T, TlsError>;
@@ -76,6 +79,9 @@ pub enum TlsError {
     MissingRequiredIdentity,
     #[snafu(display("TLS handshake failed: {}", source))]
     Handshake { source: openssl::ssl::Error },
+    #[cfg(unix)]
+    #[snafu(display("TLS handshake failed: {}", message))]
+    UnixHandshake { message: String },
     #[snafu(display("Not ready for I/O during TLS handshake"))]
     HandshakeNotReady,
     #[snafu(display("TLS handshake setup failed: {}", source))]
@@ -119,9 +125,17 @@ pub enum TlsError {
 
 impl MaybeTlsStream<TcpStream> {
     pub fn peer_addr(&self) -> std::result::Result<SocketAddr, std::io::Error> {
+        self.tcp_stream().peer_addr()
+    }
+
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> std::io::Result<()> {
+        self.tcp_stream().set_keepalive(keepalive)
+    }
+
+    fn tcp_stream(&self) -> &TcpStream {
         match self {
-            Self::Raw(raw) => raw.peer_addr(),
-            Self::Tls(tls) => tls.get_ref().get_ref().peer_addr(),
+            Self::Raw(raw) => raw,
+            Self::Tls(tls) => tls.get_ref().get_ref(),
         }
     }
 }
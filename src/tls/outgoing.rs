@@ -1,7 +1,13 @@
 use super::{tls_connector, MaybeTlsSettings, MaybeTlsStream, Result, TlsError};
+#[cfg(unix)]
+use super::{Connect, MaybeTls};
 use futures01::{Async, Future};
 use openssl::ssl::{ConnectConfiguration, HandshakeError};
+#[cfg(unix)]
+use snafu::ResultExt;
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
 use tokio01::net::tcp::{ConnectFuture, TcpStream};
 use tokio_openssl03::{ConnectAsync, ConnectConfigurationExt};
 
@@ -74,8 +80,38 @@ impl Future for MaybeTlsConnector {
     }
 }
 
+#[cfg(unix)]
+pub type MaybeTlsUnixStream =
+    MaybeTls<tokio::net::UnixStream, tokio_openssl::SslStream<tokio::net::UnixStream>>;
+
 impl MaybeTlsSettings {
     pub(crate) fn connect(&self, host: String, addr: SocketAddr) -> Result<MaybeTlsConnector> {
         MaybeTlsConnector::new(host, addr, self)
     }
+
+    /// Connects to a Unix socket, negotiating a TLS session if enabled. As
+    /// there is no host name, the server certificate is verified against
+    /// the given `server_name`.
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(
+        &self,
+        server_name: &str,
+        path: &Path,
+    ) -> Result<MaybeTlsUnixStream> {
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .context(Connect)?;
+        match self {
+            Self::Raw(()) => Ok(MaybeTls::Raw(stream)),
+            Self::Tls(_) => {
+                let config = tls_connector(self)?;
+                tokio_openssl::connect(config, server_name, stream)
+                    .await
+                    .map(MaybeTls::Tls)
+                    .map_err(|error| TlsError::UnixHandshake {
+                        message: error.to_string(),
+                    })
+            }
+        }
+    }
 }
@@ -712,7 +712,10 @@ fn retain<T>(vec: &mut Vec<T>, mut retain_filter: impl FnMut(&mut T) -> bool) {
 
 #[cfg(all(test, feature = "sinks-console", feature = "sources-socket"))]
 mod tests {
-    use crate::sinks::console::{ConsoleSinkConfig, Encoding, Target};
+    use crate::sinks::{
+        console::{ConsoleSinkConfig, Target},
+        util::encoding::StandardEncodings,
+    };
     use crate::sources::socket::SocketConfig;
     use crate::test_util::{next_addr, runtime};
     use crate::topology;
@@ -731,7 +734,7 @@ mod tests {
             &[&"in"],
             ConsoleSinkConfig {
                 target: Target::Stdout,
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
             },
         );
         old_config.global.data_dir = Some(Path::new("/asdf").to_path_buf());
@@ -757,7 +760,10 @@ mod tests {
 
 #[cfg(all(test, feature = "sinks-console", feature = "sources-splunk_hec"))]
 mod reload_tests {
-    use crate::sinks::console::{ConsoleSinkConfig, Encoding, Target};
+    use crate::sinks::{
+        console::{ConsoleSinkConfig, Target},
+        util::encoding::StandardEncodings,
+    };
     use crate::sources::splunk_hec::SplunkConfig;
     use crate::test_util::{next_addr, runtime};
     use crate::topology;
@@ -776,7 +782,7 @@ mod reload_tests {
             &[&"in1"],
             ConsoleSinkConfig {
                 target: Target::Stdout,
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
             },
         );
 
@@ -788,7 +794,7 @@ mod reload_tests {
             &[&"in1", &"in2"],
             ConsoleSinkConfig {
                 target: Target::Stdout,
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
             },
         );
 
@@ -815,7 +821,7 @@ mod reload_tests {
             &[&"in1"],
             ConsoleSinkConfig {
                 target: Target::Stdout,
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
             },
         );
 
@@ -832,7 +838,10 @@ mod reload_tests {
 
 #[cfg(all(test, feature = "sinks-console", feature = "sources-generator"))]
 mod source_finished_tests {
-    use crate::sinks::console::{ConsoleSinkConfig, Encoding, Target};
+    use crate::sinks::{
+        console::{ConsoleSinkConfig, Target},
+        util::encoding::StandardEncodings,
+    };
     use crate::sources::generator::GeneratorConfig;
     use crate::topology::{self, config::Config};
     use futures::compat::Future01CompatExt;
@@ -847,7 +856,7 @@ mod source_finished_tests {
             &[&"in"],
             ConsoleSinkConfig {
                 target: Target::Stdout,
-                encoding: Encoding::Text.into(),
+                encoding: StandardEncodings::text().into(),
             },
         );
 
@@ -1,14 +1,33 @@
+use crate::event::{self, LogEvent, Value};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use std::fmt;
+use tokio::sync::broadcast;
 use tracing::{
     dispatcher::{set_global_default, Dispatch},
-    span::Span,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record, Span},
+    Event, Subscriber,
 };
 use tracing_limit::Limit;
 use tracing_log::LogTracer;
-use tracing_subscriber::{layer::SubscriberExt, FmtSubscriber};
+use tracing_subscriber::{
+    layer::{Context, Layer, SubscriberExt},
+    registry::LookupSpan,
+    FmtSubscriber,
+};
 
 pub use tracing_futures::Instrument;
 pub use tracing_tower::{InstrumentableService, InstrumentedService};
 
+/// The number of log events buffered for each subscriber before the slowest
+/// ones start missing events.
+const BROADCAST_CAPACITY: usize = 1000;
+
+/// Vector's own log events, sent to every `subscribe`r. Only initialized once
+/// someone subscribes, so nothing is converted until then.
+static SENDER: OnceCell<broadcast::Sender<LogEvent>> = OnceCell::new();
+
 pub fn init(color: bool, json: bool, levels: &str) {
     let dispatch = if json {
         let subscriber = FmtSubscriber::builder()
@@ -16,6 +35,7 @@ pub fn init(color: bool, json: bool, levels: &str) {
             .json()
             .flatten_event(true)
             .finish()
+            .with(BroadcastLayer)
             .with(Limit::default());
 
         Dispatch::new(subscriber)
@@ -24,6 +44,7 @@ pub fn init(color: bool, json: bool, levels: &str) {
             .with_ansi(color)
             .with_env_filter(levels)
             .finish()
+            .with(BroadcastLayer)
             .with(Limit::default());
 
         Dispatch::new(subscriber)
@@ -36,3 +57,162 @@ pub fn init(color: bool, json: bool, levels: &str) {
 pub fn current_span() -> Span {
     Span::current()
 }
+
+/// Subscribe to Vector's own log events, as seen after filtering and rate
+/// limiting.
+pub fn subscribe() -> broadcast::Receiver<LogEvent> {
+    SENDER
+        .get_or_init(|| broadcast::channel(BROADCAST_CAPACITY).0)
+        .subscribe()
+}
+
+/// Converts tracing events into `LogEvent`s and broadcasts them to the
+/// subscribers. Being inside `Limit`, it only sees the events that passed the
+/// level filters and weren't rate limited, along with the rate limiting
+/// notices.
+pub(crate) struct BroadcastLayer;
+
+/// Targets whose events are not broadcast, as the `internal_logs` source would
+/// otherwise feed its own errors back into itself.
+const IGNORED_TARGETS: &[&str] = &["vector::sources::internal_logs", "vector::pipeline"];
+
+/// The fields recorded on a span, kept in the span's extensions.
+#[derive(Default)]
+struct SpanFields(Vec<(String, Value)>);
+
+impl<S> Layer<S> for BroadcastLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = SpanFields::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let sender = match SENDER.get() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let metadata = event.metadata();
+        if IGNORED_TARGETS
+            .iter()
+            .any(|target| metadata.target().starts_with(target))
+        {
+            return;
+        }
+
+        let mut log = LogEvent::default();
+        log.insert(event::log_schema().timestamp_key().clone(), Utc::now());
+        log.insert("metadata.level", metadata.level().to_string());
+        log.insert("metadata.target", metadata.target());
+        if let Some(module_path) = metadata.module_path() {
+            log.insert("metadata.module_path", module_path);
+        }
+
+        // Span fields, from the outermost span in, so inner spans take
+        // precedence. Topology spans name the component the event comes from.
+        if let Some(current) = ctx.lookup_current() {
+            let mut spans = current.parents().collect::<Vec<_>>();
+            spans.reverse();
+            spans.push(current);
+            for span in spans {
+                let extensions = span.extensions();
+                let fields = match extensions.get::<SpanFields>() {
+                    Some(fields) => fields,
+                    None => continue,
+                };
+                let is_component = ["source", "transform", "sink"].contains(&span.name());
+                if is_component {
+                    log.insert("component_kind", span.name());
+                }
+                for (name, value) in &fields.0 {
+                    if is_component && (name == "name" || name == "type") {
+                        log.insert(format!("component_{}", name), value.clone());
+                    } else {
+                        log.insert(format!("span.{}", name), value.clone());
+                    }
+                }
+            }
+        }
+
+        let mut visitor = LogEventVisitor(&mut log);
+        event.record(&mut visitor);
+
+        // Fails only if there are no subscribers left, which is fine.
+        let _ = sender.send(log);
+    }
+}
+
+impl Visit for SpanFields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.push((field.name().into(), value.into()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.push((field.name().into(), (value as i64).into()));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.push((field.name().into(), value.into()));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().into(), value.into()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().into(), format!("{:?}", value).into()));
+    }
+}
+
+/// Records the event fields into the log event, the `message` field going to
+/// the configured message key. Rate limiting notices keep their
+/// `rate_limit_secs` field, which flags them as such.
+struct LogEventVisitor<'a>(&'a mut LogEvent);
+
+impl<'a> LogEventVisitor<'a> {
+    fn insert(&mut self, field: &Field, value: impl Into<Value>) {
+        if field.name() == "message" {
+            self.0
+                .insert(event::log_schema().message_key().clone(), value);
+        } else {
+            self.0.insert(field.name(), value);
+        }
+    }
+}
+
+impl<'a> Visit for LogEventVisitor<'a> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value as i64);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value));
+    }
+}
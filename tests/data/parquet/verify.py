@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+"""Reads rows.parquet with pyarrow, independently of Vector's Parquet writer.
+
+The file is what the `matches_verified_fixture` test in
+src/sinks/util/parquet/writer.rs expects the writer to produce. When the
+writer's output changes, write the new output over the file and run this
+script again before checking it in.
+"""
+
+import os
+
+import pyarrow as pa
+import pyarrow.parquet as pq
+
+path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "rows.parquet")
+table = pq.read_table(path)
+
+assert table.column_names == ["message", "count", "ratio", "ok", "ts"]
+assert table.schema.field("message").type == pa.string()
+assert table.schema.field("count").type == pa.int64()
+assert table.schema.field("ratio").type == pa.float64()
+assert table.schema.field("ok").type == pa.bool_()
+ts = table.schema.field("ts").type
+assert pa.types.is_timestamp(ts) and ts.unit == "ms"
+assert all(field.nullable for field in table.schema)
+
+assert table.num_rows == 3
+assert pq.ParquetFile(path).metadata.num_row_groups == 2
+assert table.column("message").to_pylist() == ["hello", None, "wörld"]
+assert table.column("count").to_pylist() == [1, -2, None]
+assert table.column("ratio").to_pylist() == [0.5, None, 2.25]
+assert table.column("ok").to_pylist() == [True, False, None]
+assert table.column("ts").cast(pa.int64()).to_pylist() == [
+    1600000000000,
+    1600000001000,
+    None,
+]
+
+print("ok")
@@ -9,7 +9,7 @@ use serde::Deserialize;
 use serde_json::Value;
 use sinks::{
     socket::SocketSinkConfig,
-    util::{encoding::EncodingConfig, Encoding},
+    util::encoding::{EncodingConfig, StandardEncodings},
 };
 use std::fmt;
 use std::{collections::HashMap, str::FromStr};
@@ -350,5 +350,5 @@ fn encode_priority(severity: Severity, facility: Facility) -> u8 {
 }
 
 fn tcp_json_sink(address: String) -> SocketSinkConfig {
-    SocketSinkConfig::make_tcp_config(address, EncodingConfig::from(Encoding::Json), None)
+    SocketSinkConfig::make_tcp_config(address, EncodingConfig::from(StandardEncodings::Json), None)
 }